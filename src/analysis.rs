@@ -0,0 +1,1782 @@
+//! Per-frame analyses of structures, and export of their results as CSV or TSV tables for plotting.
+
+use std::io::{self, Write};
+
+use super::cell::Cell;
+use super::data::{AtomData, Data, File};
+use super::math;
+use super::position::Position;
+use super::voronoi::Polyhedron;
+use super::writer::{extended_comment, format_number};
+
+/// A named series of per-frame values, e.g. the RMSD of every frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    /// The values, one per frame. `NaN` marks a missing value.
+    pub values: Vec<f64>,
+}
+impl Series {
+    /// Creates a series from its name and values.
+    pub fn new<S: Into<String>>(name: S, values: Vec<f64>) -> Series {
+        Series {
+            name: name.into(),
+            values,
+        }
+    }
+    /// The mean of the values, ignoring missing values, or `NaN` if there are none.
+    pub fn mean(&self) -> f64 {
+        let values = self.values.iter().filter(|v| !v.is_nan());
+        values.clone().sum::<f64>() / values.count() as f64
+    }
+
+    /// The sample standard deviation of the values, ignoring missing values,
+    /// or `NaN` if there are fewer than two.
+    pub fn std(&self) -> f64 {
+        let mean = self.mean();
+        let values = self.values.iter().filter(|v| !v.is_nan());
+        let n = values.clone().count();
+        if n < 2 {
+            return f64::NAN;
+        }
+        (values.map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+    }
+
+    /// The circular mean of angles in degrees, `atan2(Σ sin, Σ cos)` from -180° to 180°, e.g. of dihedrals,
+    /// which wrap around at ±180°. Ignores missing values, and is `NaN` if there are none.
+    pub fn circular_mean(&self) -> f64 {
+        let (sin, cos, _) = self.resultant();
+        sin.atan2(cos).to_degrees()
+    }
+
+    /// The circular standard deviation of angles in degrees, `sqrt(-2 ln R)` of the mean resultant length `R`,
+    /// which is close to the standard deviation for narrow distributions. Ignores missing values,
+    /// and is `NaN` if there are fewer than two.
+    pub fn circular_std(&self) -> f64 {
+        let (sin, cos, n) = self.resultant();
+        if n < 2 {
+            return f64::NAN;
+        }
+        let length = (sin.hypot(cos) / n as f64).min(1.0);
+        (-2.0 * length.ln()).sqrt().to_degrees()
+    }
+
+    /// The sums of the sines and cosines of the angles in degrees, and their number, ignoring missing values.
+    fn resultant(&self) -> (f64, f64, usize) {
+        let angles = self.values.iter().filter(|v| !v.is_nan()).map(|v| v.to_radians());
+        let n = angles.clone().count();
+        if n == 0 {
+            return (f64::NAN, f64::NAN, 0);
+        }
+        angles.fold((0.0, 0.0, n), |(sin, cos, n), angle| (sin + angle.sin(), cos + angle.cos(), n))
+    }
+}
+
+/// Writes series as comma-separated columns, after a `frame` column with the frame index.
+/// Missing values, and the values past the end of shorter series, are left empty.
+pub fn to_csv<W: Write + ?Sized>(writer: &mut W, series: &[Series]) -> io::Result<()> {
+    write_table(writer, series, ',')
+}
+
+/// Writes series as tab-separated columns, like [`to_csv`].
+pub fn to_tsv<W: Write + ?Sized>(writer: &mut W, series: &[Series]) -> io::Result<()> {
+    write_table(writer, series, '\t')
+}
+
+fn write_table<W: Write + ?Sized>(writer: &mut W, series: &[Series], separator: char) -> io::Result<()> {
+    let mut buf = String::from("frame");
+    for s in series {
+        buf.push(separator);
+        if s.name.contains([separator, '"', '\n']) {
+            buf.push_str(&format!("\"{}\"", s.name.replace('"', "\"\"")));
+        } else {
+            buf.push_str(&s.name);
+        }
+    }
+    buf.push('\n');
+
+    let rows = series.iter().map(|s| s.values.len()).max().unwrap_or(0);
+    for frame in 0..rows {
+        buf.push_str(&frame.to_string());
+        for s in series {
+            buf.push(separator);
+            if let Some(value) = s.values.get(frame).filter(|v| !v.is_nan()) {
+                buf.push_str(&value.to_string());
+            }
+        }
+        buf.push('\n');
+    }
+    writer.write_all(buf.as_bytes())
+}
+
+/// A histogram of values in `[min, max)`, with bins of equal width.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    /// The (possibly weighted or normalized) count of every bin.
+    pub counts: Vec<f64>,
+}
+impl Histogram {
+    /// Creates an empty histogram of `bins` bins spanning `[min, max)`.
+    pub fn new(min: f64, max: f64, bins: usize) -> Histogram {
+        Histogram {
+            min,
+            max,
+            counts: vec![0.0; bins],
+        }
+    }
+
+    /// Creates an empty histogram spanning `[min, max)` with bins of about `width`,
+    /// adjusted so a whole number of bins fits.
+    pub fn with_width(min: f64, max: f64, width: f64) -> Histogram {
+        Histogram::new(min, max, ((max - min) / width).round().max(1.0) as usize)
+    }
+
+    /// The number of bins.
+    pub fn bins(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The width of the bins.
+    pub fn width(&self) -> f64 {
+        (self.max - self.min) / self.bins() as f64
+    }
+
+    /// The centers of the bins.
+    pub fn centers(&self) -> Vec<f64> {
+        (0..self.bins()).map(|i| self.min + (i as f64 + 0.5) * self.width()).collect()
+    }
+
+    /// The bin holding `value`, or `None` if it lies outside the histogram.
+    pub fn bin(&self, value: f64) -> Option<usize> {
+        let bin = ((value - self.min) / self.width()).floor();
+        if bin >= 0.0 && bin < self.bins() as f64 {
+            Some(bin as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Counts a value. Values outside the histogram are ignored.
+    pub fn add(&mut self, value: f64) {
+        self.add_weighted(value, 1.0);
+    }
+
+    /// Counts a value with a weight. Values outside the histogram are ignored.
+    pub fn add_weighted(&mut self, value: f64, weight: f64) {
+        if let Some(bin) = self.bin(value) {
+            self.counts[bin] += weight;
+        }
+    }
+
+    /// Counts every value.
+    pub fn fill<I: IntoIterator<Item = f64>>(&mut self, values: I) {
+        for value in values {
+            self.add(value);
+        }
+    }
+
+    /// The sum of the counts.
+    pub fn total(&self) -> f64 {
+        self.counts.iter().sum()
+    }
+
+    /// The counts normalized to a probability density, which integrates to 1 over the histogram.
+    pub fn density(&self) -> Vec<f64> {
+        let norm = self.total() * self.width();
+        self.counts.iter().map(|count| if norm > 0.0 { count / norm } else { 0.0 }).collect()
+    }
+
+    /// Writes the bin centers and counts as comma-separated columns `value,count`.
+    pub fn to_csv<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        let mut buf = String::from("value,count\n");
+        for (center, count) in self.centers().iter().zip(&self.counts) {
+            buf.push_str(&format!("{},{}\n", center, count));
+        }
+        writer.write_all(buf.as_bytes())
+    }
+}
+
+/// The lengths of the perceived bonds (see [`Data::bonds`]) between elements `a` and `b` in all frames, in Ångström.
+pub fn bond_lengths(frames: &[Data], a: &str, b: &str) -> Vec<f64> {
+    let mut lengths = vec![];
+    for data in frames {
+        for (i, j) in data.bonds() {
+            let (x, y) = (&data.atoms[i], &data.atoms[j]);
+            if (is(x, a) && is(y, b)) || (is(x, b) && is(y, a)) {
+                lengths.push(x.position.distance(y.position));
+            }
+        }
+    }
+    lengths
+}
+
+/// A histogram of the bond lengths between elements `a` and `b` in all frames, from 0 to 4 Å in bins of 0.01 Å.
+/// Use [`bond_lengths`] and [`Histogram::fill`] for other binnings.
+pub fn bond_length_distribution(frames: &[Data], a: &str, b: &str) -> Histogram {
+    let mut histogram = Histogram::with_width(0.0, 4.0, 0.01);
+    histogram.fill(bond_lengths(frames, a, b));
+    histogram
+}
+
+/// The angles `a-center-b` in degrees between pairs of perceived bonds of a `center` atom in all frames.
+pub fn bond_angles(frames: &[Data], a: &str, center: &str, b: &str) -> Vec<f64> {
+    let mut angles = vec![];
+    for data in frames {
+        let mut neighbours = vec![vec![]; data.atoms.len()];
+        for (i, j) in data.bonds() {
+            neighbours[i].push(j);
+            neighbours[j].push(i);
+        }
+        for (c, atom) in data.atoms.iter().enumerate().filter(|(_, atom)| is(atom, center)) {
+            for (k, &i) in neighbours[c].iter().enumerate() {
+                for &j in &neighbours[c][k + 1..] {
+                    let (x, y) = (&data.atoms[i], &data.atoms[j]);
+                    if (is(x, a) && is(y, b)) || (is(x, b) && is(y, a)) {
+                        let (u, v) = (x.position - atom.position, y.position - atom.position);
+                        let cos = u.dot(v) / (u.norm() * v.norm());
+                        angles.push(cos.clamp(-1.0, 1.0).acos().to_degrees());
+                    }
+                }
+            }
+        }
+    }
+    angles
+}
+
+/// A histogram of the angles `a-center-b` in all frames, from 0° to 180° in bins of 1°.
+/// Use [`bond_angles`] and [`Histogram::fill`] for other binnings.
+pub fn angle_distribution(frames: &[Data], a: &str, center: &str, b: &str) -> Histogram {
+    let mut histogram = Histogram::new(0.0, 180.0, 180);
+    histogram.fill(bond_angles(frames, a, center, b));
+    histogram
+}
+
+/// The radial distribution function g(r) of atoms of element `b` around atoms of element `a`,
+/// averaged over the frames, with `bins` bins up to `r_max` Ångström.
+/// Distances follow the minimum image convention, so `r_max` should not exceed half the cell width.
+/// Frames without a cell are skipped.
+pub fn rdf(frames: &[Data], a: &str, b: &str, r_max: f64, bins: usize) -> Histogram {
+    let mut histogram = Histogram::new(0.0, r_max, bins);
+    let mut pair_density = 0.0;
+
+    for data in frames {
+        let Some(cell) = &data.cell else {
+            continue;
+        };
+        let volume = cell.volume().abs();
+        let first: Vec<&AtomData> = data.atoms.iter().filter(|atom| is(atom, a)).collect();
+        let second: Vec<&AtomData> = data.atoms.iter().filter(|atom| is(atom, b)).collect();
+        if volume == 0.0 || first.is_empty() || second.is_empty() {
+            continue;
+        }
+
+        let mut pairs = 0;
+        for x in &first {
+            for y in &second {
+                if std::ptr::eq(*x, *y) {
+                    continue;
+                }
+                pairs += 1;
+                histogram.add(minimum_image_distance(cell, x.position, y.position));
+            }
+        }
+        pair_density += pairs as f64 / volume;
+    }
+
+    let width = histogram.width();
+    for (i, count) in histogram.counts.iter_mut().enumerate() {
+        let (inner, outer) = (i as f64 * width, (i + 1) as f64 * width);
+        let shell = 4.0 / 3.0 * std::f64::consts::PI * (outer.powi(3) - inner.powi(3));
+        if pair_density > 0.0 {
+            *count /= pair_density * shell;
+        }
+    }
+    histogram
+}
+
+/// How [`partial_rdfs`] normalizes the pair counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RdfNormalization {
+    /// Relative to an ideal gas of the same density, so g(r) tends to 1 at large distances.
+    #[default]
+    IdealGas,
+    /// As the number density in Å⁻³ of the second element of a pair around atoms of the first.
+    NumberDensity,
+}
+
+/// Options for [`partial_rdfs`].
+#[derive(Debug, Clone)]
+pub struct RdfOptions {
+    /// The largest distance in Ångström.
+    pub r_max: f64,
+    /// The number of bins.
+    pub bins: usize,
+    /// The standard deviation in Ångström of a Gaussian the RDFs are smoothed with, if any.
+    pub smearing: Option<f64>,
+    pub normalization: RdfNormalization,
+}
+impl Default for RdfOptions {
+    fn default() -> RdfOptions {
+        RdfOptions {
+            r_max: 6.0,
+            bins: 300,
+            smearing: None,
+            normalization: RdfNormalization::IdealGas,
+        }
+    }
+}
+
+/// The partial radial distribution functions of every pair of elements, see [`partial_rdfs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialRdfs {
+    /// The element pairs, with the symbols of each pair in alphabetical order, and their RDFs.
+    pub pairs: Vec<((String, String), Histogram)>,
+}
+impl PartialRdfs {
+    /// The RDF of a pair of elements, in either order.
+    pub fn get(&self, a: &str, b: &str) -> Option<&Histogram> {
+        self.pairs
+            .iter()
+            .find(|((x, y), _)| {
+                (x.eq_ignore_ascii_case(a) && y.eq_ignore_ascii_case(b))
+                    || (x.eq_ignore_ascii_case(b) && y.eq_ignore_ascii_case(a))
+            })
+            .map(|(_, histogram)| histogram)
+    }
+
+    /// Writes the bin centers and the RDF of every pair as comma-separated columns `r,A-B,...`.
+    pub fn to_csv<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        let mut buf = String::from("r");
+        for ((a, b), _) in &self.pairs {
+            buf.push_str(&format!(",{}-{}", a, b));
+        }
+        buf.push('\n');
+        if let Some((_, first)) = self.pairs.first() {
+            for (i, center) in first.centers().iter().enumerate() {
+                buf.push_str(&center.to_string());
+                for (_, histogram) in &self.pairs {
+                    buf.push_str(&format!(",{}", histogram.counts[i]));
+                }
+                buf.push('\n');
+            }
+        }
+        writer.write_all(buf.as_bytes())
+    }
+}
+
+/// The radial distribution functions of all pairs of elements, computed in a single pass over the frames.
+/// Atoms that are not known elements are grouped by their atomic symbol.
+/// Distances follow the minimum image convention, so `r_max` should not exceed half the cell width.
+/// Frames without a cell are skipped.
+pub fn partial_rdfs(frames: &[Data], options: &RdfOptions) -> PartialRdfs {
+    let label = |atom: &AtomData| atom.element().map_or(atom.symbol.clone(), |e| e.symbol().to_string());
+    let mut labels: Vec<String> = frames.iter().flat_map(|data| data.atoms.iter().map(label)).collect();
+    labels.sort();
+    labels.dedup();
+
+    let n = labels.len();
+    let pair = |a: usize, b: usize| a.min(b) * n + a.max(b);
+    let mut histograms = vec![Histogram::new(0.0, options.r_max, options.bins); n * n];
+    // The normalization of every pair, accumulated over the frames.
+    let mut norms = vec![0.0; n * n];
+
+    for data in frames {
+        let Some(cell) = &data.cell else {
+            continue;
+        };
+        let volume = cell.volume().abs();
+        if volume == 0.0 {
+            continue;
+        }
+        let species: Vec<usize> = data.atoms.iter().map(|atom| labels.binary_search(&label(atom)).unwrap()).collect();
+        let mut counts = vec![0usize; n];
+        for &s in &species {
+            counts[s] += 1;
+        }
+
+        for (i, x) in data.atoms.iter().enumerate() {
+            for (j, y) in data.atoms.iter().enumerate().skip(i + 1) {
+                let distance = minimum_image_distance(cell, x.position, y.position);
+                histograms[pair(species[i], species[j])].add(distance);
+            }
+        }
+
+        for a in 0..n {
+            for b in a..n {
+                let (na, nb) = (counts[a] as f64, counts[b] as f64);
+                norms[pair(a, b)] += match options.normalization {
+                    // Unordered pairs per volume.
+                    RdfNormalization::IdealGas if a == b => na * (na - 1.0) / 2.0 / volume,
+                    RdfNormalization::IdealGas => na * nb / volume,
+                    // Every pair of identical elements is seen from both atoms.
+                    RdfNormalization::NumberDensity if a == b => na / 2.0,
+                    RdfNormalization::NumberDensity => na,
+                };
+            }
+        }
+    }
+
+    let mut pairs = vec![];
+    for a in 0..n {
+        for b in a..n {
+            let mut histogram = histograms[pair(a, b)].clone();
+            let width = histogram.width();
+            let norm = norms[pair(a, b)];
+            for (i, count) in histogram.counts.iter_mut().enumerate() {
+                let (inner, outer) = (i as f64 * width, (i + 1) as f64 * width);
+                let shell = 4.0 / 3.0 * std::f64::consts::PI * (outer.powi(3) - inner.powi(3));
+                *count = if norm > 0.0 { *count / (norm * shell) } else { 0.0 };
+            }
+            if let Some(sigma) = options.smearing {
+                smear(&mut histogram, sigma);
+            }
+            pairs.push(((labels[a].clone(), labels[b].clone()), histogram));
+        }
+    }
+    PartialRdfs { pairs }
+}
+
+/// Smooths the counts of a histogram with a Gaussian of standard deviation `sigma`,
+/// renormalized at the ends so a constant stays constant.
+fn smear(histogram: &mut Histogram, sigma: f64) {
+    let width = histogram.width();
+    let reach = (4.0 * sigma / width).ceil() as isize;
+    let weight = |offset: isize| (-0.5 * (offset as f64 * width / sigma).powi(2)).exp();
+
+    let counts = histogram.counts.clone();
+    for (i, smeared) in histogram.counts.iter_mut().enumerate() {
+        let (mut sum, mut total) = (0.0, 0.0);
+        for offset in -reach..=reach {
+            if let Some(count) = usize::try_from(i as isize + offset).ok().and_then(|j| counts.get(j)) {
+                sum += weight(offset) * count;
+                total += weight(offset);
+            }
+        }
+        *smeared = sum / total;
+    }
+}
+
+/// The velocity autocorrelation function `<v(0)·v(t)>` for lags `t` of 0 up to the number of frames minus one,
+/// averaged over the atoms and all time origins.
+/// Returns `None` if there are no frames, the atom counts differ, or an atom has no velocity.
+pub fn vacf(frames: &[Data]) -> Option<Vec<f64>> {
+    let atoms = frames.first()?.atoms.len();
+    let velocities = frames
+        .iter()
+        .map(|data| {
+            if data.atoms.len() != atoms {
+                return None;
+            }
+            data.atoms.iter().map(|atom| atom.velocity).collect::<Option<Vec<Position>>>()
+        })
+        .collect::<Option<Vec<Vec<Position>>>>()?;
+
+    let n = velocities.len();
+    Some(
+        (0..n)
+            .map(|lag| {
+                let sum: f64 = (0..n - lag)
+                    .map(|t| velocities[t].iter().zip(&velocities[t + lag]).map(|(a, b)| a.dot(*b)).sum::<f64>())
+                    .sum();
+                sum / ((n - lag) * atoms.max(1)) as f64
+            })
+            .collect(),
+    )
+}
+
+/// The vibrational density of states: the cosine transform of a velocity autocorrelation function,
+/// see [`vacf`], tapered with a Hann window. `timestep` is the time between frames.
+/// Returns `(frequency, intensity)` pairs, with frequencies in the inverse unit of `timestep`,
+/// e.g. multiply by 33356.41 to convert from fs⁻¹ to cm⁻¹.
+pub fn vdos(vacf: &[f64], timestep: f64) -> Vec<(f64, f64)> {
+    let n = vacf.len();
+    let window = |t: usize| 0.5 * (1.0 + (std::f64::consts::PI * t as f64 / n as f64).cos());
+    (0..n)
+        .map(|k| {
+            let frequency = k as f64 / (2.0 * n as f64 * timestep);
+            let sum: f64 = (1..n)
+                .map(|t| window(t) * vacf[t] * (2.0 * std::f64::consts::PI * frequency * t as f64 * timestep).cos())
+                .sum();
+            (frequency, timestep * (vacf[0] + 2.0 * sum))
+        })
+        .collect()
+}
+
+/// The distance in Ångström from every atom to its nearest other atom, or `NaN` for a lone atom.
+/// Distances follow the minimum image convention if the frame has a cell.
+pub fn nearest_neighbor_distances(data: &Data) -> Vec<f64> {
+    (0..data.atoms.len())
+        .map(|i| {
+            (0..data.atoms.len())
+                .filter(|&j| j != i)
+                .map(|j| pair_distance(data, i, j))
+                .fold(f64::NAN, f64::min)
+        })
+        .collect()
+}
+
+/// The distances from atoms of one element to their nearest atom of another, or the same, element
+/// across a trajectory, see [`nearest_neighbor_stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeighborStats {
+    /// The element of the atoms the distances are measured from.
+    pub center: String,
+    /// The element of the nearest atoms.
+    pub neighbor: String,
+    /// The number of distances, one for every atom of `center` in every frame with an atom of `neighbor`.
+    pub count: usize,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+    /// The distribution of the distances.
+    pub histogram: Histogram,
+}
+
+/// Statistics of the first-shell distances between elements across a trajectory: for every pair of elements
+/// `(center, neighbor)`, the distances from each atom of `center` to its nearest atom of `neighbor`,
+/// with their distribution in `bins` bins up to `r_max` Ångström.
+/// A minimum far below a bond length flags atoms that collapsed onto each other, and a growing mean
+/// a melted or exploded structure. Atoms that are not known elements are grouped by their atomic symbol,
+/// and distances follow the minimum image convention in frames with a cell.
+pub fn nearest_neighbor_stats(frames: &[Data], r_max: f64, bins: usize) -> Vec<NeighborStats> {
+    let label = |atom: &AtomData| atom.element().map_or(atom.symbol.clone(), |e| e.symbol().to_string());
+    let mut labels: Vec<String> = frames.iter().flat_map(|data| data.atoms.iter().map(label)).collect();
+    labels.sort();
+    labels.dedup();
+
+    let n = labels.len();
+    let mut stats: Vec<NeighborStats> = (0..n * n)
+        .map(|k| NeighborStats {
+            center: labels[k / n].clone(),
+            neighbor: labels[k % n].clone(),
+            count: 0,
+            min: f64::NAN,
+            mean: 0.0,
+            max: f64::NAN,
+            histogram: Histogram::new(0.0, r_max, bins),
+        })
+        .collect();
+    for data in frames {
+        let species: Vec<usize> = data.atoms.iter().map(|atom| labels.binary_search(&label(atom)).unwrap()).collect();
+        for i in 0..data.atoms.len() {
+            let mut nearest = vec![f64::NAN; n];
+            for j in (0..data.atoms.len()).filter(|&j| j != i) {
+                nearest[species[j]] = nearest[species[j]].min(pair_distance(data, i, j));
+            }
+            for (neighbor, distance) in nearest.into_iter().enumerate().filter(|(_, d)| !d.is_nan()) {
+                let s = &mut stats[species[i] * n + neighbor];
+                s.count += 1;
+                s.min = s.min.min(distance);
+                s.max = s.max.max(distance);
+                s.mean += distance;
+                s.histogram.add(distance);
+            }
+        }
+    }
+
+    stats.retain(|s| s.count > 0);
+    stats.iter_mut().for_each(|s| s.mean /= s.count as f64);
+    stats
+}
+
+/// The spatial clusters of the atoms of a frame, see [`cluster`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Clusters {
+    /// The cluster of every atom, or `None` for noise atoms that belong to no cluster.
+    pub labels: Vec<Option<usize>>,
+    /// The number of atoms in every cluster.
+    pub sizes: Vec<usize>,
+    /// The mean position of the atoms of every cluster, unwrapped across the cell boundaries in frames with a cell.
+    pub centroids: Vec<Position>,
+}
+
+/// Groups the atoms of a frame into clusters with DBSCAN, e.g. to follow the aggregation of nanoparticles.
+/// Atoms with at least `min_pts` atoms, including themselves, within `eps` Ångström are core atoms,
+/// and clusters are the core atoms connected through such neighbours together with the atoms they reach.
+/// Clusters are numbered in the order of their first atom. Distances follow the minimum image convention
+/// if the frame has a cell. The run time grows quadratically with the number of atoms.
+pub fn cluster(data: &Data, eps: f64, min_pts: usize) -> Clusters {
+    let n = data.atoms.len();
+    let neighbors: Vec<Vec<usize>> =
+        (0..n).map(|i| (0..n).filter(|&j| j == i || pair_distance(data, i, j) <= eps).collect()).collect();
+    let is_core = |i: usize| neighbors[i].len() >= min_pts;
+
+    let mut clusters = Clusters {
+        labels: vec![None; n],
+        ..Clusters::default()
+    };
+    for seed in 0..n {
+        if clusters.labels[seed].is_some() || !is_core(seed) {
+            continue;
+        }
+        let label = clusters.sizes.len();
+        clusters.labels[seed] = Some(label);
+        let (mut members, mut stack) = (vec![seed], vec![seed]);
+        while let Some(i) = stack.pop() {
+            for &j in &neighbors[i] {
+                if clusters.labels[j].is_none() {
+                    clusters.labels[j] = Some(label);
+                    members.push(j);
+                    if is_core(j) {
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+
+        let origin = data.atoms[seed].position;
+        let offset = |j: usize| {
+            let d = data.atoms[j].position - origin;
+            data.cell.map_or(d, |cell| cell.cartesian(cell.fractional(d).map(|f| f - f.round())))
+        };
+        let centroid = origin + members.iter().map(|&j| offset(j)).sum::<Position>() / members.len() as f64;
+        clusters.sizes.push(members.len());
+        clusters.centroids.push(centroid);
+    }
+    clusters
+}
+
+/// The solvent-accessible surface area of a frame, see [`sasa`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sasa {
+    /// The accessible area of every atom in Å².
+    pub atoms: Vec<f64>,
+    /// The accessible area of all atoms in Å².
+    pub total: f64,
+}
+
+/// The solvent-accessible surface area of the atoms of a frame by the Shrake–Rupley method:
+/// every atom is covered with `points` points evenly spread on a sphere of its van der Waals radius
+/// (see [`Element::vdw_radius`](crate::element::Element::vdw_radius)) plus `probe_radius`, typically 1.4 Å
+/// for water, and the area of the sphere is scaled by the fraction of points not inside the sphere of another atom.
+/// A few hundred points give areas within about 1 %. Atoms that are not known elements get a radius of 2 Å,
+/// and distances follow the minimum image convention if the frame has a cell.
+pub fn sasa(data: &Data, probe_radius: f64, points: usize) -> Sasa {
+    let radii: Vec<f64> =
+        data.atoms.iter().map(|atom| atom.element().map_or(2.0, |e| e.vdw_radius()) + probe_radius).collect();
+    // Points on a Fibonacci spiral cover the unit sphere almost evenly.
+    let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+    let sphere: Vec<Position> = (0..points)
+        .map(|k| {
+            let z = 1.0 - (2 * k + 1) as f64 / points as f64;
+            let r = (1.0 - z * z).sqrt();
+            let phi = k as f64 * golden_angle;
+            Position::new(r * phi.cos(), r * phi.sin(), z)
+        })
+        .collect();
+
+    let offset = |i: usize, j: usize| {
+        let d = data.atoms[j].position - data.atoms[i].position;
+        data.cell.map_or(d, |cell| cell.cartesian(cell.fractional(d).map(|f| f - f.round())))
+    };
+    let atoms: Vec<f64> = (0..data.atoms.len())
+        .map(|i| {
+            let neighbors: Vec<(Position, f64)> = (0..data.atoms.len())
+                .filter(|&j| j != i)
+                .map(|j| (offset(i, j), radii[j]))
+                .filter(|(d, r)| d.norm() < radii[i] + r)
+                .collect();
+            let accessible = sphere
+                .iter()
+                .filter(|&&p| neighbors.iter().all(|&(d, r)| (p * radii[i]).distance(d) >= r))
+                .count();
+            4.0 * std::f64::consts::PI * radii[i] * radii[i] * accessible as f64 / points.max(1) as f64
+        })
+        .collect();
+    Sasa {
+        total: atoms.iter().sum(),
+        atoms,
+    }
+}
+
+/// The principal components of the fluctuations of atoms across a trajectory, see [`pca`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pca {
+    /// The indices of the analyzed atoms.
+    pub atoms: Vec<usize>,
+    /// The mean positions of the analyzed atoms after alignment.
+    pub mean: Vec<Position>,
+    /// The variance along every component in Å², in descending order.
+    pub eigenvalues: Vec<f64>,
+    /// The unit vector of every component, with the x, y and z displacements of every analyzed atom in turn.
+    pub eigenvectors: Vec<Vec<f64>>,
+    /// The coordinates of every frame along every component in Å, relative to the mean structure.
+    pub projections: Vec<Vec<f64>>,
+}
+impl Pca {
+    /// The fraction of the total variance along every component.
+    pub fn variance_ratios(&self) -> Vec<f64> {
+        let total: f64 = self.eigenvalues.iter().sum();
+        self.eigenvalues.iter().map(|&v| if total > 0.0 { v / total } else { 0.0 }).collect()
+    }
+}
+
+/// Principal component analysis of the positions of the atoms at indices `selection`, or of all atoms,
+/// for essential dynamics: every frame is superimposed onto the first by the selected atoms, and the covariance
+/// matrix of their coordinates is diagonalized. The run time grows with the cube of the number of selected atoms,
+/// so select e.g. the backbone of larger molecules.
+/// Returns `None` for fewer than two frames, frames with different atom counts, no atoms or an index out of range.
+pub fn pca(frames: &[Data], selection: Option<&[usize]>) -> Option<Pca> {
+    let (aligned, atoms) = aligned_positions(frames, selection)?;
+    let n = 3 * atoms.len();
+    let mean = mean_positions(&aligned);
+    let deviations: Vec<Vec<f64>> = aligned
+        .iter()
+        .map(|positions| positions.iter().zip(&mean).flat_map(|(&p, &m)| (p - m).0).collect())
+        .collect();
+
+    let mut covariance = vec![vec![0.0; n]; n];
+    for d in &deviations {
+        for (row, di) in covariance.iter_mut().zip(d) {
+            row.iter_mut().zip(d).for_each(|(c, dj)| *c += di * dj / frames.len() as f64);
+        }
+    }
+
+    let (values, vectors) = math::symmetric_eigen(covariance);
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+    let eigenvectors: Vec<Vec<f64>> = order.iter().map(|&k| vectors.iter().map(|row| row[k]).collect()).collect();
+    let projections = deviations
+        .iter()
+        .map(|d| eigenvectors.iter().map(|v| v.iter().zip(d).map(|(a, b)| a * b).sum()).collect())
+        .collect();
+    Some(Pca {
+        atoms,
+        mean,
+        eigenvalues: order.iter().map(|&k| values[k].max(0.0)).collect(),
+        eigenvectors,
+        projections,
+    })
+}
+
+/// The root mean square fluctuation in Å of every atom at indices `selection`, or of every atom, around its mean
+/// position across a trajectory, after superimposing every frame onto the first by the selected atoms.
+/// Use [`write_atom_values`] to color the atoms by their fluctuation in a viewer.
+/// Returns `None` for fewer than two frames, frames with different atom counts, no atoms or an index out of range.
+pub fn rmsf(frames: &[Data], selection: Option<&[usize]>) -> Option<Vec<f64>> {
+    let (aligned, _) = aligned_positions(frames, selection)?;
+    Some(mean_square_fluctuations(&aligned).into_iter().map(f64::sqrt).collect())
+}
+
+/// Writes a frame as extended XYZ with a value for every atom in the extra column `name:R:1`,
+/// e.g. the [`rmsf`] of every atom to color them in OVITO or VMD. Atoms without a value can be set to `NaN`.
+/// The `key=value` properties of the comment are kept. Fails if there is not one value per atom.
+pub fn write_atom_values<W: Write + ?Sized>(writer: &mut W, data: &Data, name: &str, values: &[f64]) -> io::Result<()> {
+    if values.len() != data.atoms.len() {
+        let message = format!("expected {} values, one per atom, but found {}", data.atoms.len(), values.len());
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+    let comment = extended_comment(data, &format!("species:S:1:pos:R:3:{}:R:1", name));
+
+    let mut buf = format!("{}\n{}\n", data.atoms.len(), comment);
+    for (atom, &value) in data.atoms.iter().zip(values) {
+        buf.push_str(&atom.symbol);
+        for v in atom.position.0.into_iter().chain([value]) {
+            buf.push(' ');
+            format_number(&mut buf, v, None);
+        }
+        buf.push('\n');
+    }
+    writer.write_all(buf.as_bytes())
+}
+
+/// The motion of a group of atoms across a trajectory, see [`group_stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupStats {
+    /// The group label of the atoms, e.g. a residue name.
+    pub group: String,
+    /// The indices of the atoms of the group.
+    pub atoms: Vec<usize>,
+    /// The geometric center of the group in every frame, in the original coordinates.
+    pub centroids: Vec<Position>,
+    /// The RMSD of the group from the first frame in every frame, in Å.
+    pub rmsd: Vec<f64>,
+    /// The root mean square fluctuation of the atoms of the group around their mean positions, in Å.
+    pub rmsf: f64,
+}
+
+/// The centroids, RMSD and RMSF of every group of atoms across a trajectory, where groups are the atoms
+/// with the same [`group`](AtomData::group) label, e.g. residues, in the order they first appear.
+/// The RMSD and RMSF are computed after superimposing every frame onto the first by all atoms,
+/// which is how the flexibility of biomolecules is typically reported. Atoms without a group are left out.
+/// Returns `None` for fewer than two frames, frames with different atom counts or no atoms.
+pub fn group_stats(frames: &[Data]) -> Option<Vec<GroupStats>> {
+    let (aligned, _) = aligned_positions(frames, None)?;
+    let fluctuations = mean_square_fluctuations(&aligned);
+
+    let mut groups: Vec<(String, Vec<usize>)> = vec![];
+    for (i, atom) in frames[0].atoms.iter().enumerate() {
+        let Some(group) = &atom.group else { continue };
+        match groups.iter_mut().find(|(name, _)| name == group) {
+            Some((_, atoms)) => atoms.push(i),
+            None => groups.push((group.clone(), vec![i])),
+        }
+    }
+
+    let stats = groups
+        .into_iter()
+        .map(|(group, atoms)| {
+            let n = atoms.len() as f64;
+            let centroids = frames
+                .iter()
+                .map(|data| atoms.iter().map(|&i| data.atoms[i].position).sum::<Position>() / n)
+                .collect();
+            let rmsd = aligned
+                .iter()
+                .map(|positions| {
+                    let deviation = |i: usize| positions[i] - aligned[0][i];
+                    (atoms.iter().map(|&i| deviation(i).dot(deviation(i))).sum::<f64>() / n).sqrt()
+                })
+                .collect();
+            let rmsf = (atoms.iter().map(|&i| fluctuations[i]).sum::<f64>() / n).sqrt();
+            GroupStats {
+                group,
+                atoms,
+                centroids,
+                rmsd,
+                rmsf,
+            }
+        })
+        .collect();
+    Some(stats)
+}
+
+/// The RMSF of every group of atoms across a trajectory in Å, see [`group_stats`].
+pub fn rmsf_by_group(frames: &[Data]) -> Option<Vec<(String, f64)>> {
+    Some(group_stats(frames)?.into_iter().map(|s| (s.group, s.rmsf)).collect())
+}
+
+/// The backbone of a polymer chain: the shortest path of bonded atoms (see [`Data::bonds`]) other than hydrogen
+/// between the two such atoms farthest apart in the bond graph, from the end with the lower index.
+/// Side chains are left out of branched molecules, and rings are crossed along their shorter side.
+/// Returns an empty chain if there are no heavy atoms.
+pub fn backbone(data: &Data) -> Vec<usize> {
+    let heavy = |i: usize| data.atoms[i].element().is_some_and(|e| e.atomic_number() > 1);
+    let mut neighbours = vec![vec![]; data.atoms.len()];
+    for (i, j) in data.bonds().into_iter().filter(|&(i, j)| heavy(i) && heavy(j)) {
+        neighbours[i].push(j);
+        neighbours[j].push(i);
+    }
+    // The farthest atom from any atom is an end of a longest path, for trees.
+    let farthest = |start: usize| {
+        let mut previous = vec![None; data.atoms.len()];
+        let (mut queue, mut last) = (std::collections::VecDeque::from([start]), start);
+        previous[start] = Some(start);
+        while let Some(i) = queue.pop_front() {
+            last = i;
+            for &j in &neighbours[i] {
+                if previous[j].is_none() {
+                    previous[j] = Some(i);
+                    queue.push_back(j);
+                }
+            }
+        }
+        let mut path = vec![last];
+        while let Some(&i) = path.last().filter(|&&i| i != start) {
+            path.push(previous[i].unwrap());
+        }
+        path
+    };
+
+    let Some(start) = (0..data.atoms.len()).find(|&i| heavy(i)) else {
+        return vec![];
+    };
+    let mut chain = farthest(farthest(start)[0]);
+    if chain.first() > chain.last() {
+        chain.reverse();
+    }
+    chain
+}
+
+/// The end-to-end distance in Å of a chain of atoms, given by their indices in order, in every frame.
+/// The distance is the length of the sum of the bond vectors along the chain, which follow the minimum image
+/// convention in frames with a cell, so chains wrapped across the cell boundaries are measured unwrapped.
+/// Frames without an atom of the chain get `NaN`.
+pub fn end_to_end(frames: &[Data], chain: &[usize]) -> Vec<f64> {
+    frames
+        .iter()
+        .map(|data| match bond_vectors(data, chain) {
+            Some(bonds) => bonds.into_iter().sum::<Position>().norm(),
+            None => f64::NAN,
+        })
+        .collect()
+}
+
+/// The persistence length in Å of a chain of atoms, given by their indices in order, from the decay of the
+/// correlation `<u_i · u_i+k> = exp(-k l / L_p)` between the unit bond vectors `k` bonds apart, averaged over
+/// all frames, where `l` is the mean bond length. The correlations are fitted while they stay above `exp(-2)`.
+/// Returns `None` if the chain has fewer than three atoms, an index is out of range, or the bond directions
+/// are uncorrelated.
+pub fn persistence_length(frames: &[Data], chain: &[usize]) -> Option<f64> {
+    let mut correlations = vec![0.0; chain.len().saturating_sub(1)];
+    let mut counts = vec![0usize; correlations.len()];
+    let (mut length, mut bonds) = (0.0, 0);
+    for data in frames {
+        let vectors = bond_vectors(data, chain)?;
+        length += vectors.iter().map(Position::norm).sum::<f64>();
+        bonds += vectors.len();
+        let units: Vec<Position> = vectors.iter().filter_map(Position::normalized).collect();
+        for i in 0..units.len() {
+            for k in 0..units.len() - i {
+                correlations[k] += units[i].dot(units[i + k]);
+                counts[k] += 1;
+            }
+        }
+    }
+    if chain.len() < 3 || bonds == 0 {
+        return None;
+    }
+
+    let length = length / bonds as f64;
+    let (mut sxx, mut sxy) = (0.0, 0.0);
+    for k in 1..correlations.len() {
+        let correlation = correlations[k] / counts[k].max(1) as f64;
+        if counts[k] == 0 || correlation <= (-2.0f64).exp() {
+            break;
+        }
+        let s = k as f64 * length;
+        sxx += s * s;
+        sxy += s * correlation.ln();
+    }
+    (sxy < 0.0).then(|| -sxx / sxy)
+}
+
+/// The vectors between consecutive atoms of a chain, under the minimum image convention if the frame has a cell,
+/// or `None` if an index is out of range.
+fn bond_vectors(data: &Data, chain: &[usize]) -> Option<Vec<Position>> {
+    if chain.iter().any(|&i| i >= data.atoms.len()) {
+        return None;
+    }
+    let vectors = chain
+        .windows(2)
+        .map(|pair| {
+            let d = data.atoms[pair[1]].position - data.atoms[pair[0]].position;
+            data.cell.map_or(d, |cell| cell.cartesian(cell.fractional(d).map(|f| f - f.round())))
+        })
+        .collect();
+    Some(vectors)
+}
+
+/// The distance between two atoms of a frame, under the minimum image convention if it has a cell.
+fn pair_distance(data: &Data, i: usize, j: usize) -> f64 {
+    let (a, b) = (data.atoms[i].position, data.atoms[j].position);
+    match &data.cell {
+        Some(cell) => minimum_image_distance(cell, a, b),
+        None => a.distance(b),
+    }
+}
+
+/// The volume in Å³ of the Voronoi cell of every atom under the periodic boundary conditions of `cell`:
+/// the region closer to the atom than to any other atom or periodic image, e.g. to find the free volume
+/// around atoms in amorphous systems. The volumes add up to the volume of the cell.
+/// Atoms at the same position as another atom split their shared cell evenly.
+/// The run time grows quadratically with the number of atoms. Returns `None` if the cell is degenerate.
+pub fn voronoi_volumes(data: &Data, cell: &Cell) -> Option<Vec<f64>> {
+    let volume = cell.volume();
+    if volume.is_nan() || volume <= 0.0 || data.atoms.iter().any(|atom| !atom.position.is_finite()) {
+        return None;
+    }
+    let vectors = cell.vectors.map(Position);
+    // The distance between opposite faces of the cell along each lattice vector.
+    let widths = [0, 1, 2].map(|k| volume / vectors[(k + 1) % 3].cross(vectors[(k + 2) % 3]).norm());
+    let extent: f64 = vectors.iter().map(Position::norm).sum();
+    let spacing = (volume / data.atoms.len().max(1) as f64).cbrt();
+    let fractional: Vec<[f64; 3]> = data.atoms.iter().map(|atom| cell.fractional(atom.position)).collect();
+
+    // The displacements to the other atoms and the periodic images within `cutoff`, nearest first.
+    let neighbours = |i: usize, cutoff: f64| {
+        let images = widths.map(|w| (cutoff / w).ceil() as i64 + 1);
+        let mut found = vec![];
+        for f in &fractional {
+            let base: [f64; 3] = std::array::from_fn(|k| (f[k] - fractional[i][k]) - (f[k] - fractional[i][k]).round());
+            for a in -images[0]..=images[0] {
+                for b in -images[1]..=images[1] {
+                    for c in -images[2]..=images[2] {
+                        let d = cell.cartesian([base[0] + a as f64, base[1] + b as f64, base[2] + c as f64]);
+                        let distance = d.norm();
+                        if distance <= cutoff && distance > 1e-9 * spacing {
+                            found.push((distance, d));
+                        }
+                    }
+                }
+            }
+        }
+        found.sort_by(|a, b| a.0.total_cmp(&b.0));
+        found
+    };
+
+    let mut volumes: Vec<f64> = (0..data.atoms.len())
+        .map(|i| {
+            let mut cutoff = 3.0 * spacing;
+            loop {
+                let mut polyhedron = Polyhedron::cube(extent);
+                for (_, d) in neighbours(i, cutoff) {
+                    polyhedron.cut(d);
+                }
+                // Atoms farther than twice the largest vertex distance cannot cut the cell.
+                let radius = polyhedron.radius();
+                if 2.0 * radius <= cutoff {
+                    return polyhedron.volume();
+                }
+                cutoff = 2.0 * radius * 1.001;
+            }
+        })
+        .collect();
+
+    // Coincident atoms share one cell.
+    for i in 0..data.atoms.len() {
+        let same: Vec<usize> = (0..data.atoms.len())
+            .filter(|&j| minimum_image_distance(cell, data.atoms[i].position, data.atoms[j].position) <= 1e-9 * spacing)
+            .collect();
+        if same.len() > 1 && same[0] == i {
+            let shared = volumes[i] / same.len() as f64;
+            same.iter().for_each(|&j| volumes[j] = shared);
+        }
+    }
+    Some(volumes)
+}
+
+/// The distance between two positions under the minimum image convention of a cell.
+pub(crate) fn minimum_image_distance(cell: &Cell, a: Position, b: Position) -> f64 {
+    let fractional = cell.fractional(b - a).map(|f| f - f.round());
+    cell.cartesian(fractional).norm()
+}
+
+/// The positions of the atoms at indices `selection`, or of all atoms, in every frame superimposed onto the first,
+/// with the selected indices. Returns `None` for fewer than two frames, frames with different atom counts,
+/// no atoms or an index out of range.
+fn aligned_positions(frames: &[Data], selection: Option<&[usize]>) -> Option<(Vec<Vec<Position>>, Vec<usize>)> {
+    let count = frames.first()?.atoms.len();
+    if frames.len() < 2 || frames.iter().any(|data| data.atoms.len() != count) {
+        return None;
+    }
+    let atoms = selection.map_or_else(|| (0..count).collect(), <[usize]>::to_vec);
+    if atoms.is_empty() || atoms.iter().any(|&i| i >= count) {
+        return None;
+    }
+    let positions = |data: &Data| atoms.iter().map(|&i| data.atoms[i].position).collect::<Vec<Position>>();
+    let reference = positions(&frames[0]);
+    let aligned = frames.iter().map(|data| superimposed(&positions(data), &reference)).collect();
+    Some((aligned, atoms))
+}
+
+/// The mean of every position across frames.
+fn mean_positions(frames: &[Vec<Position>]) -> Vec<Position> {
+    let mut mean = vec![Position::default(); frames[0].len()];
+    for positions in frames {
+        mean.iter_mut().zip(positions).for_each(|(m, &p)| *m += p / frames.len() as f64);
+    }
+    mean
+}
+
+/// The mean square distance of every position from its mean across frames.
+fn mean_square_fluctuations(frames: &[Vec<Position>]) -> Vec<f64> {
+    let mean = mean_positions(frames);
+    let mut fluctuations = vec![0.0; mean.len()];
+    for positions in frames {
+        for ((f, &p), &m) in fluctuations.iter_mut().zip(positions).zip(&mean) {
+            *f += (p - m).dot(p - m) / frames.len() as f64;
+        }
+    }
+    fluctuations
+}
+
+/// The positions optimally translated and rotated onto `reference`, with the quaternion method of Horn (1987).
+fn superimposed(positions: &[Position], reference: &[Position]) -> Vec<Position> {
+    let n = positions.len() as f64;
+    let ca = positions.iter().copied().sum::<Position>() / n;
+    let cb = reference.iter().copied().sum::<Position>() / n;
+    let mut s = [[0.0; 3]; 3];
+    for (&a, &b) in positions.iter().zip(reference) {
+        let (x, y) = (a - ca, b - cb);
+        for i in 0..3 {
+            for j in 0..3 {
+                s[i][j] += x[i] * y[j];
+            }
+        }
+    }
+
+    let [[xx, xy, xz], [yx, yy, yz], [zx, zy, zz]] = s;
+    let k = vec![
+        vec![xx + yy + zz, yz - zy, zx - xz, xy - yx],
+        vec![yz - zy, xx - yy - zz, xy + yx, zx + xz],
+        vec![zx - xz, xy + yx, -xx + yy - zz, yz + zy],
+        vec![xy - yx, zx + xz, yz + zy, -xx - yy + zz],
+    ];
+    let (values, vectors) = math::symmetric_eigen(k);
+    let largest = (0..4).max_by(|&a, &b| values[a].total_cmp(&values[b])).unwrap();
+    let [q0, q1, q2, q3] = [0, 1, 2, 3].map(|i| vectors[i][largest]);
+    let rotation = [
+        [q0 * q0 + q1 * q1 - q2 * q2 - q3 * q3, 2.0 * (q1 * q2 - q0 * q3), 2.0 * (q1 * q3 + q0 * q2)],
+        [2.0 * (q1 * q2 + q0 * q3), q0 * q0 - q1 * q1 + q2 * q2 - q3 * q3, 2.0 * (q2 * q3 - q0 * q1)],
+        [2.0 * (q1 * q3 - q0 * q2), 2.0 * (q2 * q3 + q0 * q1), q0 * q0 - q1 * q1 - q2 * q2 + q3 * q3],
+    ];
+    positions
+        .iter()
+        .map(|&p| {
+            let x = p - ca;
+            cb + Position(rotation.map(|row| row[0] * x[0] + row[1] * x[1] + row[2] * x[2]))
+        })
+        .collect()
+}
+
+/// Whether an atom is of the element or has the atomic symbol `symbol`, ignoring case.
+fn is(atom: &AtomData, symbol: &str) -> bool {
+    match atom.element() {
+        Some(element) => element.symbol().eq_ignore_ascii_case(symbol),
+        None => atom.symbol.eq_ignore_ascii_case(symbol),
+    }
+}
+
+/// The mass-weighted gyration tensor of a frame and its principal moments, see [`Data::gyration_tensor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GyrationTensor {
+    /// The tensor in Å², the mass-weighted mean outer product of the positions relative to the center of mass.
+    pub tensor: [[f64; 3]; 3],
+    /// The principal moments in Å², in ascending order.
+    pub moments: [f64; 3],
+}
+impl GyrationTensor {
+    /// The radius of gyration in Ångström, the square root of the sum of the principal moments.
+    pub fn radius_of_gyration(&self) -> f64 {
+        self.moments.iter().sum::<f64>().sqrt()
+    }
+
+    /// The asphericity in Å², the largest moment minus the mean of the others, which is zero for spherical symmetry.
+    pub fn asphericity(&self) -> f64 {
+        let [x, y, z] = self.moments;
+        z - (x + y) / 2.0
+    }
+
+    /// The acylindricity in Å², the difference of the two smaller moments, which is zero for cylindrical symmetry.
+    pub fn acylindricity(&self) -> f64 {
+        let [x, y, _] = self.moments;
+        y - x
+    }
+
+    /// The relative shape anisotropy κ², from 0 for spherical symmetry to 1 for atoms on a line.
+    pub fn relative_shape_anisotropy(&self) -> f64 {
+        let (b, c) = (self.asphericity(), self.acylindricity());
+        let rg2: f64 = self.moments.iter().sum();
+        if rg2 > 0.0 {
+            (b * b + 0.75 * c * c) / (rg2 * rg2)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The unit of atom velocities, see [`Data::kinetic_energy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VelocityUnit {
+    /// Å/fs.
+    #[default]
+    AngstromPerFemtosecond,
+    /// Å/ps.
+    AngstromPerPicosecond,
+    /// The ASE unit √(eV/Da), as in extended XYZ files written by ASE.
+    Ase,
+}
+impl VelocityUnit {
+    /// The kinetic energy in eV of 1 Da moving at 1 unit of velocity, times two.
+    fn energy(&self) -> f64 {
+        match self {
+            VelocityUnit::AngstromPerFemtosecond => 103.642_696_5,
+            VelocityUnit::AngstromPerPicosecond => 103.642_696_5e-6,
+            VelocityUnit::Ase => 1.0,
+        }
+    }
+}
+
+/// The Boltzmann constant in eV/K.
+const BOLTZMANN: f64 = 8.617_333_262e-5;
+
+impl Data {
+    /// The center of mass of the atoms, or `None` if there are no atoms or an atom is not a known element.
+    pub fn center_of_mass(&self) -> Option<Position> {
+        let mass = self.mass().filter(|&m| m > 0.0)?;
+        let weighted: Position = self.atoms.iter().map(|atom| atom.position * atom.element().unwrap().mass()).sum();
+        Some(weighted / mass)
+    }
+
+    /// The mass-weighted radius of gyration in Ångström,
+    /// or `None` if there are no atoms or an atom is not a known element.
+    pub fn radius_of_gyration(&self) -> Option<f64> {
+        let center = self.center_of_mass()?;
+        let moment: f64 = self
+            .atoms
+            .iter()
+            .map(|atom| atom.element().unwrap().mass() * (atom.position - center).dot(atom.position - center))
+            .sum();
+        Some((moment / self.mass()?).sqrt())
+    }
+
+    /// The mass-weighted gyration tensor with the shape descriptors of polymer physics,
+    /// or `None` if there are no atoms or an atom is not a known element.
+    pub fn gyration_tensor(&self) -> Option<GyrationTensor> {
+        let center = self.center_of_mass()?;
+        let mass = self.mass()?;
+        let mut tensor = [[0.0; 3]; 3];
+        for atom in &self.atoms {
+            let (m, d) = (atom.element().unwrap().mass() / mass, atom.position - center);
+            for i in 0..3 {
+                for j in 0..3 {
+                    tensor[i][j] += m * d[i] * d[j];
+                }
+            }
+        }
+        let mut moments = math::symmetric_eigenvalues(tensor.iter().map(|row| row.to_vec()).collect());
+        moments.sort_by(f64::total_cmp);
+        Some(GyrationTensor {
+            tensor,
+            moments: [0, 1, 2].map(|i| moments[i].max(0.0)),
+        })
+    }
+
+    /// The root mean square deviation of the atom positions from those of `reference`, atom by atom,
+    /// without superimposing the frames. Returns `None` if the atom counts differ or there are no atoms.
+    pub fn rmsd(&self, reference: &Data) -> Option<f64> {
+        if self.atoms.len() != reference.atoms.len() || self.atoms.is_empty() {
+            return None;
+        }
+        let sum: f64 = self
+            .atoms
+            .iter()
+            .zip(&reference.atoms)
+            .map(|(a, b)| (a.position - b.position).dot(a.position - b.position))
+            .sum();
+        Some((sum / self.atoms.len() as f64).sqrt())
+    }
+
+    /// The root mean square deviation from `reference` after optimally translating and rotating this frame onto it,
+    /// using the quaternion method of Horn (1987). Returns `None` if the atom counts differ or there are no atoms.
+    pub fn aligned_rmsd(&self, reference: &Data) -> Option<f64> {
+        if self.atoms.len() != reference.atoms.len() || self.atoms.is_empty() {
+            return None;
+        }
+        let n = self.atoms.len() as f64;
+        let center = |data: &Data| data.atoms.iter().map(|atom| atom.position).sum::<Position>() / n;
+        let (ca, cb) = (center(self), center(reference));
+
+        let mut s = [[0.0; 3]; 3];
+        let mut e0 = 0.0;
+        for (a, b) in self.atoms.iter().zip(&reference.atoms) {
+            let (x, y) = (a.position - ca, b.position - cb);
+            e0 += x.dot(x) + y.dot(y);
+            for i in 0..3 {
+                for j in 0..3 {
+                    s[i][j] += x[i] * y[j];
+                }
+            }
+        }
+
+        let [[xx, xy, xz], [yx, yy, yz], [zx, zy, zz]] = s;
+        let k = vec![
+            vec![xx + yy + zz, yz - zy, zx - xz, xy - yx],
+            vec![yz - zy, xx - yy - zz, xy + yx, zx + xz],
+            vec![zx - xz, xy + yx, -xx + yy - zz, yz + zy],
+            vec![xy - yx, zx + xz, yz + zy, -xx - yy + zz],
+        ];
+        let largest = math::symmetric_eigenvalues(k).into_iter().fold(f64::NEG_INFINITY, f64::max);
+        Some(((e0 - 2.0 * largest) / n).max(0.0).sqrt())
+    }
+
+    /// The kinetic energy of the atoms in eV, from their velocities in `unit` and the element masses.
+    /// Returns `None` if an atom has no velocity or is not a known element.
+    pub fn kinetic_energy(&self, unit: VelocityUnit) -> Option<f64> {
+        let sum: f64 = self
+            .atoms
+            .iter()
+            .map(|atom| Some(atom.element()?.mass() * atom.velocity?.dot(atom.velocity?)))
+            .sum::<Option<f64>>()?;
+        Some(0.5 * sum * unit.energy())
+    }
+
+    /// The instantaneous temperature in K, from the kinetic energy and 3 degrees of freedom per atom.
+    /// Returns `None` if there are no atoms, an atom has no velocity or is not a known element.
+    pub fn temperature(&self, unit: VelocityUnit) -> Option<f64> {
+        if self.atoms.is_empty() {
+            return None;
+        }
+        Some(2.0 * self.kinetic_energy(unit)? / (3.0 * self.atoms.len() as f64 * BOLTZMANN))
+    }
+}
+
+impl File {
+    /// The temperature of every frame as a series named `temperature`, see [`Data::temperature`].
+    pub fn temperature_series(&self, unit: VelocityUnit) -> Series {
+        Series::new(
+            "temperature",
+            self.data.iter().map(|data| data.temperature(unit).unwrap_or(f64::NAN)).collect(),
+        )
+    }
+
+    /// The shape descriptors of every frame from its gyration tensor (see [`Data::gyration_tensor`]),
+    /// as series named `radius_of_gyration`, `asphericity`, `acylindricity` and `relative_shape_anisotropy`.
+    pub fn shape_series(&self) -> Vec<Series> {
+        let tensors: Vec<Option<GyrationTensor>> = self.data.iter().map(Data::gyration_tensor).collect();
+        let series = |name: &str, f: fn(&GyrationTensor) -> f64| {
+            Series::new(name, tensors.iter().map(|t| t.as_ref().map_or(f64::NAN, f)).collect())
+        };
+        vec![
+            series("radius_of_gyration", GyrationTensor::radius_of_gyration),
+            series("asphericity", GyrationTensor::asphericity),
+            series("acylindricity", GyrationTensor::acylindricity),
+            series("relative_shape_anisotropy", GyrationTensor::relative_shape_anisotropy),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::cell::Cell;
+    use crate::element::Element;
+    use crate::testing::trajectory;
+    use crate::testing::{frame, water};
+
+    #[test]
+    fn csv_and_tsv_tables() {
+        let series = [Series::new("rmsd", vec![0.0, f64::NAN, 0.25]), Series::new("a,\"b\"", vec![1.5])];
+        let mut csv = vec![];
+        to_csv(&mut csv, &series).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "frame,rmsd,\"a,\"\"b\"\"\"\n0,0,1.5\n1,,\n2,0.25,\n");
+        let mut tsv = vec![];
+        to_tsv(&mut tsv, &series[..1]).unwrap();
+        assert_eq!(String::from_utf8(tsv).unwrap(), "frame\trmsd\n0\t0\n1\t\n2\t0.25\n");
+    }
+
+    #[test]
+    fn center_of_mass_and_radius_of_gyration() {
+        let hydrogen = frame("", &[("H", [0.0; 3]), ("H", [2.0, 0.0, 0.0])]);
+        assert_eq!(hydrogen.center_of_mass(), Some(Position::new(1.0, 0.0, 0.0)));
+        assert_eq!(hydrogen.radius_of_gyration(), Some(1.0));
+        assert_eq!(frame("", &[]).center_of_mass(), None);
+        assert_eq!(frame("", &[("Xx", [0.0; 3])]).radius_of_gyration(), None);
+    }
+
+    #[test]
+    fn rmsd_with_and_without_alignment() {
+        let reference = water();
+        let mut moved = water();
+        for atom in &mut moved.atoms {
+            let [x, y, z] = atom.position.0;
+            atom.position = Position::new(1.0 - y, 2.0 + x, 3.0 + z);
+        }
+        assert!(moved.rmsd(&reference).unwrap() > 1.0);
+        assert!(moved.aligned_rmsd(&reference).unwrap() < 1e-6);
+
+        let mut shifted = water();
+        shifted.atoms[0].position[0] += 0.3;
+        assert!((shifted.rmsd(&reference).unwrap() - 0.3 / 3f64.sqrt()).abs() < 1e-12);
+        assert_eq!(shifted.rmsd(&frame("", &[])), None);
+        assert_eq!(shifted.aligned_rmsd(&frame("", &[])), None);
+    }
+
+    #[test]
+    fn histogram_bins_and_density() {
+        let mut histogram = Histogram::with_width(0.0, 1.0, 0.24);
+        assert_eq!((histogram.bins(), histogram.width()), (4, 0.25));
+        assert_eq!(histogram.centers(), [0.125, 0.375, 0.625, 0.875]);
+        histogram.fill([0.0, 0.1, 0.3, 0.99, 1.0, -0.1]);
+        histogram.add_weighted(0.5, 2.0);
+        assert_eq!(histogram.counts, [2.0, 1.0, 2.0, 1.0]);
+        assert_eq!(histogram.density(), [4.0 / 3.0, 2.0 / 3.0, 4.0 / 3.0, 2.0 / 3.0]);
+        let mut csv = vec![];
+        histogram.to_csv(&mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().starts_with("value,count\n0.125,2\n0.375,1\n"));
+        assert_eq!(Histogram::new(0.0, 1.0, 2).density(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn bond_length_and_angle_distributions() {
+        let frames = [water()];
+        let lengths = bond_lengths(&frames, "H", "o");
+        assert_eq!(lengths.len(), 2);
+        assert_eq!(lengths[0], 0.96);
+        let angles = bond_angles(&frames, "H", "O", "H");
+        assert_eq!(angles.len(), 1);
+        assert!((angles[0] - 0.93f64.atan2(-0.24).to_degrees()).abs() < 1e-9);
+        assert_eq!(angle_distribution(&frames, "H", "O", "H").bin(angles[0]), Some(104));
+        assert_eq!(bond_length_distribution(&frames, "O", "H").total(), 2.0);
+        assert!(bond_lengths(&frames, "H", "H").is_empty());
+    }
+
+    #[test]
+    fn rdf_of_a_pair_in_a_periodic_cell() {
+        let mut data = frame("", &[("H", [0.0; 3]), ("H", [1.0, 0.0, 0.0]), ("O", [3.0, 3.0, 3.0])]);
+        data.cell = Some(Cell::new([4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]));
+        let rdf = rdf(&[data, water()], "H", "H", 2.0, 4);
+        // 2 ordered pairs in 64 Å³, at a distance of 1 Å in the shell from 1 to 1.5 Å.
+        let shell = 4.0 / 3.0 * std::f64::consts::PI * (1.5f64.powi(3) - 1.0);
+        assert_eq!(rdf.counts[..2], [0.0, 0.0]);
+        assert!((rdf.counts[2] - 2.0 / (2.0 / 64.0 * shell)).abs() < 1e-9);
+        assert_eq!(rdf.counts[3], 0.0);
+    }
+
+    #[test]
+    fn partial_rdfs_of_all_pairs() {
+        let mut data = frame("", &[("H", [0.0; 3]), ("H", [1.0, 0.0, 0.0]), ("O", [3.0, 3.0, 3.0])]);
+        data.cell = Some(Cell::new([4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]));
+        let frames = [data];
+        let options = RdfOptions {
+            r_max: 2.0,
+            bins: 4,
+            ..RdfOptions::default()
+        };
+        let partial = partial_rdfs(&frames, &options);
+        let pairs: Vec<(&str, &str)> = partial.pairs.iter().map(|((a, b), _)| (a.as_str(), b.as_str())).collect();
+        assert_eq!(pairs, [("H", "H"), ("H", "O"), ("O", "O")]);
+        assert_eq!(partial.get("H", "H").unwrap().counts, rdf(&frames, "H", "H", 2.0, 4).counts);
+        // Only one H-O distance, of √3 Å, is within 2 Å.
+        let shell = 4.0 / 3.0 * PI * (8.0 - 1.5f64.powi(3));
+        assert!((partial.get("o", "h").unwrap().counts[3] - 1.0 / (2.0 / 64.0 * shell)).abs() < 1e-12);
+        let mut csv = vec![];
+        partial.to_csv(&mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().starts_with("r,H-H,H-O,O-O\n0.25,0,0,0\n"));
+
+        let density = RdfOptions {
+            normalization: RdfNormalization::NumberDensity,
+            ..options
+        };
+        let shell = 4.0 / 3.0 * PI * (1.5f64.powi(3) - 1.0);
+        assert_eq!(partial_rdfs(&frames, &density).get("H", "H").unwrap().counts[2], 1.0 / shell);
+    }
+
+    #[test]
+    fn smearing_keeps_constants() {
+        let mut histogram = Histogram::new(0.0, 1.0, 10);
+        histogram.counts = vec![2.0; 10];
+        smear(&mut histogram, 0.15);
+        assert!(histogram.counts.iter().all(|count| (count - 2.0).abs() < 1e-12));
+        histogram.counts = vec![0.0; 10];
+        histogram.counts[5] = 1.0;
+        smear(&mut histogram, 0.05);
+        assert!(histogram.counts[4] > 0.0 && (histogram.counts[4] - histogram.counts[6]).abs() < 1e-12);
+        assert!(histogram.counts[5] < 1.0);
+    }
+
+    /// Frames of a single hydrogen atom with the given velocities along x.
+    fn moving(velocities: &[f64]) -> Vec<Data> {
+        let frames = velocities.iter().map(|&v| {
+            let mut data = frame("", &[("H", [0.0; 3])]);
+            data.atoms[0].velocity = Some(Position::new(v, 0.0, 0.0));
+            data
+        });
+        frames.collect()
+    }
+
+    #[test]
+    fn velocity_autocorrelation() {
+        assert_eq!(vacf(&moving(&[2.0; 4])), Some(vec![4.0; 4]));
+        assert_eq!(vacf(&moving(&[1.0, -1.0, 1.0])), Some(vec![1.0, -1.0, 1.0]));
+        let mut frames = moving(&[1.0, 1.0]);
+        frames[1].atoms[0].velocity = None;
+        assert_eq!(vacf(&frames), None);
+        assert_eq!(vacf(&[]), None);
+    }
+
+    #[test]
+    fn vibrational_density_of_states() {
+        let strongest = |vdos: Vec<(f64, f64)>| vdos.into_iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap().0;
+        assert_eq!(strongest(vdos(&[1.0; 8], 1.0)), 0.0);
+        // A velocity flipping every frame oscillates at half the sampling frequency.
+        let alternating = vacf(&moving(&[1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0])).unwrap();
+        assert_eq!(strongest(vdos(&alternating, 2.0)), 7.0 / 32.0);
+    }
+
+    #[test]
+    fn kinetic_energy_and_temperature() {
+        let data = &moving(&[1.0])[0];
+        let mass = Element::from_symbol("H").unwrap().mass();
+        assert_eq!(data.kinetic_energy(VelocityUnit::Ase), Some(0.5 * mass));
+        let per_fs = data.kinetic_energy(VelocityUnit::AngstromPerFemtosecond).unwrap();
+        let per_ps = data.kinetic_energy(VelocityUnit::AngstromPerPicosecond).unwrap();
+        assert!((per_fs / per_ps - 1e6).abs() < 1e-3);
+        let temperature = data.temperature(VelocityUnit::Ase).unwrap();
+        assert!((temperature - mass / (3.0 * BOLTZMANN)).abs() < 1e-6);
+
+        let mut file = File { data: moving(&[1.0, 0.0]) };
+        file.data[1].atoms[0].velocity = None;
+        let series = file.temperature_series(VelocityUnit::Ase);
+        assert_eq!(series.name, "temperature");
+        assert_eq!(series.values[0], temperature);
+        assert!(series.values[1].is_nan());
+        assert_eq!(frame("", &[]).temperature(VelocityUnit::Ase), None);
+    }
+
+    #[test]
+    fn mean_and_standard_deviation() {
+        let series = Series::new("bond", vec![1.0, f64::NAN, 2.0, 3.0]);
+        assert_eq!(series.mean(), 2.0);
+        assert_eq!(series.std(), 1.0);
+        assert!(Series::new("bond", vec![1.0]).std().is_nan());
+        assert!(Series::new("bond", vec![]).mean().is_nan());
+    }
+
+    #[test]
+    fn circular_mean_and_spread() {
+        let series = Series::new("dihedral", vec![179.0, f64::NAN, -179.0, 177.0, -177.0]);
+        assert!((series.circular_mean().abs() - 180.0).abs() < 1e-9);
+        // The deviations from 180° are 1°, 1°, 3° and 3°, with a root mean square of √5°.
+        assert!((series.circular_std() - 5f64.sqrt()).abs() < 1e-3);
+        assert!(series.std() > 170.0);
+        let narrow = Series::new("dihedral", vec![59.0, 61.0]);
+        assert!((narrow.circular_mean() - 60.0).abs() < 1e-9);
+        assert!((narrow.circular_std() - narrow.std() / 2f64.sqrt()).abs() < 1e-3);
+        assert!(Series::new("dihedral", vec![10.0]).circular_std().is_nan());
+        assert!(Series::new("dihedral", vec![]).circular_mean().is_nan());
+    }
+
+    #[test]
+    fn voronoi_volumes_of_lattices() {
+        let cubic = Cell::new([4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]);
+        let atoms: Vec<(&str, [f64; 3])> =
+            (0..8).map(|i| ("Ar", [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|bit| 2.0 * bit as f64))).collect();
+        let volumes = voronoi_volumes(&frame("", &atoms), &cubic).unwrap();
+        assert!(volumes.iter().all(|v| (v - 8.0).abs() < 1e-9), "{:?}", volumes);
+
+        // Body-centered cubic, in a skewed cell with the same lattice.
+        let bcc = frame("", &[("Fe", [0.0, 0.0, 0.0]), ("Fe", [1.0, 1.0, 1.0])]);
+        let skewed = Cell::new([2.0, 0.0, 0.0], [2.0, 2.0, 0.0], [0.0, 2.0, 2.0]);
+        let volumes = voronoi_volumes(&bcc, &skewed).unwrap();
+        assert!(volumes.iter().all(|v| (v - 4.0).abs() < 1e-9), "{:?}", volumes);
+    }
+
+    #[test]
+    fn voronoi_volumes_add_up_to_the_cell() {
+        let cell = Cell::new([5.0, 0.0, 0.0], [1.0, 4.0, 0.0], [0.0, 0.5, 6.0]);
+        let atoms = [
+            ("O", [0.3, 0.1, 0.2]),
+            ("H", [1.2, 0.4, 0.1]),
+            ("C", [3.9, 2.5, 4.4]),
+            ("N", [2.2, 3.1, 1.7]),
+            ("N", [2.2, 3.1, 1.7]),
+            ("O", [-1.0, 7.3, 11.8]),
+        ];
+        let volumes = voronoi_volumes(&frame("", &atoms), &cell).unwrap();
+        assert!((volumes.iter().sum::<f64>() - cell.volume()).abs() < 1e-9 * cell.volume());
+        assert!(volumes.iter().all(|&v| v > 0.0));
+        assert_eq!(volumes[3], volumes[4]);
+
+        let flat = Cell::new([5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [5.0, 5.0, 0.0]);
+        assert_eq!(voronoi_volumes(&frame("", &atoms), &flat), None);
+    }
+
+    #[test]
+    fn nearest_neighbor_distances_and_stats() {
+        let distances = nearest_neighbor_distances(&water());
+        assert_eq!(distances[0], 0.96);
+        assert!((distances[2] - 0.24f64.hypot(0.93)).abs() < 1e-12);
+        assert!(nearest_neighbor_distances(&frame("", &[("He", [0.0; 3])]))[0].is_nan());
+        let mut periodic = frame("", &[("He", [0.5, 0.0, 0.0]), ("He", [9.0, 0.0, 0.0])]);
+        periodic.cell = Some(Cell::new([10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]));
+        assert!((nearest_neighbor_distances(&periodic)[0] - 1.5).abs() < 1e-12);
+
+        let stats = nearest_neighbor_stats(&trajectory(2).data, 2.0, 4);
+        let pairs: Vec<(&str, &str, usize)> =
+            stats.iter().map(|s| (s.center.as_str(), s.neighbor.as_str(), s.count)).collect();
+        assert_eq!(pairs, [("H", "H", 4), ("H", "O", 4), ("O", "H", 2)]);
+        let oh = &stats[2];
+        assert!([oh.min, oh.mean, oh.max].iter().all(|d| (d - 0.96).abs() < 1e-12));
+        assert_eq!(oh.histogram.counts, [0.0, 2.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn dbscan_clusters() {
+        let atoms = [
+            ("Au", [0.0, 0.0, 0.0]),
+            ("Au", [1.0, 0.0, 0.0]),
+            ("Au", [2.0, 0.0, 0.0]),
+            ("Ar", [5.0, 5.0, 5.0]),
+            ("Au", [0.5, 9.5, 0.0]),
+            ("Au", [0.5, 8.5, 0.0]),
+        ];
+        let mut data = frame("", &atoms);
+        let clusters = cluster(&data, 1.1, 2);
+        assert_eq!(clusters.labels, [Some(0), Some(0), Some(0), None, Some(1), Some(1)]);
+        assert_eq!(clusters.sizes, [3, 2]);
+        assert_eq!(clusters.centroids[0], Position::new(1.0, 0.0, 0.0));
+
+        // With three points per core atom, the end atoms are only reached from the middle one.
+        let clusters = cluster(&data, 1.1, 3);
+        assert_eq!(clusters.labels, [Some(0), Some(0), Some(0), None, None, None]);
+
+        // Across the periodic boundary, the two clusters merge and the centroid is unwrapped.
+        data.cell = Some(Cell::new([10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]));
+        let clusters = cluster(&data, 1.1, 2);
+        assert_eq!(clusters.sizes, [5]);
+        assert!(clusters.centroids[0].distance(Position::new(0.8, -0.4, 0.0)) < 1e-12);
+    }
+
+    #[test]
+    fn solvent_accessible_surface_areas() {
+        let sphere = |r: f64| 4.0 * PI * r * r;
+        let argon = frame("", &[("Ar", [0.0; 3])]);
+        let area = sasa(&argon, 1.4, 100);
+        assert!((area.total - sphere(1.88 + 1.4)).abs() < 1e-9);
+
+        // Two touching spheres of 3 Å each hide a cap of height 1.5 Å, a quarter of each sphere.
+        let pair = frame("", &[("Xx", [0.0; 3]), ("Xx", [3.0, 0.0, 0.0])]);
+        let area = sasa(&pair, 1.0, 1000);
+        assert!((area.atoms[0] - 0.75 * sphere(3.0)).abs() < 0.01 * sphere(3.0), "{:?}", area);
+        assert!((area.atoms[1] - area.atoms[0]).abs() < 0.01 * sphere(3.0));
+
+        let mut crowded = pair.clone();
+        crowded.cell = Some(Cell::new([5.0, 0.0, 0.0], [0.0, 20.0, 0.0], [0.0, 0.0, 20.0]));
+        assert!(sasa(&crowded, 1.0, 1000).total < area.total);
+    }
+
+    #[test]
+    fn principal_components_of_a_stretch() {
+        let frames: Vec<Data> = (0..10)
+            .map(|i| {
+                let stretch = 0.2 * (i as f64).sin();
+                let atoms = [
+                    ("C", [0.0, 0.0, 0.0]),
+                    ("H", [1.0, 0.0, 0.0]),
+                    ("H", [0.0, 1.0, 0.0]),
+                    ("H", [0.0, 0.0, 1.0 + stretch]),
+                ];
+                frame("", &atoms)
+            })
+            .collect();
+        let result = pca(&frames, None).unwrap();
+        assert_eq!(result.atoms, [0, 1, 2, 3]);
+        assert_eq!(result.eigenvalues.len(), 12);
+        assert!(result.eigenvalues.windows(2).all(|w| w[0] >= w[1]));
+        let ratios = result.variance_ratios();
+        assert!(ratios[0] > 0.999, "{:?}", ratios);
+        assert!((ratios.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        let norm: f64 = result.eigenvectors[0].iter().map(|v| v * v).sum();
+        assert!((norm - 1.0).abs() < 1e-9);
+        let first: Vec<f64> = result.projections.iter().map(|p| p[0]).collect();
+        assert!(first.iter().sum::<f64>().abs() < 1e-9);
+
+        let selected = pca(&frames, Some(&[1, 3])).unwrap();
+        assert_eq!(selected.mean.len(), 2);
+        assert!(pca(&frames[..1], None).is_none());
+        assert!(pca(&frames, Some(&[4])).is_none());
+        assert!(pca(&frames, Some(&[])).is_none());
+    }
+
+    #[test]
+    fn group_centroids_rmsd_and_rmsf() {
+        let mut frames = trajectory(3).data;
+        for data in &mut frames {
+            data.atoms[0].group = Some("HOH".to_string());
+            data.atoms[1].group = Some("HOH".to_string());
+            data.atoms[2].group = Some("TIP".to_string());
+        }
+        // The last hydrogen vibrates, the rest of the molecule only moves along x.
+        frames[1].atoms[2].position.0[1] += 0.3;
+
+        let stats = group_stats(&frames).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].group.as_str(), stats[0].atoms.as_slice()), ("HOH", [0, 1].as_slice()));
+        assert!((stats[0].centroids[2].0[0] - 0.68).abs() < 1e-12);
+        assert_eq!(stats[1].rmsd[0], 0.0);
+        assert!(stats[1].rmsd[1] > 0.1);
+        assert!(stats[1].rmsf > stats[0].rmsf);
+        assert_eq!(rmsf_by_group(&frames).unwrap()[1], ("TIP".to_string(), stats[1].rmsf));
+
+        frames.iter_mut().for_each(|data| data.atoms[2].group = None);
+        assert_eq!(group_stats(&frames).unwrap().len(), 1);
+        assert!(group_stats(&frames[..1]).is_none());
+    }
+
+    #[test]
+    fn rmsf_per_atom() {
+        let mut frames = trajectory(3).data;
+        frames[1].atoms[2].position.0[2] += 0.3;
+        let fluctuations = rmsf(&frames, None).unwrap();
+        assert_eq!(fluctuations.len(), 3);
+        assert!(fluctuations[2] > fluctuations[0] && fluctuations[2] > fluctuations[1]);
+        // Two atoms are superimposed exactly along their bond.
+        assert!(rmsf(&frames, Some(&[0, 1])).unwrap().iter().all(|&f| f < 1e-6));
+
+        let mut buf = vec![];
+        write_atom_values(&mut buf, &frames[0], "rmsf", &[0.5, 0.25, f64::NAN]).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert_eq!(text.lines().nth(1), Some("Properties=species:S:1:pos:R:3:rmsf:R:1 step=0 pbc=\"F F F\""));
+        assert_eq!(text.lines().nth(3), Some("H 0.96 0 0 0.25"));
+        let error = write_atom_values(&mut vec![], &frames[0], "rmsf", &[1.0]).unwrap_err();
+        assert_eq!(error.to_string(), "expected 3 values, one per atom, but found 1");
+    }
+
+    #[test]
+    fn gyration_tensors_and_shapes() {
+        let line = frame("", &[("C", [-1.0, 0.0, 0.0]), ("C", [1.0, 0.0, 0.0])]);
+        let gyration = line.gyration_tensor().unwrap();
+        assert_eq!(gyration.tensor[0][0], 1.0);
+        assert_eq!(gyration.moments[2], 1.0);
+        assert!(gyration.moments[..2].iter().all(|m| m.abs() < 1e-12));
+        assert!((gyration.radius_of_gyration() - 1.0).abs() < 1e-12);
+        assert!((gyration.relative_shape_anisotropy() - 1.0).abs() < 1e-12);
+
+        let octahedron = frame(
+            "",
+            &[
+                ("C", [1.0, 0.0, 0.0]),
+                ("C", [-1.0, 0.0, 0.0]),
+                ("C", [0.0, 1.0, 0.0]),
+                ("C", [0.0, -1.0, 0.0]),
+                ("C", [0.0, 0.0, 1.0]),
+                ("C", [0.0, 0.0, -1.0]),
+            ],
+        );
+        let gyration = octahedron.gyration_tensor().unwrap();
+        assert!(gyration.asphericity().abs() < 1e-12 && gyration.acylindricity().abs() < 1e-12);
+        assert!(gyration.relative_shape_anisotropy() < 1e-12);
+
+        let mut file = File { data: vec![line, frame("", &[("Xx", [0.0; 3])])] };
+        file.data.push(octahedron);
+        let series = file.shape_series();
+        let names: Vec<&str> = series.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["radius_of_gyration", "asphericity", "acylindricity", "relative_shape_anisotropy"]);
+        assert!(series[0].values[1].is_nan());
+        assert!((series[0].values[2] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn polymer_backbones() {
+        // A chain of six carbons along x with a methyl branch on the third and a hydrogen on the first.
+        let atoms = [
+            ("C", [6.0, 0.0, 0.0]),
+            ("C", [1.5, 0.0, 0.0]),
+            ("C", [0.0, 0.0, 0.0]),
+            ("C", [1.5, 1.5, 0.0]),
+            ("C", [3.0, 0.0, 0.0]),
+            ("C", [4.5, 0.0, 0.0]),
+            ("H", [7.1, 0.0, 0.0]),
+            ("C", [-1.5, 0.0, 0.0]),
+        ];
+        let data = frame("", &atoms);
+        assert_eq!(backbone(&data), [0, 5, 4, 1, 2, 7]);
+        assert_eq!(backbone(&frame("", &[("H", [0.0; 3])])), []);
+
+        let chain = [2, 1, 4, 5, 0];
+        let mut bent = data.clone();
+        bent.atoms[0].position = Position::new(4.5, 1.5, 0.0);
+        let frames = [data, bent];
+        assert_eq!(end_to_end(&frames, &chain), [6.0, 4.5f64.hypot(1.5)]);
+        assert!(end_to_end(&[water()], &chain)[0].is_nan());
+
+        // A straight chain has an infinite persistence length, and fully uncorrelated bonds have none.
+        assert_eq!(persistence_length(&frames[..1], &chain), None);
+        let zigzag = |angle: f64| {
+            let y = |i: usize| (i % 2) as f64 * angle.sin();
+            let atoms: Vec<(&str, [f64; 3])> = (0..20).map(|i| ("C", [i as f64 * angle.cos(), y(i), 0.0])).collect();
+            frame("", &atoms)
+        };
+        let chain: Vec<usize> = (0..20).collect();
+        let stiff = persistence_length(&[zigzag(0.2)], &chain).unwrap();
+        let flexible = persistence_length(&[zigzag(0.6)], &chain).unwrap();
+        assert!(stiff > flexible && flexible > 0.0, "{} {}", stiff, flexible);
+        assert_eq!(persistence_length(&frames, &[0, 1]), None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Sanity checks of simulation trajectories, flagging frames with collapsed or exploded geometries,
+//! e.g. to stop a pipeline before a broken run is analyzed.
+
+use super::analysis::minimum_image_distance;
+use super::bonds::close_pairs;
+use super::data::{Data, File};
+use super::position::Position;
+
+/// The limits checked by [`File::detect_anomalies`]. Checks set to `None` are skipped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// The smallest allowed distance between two atoms in Ångström. Defaults to 0.5 Å.
+    pub min_distance: Option<f64>,
+    /// The largest allowed displacement of an atom between consecutive frames in Ångström. Defaults to 2 Å.
+    pub max_displacement: Option<f64>,
+    /// The largest allowed absolute value of a coordinate in Ångström. Defaults to 10⁴ Å.
+    pub max_coordinate: Option<f64>,
+}
+impl Default for Thresholds {
+    fn default() -> Thresholds {
+        Thresholds {
+            min_distance: Some(0.5),
+            max_displacement: Some(2.0),
+            max_coordinate: Some(1e4),
+        }
+    }
+}
+
+/// A frame that failed a check of [`File::detect_anomalies`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anomaly {
+    pub frame: usize,
+    pub reason: Reason,
+}
+impl std::fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "frame {}: {}", self.frame, self.reason)
+    }
+}
+
+/// Why a frame was flagged, with the worst offending atoms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reason {
+    /// An atom has a NaN or infinite coordinate.
+    NonFinite { atom: usize },
+    /// Two atoms are closer than the minimum distance.
+    TooClose { atoms: (usize, usize), distance: f64 },
+    /// An atom moved farther than the maximum displacement since the previous frame.
+    Jump { atom: usize, displacement: f64 },
+    /// A coordinate of an atom exceeds the maximum.
+    OutOfBounds { atom: usize, coordinate: f64 },
+}
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::NonFinite { atom } => write!(f, "atom {} has a coordinate that is not finite", atom),
+            Reason::TooClose { atoms, distance } => {
+                write!(f, "atoms {} and {} are {} Å apart", atoms.0, atoms.1, distance)
+            }
+            Reason::Jump { atom, displacement } => {
+                write!(f, "atom {} moved {} Å since the previous frame", atom, displacement)
+            }
+            Reason::OutOfBounds { atom, coordinate } => write!(f, "atom {} has the coordinate {} Å", atom, coordinate),
+        }
+    }
+}
+
+impl File {
+    /// Checks every frame against `thresholds`, returning an anomaly for each failed check,
+    /// by frame and in the order of [`Reason`], with the worst offending atoms of each check.
+    /// Coordinates that are not finite are always reported, and such frames are not checked further.
+    /// Distances and displacements follow the minimum image convention in frames with a cell.
+    /// Displacements are only checked between consecutive frames with the same number of atoms.
+    pub fn detect_anomalies(&self, thresholds: &Thresholds) -> Vec<Anomaly> {
+        let mut anomalies = vec![];
+        for (frame, data) in self.data.iter().enumerate() {
+            let mut flag = |reason| anomalies.push(Anomaly { frame, reason });
+            if let Some(atom) = data.atoms.iter().position(|atom| !atom.position.is_finite()) {
+                flag(Reason::NonFinite { atom });
+                continue;
+            }
+
+            if let Some(min_distance) = thresholds.min_distance {
+                if let Some((atoms, distance)) = closest_pair(data, min_distance) {
+                    flag(Reason::TooClose { atoms, distance });
+                }
+            }
+
+            let previous = frame.checked_sub(1).map(|i| &self.data[i]);
+            let previous = previous.filter(|p| p.atoms.len() == data.atoms.len() && !p.has_invalid_coords());
+            if let (Some(max_displacement), Some(previous)) = (thresholds.max_displacement, previous) {
+                let displacements = previous.atoms.iter().zip(&data.atoms);
+                let displacements = displacements.map(|(p, a)| distance(data, p.position, a.position));
+                let worst = displacements.enumerate().max_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((atom, displacement)) = worst.filter(|&(_, d)| d > max_displacement) {
+                    flag(Reason::Jump { atom, displacement });
+                }
+            }
+
+            if let Some(max_coordinate) = thresholds.max_coordinate {
+                let coordinates = data.atoms.iter().enumerate().flat_map(|(i, atom)| atom.position.0.map(|v| (i, v)));
+                let worst = coordinates.max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()));
+                if let Some((atom, coordinate)) = worst.filter(|&(_, v)| v.abs() > max_coordinate) {
+                    flag(Reason::OutOfBounds { atom, coordinate });
+                }
+            }
+        }
+        anomalies
+    }
+}
+
+/// The distance between two positions, under the minimum image convention in a frame with a cell.
+fn distance(data: &Data, a: Position, b: Position) -> f64 {
+    match &data.cell {
+        Some(cell) => minimum_image_distance(cell, a, b),
+        None => a.distance(b),
+    }
+}
+
+/// The closest pair of atoms closer than `cutoff`, the first pair if several are as close, and their distance.
+fn closest_pair(data: &Data, cutoff: f64) -> Option<((usize, usize), f64)> {
+    let mut closest: Option<((usize, usize), f64)> = None;
+    close_pairs(data, data.cell.as_ref(), cutoff, 0..data.atoms.len(), |i, j| {
+        let distance = distance(data, data.atoms[i].position, data.atoms[j].position);
+        if distance < cutoff && closest.is_none_or(|(pair, c)| (distance, (i, j)) < (c, pair)) {
+            closest = Some(((i, j), distance));
+        }
+    });
+    closest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Cell;
+    use crate::testing::{frame, trajectory};
+
+    #[test]
+    fn flag_broken_frames() {
+        let mut file = trajectory(5);
+        assert_eq!(file.detect_anomalies(&Thresholds::default()), []);
+
+        file.data[1].atoms[2].position = file.data[1].atoms[0].position + Position::new(0.1, 0.0, 0.0);
+        file.data[2].atoms[1].position.0[0] += 3.0;
+        file.data[3].atoms[1].position.0[1] = f64::NAN;
+        file.data[4].atoms[0].position.0[2] = -2e4;
+        let anomalies = file.detect_anomalies(&Thresholds::default());
+        let reasons: Vec<(usize, String)> = anomalies.iter().map(|a| (a.frame, format!("{:?}", a.reason))).collect();
+        assert_eq!(anomalies.len(), 4, "{:?}", reasons);
+        assert!(matches!(anomalies[0], Anomaly { frame: 1, reason: Reason::TooClose { atoms: (0, 2), .. } }));
+        assert!(matches!(anomalies[1], Anomaly { frame: 2, reason: Reason::Jump { atom: 1, .. } }));
+        assert_eq!(anomalies[2], Anomaly { frame: 3, reason: Reason::NonFinite { atom: 1 } });
+        // The displacement after a frame with NaN coordinates is not checked.
+        assert_eq!(anomalies[3], Anomaly { frame: 4, reason: Reason::OutOfBounds { atom: 0, coordinate: -2e4 } });
+        assert_eq!(anomalies[3].to_string(), "frame 4: atom 0 has the coordinate -20000 Å");
+
+        let skip = Thresholds {
+            min_distance: None,
+            max_displacement: None,
+            max_coordinate: None,
+        };
+        assert_eq!(file.detect_anomalies(&skip).len(), 1);
+    }
+
+    #[test]
+    fn close_atoms_across_the_cell_boundary() {
+        let mut data = frame("", &[("O", [0.1, 5.0, 5.0]), ("O", [5.0, 5.0, 5.0]), ("O", [9.8, 5.0, 5.0])]);
+        let file = File { data: vec![data.clone()] };
+        assert_eq!(file.detect_anomalies(&Thresholds::default()), []);
+
+        data.cell = Some(Cell::new([10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]));
+        let anomalies = File { data: vec![data] }.detect_anomalies(&Thresholds::default());
+        assert!(matches!(anomalies[..], [Anomaly { frame: 0, reason: Reason::TooClose { atoms: (0, 2), distance } }]
+            if (distance - 0.3).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,139 @@
+/// Command line arguments of a subcommand, split into options and positional arguments.
+pub struct Args {
+    options: Vec<(String, Option<String>)>,
+    positional: Vec<String>,
+}
+impl Args {
+    /// Splits the arguments, where `flags` lists the options that take no value, `valued` the options that take
+    /// a value, and `multi` the options that take several values, e.g. `--bond 1 2`, stored separated by spaces.
+    /// Options are long options like `--frame`, or single letters like `-o`; a lone `-` is positional.
+    /// Fails on any other option, so that a typo like `--jsn` is not silently ignored.
+    pub fn parse<I: IntoIterator<Item = String>>(
+        args: I,
+        flags: &[&str],
+        valued: &[&str],
+        multi: &[(&str, usize)],
+    ) -> Result<Args, String> {
+        let mut options = vec![];
+        let mut positional = vec![];
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let short = arg.len() == 2 && arg.starts_with('-') && arg.as_bytes()[1].is_ascii_alphabetic();
+            if (arg.starts_with("--") && arg.len() > 2) || short {
+                let (name, value) = match arg.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None if valued.contains(&arg.as_str()) => {
+                        let value = args.next().ok_or(format!("Missing value for option {}", arg))?;
+                        (arg, Some(value))
+                    }
+                    None => match multi.iter().find(|(name, _)| *name == arg) {
+                        Some(&(_, n)) => {
+                            let values = args.by_ref().take(n).collect::<Vec<String>>();
+                            if values.len() < n {
+                                return Err(format!("Option {} takes {} values", arg, n));
+                            }
+                            (arg, Some(values.join(" ")))
+                        }
+                        None => (arg, None),
+                    },
+                };
+                let mut known = flags.iter().chain(valued).chain(multi.iter().map(|(name, _)| name));
+                if !known.any(|known| *known == name) {
+                    return Err(match name.as_str() {
+                        "--json" => "--json is not supported by this command".to_string(),
+                        _ => format!("Unknown option {}", name),
+                    });
+                }
+                options.push((name, value));
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        Ok(Args { options, positional })
+    }
+
+    /// The value of the last occurrence of option `name`.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.iter().rev().find(|(n, _)| n == name).and_then(|(_, v)| v.as_deref())
+    }
+
+    /// The values of every occurrence of option `name`, in order.
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.options.iter().filter(|(n, _)| n == name).filter_map(|(_, v)| v.as_deref()).collect()
+    }
+
+    /// Whether option `name` is given.
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| n == name)
+    }
+
+    /// The value of option `name`, parsed as `T`.
+    pub fn parsed<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.value(name)
+            .map(|v| v.parse().map_err(|_| format!("Invalid value for option {}: {}", name, v)))
+            .transpose()
+    }
+
+    /// The positional arguments.
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        let flags = ["--json", "-v", "-t"];
+        Args::parse(args.iter().map(|arg| arg.to_string()), &flags, &["--tol"], &[("--pair", 2)])
+    }
+
+    #[test]
+    fn options_and_positional_arguments() {
+        let args = parse(&["a.xyz", "--tol", "0.1", "--json", "b.xyz", "--tol=0.2"]).unwrap();
+        assert_eq!(args.positional(), ["a.xyz", "b.xyz"]);
+        assert_eq!(args.value("--tol"), Some("0.2"));
+        assert_eq!(args.parsed::<f64>("--tol").unwrap(), Some(0.2));
+        assert_eq!(args.value("--json"), None);
+        assert!(args.flag("--json"));
+        assert!(!args.flag("--quiet"));
+    }
+
+    #[test]
+    fn missing_and_invalid_values() {
+        assert!(parse(&["a.xyz", "--tol"]).is_err());
+        assert!(parse(&["--tol", "x"]).unwrap().parsed::<f64>("--tol").is_err());
+    }
+
+    #[test]
+    fn short_options_and_stdin() {
+        let args = parse(&["-v", "-", "-5", "-t", "0.1"]).unwrap();
+        assert!(args.flag("-v"));
+        assert_eq!(args.positional(), ["-", "-5", "0.1"]);
+    }
+
+    #[test]
+    fn options_with_several_values() {
+        let args = parse(&["--pair", "1", "2", "a.xyz", "--pair", "3", "4"]).unwrap();
+        assert_eq!(args.values("--pair"), ["1 2", "3 4"]);
+        assert_eq!(args.positional(), ["a.xyz"]);
+        assert!(parse(&["--pair", "1"]).is_err());
+    }
+
+    #[test]
+    fn unknown_options() {
+        assert_eq!(parse(&["a.xyz", "--jsn"]).err().unwrap(), "Unknown option --jsn");
+        assert_eq!(parse(&["--tl=0.1"]).err().unwrap(), "Unknown option --tl");
+        assert!(parse(&["-", "--", "-x"]).is_err());
+    }
+
+    #[test]
+    fn json_without_json_output() {
+        let args = ["a.xyz", "--json"].map(String::from);
+        let error = Args::parse(args, &[], &["-o"], &[]).err().unwrap();
+        assert_eq!(error, "--json is not supported by this command");
+    }
+}
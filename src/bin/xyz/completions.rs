@@ -0,0 +1,101 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "xyz completions bash|zsh|fish";
+
+/// Prints a shell completion script completing the subcommands and their options, e.g.
+/// `xyz completions bash > ~/.local/share/bash-completion/completions/xyz`.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [shell] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let script = match shell.as_str() {
+        "bash" => bash(),
+        "zsh" => zsh(),
+        "fish" => fish(),
+        other => return Err(format!("unknown shell {}, expected bash, zsh or fish", other).into()),
+    };
+    out.write_all(script.as_bytes())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The options of every subcommand, as parsed by [`Args`].
+fn options() -> Vec<(&'static str, Vec<&'static str>)> {
+    crate::COMMANDS
+        .iter()
+        .map(|command| {
+            let mut options: Vec<&str> = command.flags.iter().chain(command.valued).copied().collect();
+            options.extend(command.multi.iter().map(|&(option, _)| option));
+            options.sort();
+            options.dedup();
+            (command.name, options)
+        })
+        .collect()
+}
+
+fn names() -> String {
+    crate::COMMANDS.iter().map(|command| command.name).collect::<Vec<&str>>().join(" ")
+}
+
+fn bash() -> String {
+    let mut script = String::from("_xyz() {\n    local cur=${COMP_WORDS[COMP_CWORD]} opts\n");
+    script.push_str("    if [ \"$COMP_CWORD\" -eq 1 ]; then\n");
+    script.push_str(&format!("        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n", names()));
+    script.push_str("        return\n    fi\n");
+    script.push_str("    case ${COMP_WORDS[1]} in\n");
+    for (name, options) in options() {
+        script.push_str(&format!("        {}) opts=\"{}\" ;;\n", name, options.join(" ")));
+    }
+    script.push_str("    esac\n    if [[ $cur == -* ]]; then\n");
+    script.push_str("        COMPREPLY=($(compgen -W \"$opts\" -- \"$cur\"))\n    else\n");
+    script.push_str("        COMPREPLY=($(compgen -f -- \"$cur\"))\n    fi\n}\n");
+    script.push_str("complete -o filenames -F _xyz xyz\n");
+    script
+}
+
+fn zsh() -> String {
+    let mut script = String::from("#compdef xyz\n\n_xyz() {\n    local -a opts\n");
+    script.push_str(&format!("    if (( CURRENT == 2 )); then\n        compadd {}\n        return\n    fi\n", names()));
+    script.push_str("    case $words[2] in\n");
+    for (name, options) in options() {
+        script.push_str(&format!("        {}) opts=({}) ;;\n", name, options.join(" ")));
+    }
+    script.push_str("    esac\n    if [[ $PREFIX == -* ]]; then\n        compadd -a opts\n    else\n");
+    script.push_str("        _files\n    fi\n}\n\ncompdef _xyz xyz\n");
+    script
+}
+
+fn fish() -> String {
+    let mut script = format!("complete -c xyz -n __fish_use_subcommand -f -a \"{}\"\n", names());
+    for (name, options) in options() {
+        for option in options {
+            let flag = match option.strip_prefix("--") {
+                Some(long) => format!("-l {}", long),
+                None => format!("-s {}", &option[1..]),
+            };
+            script.push_str(&format!("complete -c xyz -n \"__fish_seen_subcommand_from {}\" {}\n", name, flag));
+        }
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::xyz;
+
+    #[test]
+    fn scripts_for_every_shell() {
+        let (_, bash) = xyz(&["completions", "bash"]).unwrap();
+        assert!(crate::COMMANDS.iter().all(|command| bash.contains(&format!("        {}) opts=", command.name))));
+        assert!(bash.contains("        measure) opts=\"--angle --bond --csv --dihedral --json --tsv\" ;;\n"));
+        assert!(bash.contains("        info) opts=\"--json --output -o\" ;;\n"));
+        let (_, zsh) = xyz(&["completions", "zsh"]).unwrap();
+        assert!(zsh.starts_with("#compdef xyz\n") && zsh.contains("        edit) opts=("));
+        let (_, fish) = xyz(&["completions", "fish"]).unwrap();
+        assert!(fish.contains("complete -c xyz -n \"__fish_seen_subcommand_from edit\" -s o\n"));
+        assert!(fish.contains("complete -c xyz -n \"__fish_seen_subcommand_from grep\" -l count\n"));
+        assert!(xyz(&["completions", "powershell"]).is_err());
+    }
+}
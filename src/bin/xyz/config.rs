@@ -0,0 +1,199 @@
+use std::path::PathBuf;
+
+use rust_xyz_chem::element::Element;
+use rust_xyz_chem::labels::LabelMap;
+use rust_xyz_chem::{ParseOptions, WriteOptions};
+
+/// Bohr per Ångström.
+const BOHR: f64 = 1.0 / rust_xyz_chem::units::BOHR;
+
+/// The length unit of printed distances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Units {
+    #[default]
+    Angstrom,
+    Bohr,
+}
+
+/// Defaults of the command line interface, read from `$XDG_CONFIG_HOME/xyz/config.toml`,
+/// or `~/.config/xyz/config.toml`, e.g.
+///
+/// ```toml
+/// precision = 6      # decimals of written coordinates
+/// units = "bohr"     # "angstrom" or "bohr", for printed distances
+///
+/// [aliases]          # atom labels resolved to elements
+/// OW = "O"
+/// HW1 = "H"
+/// ```
+///
+/// Only this subset of TOML is understood: comments, the `[aliases]` table, and keys with integer or double-quoted
+/// string values, each key once. Other syntax, such as escape sequences, single-quoted strings, dotted keys, arrays
+/// and inline tables, is rejected with an error rather than misread.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub precision: Option<usize>,
+    pub units: Units,
+    pub aliases: Vec<(String, Element)>,
+}
+impl Config {
+    /// Loads the configuration file, or the defaults if there is none.
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = path() else {
+            return Ok(Config::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Config::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        let mut keys: Vec<(String, String)> = vec![];
+        for (n, line) in contents.lines().enumerate() {
+            let invalid = |what: &str| format!("line {}: {}", n + 1, what);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.contains('\\') {
+                return Err(invalid("escape sequences are not supported"));
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                if section != "aliases" {
+                    return Err(invalid(&format!("unknown table [{}]", section)));
+                }
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid("expected key = value"))?;
+            let key = unquote(key.trim()).unwrap_or(key.trim());
+            let value = value.trim();
+            if key.starts_with('\'') || value.starts_with('\'') {
+                return Err(invalid("single-quoted strings are not supported, use double quotes"));
+            }
+            if keys.iter().any(|(s, k)| *s == section && k == key) {
+                return Err(invalid(&format!("duplicate key {}", key)));
+            }
+            keys.push((section.clone(), key.to_string()));
+            let string = || unquote(value).ok_or_else(|| invalid(&format!("expected a string for {}", key)));
+            match (section.as_str(), key) {
+                ("", "precision") => {
+                    config.precision = Some(value.parse().map_err(|_| invalid("expected an integer for precision"))?)
+                }
+                ("", "units") => {
+                    config.units = match string()? {
+                        "angstrom" => Units::Angstrom,
+                        "bohr" => Units::Bohr,
+                        other => return Err(invalid(&format!("unknown units {}", other))),
+                    }
+                }
+                ("aliases", label) => {
+                    let symbol = string()?;
+                    let element = Element::from_symbol(symbol).ok_or_else(|| match Element::closest(symbol) {
+                        Some(closest) => invalid(&format!("unknown element {}, did you mean {}?", symbol, closest)),
+                        None => invalid(&format!("unknown element {}", symbol)),
+                    })?;
+                    config.aliases.push((label.to_string(), element));
+                }
+                _ => return Err(invalid(&format!("unknown key {}", key))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The options for reading files, resolving the configured aliases.
+    pub fn parse_options(&self) -> ParseOptions {
+        let mut options = ParseOptions::default();
+        if !self.aliases.is_empty() {
+            let mut labels = LabelMap::new();
+            self.aliases.iter().for_each(|(label, element)| labels.insert(label.as_str(), *element));
+            options.labels = Some(labels);
+        }
+        options
+    }
+
+    /// The options for writing files, with the configured precision.
+    pub fn write_options(&self) -> WriteOptions {
+        WriteOptions {
+            precision: self.precision,
+            ..WriteOptions::default()
+        }
+    }
+
+    /// Converts a distance in Ångström to the configured units.
+    pub fn length(&self, angstrom: f64) -> f64 {
+        match self.units {
+            Units::Angstrom => angstrom,
+            Units::Bohr => angstrom * BOHR,
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    let directory = match std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        Some(directory) => PathBuf::from(directory),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(directory.join("xyz").join("config.toml"))
+}
+
+/// The line up to a `#` that is not inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// The contents of a double-quoted string.
+fn unquote(value: &str) -> Option<&str> {
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_supported_subset() {
+        let contents = "# defaults\nprecision = 6\nunits = \"bohr\"  # for distances\n\n\
+                        [aliases]\nOW = \"O\"\n\"H#1\" = \"H\"\n";
+        let config = Config::parse(contents).unwrap();
+        assert_eq!(config.precision, Some(6));
+        assert_eq!(config.units, Units::Bohr);
+        let aliases: Vec<(&str, &str)> = config.aliases.iter().map(|(label, e)| (label.as_str(), e.symbol())).collect();
+        assert_eq!(aliases, [("OW", "O"), ("H#1", "H")]);
+        assert!((config.length(0.529177210903) - 1.0).abs() < 1e-12);
+        assert_eq!(config.write_options().precision, Some(6));
+        assert!(config.parse_options().labels.is_some());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Config::parse("precision = 6.5").unwrap_err(), "line 1: expected an integer for precision");
+        assert_eq!(Config::parse("\nunits = bohr").unwrap_err(), "line 2: expected a string for units");
+        assert_eq!(Config::parse("[colors]").unwrap_err(), "line 1: unknown table [colors]");
+        let error = Config::parse("[aliases]\nX = \"Carbn\"").unwrap_err();
+        assert_eq!(error, "line 2: unknown element Carbn, did you mean C?");
+        assert_eq!(Config::parse("[aliases]\nX = \"Qqqqq\"").unwrap_err(), "line 2: unknown element Qqqqq");
+        assert_eq!(Config::parse("precision").unwrap_err(), "line 1: expected key = value");
+        assert_eq!(Config::parse("color = \"red\"").unwrap_err(), "line 1: unknown key color");
+        let error = Config::parse("[aliases]\n\"O\\\"W\" = \"O\"").unwrap_err();
+        assert_eq!(error, "line 2: escape sequences are not supported");
+        let error = Config::parse("units = 'bohr'").unwrap_err();
+        assert_eq!(error, "line 1: single-quoted strings are not supported, use double quotes");
+        let error = Config::parse("precision = 6\nprecision = 3").unwrap_err();
+        assert_eq!(error, "line 2: duplicate key precision");
+        assert_eq!(Config::parse("aliases.OW = \"O\"").unwrap_err(), "line 1: unknown key aliases.OW");
+    }
+}
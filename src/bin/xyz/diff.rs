@@ -0,0 +1,118 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str = "xyz diff [--tol TOL] [-o OUTPUT] [--json] A.xyz B.xyz";
+
+/// Compares two files frame by frame and prints the atoms that differ.
+/// With `--json`, prints an object with the frame counts and the differences of every differing frame.
+/// Exits with 0 if the files are identical within the tolerance, and 1 otherwise.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let mut report = vec![];
+    let code = compare(&args, &mut report)?;
+    let output = args.value("-o").or(args.value("--output")).unwrap_or("-");
+    crate::write_to(output, out, |out| out.write_all(&report))?;
+    Ok(code)
+}
+
+/// Writes the differences printed by [`run`], and returns the exit code.
+fn compare(args: &Args, out: &mut dyn Write) -> crate::Result {
+    let tol = args.parsed("--tol")?.unwrap_or(1e-6);
+    let [a, b] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+
+    if a == "-" && b == "-" {
+        return Err("only one file can be read from stdin".into());
+    }
+    let a = crate::read(a)?;
+    let b = crate::read(b)?;
+    let mut identical = a.data.len() == b.data.len();
+    let json = args.flag("--json");
+    let mut frames = vec![];
+
+    if a.data.len() != b.data.len() && !json {
+        writeln!(out, "frame count: {} != {}", a.data.len(), b.data.len())?;
+    }
+
+    for (frame, (x, y)) in a.data.iter().zip(&b.data).enumerate() {
+        let diff = x.diff(y, tol);
+        if diff.is_empty() {
+            continue;
+        }
+        identical = false;
+
+        if json {
+            let moved = diff.moved.iter().map(|&i| {
+                Json::object([
+                    ("index", i.into()),
+                    ("symbol", x.atoms[i].symbol.as_str().into()),
+                    ("displacement", Json::numbers(diff.displacements[i].0)),
+                ])
+            });
+            frames.push(Json::object([
+                ("frame", frame.into()),
+                ("atom_counts", Json::Array(vec![diff.atom_counts.0.into(), diff.atom_counts.1.into()])),
+                ("reordered", diff.is_reordered().into()),
+                ("changed_symbols", Json::Array(diff.changed_symbols.iter().map(|&i| i.into()).collect())),
+                ("moved", Json::Array(moved.collect())),
+                ("max_displacement", diff.max_displacement_atom.map(|_| diff.max_displacement).into()),
+                ("max_displacement_atom", diff.max_displacement_atom.into()),
+            ]));
+            continue;
+        }
+
+        writeln!(out, "frame {}:", frame)?;
+        if diff.atom_counts.0 != diff.atom_counts.1 {
+            writeln!(out, "  atom count: {} != {}", diff.atom_counts.0, diff.atom_counts.1)?;
+        }
+        if diff.is_reordered() {
+            writeln!(out, "  atoms are reordered")?;
+        }
+        for &i in &diff.changed_symbols {
+            writeln!(out, "  atom {}: {} -> {}", i, x.atoms[i].symbol, y.atoms[i].symbol)?;
+        }
+        for &i in &diff.moved {
+            let [dx, dy, dz] = diff.displacements[i].0;
+            writeln!(out, "  atom {} {}: moved {:.6} {:.6} {:.6}", i, x.atoms[i].symbol, dx, dy, dz)?;
+        }
+        if let Some(i) = diff.max_displacement_atom {
+            writeln!(out, "  max displacement: {:.6} (atom {})", diff.max_displacement, i)?;
+        }
+    }
+
+    if json {
+        let json = Json::object([
+            ("identical", identical.into()),
+            ("frame_counts", Json::Array(vec![a.data.len().into(), b.data.len().into()])),
+            ("frames", Json::Array(frames)),
+        ]);
+        writeln!(out, "{}", json)?;
+    }
+
+    Ok(if identical { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn identical_and_different_files() {
+        let directory = directory("diff");
+        let (a, b) = (directory.join("a.xyz"), directory.join("b.xyz"));
+        std::fs::write(&a, TRAJECTORY).unwrap();
+        std::fs::write(&b, TRAJECTORY.replace("H -0.14 0.93", "H -0.14 0.83")).unwrap();
+        let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+        assert_eq!(xyz(&["diff", a, a]).unwrap(), (ExitCode::SUCCESS, String::new()));
+        let (code, report) = xyz(&["diff", "--tol", "0.01", a, b]).unwrap();
+        assert_eq!(code, ExitCode::from(1));
+        assert!(report.starts_with("frame 1:\n  atom 2 H: moved 0.000000 -0.100000 0.000000\n"), "{}", report);
+        assert!(xyz(&["diff", "-", "-"]).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::Write;
+use std::path::Path;
+
+use rust_xyz_chem::data::Data;
+use rust_xyz_chem::labels::LabelMap;
+use rust_xyz_chem::position::Position;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "xyz edit [--fix-symbols] [--strip-element SYMBOLS] [--translate X,Y,Z] [--center] \
+                         [--sort-by-element] [--set-comment TEXT] [-o OUTPUT | --in-place] [--checkpoint FILE] FILE";
+
+/// Applies edits to every frame, reading and writing one frame at a time.
+/// The edits are applied in the order of the usage, whatever the order of the options.
+/// `--set-comment` keeps the cell of frames that have one, and extended XYZ columns are kept.
+/// `--fix-symbols` replaces labels by element symbols, using the configured aliases or common force field labels.
+/// With `--checkpoint`, an interrupted run resumes where it stopped, see [`stream`](crate::stream::stream).
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let labels = crate::config().parse_options().labels.unwrap_or_else(LabelMap::common);
+    let strip: Vec<&str> = args.value("--strip-element").map_or(vec![], |s| s.split(',').map(str::trim).collect());
+    let translate = args
+        .value("--translate")
+        .map(|v| match v.split(',').map(|x| x.trim().parse::<f64>()).collect::<Result<Vec<f64>, _>>() {
+            Ok(v) if v.len() == 3 => Ok(Position::new(v[0], v[1], v[2])),
+            _ => Err(format!("Invalid value for option --translate: {}", v)),
+        })
+        .transpose()?;
+    let output = args.value("-o").or(args.value("--output")).unwrap_or("-");
+    let in_place = args.flag("--in-place");
+    if in_place && (path == "-" || output != "-") {
+        return Err("--in-place needs an input file and no output".into());
+    }
+
+    let edit = |mut data: Data| {
+        if args.flag("--fix-symbols") {
+            data.fix_symbols(&labels);
+        }
+        if !strip.is_empty() {
+            data.atoms.retain(|atom| {
+                let symbol = atom.element().map_or(atom.symbol.as_str(), |e| e.symbol());
+                !strip.iter().any(|s| s.eq_ignore_ascii_case(symbol) || s.eq_ignore_ascii_case(&atom.symbol))
+            });
+            data.count = data.atoms.len();
+        }
+        if let Some(shift) = translate {
+            data.atoms.iter_mut().for_each(|atom| atom.position += shift);
+        }
+        if args.flag("--center") {
+            if data.cell.is_some() {
+                data.center_in_cell();
+            } else if !data.atoms.is_empty() {
+                let centroid = data.atoms.iter().map(|atom| atom.position).sum::<Position>() / data.atoms.len() as f64;
+                data.atoms.iter_mut().for_each(|atom| atom.position -= centroid);
+            }
+        }
+        if args.flag("--sort-by-element") {
+            // Unknown symbols go last, in alphabetical order.
+            data.atoms.sort_by_key(|atom| (atom.element().map_or(u8::MAX, |e| e.atomic_number()), atom.symbol.clone()));
+        }
+        if let Some(comment) = args.value("--set-comment") {
+            // The cell is kept, and the per-atom columns are declared again when the frame is written.
+            data.comment = match &data.cell {
+                Some(cell) => format!("{} {} pbc=\"T T T\"", cell.to_comment(), comment),
+                None => comment.to_string(),
+            };
+        }
+        data
+    };
+
+    let checkpoint = args.value("--checkpoint");
+    if output == "-" && !in_place {
+        return crate::stream::stream(path, "-", checkpoint, out, |_, data, _| Some(edit(data)));
+    }
+
+    // Frames are read while they are written, so an edited input is only replaced once it is completely written.
+    let target = if in_place {
+        let path = Path::new(path);
+        let name = path.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+        path.with_file_name(format!(".{}.edit.tmp", name)).to_string_lossy().into_owned()
+    } else {
+        output.to_string()
+    };
+    let result = crate::stream::stream(path, &target, checkpoint, out, |_, data, _| Some(edit(data)));
+    if in_place {
+        match &result {
+            Ok(_) => std::fs::rename(&target, path)?,
+            // A checkpointed run resumes writing the temporary file.
+            Err(_) if checkpoint.is_some() => {}
+            Err(_) => {
+                let _ = std::fs::remove_file(&target);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{directory, frames, xyz, EXTENDED, TRAJECTORY};
+
+    #[test]
+    fn edits_to_stdout_and_in_place() {
+        let directory = directory("edit");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let args = ["edit", "--strip-element", "h", "--translate", "1,0,0", "--set-comment", "O", input];
+        let (_, edited) = xyz(&args).unwrap();
+        assert!(edited.starts_with("1\nO\nO\t1\t0\t0\n1\nO\nO\t1.1\t0\t0\n"));
+        assert!(xyz(&["edit", "--translate", "1,0", input]).is_err());
+        assert!(xyz(&["edit", "--in-place", "-o", "out.xyz", input]).is_err());
+
+        xyz(&["edit", "--sort-by-element", "--in-place", input]).unwrap();
+        let sorted = std::fs::read_to_string(input).unwrap();
+        assert!(sorted.starts_with("3\nframe 0\nH\t0.96\t0\t0\nH\t-0.24\t0.93\t0\nO\t0\t0\t0\n"));
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn extended_frames_keep_their_columns_and_cell() {
+        let directory = directory("edit-extended");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, EXTENDED).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, edited) = xyz(&["edit", "--strip-element", "O", "--set-comment", "stripped", input]).unwrap();
+        let edited = frames(&edited);
+        assert_eq!(edited.len(), 2);
+        assert_eq!(edited[1].cell.unwrap().vectors, [[10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]]);
+        assert!(edited[1].comment.contains(" stripped "), "{}", edited[1].comment);
+        assert_eq!(edited[1].atoms[0].velocity.unwrap().0, [0.2, 0.0, 0.0]);
+        assert_eq!(edited[1].atoms[1].charge, Some(0.4));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
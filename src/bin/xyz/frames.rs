@@ -0,0 +1,172 @@
+use std::io::Write;
+
+use rust_xyz_chem::data::Data;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "xyz frames [--select FRAMES] [--where CONDITION] [-o OUTPUT] [--checkpoint FILE] FILE";
+
+/// Extracts frames by number and by their comment metadata, reading one frame at a time.
+///
+/// `--select` takes zero-based frame numbers and inclusive ranges separated by commas, e.g. `1,5,10..20,last`,
+/// where ranges may be open like `100..` and `last` is the final frame. `--where` takes comparisons of numeric
+/// comment properties joined by `and`, e.g. `energy < -100 and step >= 5000`; frames without the property
+/// do not match. The frames are written in the order of the file.
+/// With `--checkpoint`, an interrupted run resumes where it stopped, see [`stream`](crate::stream::stream).
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let select = args.value("--select").map(parse_select).transpose()?;
+    let conditions = args.value("--where").map(parse_where).transpose()?.unwrap_or_default();
+    let output = args.value("-o").or(args.value("--output")).unwrap_or("-");
+
+    crate::stream::stream(path, output, args.value("--checkpoint"), out, |i, data, is_last| {
+        let selected = select.as_ref().is_none_or(|specs| specs.iter().any(|s| s.matches(i, is_last)));
+        (selected && conditions.iter().all(|c| c.matches(&data))).then_some(data)
+    })
+}
+
+/// A part of a frame selection.
+enum Spec {
+    Frame(usize),
+    Range(Option<usize>, Option<usize>),
+    Last,
+}
+impl Spec {
+    fn matches(&self, frame: usize, is_last: bool) -> bool {
+        match self {
+            Spec::Frame(n) => frame == *n,
+            Spec::Range(start, end) => start.is_none_or(|s| s <= frame) && end.is_none_or(|e| frame <= e),
+            Spec::Last => is_last,
+        }
+    }
+}
+
+fn parse_select(select: &str) -> Result<Vec<Spec>, String> {
+    let invalid = || format!("Invalid frame selection: {}", select);
+    let number = |s: &str| -> Result<Option<usize>, String> {
+        let s = s.trim();
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            s.parse().map(Some).map_err(|_| invalid())
+        }
+    };
+
+    select
+        .split(',')
+        .map(|part| match part.trim() {
+            "last" => Ok(Spec::Last),
+            part => match part.split_once("..") {
+                Some((start, end)) => Ok(Spec::Range(number(start)?, number(end)?)),
+                None => number(part)?.map(Spec::Frame).ok_or_else(invalid),
+            },
+        })
+        .collect()
+}
+
+/// A comparison of a numeric comment property with a number.
+struct Condition {
+    key: String,
+    operator: String,
+    value: f64,
+}
+impl Condition {
+    fn matches(&self, data: &Data) -> bool {
+        let Some(v) = data.numeric_property(&self.key) else {
+            return false;
+        };
+        match self.operator.as_str() {
+            "<" => v < self.value,
+            "<=" => v <= self.value,
+            ">" => v > self.value,
+            ">=" => v >= self.value,
+            "!=" => v != self.value,
+            _ => v == self.value,
+        }
+    }
+}
+
+fn parse_where(condition: &str) -> Result<Vec<Condition>, String> {
+    condition
+        .split(" and ")
+        .map(|part| {
+            let invalid = || format!("Invalid condition: {}", part.trim());
+            let operator = ["<=", ">=", "!=", "==", "<", ">", "="]
+                .into_iter()
+                .find(|op| part.contains(op))
+                .ok_or_else(invalid)?;
+            let (key, value) = part.split_once(operator).unwrap();
+            let key = key.trim();
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return Err(invalid());
+            }
+            Ok(Condition {
+                key: key.to_string(),
+                operator: operator.to_string(),
+                value: value.trim().parse().map_err(|_| invalid())?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{directory, frames, xyz, EXTENDED, TRAJECTORY};
+
+    fn comments(output: &str) -> Vec<&str> {
+        output.lines().filter(|line| line.starts_with("step=")).collect()
+    }
+
+    #[test]
+    fn select_by_number_and_metadata() {
+        let directory = directory("frames");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY.replace("frame ", "step=")).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, output) = xyz(&["frames", "--select", "0,last", input]).unwrap();
+        assert_eq!(comments(&output), ["step=0", "step=2"]);
+        let (_, output) = xyz(&["frames", "--select", "1..", input]).unwrap();
+        assert_eq!(comments(&output), ["step=1", "step=2"]);
+        let (_, output) = xyz(&["frames", "--where", "step >= 1 and step<2", input]).unwrap();
+        assert_eq!(comments(&output), ["step=1"]);
+        let (_, output) = xyz(&["frames", "--where", "energy < 0", input]).unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn invalid_selections_and_conditions() {
+        assert!(parse_select("1,x").is_err());
+        assert!(parse_select("..").is_ok());
+        assert!(parse_where("energy").is_err());
+        assert!(parse_where("free energy < 1").is_err());
+        assert!(parse_where("energy < low").is_err());
+    }
+
+    #[test]
+    fn json_is_a_usage_error() {
+        assert!(xyz(&["frames", "--json", "in.xyz"]).unwrap_err().to_string().contains("--json is not supported"));
+    }
+
+    #[test]
+    fn selected_frames_read_back() {
+        let directory = directory("frames-extended");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, EXTENDED).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, output) = xyz(&["frames", "--where", "step >= 1", input]).unwrap();
+        let selected = frames(&output);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].property("step").as_deref(), Some("1"));
+        assert!(selected[0].cell.is_some());
+        let original = &frames(EXTENDED)[1];
+        for (a, b) in original.atoms.iter().zip(&selected[0].atoms) {
+            assert_eq!((a.position, a.velocity, a.charge), (b.position, b.velocity, b.charge));
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
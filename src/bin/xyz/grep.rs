@@ -0,0 +1,92 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use rust_xyz_chem::select::Selection;
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str = "xyz grep [--count] [--json] EXPR FILE";
+
+/// Prints the atoms matching a selection expression in every frame, prefixed with the frame and atom index,
+/// reading one frame at a time. With `--count`, prints the number of matching atoms per frame instead.
+/// With `--json`, prints one JSON object per line for every match, or every frame with `--count`.
+/// Like grep, exits with status 1 when no atom matches.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [expression, path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let selection = Selection::parse(expression)?;
+    let count = args.flag("--count");
+    let json = args.flag("--json");
+
+    let mut found = false;
+    for (frame, data) in crate::frames(path)?.enumerate() {
+        let data = data?;
+        let selected = selection.apply(&data);
+        found |= !selected.is_empty();
+        if count {
+            if json {
+                writeln!(out, "{}", Json::object([("frame", frame.into()), ("count", selected.len().into())]))?;
+            } else {
+                writeln!(out, "{}:{}", frame, selected.len())?;
+            }
+            continue;
+        }
+        for i in selected {
+            let atom = &data.atoms[i];
+            let [x, y, z] = [0, 1, 2].map(|axis| atom.position[axis]);
+            if json {
+                let json = Json::object([
+                    ("frame", frame.into()),
+                    ("index", i.into()),
+                    ("symbol", atom.symbol.as_str().into()),
+                    ("position", Json::numbers([x, y, z])),
+                ]);
+                writeln!(out, "{}", json)?;
+            } else {
+                writeln!(out, "{}:{}:\t{}\t{}\t{}\t{}", frame, i, atom.symbol, x, y, z)?;
+            }
+        }
+    }
+
+    Ok(if found { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitCode;
+
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn matching_atoms_and_counts() {
+        let directory = directory("grep");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (code, output) = xyz(&["grep", "element H and y > 0.5", input]).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(output, "0:2:\tH\t-0.24\t0.93\t0\n1:2:\tH\t-0.14\t0.93\t0\n2:2:\tH\t-0.04\t0.93\t0\n");
+        let (_, counts) = xyz(&["grep", "--count", "x > 0.05", input]).unwrap();
+        assert_eq!(counts, "0:1\n1:2\n2:2\n");
+
+        let (code, output) = xyz(&["grep", "element C", input]).unwrap();
+        assert_eq!((code, output.as_str()), (ExitCode::from(1), ""));
+        assert!(xyz(&["grep", "element", input]).is_err());
+    }
+
+    #[test]
+    fn matches_as_json_lines() {
+        let directory = directory("grep-json");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, lines) = xyz(&["grep", "--json", "index 2", input]).unwrap();
+        assert_eq!(lines.lines().next(), Some(r#"{"frame":0,"index":2,"symbol":"H","position":[-0.24,0.93,0]}"#));
+        let (_, counts) = xyz(&["grep", "--json", "--count", "element O", input]).unwrap();
+        assert_eq!(counts.lines().last(), Some(r#"{"frame":2,"count":1}"#));
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str = "xyz info [-o OUTPUT] [--json] FILE";
+
+/// Prints a summary of a file: the number of frames and atoms, the formula and the cell,
+/// with lengths in the configured units, and the dialect detected by sniffing a local file.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let mut summary = vec![];
+    let code = summarize(&args, &mut summary)?;
+    let output = args.value("-o").or(args.value("--output")).unwrap_or("-");
+    crate::write_to(output, out, |out| out.write_all(&summary))?;
+    Ok(code)
+}
+
+/// Writes the summary printed by [`run`].
+fn summarize(args: &Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+
+    let file = crate::read(path)?;
+    let dialect = match path.as_str() {
+        "-" => None,
+        path => rust_xyz_chem::sniff::sniff(path).ok().filter(|report| report.frames > 0),
+    };
+    if args.flag("--json") {
+        let counts = file.data.iter().map(|data| data.atoms.len());
+        let first = file.data.first();
+        let cell = first.and_then(|data| data.cell.as_ref()).map(|cell| {
+            let (lengths, angles) = cell.lengths_angles();
+            Json::object([
+                ("lengths", Json::numbers(lengths.map(|l| crate::config().length(l)))),
+                ("angles", Json::numbers(angles)),
+            ])
+        });
+        let json = Json::object([
+            ("frames", file.data.len().into()),
+            ("min_atoms", counts.clone().min().into()),
+            ("max_atoms", counts.max().into()),
+            ("formula", first.map(|data| data.formula()).into()),
+            ("comment", first.map(|data| data.comment.as_str()).into()),
+            ("cell", cell.into()),
+            ("dialect", dialect.map(|report| report.to_string()).into()),
+        ]);
+        writeln!(out, "{}", json)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    writeln!(out, "frames: {}", file.data.len())?;
+    if let Some(report) = dialect {
+        writeln!(out, "dialect: {}", report)?;
+    }
+
+    let counts = file.data.iter().map(|data| data.atoms.len());
+    if let (Some(min), Some(max)) = (counts.clone().min(), counts.max()) {
+        if min == max {
+            writeln!(out, "atoms: {}", min)?;
+        } else {
+            writeln!(out, "atoms: {} to {}", min, max)?;
+        }
+    }
+
+    if let Some(first) = file.data.first() {
+        writeln!(out, "formula: {}", first.formula())?;
+        writeln!(out, "comment: {}", first.comment)?;
+        if let Some(cell) = &first.cell {
+            let ([a, b, c], [alpha, beta, gamma]) = cell.lengths_angles();
+            let [a, b, c] = [a, b, c].map(|l| crate::config().length(l));
+            writeln!(
+                out,
+                "cell: {:.4} {:.4} {:.4} {:.2} {:.2} {:.2}",
+                a, b, c, alpha, beta, gamma
+            )?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn summary_to_stdout_and_file() {
+        let directory = directory("info");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (code, summary) = xyz(&["info", input]).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        let expected = "frames: 3\ndialect: plain XYZ (confidence 1.00)\natoms: 3\nformula: H2O\ncomment: frame 0\n";
+        assert_eq!(summary, expected);
+        assert_eq!(xyz(&["info", input, "--output", "-"]).unwrap().1, summary);
+
+        let output = directory.join("info.txt");
+        let (_, printed) = xyz(&["info", input, "--output", output.to_str().unwrap()]).unwrap();
+        assert!(printed.is_empty());
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), summary);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn summary_as_json() {
+        let directory = directory("info-json");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+
+        let (_, json) = xyz(&["info", "--json", input.to_str().unwrap()]).unwrap();
+        let expected = r#"{"frames":3,"min_atoms":3,"max_atoms":3,"formula":"H2O","comment":"frame 0","cell":null,"#;
+        let expected = format!(r#"{}"dialect":"plain XYZ (confidence 1.00)"}}"#, expected);
+        assert_eq!(json, format!("{}\n", expected));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+/// A JSON value, for the `--json` output of the subcommands.
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    /// An object from its fields, in order.
+    pub fn object<K: Into<String>, I: IntoIterator<Item = (K, Json)>>(fields: I) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.into(), value)).collect())
+    }
+
+    /// An array of numbers.
+    pub fn numbers<I: IntoIterator<Item = f64>>(values: I) -> Json {
+        Json::Array(values.into_iter().map(Json::from).collect())
+    }
+}
+impl From<f64> for Json {
+    /// A number, or `null` for NaN and infinite values, which JSON cannot represent.
+    fn from(value: f64) -> Json {
+        if value.is_finite() {
+            Json::Number(value)
+        } else {
+            Json::Null
+        }
+    }
+}
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Number(value as f64)
+    }
+}
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+impl From<&str> for Json {
+    fn from(value: &str) -> Json {
+        Json::String(value.to_string())
+    }
+}
+impl From<String> for Json {
+    fn from(value: String) -> Json {
+        Json::String(value)
+    }
+}
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+impl fmt::Display for Json {
+    /// Formats the value compactly on a single line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_formatting_and_escapes() {
+        let json = Json::object([
+            ("name", "a \"b\"\n\t\u{1}".into()),
+            ("values", Json::numbers([1.0, 0.5, f64::NAN])),
+            ("missing", Option::<usize>::None.into()),
+            ("nested", Json::object([("ok", true.into())])),
+        ]);
+        let expected = r#"{"name":"a \"b\"\n\t\u0001","values":[1,0.5,null],"missing":null,"nested":{"ok":true}}"#;
+        assert_eq!(json.to_string(), expected);
+        assert_eq!(Json::Array(vec![]).to_string(), "[]");
+    }
+}
@@ -0,0 +1,252 @@
+mod args;
+mod completions;
+mod config;
+mod diff;
+mod edit;
+mod frames;
+mod grep;
+mod info;
+mod json;
+mod measure;
+mod meta;
+mod rmsd;
+mod show;
+mod stream;
+#[cfg(test)]
+mod testing;
+mod validate;
+mod view;
+mod watch;
+
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+use std::sync::OnceLock;
+
+use args::Args;
+use config::Config;
+use rust_xyz_chem::data::{Data, File};
+use rust_xyz_chem::error::FileParseError;
+use rust_xyz_chem::Frames;
+
+type Result = std::result::Result<ExitCode, Box<dyn std::error::Error>>;
+type FrameResult = std::result::Result<Data, FileParseError>;
+
+/// A subcommand of the command line interface.
+struct Command {
+    name: &'static str,
+    usage: &'static str,
+    /// The options that take no value.
+    flags: &'static [&'static str],
+    /// The options that take a value.
+    valued: &'static [&'static str],
+    /// The options that take several values, with their number of values.
+    multi: &'static [(&'static str, usize)],
+    run: fn(Args, &mut dyn Write) -> Result,
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "completions",
+        usage: completions::USAGE,
+        flags: &[],
+        valued: &[],
+        multi: &[],
+        run: completions::run,
+    },
+    Command {
+        name: "diff",
+        usage: diff::USAGE,
+        flags: &["--json"],
+        valued: &["--tol", "-o", "--output"],
+        multi: &[],
+        run: diff::run,
+    },
+    Command {
+        name: "edit",
+        usage: edit::USAGE,
+        flags: &["--fix-symbols", "--center", "--sort-by-element", "--in-place"],
+        valued: &["--strip-element", "--translate", "--set-comment", "-o", "--output", "--checkpoint"],
+        multi: &[],
+        run: edit::run,
+    },
+    Command {
+        name: "frames",
+        usage: frames::USAGE,
+        flags: &[],
+        valued: &["--select", "--where", "-o", "--output", "--checkpoint"],
+        multi: &[],
+        run: frames::run,
+    },
+    Command {
+        name: "grep",
+        usage: grep::USAGE,
+        flags: &["--count", "--json"],
+        valued: &[],
+        multi: &[],
+        run: grep::run,
+    },
+    Command {
+        name: "info",
+        usage: info::USAGE,
+        flags: &["--json"],
+        valued: &["-o", "--output"],
+        multi: &[],
+        run: info::run,
+    },
+    Command {
+        name: "measure",
+        usage: measure::USAGE,
+        flags: &["--json"],
+        valued: &["--csv", "--tsv"],
+        multi: &[("--bond", 2), ("--angle", 3), ("--dihedral", 4)],
+        run: measure::run,
+    },
+    Command {
+        name: "meta",
+        usage: meta::USAGE,
+        flags: &[],
+        valued: &["--fields", "-o", "--output"],
+        multi: &[],
+        run: meta::run,
+    },
+    Command {
+        name: "rmsd",
+        usage: rmsd::USAGE,
+        flags: &["--no-align", "--json"],
+        valued: &["--reference", "--frame", "--csv", "--tsv"],
+        multi: &[],
+        run: rmsd::run,
+    },
+    Command {
+        name: "show",
+        usage: show::USAGE,
+        flags: &["--json"],
+        valued: &["--frame", "--select", "--color"],
+        multi: &[],
+        run: show::run,
+    },
+    Command {
+        name: "validate",
+        usage: validate::USAGE,
+        flags: &["--json"],
+        valued: &[],
+        multi: &[],
+        run: validate::run,
+    },
+    Command {
+        name: "view",
+        usage: view::USAGE,
+        flags: &[],
+        valued: &["--frame", "--axis", "--width", "--height", "--color"],
+        multi: &[],
+        run: view::run,
+    },
+    Command {
+        name: "watch",
+        usage: watch::USAGE,
+        flags: &["--tail", "--json"],
+        valued: &["--interval", "--timeout"],
+        multi: &[],
+        run: watch::run,
+    },
+];
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The configuration, loaded at startup.
+fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Reads a file, or stdin if the path is `-`.
+fn read(path: &str) -> std::result::Result<File, FileParseError> {
+    Ok(File {
+        data: frames(path)?.collect::<std::result::Result<_, _>>()?,
+    })
+}
+
+/// Opens a file, or stdin if the path is `-`, for reading.
+/// With the `remote` feature, the path can also be a URL.
+fn open(path: &str) -> io::Result<Box<dyn BufRead>> {
+    #[cfg(feature = "remote")]
+    if rust_xyz_chem::remote::is_url(path) {
+        return Ok(Box::new(io::BufReader::new(rust_xyz_chem::remote::open(path)?)));
+    }
+    if path == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(io::BufReader::new(std::fs::File::open(path)?)))
+    }
+}
+
+/// Opens a file like [`open`] for reading one frame at a time.
+fn frames(path: &str) -> std::result::Result<Frames<Box<dyn BufRead>>, FileParseError> {
+    Ok(Frames::with_options(open(path)?, config().parse_options()))
+}
+
+/// Writes with `write` to a file, or to `out` if the path is `-`.
+fn write_to(
+    path: &str,
+    out: &mut dyn Write,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    if path == "-" {
+        write(out)
+    } else {
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+        write(&mut file)?;
+        file.flush()
+    }
+}
+
+/// Whether the error is caused by the reader of stdout going away, e.g. `xyz info traj.xyz | head -1`.
+fn is_broken_pipe(e: &(dyn std::error::Error + 'static)) -> bool {
+    e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn usage() -> String {
+    let mut usage = String::from("usage:");
+    for command in COMMANDS {
+        usage.push_str("\n  ");
+        usage.push_str(command.usage);
+    }
+    usage
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(name) = args.next() else {
+        eprintln!("{}", usage());
+        return ExitCode::from(2);
+    };
+
+    let Some(command) = COMMANDS.iter().find(|c| c.name == name) else {
+        eprintln!("unknown command: {}\n{}", name, usage());
+        return ExitCode::from(2);
+    };
+
+    match Config::load() {
+        Ok(config) => {
+            let _ = CONFIG.set(config);
+        }
+        Err(e) => {
+            eprintln!("xyz: {}", e);
+            return ExitCode::from(2);
+        }
+    }
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = Args::parse(args, command.flags, command.valued, command.multi)
+        .map_err(|e| format!("{}\nusage: {}", e, command.usage).into())
+        .and_then(|args| (command.run)(args, &mut out))
+        .and_then(|code| out.flush().map(|_| code).map_err(Into::into));
+
+    match result {
+        Ok(code) => code,
+        Err(e) if is_broken_pipe(e.as_ref()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("xyz {}: {}", name, e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use rust_xyz_chem::analysis::{self, Series};
+use rust_xyz_chem::data::Data;
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str = "xyz measure [--bond I J]... [--angle I J K]... [--dihedral I J K L]... \
+                         [--csv OUT.csv] [--tsv OUT.tsv] [--json] FILE";
+
+/// Prints bond lengths, in the configured units, angles and dihedrals between zero-based atom indices for every frame,
+/// followed by their mean and standard deviation, reading one frame at a time.
+/// Dihedrals wrap around at ±180°, so their mean and spread are circular.
+/// Values that cannot be measured, e.g. for an index out of range, are left empty,
+/// and so are summaries of too few values.
+/// With `--csv` or `--tsv`, the per-frame values are written to that file and only the summary is printed.
+/// With `--json`, prints an object with the `values`, `mean` and `std` of every coordinate.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+
+    let mut coordinates = vec![];
+    for (option, name, n) in [("--bond", "bond", 2), ("--angle", "angle", 3), ("--dihedral", "dihedral", 4)] {
+        for value in args.values(option) {
+            let indices = value
+                .split([' ', ','])
+                .filter(|s| !s.is_empty())
+                .map(str::parse::<usize>)
+                .collect::<Result<Vec<usize>, _>>()
+                .ok()
+                .filter(|indices| indices.len() == n)
+                .ok_or_else(|| format!("Invalid value for option {}: {}", option, value))?;
+            let label = indices.iter().map(usize::to_string).collect::<Vec<String>>().join("_");
+            coordinates.push((format!("{}_{}", name, label), indices));
+        }
+    }
+    if coordinates.is_empty() {
+        return Err("nothing to measure, give --bond, --angle or --dihedral".into());
+    }
+
+    let measure = |data: &Data, indices: &[usize]| match *indices {
+        [i, j] => data.distance(i, j).map(|d| crate::config().length(d)),
+        [i, j, k] => data.angle(i, j, k),
+        [i, j, k, l] => data.dihedral(i, j, k, l),
+        _ => unreachable!(),
+    };
+    let mut series: Vec<Series> = coordinates.iter().map(|(name, _)| Series::new(name.as_str(), vec![])).collect();
+    for data in crate::frames(path)? {
+        let data = data?;
+        for (s, (_, indices)) in series.iter_mut().zip(&coordinates) {
+            s.values.push(measure(&data, indices).unwrap_or(f64::NAN));
+        }
+    }
+
+    let stats: Vec<[f64; 2]> = series
+        .iter()
+        .zip(&coordinates)
+        .map(|(s, (_, indices))| match indices.len() {
+            4 => [s.circular_mean(), s.circular_std()],
+            _ => [s.mean(), s.std()],
+        })
+        .collect();
+
+    let mut written = false;
+    if let Some(csv) = args.value("--csv") {
+        crate::write_to(csv, out, |w| analysis::to_csv(w, &series))?;
+        written = true;
+    }
+    if let Some(tsv) = args.value("--tsv") {
+        crate::write_to(tsv, out, |w| analysis::to_tsv(w, &series))?;
+        written = true;
+    }
+
+    if args.flag("--json") {
+        let fields = |f: &dyn Fn(usize) -> Json| {
+            Json::object(series.iter().enumerate().map(|(i, s)| (s.name.as_str(), f(i))))
+        };
+        let json = Json::object([
+            ("values", fields(&|i| Json::numbers(series[i].values.iter().copied()))),
+            ("mean", fields(&|i| stats[i][0].into())),
+            ("std", fields(&|i| stats[i][1].into())),
+        ]);
+        writeln!(out, "{}", json)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // The summary continues the table printed to stdout, or gets its own header.
+    let mut summary = String::new();
+    if written {
+        summary.push_str("frame");
+        series.iter().for_each(|s| summary.push_str(&format!("\t{}", s.name)));
+        summary.push('\n');
+    } else {
+        analysis::to_tsv(out, &series)?;
+    }
+    for (k, label) in ["mean", "std"].into_iter().enumerate() {
+        summary.push_str(label);
+        for v in stats.iter().map(|s| s[k]) {
+            summary.push('\t');
+            if !v.is_nan() {
+                summary.push_str(&v.to_string());
+            }
+        }
+        summary.push('\n');
+    }
+    out.write_all(summary.as_bytes())?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn table_with_summary() {
+        let directory = directory("measure");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let args = ["measure", "--bond", "0", "1", "--angle", "1", "0", "2", "--bond", "0", "5", input];
+        let (_, table) = xyz(&args).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "frame\tbond_0_1\tbond_0_5\tangle_1_0_2");
+        assert_eq!(lines.len(), 6);
+        assert!(lines[1].starts_with("0\t0.96\t\t104."));
+        assert!(lines[4].starts_with("mean\t0.96\t\t104."));
+        let std: Vec<&str> = lines[5].split('\t').collect();
+        assert_eq!((std[0], std[2]), ("std", ""));
+        assert!(std[1].parse::<f64>().unwrap() < 1e-12);
+
+        let csv = directory.join("measure.csv");
+        let (_, summary) = xyz(&["measure", "--bond", "0", "1", "--csv", csv.to_str().unwrap(), input]).unwrap();
+        assert!(summary.starts_with("frame\tbond_0_1\nmean\t"));
+        assert_eq!(std::fs::read_to_string(csv).unwrap().lines().count(), 4);
+        assert!(xyz(&["measure", input]).is_err());
+        assert!(xyz(&["measure", "--angle", "0", "1", "x", input]).is_err());
+    }
+
+    #[test]
+    fn dihedrals_are_averaged_around_the_circle() {
+        let input = directory("measure").join("dihedral.xyz");
+        let frame = |y: f64| format!("4\n\nC 1 0 0\nC 0 0 0\nC 0 0 1\nC -1 {} 1\n", y);
+        std::fs::write(&input, frame(-0.02) + &frame(0.02)).unwrap();
+
+        let (_, table) = xyz(&["measure", "--dihedral", "0", "1", "2", "3", input.to_str().unwrap()]).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        let value = |line: &str| line.split('\t').nth(1).unwrap().parse::<f64>().unwrap();
+        assert!((value(lines[1]).abs() - 180.0).abs() < 5.0);
+        assert!(value(lines[1]) * value(lines[2]) < 0.0);
+        assert!((value(lines[3]).abs() - 180.0).abs() < 1e-6);
+        assert!(value(lines[4]) < 5.0);
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "xyz meta [--fields KEYS] [-o OUTPUT] FILE";
+
+/// Writes the `key=value` properties of the comment of every frame as a CSV table, with a `frame` column
+/// followed by a column for every key in `--fields`, or for every key found, in the order they first appear.
+/// Keys are compared case-insensitively, and missing values are left empty.
+/// An output file ending in `.tsv` gets tab-separated columns.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let output = args.value("-o").or(args.value("--output")).unwrap_or("-");
+    let separator = if output.ends_with(".tsv") { '\t' } else { ',' };
+
+    let mut rows = vec![];
+    for data in crate::frames(path)? {
+        rows.push(data?.properties());
+    }
+    let fields: Vec<String> = match args.value("--fields") {
+        Some(fields) => fields.split(',').map(str::trim).filter(|f| !f.is_empty()).map(str::to_string).collect(),
+        None => {
+            let mut fields: Vec<String> = vec![];
+            for (key, _) in rows.iter().flatten() {
+                if !fields.iter().any(|f| f.eq_ignore_ascii_case(key)) {
+                    fields.push(key.clone());
+                }
+            }
+            fields
+        }
+    };
+
+    let cell = |value: &str| {
+        if value.contains([separator, '"', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let mut table = String::from("frame");
+    fields.iter().for_each(|field| table.push_str(&format!("{}{}", separator, cell(field))));
+    table.push('\n');
+    for (frame, properties) in rows.iter().enumerate() {
+        table.push_str(&frame.to_string());
+        for field in &fields {
+            table.push(separator);
+            if let Some((_, value)) = properties.iter().find(|(key, _)| key.eq_ignore_ascii_case(field)) {
+                table.push_str(&cell(value));
+            }
+        }
+        table.push('\n');
+    }
+    crate::write_to(output, out, |w| w.write_all(table.as_bytes()))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn properties_as_csv_and_tsv() {
+        let directory = directory("meta");
+        let input = directory.join("in.xyz");
+        let trajectory = TRAJECTORY
+            .replace("frame 0", "energy=-1.5 step=0")
+            .replace("frame 1", "Step=1 note=\"a, b\"")
+            .replace("frame 2", "step=2 energy=-1.7");
+        std::fs::write(&input, trajectory).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, table) = xyz(&["meta", input]).unwrap();
+        assert_eq!(table, "frame,energy,step,note\n0,-1.5,0,\n1,,1,\"a, b\"\n2,-1.7,2,\n");
+        let tsv = directory.join("meta.tsv");
+        xyz(&["meta", "--fields", "step,energy", "-o", tsv.to_str().unwrap(), input]).unwrap();
+        assert_eq!(std::fs::read_to_string(tsv).unwrap(), "frame\tstep\tenergy\n0\t0\t-1.5\n1\t1\t\n2\t2\t-1.7\n");
+    }
+}
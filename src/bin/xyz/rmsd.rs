@@ -0,0 +1,101 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use rust_xyz_chem::analysis::{self, Series};
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str =
+    "xyz rmsd [--reference REF.xyz] [--frame N] [--no-align] [--csv OUT.csv] [--tsv OUT.tsv] [--json] FILE";
+
+/// Prints the RMSD of every frame from a reference frame, along with the radius of gyration and the energy,
+/// with distances in the configured units.
+/// The reference is frame N (default 0) of the reference file, or of the file itself.
+/// The frames are superimposed onto the reference unless `--no-align` is given.
+/// With `--json`, prints an object with an array of values per quantity, where `null` marks a missing value.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let frame = args.parsed::<usize>("--frame")?.unwrap_or(0);
+    let align = !args.flag("--no-align");
+
+    if args.value("--reference") == Some("-") && path == "-" {
+        return Err("only one file can be read from stdin".into());
+    }
+    let file = crate::read(path)?;
+    let reference_file = match args.value("--reference") {
+        Some(reference) => crate::read(reference)?,
+        None => file.clone(),
+    };
+    let reference = reference_file.data.get(frame).ok_or_else(|| {
+        format!("frame {} out of range, the reference has {} frames", frame, reference_file.data.len())
+    })?;
+
+    let rmsd = |data: &rust_xyz_chem::data::Data| {
+        let rmsd = if align {
+            data.aligned_rmsd(reference)
+        } else {
+            data.rmsd(reference)
+        };
+        rmsd.map(|r| crate::config().length(r))
+    };
+    let rg = |data: &rust_xyz_chem::data::Data| data.radius_of_gyration().map(|r| crate::config().length(r));
+    let mut series = vec![
+        Series::new("rmsd", file.data.iter().map(|data| rmsd(data).unwrap_or(f64::NAN)).collect()),
+        Series::new("rg", file.data.iter().map(|data| rg(data).unwrap_or(f64::NAN)).collect()),
+    ];
+    if file.data.iter().any(|data| data.energy().is_some()) {
+        series.push(Series::new("energy", file.data.iter().map(|data| data.energy().unwrap_or(f64::NAN)).collect()));
+    }
+
+    let mut written = false;
+    if let Some(csv) = args.value("--csv") {
+        crate::write_to(csv, out, |w| analysis::to_csv(w, &series))?;
+        written = true;
+    }
+    if let Some(tsv) = args.value("--tsv") {
+        crate::write_to(tsv, out, |w| analysis::to_tsv(w, &series))?;
+        written = true;
+    }
+    if args.flag("--json") {
+        let json = Json::object(series.iter().map(|s| (s.name.as_str(), Json::numbers(s.values.iter().copied()))));
+        writeln!(out, "{}", json)?;
+    } else if !written {
+        analysis::to_tsv(out, &series)?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn rmsd_table_to_stdout_and_csv() {
+        let directory = directory("rmsd");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, table) = xyz(&["rmsd", "--no-align", input]).unwrap();
+        let rmsd: Vec<f64> =
+            table.lines().skip(1).map(|line| line.split('\t').nth(1).unwrap().parse().unwrap()).collect();
+        assert!(rmsd.iter().zip([0.0, 0.1, 0.2]).all(|(a, b)| (a - b).abs() < 1e-12), "{:?}", rmsd);
+        assert!(table.starts_with("frame\trmsd\trg\n"));
+
+        let csv = directory.join("rmsd.csv");
+        let args = ["rmsd", "--reference", input, "--frame", "2", "--csv", csv.to_str().unwrap(), input];
+        let (_, printed) = xyz(&args).unwrap();
+        assert!(printed.is_empty());
+        let csv = std::fs::read_to_string(csv).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.lines().skip(1).all(|line| line.split(',').nth(1).unwrap().parse::<f64>().unwrap() < 1e-6));
+
+        assert!(xyz(&["rmsd", "--frame", "3", input]).is_err());
+        assert!(xyz(&["rmsd", "--reference", "-", "-"]).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
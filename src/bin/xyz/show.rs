@@ -0,0 +1,86 @@
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str = "xyz show [--frame N] [--select EXPR] [--color auto|always|never] [--json] FILE";
+
+/// Prints the atoms of a frame as a table, highlighting the selected atoms.
+/// With `--json`, prints an array of atoms, with whether each is selected if a selection is given.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let frame = args.parsed::<usize>("--frame")?.unwrap_or(0);
+    let color = match args.value("--color").unwrap_or("auto") {
+        "auto" => std::io::stdout().is_terminal(),
+        "always" => true,
+        "never" => false,
+        other => return Err(format!("Invalid value for option --color: {}", other).into()),
+    };
+
+    let file = crate::read(path)?;
+    let data = file
+        .data
+        .get(frame)
+        .ok_or_else(|| format!("frame {} out of range, the file has {} frames", frame, file.data.len()))?;
+    let selected = args.value("--select").map(|expr| data.select(expr)).transpose()?;
+
+    if args.flag("--json") {
+        let atoms = data.atoms.iter().enumerate().map(|(i, atom)| {
+            let mut fields = vec![
+                ("index", i.into()),
+                ("symbol", atom.symbol.as_str().into()),
+                ("position", Json::numbers(atom.position.0)),
+            ];
+            if let Some(selected) = &selected {
+                fields.push(("selected", selected.contains(&i).into()));
+            }
+            Json::object(fields)
+        });
+        writeln!(out, "{}", Json::Array(atoms.collect()))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for (i, line) in data.display_table().lines().enumerate() {
+        let Some(selected) = &selected else {
+            writeln!(out, "{}", line)?;
+            continue;
+        };
+        let is_selected = i > 0 && selected.contains(&(i - 1));
+        match (color, is_selected) {
+            (true, true) => writeln!(out, "\x1b[1;33m{}\x1b[0m", line)?,
+            (true, false) => writeln!(out, "{}", line)?,
+            (false, true) => writeln!(out, "* {}", line)?,
+            (false, false) => writeln!(out, "  {}", line)?,
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn table_with_selected_atoms() {
+        let directory = directory("show");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, table) = xyz(&["show", "--frame", "1", input]).unwrap();
+        assert_eq!(table.lines().nth(1), Some("0  O        0.10000  0.00000  0.00000"));
+        let (_, table) = xyz(&["show", "--select", "element H", "--color", "never", input]).unwrap();
+        let marks: Vec<&str> = table.lines().map(|line| &line[..2]).collect();
+        assert_eq!(marks, ["  ", "  ", "* ", "* "]);
+        let (_, table) = xyz(&["show", "--select", "index 0", "--color", "always", input]).unwrap();
+        assert!(table.contains("\x1b[1;33m0  O"));
+
+        assert!(xyz(&["show", "--frame", "3", input]).is_err());
+        assert!(xyz(&["show", "--color", "blue", input]).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::process::ExitCode;
+
+use rust_xyz_chem::data::Data;
+use rust_xyz_chem::{write_frame_at, Frames};
+
+/// The number of frames between checkpoints.
+const CHECKPOINT_FRAMES: usize = 1000;
+
+/// The progress of a streaming command: the frames read and written, and the bytes of the input
+/// read and of the output written up to the end of the last frame.
+struct Checkpoint {
+    frames: usize,
+    written: usize,
+    input: u64,
+    output: u64,
+}
+impl Checkpoint {
+    fn start() -> Checkpoint {
+        Checkpoint {
+            frames: 0,
+            written: 0,
+            input: 0,
+            output: 0,
+        }
+    }
+
+    fn load(path: &str) -> Result<Option<Checkpoint>, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+        let value = |key: &str| -> Result<u64, String> {
+            contents
+                .split_whitespace()
+                .find_map(|entry| entry.strip_prefix(key)?.strip_prefix('='))
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| format!("{}: invalid checkpoint", path))
+        };
+        Ok(Some(Checkpoint {
+            frames: value("frames")? as usize,
+            written: value("written")? as usize,
+            input: value("input")?,
+            output: value("output")?,
+        }))
+    }
+
+    /// Replaces the checkpoint file, such that an interruption leaves either the old or the new one.
+    fn save(&self, path: &str) -> io::Result<()> {
+        let temporary = format!("{}.tmp", path);
+        let contents = format!(
+            "frames={} written={} input={} output={}\n",
+            self.frames, self.written, self.input, self.output
+        );
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)
+    }
+}
+
+/// Streams the frames of `input` to `output`, or to `out` if the path is `-`, one frame at a time.
+/// `process` gets the frame index, the frame and whether it is the last frame, and returns the frame to write, if any.
+///
+/// With a `checkpoint` path, the progress is saved there every thousand frames, and an existing checkpoint
+/// resumes an interrupted run: the input is read from the last checkpointed frame on, and the output is
+/// truncated to the frames written up to it. The checkpoint is removed once the run completes.
+pub fn stream(
+    input: &str,
+    output: &str,
+    checkpoint: Option<&str>,
+    out: &mut dyn Write,
+    mut process: impl FnMut(usize, Data, bool) -> Option<Data>,
+) -> crate::Result {
+    let Some(checkpoint) = checkpoint else {
+        let mut frames = crate::frames(input)?;
+        if output == "-" {
+            run(&mut frames, out, Checkpoint::start(), |_, _| Ok(()), &mut process)?;
+        } else {
+            let mut file = BufWriter::new(fs::File::create(output)?);
+            run(&mut frames, &mut file, Checkpoint::start(), |_, _| Ok(()), &mut process)?;
+            file.flush()?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    };
+    if input == "-" || output == "-" {
+        return Err("--checkpoint needs an input file and an output file".into());
+    }
+
+    let resumed = Checkpoint::load(checkpoint)?;
+    let resuming = resumed.is_some();
+    let start = resumed.unwrap_or_else(Checkpoint::start);
+    let mut reader = fs::File::open(input)?;
+    if reader.metadata()?.len() < start.input {
+        return Err(format!("{} is shorter than recorded in {}", input, checkpoint).into());
+    }
+    reader.seek(SeekFrom::Start(start.input))?;
+    let mut frames = Frames::with_options(
+        Box::new(io::BufReader::new(reader)) as Box<dyn io::BufRead>,
+        crate::config().parse_options(),
+    );
+
+    let mut file = if resuming {
+        let file = fs::OpenOptions::new().write(true).open(output)?;
+        if file.metadata()?.len() < start.output {
+            return Err(format!("{} is shorter than recorded in {}", output, checkpoint).into());
+        }
+        file.set_len(start.output)?;
+        file
+    } else {
+        fs::File::create(output)?
+    };
+    file.seek(SeekFrom::End(0))?;
+    let mut file = BufWriter::new(file);
+
+    let save = |file: &mut BufWriter<fs::File>, progress: Checkpoint| -> io::Result<()> {
+        file.flush()?;
+        file.get_ref().sync_data()?;
+        Checkpoint {
+            output: file.get_mut().stream_position()?,
+            ..progress
+        }
+        .save(checkpoint)
+    };
+    run(&mut frames, &mut file, start, save, &mut process)?;
+    file.flush()?;
+    // Runs shorter than a checkpoint interval never saved one.
+    match fs::remove_file(checkpoint) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Processes and writes the frames after `start`, calling `save` with the progress every
+/// [`CHECKPOINT_FRAMES`] frames.
+fn run<W: Write + ?Sized>(
+    frames: &mut Frames<Box<dyn io::BufRead>>,
+    out: &mut W,
+    start: Checkpoint,
+    mut save: impl FnMut(&mut W, Checkpoint) -> io::Result<()>,
+    process: &mut impl FnMut(usize, Data, bool) -> Option<Data>,
+) -> crate::Result {
+    let (mut frame, mut written) = (start.frames, start.written);
+    let mut next = frames.next();
+    while let Some(data) = next {
+        // The offset is taken before looking ahead at the next frame.
+        let offset = start.input + frames.byte_offset();
+        next = frames.next();
+        if let Some(data) = process(frame, data?, next.is_none()) {
+            write_frame_at(out, &data, written, &crate::config().write_options())?;
+            written += 1;
+        }
+        frame += 1;
+        if (frame - start.frames) % CHECKPOINT_FRAMES == 0 && next.is_some() {
+            let progress = Checkpoint {
+                frames: frame,
+                written,
+                input: offset,
+                output: 0,
+            };
+            save(out, progress)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn checkpoint_files() {
+        let directory = directory("checkpoint");
+        let path = directory.join("checkpoint");
+        let path = path.to_str().unwrap();
+        assert!(Checkpoint::load(path).unwrap().is_none());
+
+        let progress = Checkpoint {
+            frames: 2000,
+            written: 10,
+            input: 123456,
+            output: 789,
+        };
+        progress.save(path).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "frames=2000 written=10 input=123456 output=789\n");
+        let loaded = Checkpoint::load(path).unwrap().unwrap();
+        assert_eq!((loaded.frames, loaded.written, loaded.input, loaded.output), (2000, 10, 123456, 789));
+
+        fs::write(path, "frames=2000 written=10").unwrap();
+        assert!(Checkpoint::load(path).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let directory = directory("stream");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+        let (output, checkpoint) = (directory.join("out.xyz"), directory.join("checkpoint"));
+        let (output, checkpoint) = (output.to_str().unwrap(), checkpoint.to_str().unwrap());
+
+        xyz(&["frames", "-o", output, "--checkpoint", checkpoint, input]).unwrap();
+        let expected = fs::read_to_string(output).unwrap();
+        assert!(fs::metadata(checkpoint).is_err());
+
+        // An interrupted run that wrote the first frame and a part of the second.
+        let mut frames = Frames::new(Cursor::new(TRAJECTORY));
+        frames.next().unwrap().unwrap();
+        let first = expected.find("3\nframe 1").unwrap();
+        fs::write(output, format!("{}3\nfra", &expected[..first])).unwrap();
+        let progress = Checkpoint {
+            frames: 1,
+            written: 1,
+            input: frames.byte_offset(),
+            output: first as u64,
+        };
+        progress.save(checkpoint).unwrap();
+        assert_eq!(Checkpoint::load(checkpoint).unwrap().unwrap().input, progress.input);
+
+        xyz(&["frames", "-o", output, "--checkpoint", checkpoint, input]).unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap(), expected);
+        assert!(xyz(&["frames", "--checkpoint", checkpoint, input]).is_err());
+        fs::write(checkpoint, "frames=1").unwrap();
+        assert!(xyz(&["frames", "-o", output, "--checkpoint", checkpoint, input]).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
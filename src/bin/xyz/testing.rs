@@ -0,0 +1,46 @@
+//! Fixtures shared by the unit tests of the subcommands.
+
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rust_xyz_chem::data::Data;
+use rust_xyz_chem::Frames;
+
+use crate::args::Args;
+
+/// Three frames of a water molecule.
+pub const TRAJECTORY: &str = "3\nframe 0\nO 0.0 0.0 0.0\nH 0.96 0.0 0.0\nH -0.24 0.93 0.0\n\n\
+                              3\nframe 1\nO 0.1 0.0 0.0\nH 1.06 0.0 0.0\nH -0.14 0.93 0.0\n\n\
+                              3\nframe 2\nO 0.2 0.0 0.0\nH 1.16 0.0 0.0\nH -0.04 0.93 0.0\n";
+
+/// Two frames of a water molecule in a cell, with velocities and charges.
+pub const EXTENDED: &str = "3\nLattice=\"10 0 0 0 10 0 0 0 10\" Properties=species:S:1:pos:R:3:velo:R:3:charge:R:1 \
+                            step=0 pbc=\"T T T\"\n\
+                            O 0 0 0 0 0 0.1 -0.8\nH 0.96 0 0 0.2 0 0 0.4\nH -0.24 0.93 0 0 0.2 0 0.4\n\
+                            3\nLattice=\"10 0 0 0 10 0 0 0 10\" Properties=species:S:1:pos:R:3:velo:R:3:charge:R:1 \
+                            step=1 pbc=\"T T T\"\n\
+                            O 0 0 0.1 0 0 0.1 -0.8\nH 0.98 0 0.1 0.2 0 0 0.4\nH -0.22 0.93 0.1 0 0.2 0 0.4\n";
+
+/// The frames of a file written by a subcommand.
+pub fn frames(text: &str) -> Vec<Data> {
+    Frames::new(Cursor::new(text)).collect::<Result<_, _>>().unwrap()
+}
+
+/// An empty temporary directory named after `name`, unique to the test process.
+pub fn directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("rust-xyz-chem-xyz-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Runs `xyz` with `args`, returning the exit code and what was printed to stdout.
+pub fn xyz(args: &[&str]) -> Result<(ExitCode, String), Box<dyn std::error::Error>> {
+    let command = crate::COMMANDS.iter().find(|c| c.name == args[0]).unwrap();
+    let mut out = vec![];
+    let args = args[1..].iter().map(|arg| arg.to_string());
+    let args = Args::parse(args, command.flags, command.valued, command.multi)?;
+    let code = (command.run)(args, &mut out)?;
+    Ok((code, String::from_utf8(out)?))
+}
@@ -0,0 +1,93 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str = "xyz validate [--json] FILE";
+
+/// Checks a file one frame at a time and prints its problems: atoms with NaN or infinite coordinates,
+/// symbols that are not elements, and the first error that stops parsing.
+/// With `--json`, prints an object with whether the file is `valid`, the frames read and the problems.
+/// Exits with 0 if the file is valid, and 1 otherwise.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+
+    let mut problems: Vec<(Option<usize>, String)> = vec![];
+    let mut count = 0;
+    for data in crate::frames(path)? {
+        let data = match data {
+            Ok(data) => data,
+            Err(e) => {
+                problems.push((None, e.to_string()));
+                break;
+            }
+        };
+        for (i, atom) in data.atoms.iter().enumerate() {
+            if !atom.position.is_finite() {
+                problems.push((Some(count), format!("atom {} has a position that is not finite", i)));
+            }
+            if atom.element().is_none() {
+                problems.push((Some(count), format!("atom {} has the unknown element symbol {}", i, atom.symbol)));
+            }
+        }
+        count += 1;
+    }
+
+    let valid = problems.is_empty();
+    if args.flag("--json") {
+        let problems = problems.iter().map(|(frame, problem)| {
+            Json::object([("frame", (*frame).into()), ("problem", problem.as_str().into())])
+        });
+        let json = Json::object([
+            ("valid", valid.into()),
+            ("frames", count.into()),
+            ("problems", Json::Array(problems.collect())),
+        ]);
+        writeln!(out, "{}", json)?;
+    } else {
+        for (frame, problem) in &problems {
+            match frame {
+                Some(frame) => writeln!(out, "frame {}: {}", frame, problem)?,
+                None => writeln!(out, "{}", problem)?,
+            }
+        }
+        writeln!(out, "frames: {}", count)?;
+        writeln!(out, "{}", if valid { "valid" } else { "invalid" })?;
+    }
+
+    Ok(if valid { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitCode;
+
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn problems_of_a_file() {
+        let directory = directory("validate");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let (code, output) = xyz(&["validate", input.to_str().unwrap()]).unwrap();
+        assert_eq!((code, output.as_str()), (ExitCode::SUCCESS, "frames: 3\nvalid\n"));
+
+        std::fs::write(&input, "2\n\nO 0 0 0\nXx 1 0 0\n2\n\nH 0 0 NaN\nH 1 0 0\n2\n\nH 0 0\n").unwrap();
+        let (code, output) = xyz(&["validate", input.to_str().unwrap()]).unwrap();
+        assert_eq!(code, ExitCode::from(1));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "frame 0: atom 1 has the unknown element symbol Xx");
+        assert_eq!(lines[1], "frame 1: atom 0 has a position that is not finite");
+        assert!(lines[2].ends_with("at line 11"));
+        assert_eq!(&lines[3..], ["frames: 2", "invalid"]);
+
+        let (_, json) = xyz(&["validate", "--json", input.to_str().unwrap()]).unwrap();
+        assert!(json.starts_with("{\"valid\":false,\"frames\":2,\"problems\":[{\"frame\":0,"));
+        assert!(json.contains("{\"frame\":null,\"problem\":"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
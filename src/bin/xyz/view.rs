@@ -0,0 +1,121 @@
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+
+use rust_xyz_chem::geometry::Axis;
+
+use crate::args::Args;
+
+pub const USAGE: &str =
+    "xyz view [--frame N] [--axis x|y|z] [--width W] [--height H] [--color auto|always|never] FILE";
+
+/// Draws a frame as characters, projected orthographically along an axis.
+/// Atoms closer to the viewer are drawn on top.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let frame = args.parsed::<usize>("--frame")?.unwrap_or(0);
+    let width = args.parsed::<usize>("--width")?.unwrap_or(72).max(1);
+    let height = args.parsed::<usize>("--height")?.unwrap_or(24).max(1);
+    let axis = match args.value("--axis").unwrap_or("z") {
+        "x" => Axis::X,
+        "y" => Axis::Y,
+        "z" => Axis::Z,
+        other => return Err(format!("Invalid value for option --axis: {}", other).into()),
+    };
+    let color = match args.value("--color").unwrap_or("auto") {
+        "auto" => std::io::stdout().is_terminal(),
+        "always" => true,
+        "never" => false,
+        other => return Err(format!("Invalid value for option --color: {}", other).into()),
+    };
+
+    let file = crate::read(path)?;
+    let data = file
+        .data
+        .get(frame)
+        .ok_or_else(|| format!("frame {} out of range, the file has {} frames", frame, file.data.len()))?;
+
+    // Horizontal, vertical and depth coordinates, looking down the axis from its positive end.
+    let [h, v, d] = match axis {
+        Axis::X => [1, 2, 0],
+        Axis::Y => [2, 0, 1],
+        Axis::Z => [0, 1, 2],
+    };
+    let mut atoms: Vec<(f64, f64, f64, &str)> = data
+        .atoms
+        .iter()
+        .map(|atom| (atom.position[h], atom.position[v], atom.position[d], atom.symbol.as_str()))
+        .collect();
+    if atoms.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    atoms.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let min = |f: fn(&(f64, f64, f64, &str)) -> f64| atoms.iter().map(f).fold(f64::INFINITY, f64::min);
+    let max = |f: fn(&(f64, f64, f64, &str)) -> f64| atoms.iter().map(f).fold(f64::NEG_INFINITY, f64::max);
+    let (min_h, max_h, min_v, max_v) = (min(|a| a.0), max(|a| a.0), min(|a| a.1), max(|a| a.1));
+
+    // Characters are about twice as tall as they are wide.
+    let scale = f64::min(
+        (width - 1) as f64 / (max_h - min_h).max(f64::EPSILON),
+        2.0 * (height - 1) as f64 / (max_v - min_v).max(f64::EPSILON),
+    );
+    let rows = (((max_v - min_v) * scale / 2.0).round() as usize + 1).min(height);
+
+    let mut grid: Vec<Vec<Option<&str>>> = vec![vec![None; width]; rows];
+    for (x, y, _, symbol) in atoms {
+        let column = (((x - min_h) * scale).round() as usize).min(width - 1);
+        let row = (((max_v - y) * scale / 2.0).round() as usize).min(rows - 1);
+        grid[row][column] = Some(symbol);
+    }
+
+    for row in grid {
+        let end = row.iter().rposition(Option::is_some).map_or(0, |i| i + 1);
+        let mut line = String::new();
+        for cell in &row[..end] {
+            match cell {
+                None => line.push(' '),
+                Some(symbol) => {
+                    let c = symbol.chars().next().unwrap_or('?');
+                    match color_code(symbol).filter(|_| color) {
+                        Some(code) => line.push_str(&format!("\x1b[{}m{}\x1b[0m", code, c)),
+                        None => line.push(c),
+                    }
+                }
+            }
+        }
+        writeln!(out, "{}", line)?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The CPK color of an element as an ANSI true color code.
+fn color_code(symbol: &str) -> Option<String> {
+    let [r, g, b] = rust_xyz_chem::element::Element::from_symbol(symbol)?.cpk_color();
+    Some(format!("38;2;{};{};{}", r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn projection_along_axes() {
+        let directory = directory("view");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, view) = xyz(&["view", "--width", "13", "--height", "5", "--color", "never", input]).unwrap();
+        assert_eq!(view, "H\n\n\n\n  O       H\n");
+        // Along x, the hydrogen atom in front hides the oxygen atom.
+        let (_, view) = xyz(&["view", "--axis", "x", "--color", "never", input]).unwrap();
+        assert_eq!(view, format!("H{}H\n", " ".repeat(70)));
+        let (_, view) = xyz(&["view", "--width", "13", "--height", "5", "--color", "always", input]).unwrap();
+        assert!(view.starts_with("\x1b[38;2;255;255;255mH\x1b[0m\n"));
+        assert!(xyz(&["view", "--axis", "w", input]).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
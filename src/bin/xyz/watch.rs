@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use rust_xyz_chem::{follow, FollowOptions};
+
+use crate::args::Args;
+use crate::json::Json;
+
+pub const USAGE: &str = "xyz watch [--tail] [--interval SECONDS] [--timeout SECONDS] [--json] FILE";
+
+/// Prints a line for every frame of a file that is still being written, as the frames are completed,
+/// like `tail -f`. With `--tail`, only frames completed after the start are printed, numbered from the first of them.
+/// Runs until interrupted, or until no data was appended for `--timeout` seconds.
+pub fn run(args: Args, out: &mut dyn Write) -> crate::Result {
+    let [path] = args.positional() else {
+        return Err(format!("usage: {}", USAGE).into());
+    };
+    let seconds = |name: &str| -> Result<Option<Duration>, String> {
+        match args.parsed::<f64>(name)? {
+            Some(s) if s.is_finite() && s >= 0.0 => Ok(Some(Duration::from_secs_f64(s))),
+            Some(s) => Err(format!("Invalid value for option {}: {}", name, s)),
+            None => Ok(None),
+        }
+    };
+    let mut options = FollowOptions {
+        parse: crate::config().parse_options(),
+        from_end: args.flag("--tail"),
+        timeout: seconds("--timeout")?,
+        ..FollowOptions::default()
+    };
+    if let Some(interval) = seconds("--interval")? {
+        options.poll_interval = interval;
+    }
+
+    // Stdin already waits for the writer at its end.
+    let frames: Box<dyn Iterator<Item = crate::FrameResult>> =
+        if path == "-" { Box::new(crate::frames(path)?) } else { Box::new(follow(path, options)?) };
+    for (frame, data) in frames.enumerate() {
+        let data = data?;
+        if args.flag("--json") {
+            let json = Json::object([
+                ("frame", frame.into()),
+                ("atoms", data.atoms.len().into()),
+                ("energy", data.energy().into()),
+                ("comment", data.comment.as_str().into()),
+            ]);
+            writeln!(out, "{}", json)?;
+        } else {
+            let energy = data.energy().map_or_else(String::new, |e| e.to_string());
+            writeln!(out, "{}\t{}\t{}\t{}", frame, data.atoms.len(), energy, data.comment)?;
+        }
+        out.flush()?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{directory, xyz, TRAJECTORY};
+
+    #[test]
+    fn frames_until_timeout() {
+        let directory = directory("watch");
+        let input = directory.join("in.xyz");
+        std::fs::write(&input, TRAJECTORY.replace("frame 1", "energy=-1.5")).unwrap();
+        let input = input.to_str().unwrap();
+
+        let (_, lines) = xyz(&["watch", "--interval", "0.01", "--timeout", "0.05", input]).unwrap();
+        assert_eq!(lines, "0\t3\t\tframe 0\n1\t3\t-1.5\tenergy=-1.5\n2\t3\t\tframe 2\n");
+        let (_, json) = xyz(&["watch", "--json", "--timeout", "0", input]).unwrap();
+        assert_eq!(json.lines().nth(1), Some(r#"{"frame":1,"atoms":3,"energy":-1.5,"comment":"energy=-1.5"}"#));
+        assert!(xyz(&["watch", "--timeout", "-1", input]).is_err());
+    }
+}
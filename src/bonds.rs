@@ -0,0 +1,139 @@
+//! Perceives bonds from interatomic distances.
+
+use std::collections::HashMap;
+
+use super::cell::Cell;
+use super::data::Data;
+use super::position::Position;
+
+/// The tolerance in Ångström added to the sum of covalent radii when perceiving bonds.
+pub const BOND_TOLERANCE: f64 = 0.45;
+
+impl Data {
+    /// The pairs of bonded atoms `(i, j)` with `i < j`, sorted.
+    /// Two atoms are bonded if their distance is at most the sum of their covalent radii plus [`BOND_TOLERANCE`].
+    /// Atoms that are not known elements, or lack a covalent radius, have no bonds. The cell is ignored.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(atoms = self.atoms.len())))]
+    pub fn bonds(&self) -> Vec<(usize, usize)> {
+        let radii: Vec<Option<f64>> = self
+            .atoms
+            .iter()
+            .map(|atom| atom.element().and_then(|element| element.covalent_radius()))
+            .collect();
+        let max_radius = radii.iter().flatten().fold(0.0, |a: f64, &b| a.max(b));
+        let cutoff = 2.0 * max_radius + BOND_TOLERANCE;
+
+        let mut bonds = vec![];
+        let atoms = (0..self.atoms.len()).filter(|&i| radii[i].is_some());
+        close_pairs(self, None, cutoff, atoms, |i, j| {
+            let distance = self.atoms[i].position.distance(self.atoms[j].position);
+            if distance <= radii[i].unwrap() + radii[j].unwrap() + BOND_TOLERANCE {
+                bonds.push((i, j));
+            }
+        });
+        bonds.sort_unstable();
+        bonds
+    }
+}
+
+/// Calls `visit` with every pair `(i, j)`, `i < j`, of the given atoms closer than `cutoff`, and some farther apart,
+/// by sorting the atoms into bins of that size so only neighbouring bins are compared.
+/// With a cell, the bins are fractional and wrap around, so pairs that are close under the minimum image convention
+/// are visited too. Atoms with coordinates that are not finite are skipped.
+pub(crate) fn close_pairs(
+    data: &Data,
+    cell: Option<&Cell>,
+    cutoff: f64,
+    atoms: impl IntoIterator<Item = usize>,
+    mut visit: impl FnMut(usize, usize),
+) {
+    if cutoff.is_nan() || cutoff <= 0.0 {
+        return;
+    }
+    // The number of bins along each lattice vector, such that a bin is at least `cutoff` high.
+    let counts = cell.map(|cell| {
+        let [a, b, c] = cell.vectors.map(Position);
+        let heights = [b.cross(c), c.cross(a), a.cross(b)].map(|normal| cell.volume() / normal.norm());
+        heights.map(|height| ((height / cutoff).floor() as i64).max(1))
+    });
+    let bin = |position: Position| match (cell, counts) {
+        (Some(cell), Some(counts)) => {
+            let fractional = cell.fractional(position);
+            [0, 1, 2].map(|k| ((fractional[k] - fractional[k].floor()) * counts[k] as f64).floor() as i64 % counts[k])
+        }
+        _ => position.0.map(|v| (v / cutoff).floor() as i64),
+    };
+    let mut bins: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    for i in atoms.into_iter().filter(|&i| data.atoms[i].position.is_finite()) {
+        bins.entry(bin(data.atoms[i].position)).or_default().push(i);
+    }
+
+    for (&[x, y, z], atoms) in &bins {
+        let offsets = (-1..=1).flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [dx, dy, dz])));
+        let mut neighbours: Vec<[i64; 3]> = offsets
+            .map(|[dx, dy, dz]| {
+                let neighbour = [x + dx, y + dy, z + dz];
+                match counts {
+                    Some(counts) => [0, 1, 2].map(|k| neighbour[k].rem_euclid(counts[k])),
+                    None => neighbour,
+                }
+            })
+            .collect();
+        // With fewer than three bins along a vector, the wrapped neighbours repeat.
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for neighbour in neighbours {
+            let Some(others) = bins.get(&neighbour) else {
+                continue;
+            };
+            for &i in atoms {
+                for &j in others.iter().filter(|&&j| i < j) {
+                    visit(i, j);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::minimum_image_distance;
+    use crate::random::Rng;
+    use crate::testing::{frame, water};
+
+    #[test]
+    fn bonds_within_covalent_radii() {
+        assert_eq!(water().bonds(), [(0, 1), (0, 2)]);
+        let far = frame("", &[("H", [0.0; 3]), ("H", [1.1, 0.0, 0.0]), ("Xx", [0.5, 0.0, 0.0])]);
+        assert!(far.bonds().is_empty());
+    }
+
+    #[test]
+    fn close_pairs_under_the_minimum_image_convention() {
+        let mut rng = Rng::new(7);
+        let atoms: Vec<(&str, [f64; 3])> =
+            (0..60).map(|_| ("C", [0; 3].map(|_| 12.0 * rng.uniform() - 1.0))).collect();
+        let mut data = frame("", &atoms);
+        let skewed = Cell::new([10.0, 0.0, 0.0], [3.0, 9.0, 0.0], [1.0, 2.0, 8.0]);
+        for (cell, cutoff) in [(None, 1.5), (Some(skewed), 3.0)] {
+            data.cell = cell;
+            let distance = |i: usize, j: usize| {
+                let (a, b) = (data.atoms[i].position, data.atoms[j].position);
+                cell.map_or(a.distance(b), |cell| minimum_image_distance(&cell, a, b))
+            };
+            let mut pairs = vec![];
+            close_pairs(&data, cell.as_ref(), cutoff, 0..atoms.len(), |i, j| pairs.push((i, j)));
+            let count = pairs.len();
+            pairs.sort_unstable();
+            pairs.dedup();
+            assert_eq!(pairs.len(), count);
+            pairs.retain(|&(i, j)| distance(i, j) < cutoff);
+
+            let all = (0..atoms.len()).flat_map(|i| (i + 1..atoms.len()).map(move |j| (i, j)));
+            let expected: Vec<(usize, usize)> = all.filter(|&(i, j)| distance(i, j) < cutoff).collect();
+            assert!(!expected.is_empty());
+            assert_eq!(pairs, expected);
+        }
+    }
+}
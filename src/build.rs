@@ -0,0 +1,1026 @@
+//! Builds new structures, from existing ones or from templates of ideal geometries.
+
+use std::collections::BTreeMap;
+
+use super::analysis::minimum_image_distance;
+use super::cell::Cell;
+use super::data::{AtomData, Data};
+use super::edit::rotate_onto;
+use super::error::BuildError;
+use super::math::{self, Mat3};
+use super::position::Position;
+use super::random::Rng;
+
+/// An ideal geometry to build with [`molecule`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Template {
+    /// Water with O–H bonds of 0.9572 Å and an angle of 104.52°, in the xy-plane.
+    Water,
+    /// Methane with C–H bonds of 1.087 Å.
+    Methane,
+    /// Benzene with C–C bonds of 1.397 Å and C–H bonds of 1.084 Å, in the xy-plane.
+    Benzene,
+    /// A straight chain of `length` atoms along x, `bond_length` Ångström apart.
+    Chain { symbol: String, length: usize, bond_length: f64 },
+    /// The conventional cubic cell of a face-centered cubic lattice with lattice constant `a`, with four atoms.
+    Fcc { symbol: String, a: f64 },
+    /// The conventional cubic cell of a body-centered cubic lattice with lattice constant `a`, with two atoms.
+    Bcc { symbol: String, a: f64 },
+    /// The hexagonal cell of a hexagonal close-packed lattice with lattice constants `a` and `c`, with two atoms.
+    /// Ideal packing has `c = a * (8 / 3).sqrt()`.
+    Hcp { symbol: String, a: f64, c: f64 },
+}
+
+/// Builds the ideal geometry of a template, e.g. a test system or a starting point for a model.
+/// Molecules have their center of mass at the origin and the central atom first, and lattices have a cell
+/// with an atom at the origin, to be repeated with [`supercell`].
+pub fn molecule(template: &Template) -> Data {
+    let atom = |symbol: &str, position: [f64; 3]| AtomData::new(symbol, position);
+    let (atoms, cell) = match template {
+        Template::Water => {
+            let (r, half) = (0.9572, 104.52f64.to_radians() / 2.0);
+            let (x, y) = (r * half.sin(), r * half.cos());
+            (vec![atom("O", [0.0; 3]), atom("H", [x, y, 0.0]), atom("H", [-x, y, 0.0])], None)
+        }
+        Template::Methane => {
+            let d = 1.087 / 3f64.sqrt();
+            let mut atoms = vec![atom("C", [0.0; 3])];
+            for [x, y, z] in [[1.0, 1.0, 1.0], [1.0, -1.0, -1.0], [-1.0, 1.0, -1.0], [-1.0, -1.0, 1.0]] {
+                atoms.push(atom("H", [x * d, y * d, z * d]));
+            }
+            (atoms, None)
+        }
+        Template::Benzene => {
+            let ring = |symbol: &'static str, r: f64| {
+                (0..6).map(move |i| {
+                    let phi = i as f64 * std::f64::consts::PI / 3.0;
+                    AtomData::new(symbol, [r * phi.cos(), r * phi.sin(), 0.0])
+                })
+            };
+            (ring("C", 1.397).chain(ring("H", 1.397 + 1.084)).collect(), None)
+        }
+        Template::Chain {
+            symbol,
+            length,
+            bond_length,
+        } => {
+            let offset = length.saturating_sub(1) as f64 * bond_length / 2.0;
+            let atoms = (0..*length).map(|i| atom(symbol, [i as f64 * bond_length - offset, 0.0, 0.0])).collect();
+            (atoms, None)
+        }
+        Template::Fcc { symbol, a } => {
+            let basis = [[0.0, 0.0, 0.0], [0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]];
+            let cell = Cell::new([*a, 0.0, 0.0], [0.0, *a, 0.0], [0.0, 0.0, *a]);
+            (basis.iter().map(|&f| atom(symbol, cell.cartesian(f).0)).collect(), Some(cell))
+        }
+        Template::Bcc { symbol, a } => {
+            let cell = Cell::new([*a, 0.0, 0.0], [0.0, *a, 0.0], [0.0, 0.0, *a]);
+            (vec![atom(symbol, [0.0; 3]), atom(symbol, [a / 2.0; 3])], Some(cell))
+        }
+        Template::Hcp { symbol, a, c } => {
+            let cell = Cell::new([*a, 0.0, 0.0], [-a / 2.0, a * 3f64.sqrt() / 2.0, 0.0], [0.0, 0.0, *c]);
+            let basis = [[0.0, 0.0, 0.0], [1.0 / 3.0, 2.0 / 3.0, 0.5]];
+            (basis.iter().map(|&f| atom(symbol, cell.cartesian(f).0)).collect(), Some(cell))
+        }
+    };
+
+    let mut data = Data {
+        count: atoms.len(),
+        comment: String::new(),
+        atoms,
+        cell: None,
+    };
+    if cell.is_none() {
+        if let Some(center) = data.center_of_mass() {
+            data.atoms.iter_mut().for_each(|atom| atom.position -= center);
+        }
+    }
+    data.set_cell(cell);
+    data
+}
+
+/// A periodic box of water molecules with the rigid geometry of [`Template::Water`], as used by TIP3P,
+/// filled to `density` g/cm³ in an orthorhombic cell with edges `dimensions` in Ångström, as a quick starting point
+/// for solvation. The molecules are placed on randomly chosen sites of a grid as fine as the density allows,
+/// with random orientations, so they do not overlap but need an equilibration before production runs.
+/// The same `seed` always gives the same box.
+pub fn water_box(density: f64, dimensions: [f64; 3], seed: u64) -> Data {
+    let volume: f64 = dimensions.iter().product();
+    // 18.015 g/mol of water and 1 Å³ = 1e-24 cm³.
+    let molecules = (density * volume * 0.602_214_076 / 18.015).round().max(0.0) as usize;
+    let water = molecule(&Template::Water);
+    let mut rng = Rng::new(seed);
+
+    let spacing = (volume / molecules.max(1) as f64).cbrt();
+    let mut sites_per_axis = dimensions.map(|l| ((l / spacing).floor() as usize).max(1));
+    // Refine the coarsest axis until the grid has enough sites.
+    while sites_per_axis.iter().product::<usize>() < molecules {
+        let coarsest = (0..3).max_by(|&a, &b| {
+            (dimensions[a] / sites_per_axis[a] as f64).total_cmp(&(dimensions[b] / sites_per_axis[b] as f64))
+        });
+        sites_per_axis[coarsest.unwrap()] += 1;
+    }
+    let [nx, ny, nz] = sites_per_axis;
+    let mut sites: Vec<[usize; 3]> =
+        (0..nx).flat_map(|i| (0..ny).flat_map(move |j| (0..nz).map(move |k| [i, j, k]))).collect();
+    rng.shuffle(&mut sites);
+
+    let mut atoms = Vec::with_capacity(3 * molecules);
+    for site in &sites[..molecules] {
+        let center = Position([0, 1, 2].map(|i| (site[i] as f64 + 0.5) * dimensions[i] / sites_per_axis[i] as f64));
+        // A random unit quaternion is a uniformly random rotation.
+        let q = Position::new(rng.normal(), rng.normal(), rng.normal());
+        let w = rng.normal();
+        let norm = (q.dot(q) + w * w).sqrt();
+        let (q, w) = (q / norm, w / norm);
+        for atom in &water.atoms {
+            let mut atom = atom.clone();
+            let p = atom.position;
+            let t = q.cross(p) * 2.0;
+            atom.position = center + p + t * w + q.cross(t);
+            atoms.push(atom);
+        }
+    }
+
+    let mut data = Data {
+        count: atoms.len(),
+        comment: String::new(),
+        atoms,
+        cell: None,
+    };
+    let [a, b, c] = dimensions;
+    data.set_cell(Some(Cell::new([a, 0.0, 0.0], [0.0, b, 0.0], [0.0, 0.0, c])));
+    data
+}
+
+/// A kind of adsorption site on a surface, see [`adsorb`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Site {
+    /// Directly above an atom of the top layer.
+    Top,
+    /// Between two neighbouring atoms of the top layer.
+    Bridge,
+    /// Above the center of three mutually neighbouring atoms of the top layer.
+    Hollow,
+}
+impl std::fmt::Display for Site {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Site::Top => write!(f, "top"),
+            Site::Bridge => write!(f, "bridge"),
+            Site::Hollow => write!(f, "hollow"),
+        }
+    }
+}
+
+/// How an adsorbed molecule is rotated before placing it, see [`adsorb`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orientation {
+    /// The direction in the molecule that is turned to point away from the surface, along z.
+    pub up: Position,
+    /// The rotation in degrees around z afterwards.
+    pub angle: f64,
+}
+impl Default for Orientation {
+    /// Keeps the molecule as it is.
+    fn default() -> Orientation {
+        Orientation {
+            up: Position::new(0.0, 0.0, 1.0),
+            angle: 0.0,
+        }
+    }
+}
+
+/// The atoms within this distance in Ångström below the topmost atom form the top layer of a surface.
+const LAYER_TOLERANCE: f64 = 0.5;
+
+/// The positions of the sites of a kind on the top layer of a slab with its surface normal along z,
+/// at the height of the topmost atom. The top layer holds the atoms within 0.5 Å of the topmost atom,
+/// and neighbours in it are at most 20 % farther apart than the closest pair, across the cell boundaries
+/// if the slab has a cell. Hollow sites above atoms of the second layer are not told apart from the others.
+pub fn surface_sites(slab: &Data, site: Site) -> Vec<Position> {
+    let top = slab.atoms.iter().map(|atom| atom.position[2]).fold(f64::NEG_INFINITY, f64::max);
+    let layer: Vec<Position> =
+        slab.atoms.iter().map(|atom| atom.position).filter(|p| p[2] >= top - LAYER_TOLERANCE).collect();
+    // The vector between two atoms in the plane, to the nearest periodic image.
+    let offset = |a: Position, b: Position| {
+        let d = Position::new(b[0] - a[0], b[1] - a[1], 0.0);
+        match slab.cell {
+            Some(cell) => {
+                let mut f = cell.fractional(d);
+                f[0] -= f[0].round();
+                f[1] -= f[1].round();
+                cell.cartesian(f)
+            }
+            None => d,
+        }
+    };
+    let closest = (0..layer.len())
+        .flat_map(|i| (i + 1..layer.len()).map(move |j| (i, j)))
+        .map(|(i, j)| offset(layer[i], layer[j]).norm())
+        .fold(f64::INFINITY, f64::min);
+    let near = |a: Position, b: Position| offset(a, b).norm() <= 1.2 * closest;
+    let at_top = |p: Position| Position::new(p[0], p[1], top);
+
+    let n = layer.len();
+    match site {
+        Site::Top => layer.iter().map(|&p| at_top(p)).collect(),
+        Site::Bridge => (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .filter(|&(i, j)| near(layer[i], layer[j]))
+            .map(|(i, j)| at_top(layer[i] + offset(layer[i], layer[j]) / 2.0))
+            .collect(),
+        Site::Hollow => {
+            let mut sites = vec![];
+            for i in 0..n {
+                for j in (i + 1..n).filter(|&j| near(layer[i], layer[j])) {
+                    for k in (j + 1..n).filter(|&k| near(layer[i], layer[k]) && near(layer[j], layer[k])) {
+                        let (a, b) = (offset(layer[i], layer[j]), offset(layer[i], layer[k]));
+                        // The images of the neighbours must also be neighbours of each other.
+                        if (b - a).norm() <= 1.2 * closest {
+                            sites.push(at_top(layer[i] + (a + b) / 3.0));
+                        }
+                    }
+                }
+            }
+            sites
+        }
+    }
+}
+
+/// Places a molecule above a site of the top layer of a slab with its surface normal along z, as when building
+/// models of catalysis. The molecule is rotated by `orientation`, and then moved so its first atom, which is
+/// usually the binding one, lies `height` Ångström above the site closest to the center of the surface.
+/// Returns the slab with the atoms of the molecule appended.
+pub fn adsorb(
+    slab: &Data,
+    molecule: &Data,
+    site: Site,
+    height: f64,
+    orientation: Orientation,
+) -> Result<Data, BuildError> {
+    let anchor = molecule.atoms.first().ok_or(BuildError::EmptyMolecule)?.position;
+    let sites = surface_sites(slab, site);
+    let center = match slab.cell {
+        Some(cell) => cell.cartesian([0.5, 0.5, 0.0]),
+        None => slab.atoms.iter().map(|atom| atom.position).sum::<Position>() / slab.atoms.len().max(1) as f64,
+    };
+    let distance = |p: &Position| (p[0] - center[0]).hypot(p[1] - center[1]);
+    let target = sites.into_iter().min_by(|a, b| distance(a).total_cmp(&distance(b)));
+    let target = target.ok_or(BuildError::MissingSite(site))?;
+
+    let up = orientation.up.normalized().unwrap_or(Position::new(0.0, 0.0, 1.0));
+    let (sin, cos) = orientation.angle.to_radians().sin_cos();
+    let rotate = |p: Position| {
+        let p = rotate_onto(p - anchor, up, Position::new(0.0, 0.0, 1.0));
+        Position::new(p[0] * cos - p[1] * sin, p[0] * sin + p[1] * cos, p[2])
+    };
+
+    let mut data = slab.clone();
+    for atom in &molecule.atoms {
+        let mut atom = atom.clone();
+        atom.position = target + Position::new(0.0, 0.0, height) + rotate(atom.position);
+        data.atoms.push(atom);
+    }
+    data.count = data.atoms.len();
+    Ok(data)
+}
+
+/// Options of [`random_alloy_with`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlloyOptions {
+    /// Only the atoms with this symbol are replaced, e.g. the cations of an oxide. All atoms by default.
+    pub sites: Option<String>,
+    /// The number of swaps of two atoms of different elements tried to bring the Warren–Cowley parameters
+    /// of the nearest neighbours toward `short_range_order`. No swaps are tried by default.
+    pub swaps: usize,
+    /// The target Warren–Cowley parameter of every pair of different elements: 0 for a special quasirandom
+    /// structure, negative for ordering and positive for clustering.
+    pub short_range_order: f64,
+}
+
+/// Replaces the elements of the atoms of `data` at random to match `composition`, the fractions of the elements
+/// by symbol, e.g. to generate a random solid solution from a supercell of a pure metal. The fractions are
+/// normalized, and the numbers of atoms rounded so they add up. The same `seed` always gives the same structure.
+pub fn random_alloy(data: &Data, composition: &BTreeMap<String, f64>, seed: u64) -> Result<Data, BuildError> {
+    random_alloy_with(data, composition, seed, &AlloyOptions::default())
+}
+
+/// Generates a random alloy like [`random_alloy`], on the sites and with the short-range order of `options`.
+/// Swaps are kept if they do not move the Warren–Cowley parameters of the nearest neighbours, found within
+/// 10 % of the closest distance between sites under the minimum image convention, away from the target.
+pub fn random_alloy_with(
+    data: &Data,
+    composition: &BTreeMap<String, f64>,
+    seed: u64,
+    options: &AlloyOptions,
+) -> Result<Data, BuildError> {
+    let total: f64 = composition.values().sum();
+    if composition.is_empty() || composition.values().any(|&f| f.is_nan() || f < 0.0) || total <= 0.0 {
+        return Err(BuildError::InvalidComposition("the fractions must be non-negative".to_string()));
+    }
+    let sites: Vec<usize> = (0..data.atoms.len())
+        .filter(|&i| options.sites.as_ref().is_none_or(|symbol| &data.atoms[i].symbol == symbol))
+        .collect();
+    if sites.is_empty() {
+        return Err(BuildError::InvalidComposition("there are no sites to replace".to_string()));
+    }
+
+    // Round the numbers of atoms by the largest remainders, so they add up to the number of sites.
+    let n = sites.len();
+    let exact: Vec<f64> = composition.values().map(|f| f / total * n as f64).collect();
+    let mut counts: Vec<usize> = exact.iter().map(|x| x.floor() as usize).collect();
+    let mut order: Vec<usize> = (0..counts.len()).collect();
+    order.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+    for &k in order.iter().cycle().take(n - counts.iter().sum::<usize>()) {
+        counts[k] += 1;
+    }
+    let mut species: Vec<usize> = counts.iter().enumerate().flat_map(|(k, &c)| std::iter::repeat_n(k, c)).collect();
+    let mut rng = Rng::new(seed);
+    rng.shuffle(&mut species);
+
+    if options.swaps > 0 && counts.iter().filter(|&&c| c > 0).count() > 1 {
+        let distance = |i: usize, j: usize| {
+            let (a, b) = (data.atoms[sites[i]].position, data.atoms[sites[j]].position);
+            data.cell.map_or_else(|| a.distance(b), |cell| minimum_image_distance(&cell, a, b))
+        };
+        let pairs = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)));
+        let closest = pairs.map(|(i, j)| distance(i, j)).fold(f64::INFINITY, f64::min);
+        let neighbours: Vec<Vec<usize>> =
+            (0..n).map(|i| (0..n).filter(|&j| j != i && distance(i, j) <= 1.1 * closest).collect()).collect();
+        let fractions: Vec<f64> = counts.iter().map(|&c| c as f64 / n as f64).collect();
+        let deviation = |species: &[usize]| {
+            let k = counts.len();
+            let mut pairs = vec![0.0; k * k];
+            for (i, list) in neighbours.iter().enumerate() {
+                for &j in list {
+                    pairs[species[i] * k + species[j]] += 1.0;
+                }
+            }
+            let mut sum = 0.0;
+            for a in (0..k).filter(|&a| counts[a] > 0) {
+                let shell: f64 = (0..k).map(|b| pairs[a * k + b]).sum();
+                for b in (0..k).filter(|&b| b != a && counts[b] > 0 && shell > 0.0) {
+                    let alpha = 1.0 - pairs[a * k + b] / shell / fractions[b];
+                    sum += (alpha - options.short_range_order).powi(2);
+                }
+            }
+            sum
+        };
+
+        let mut current = deviation(&species);
+        for _ in 0..options.swaps {
+            let i = (rng.uniform() * n as f64) as usize % n;
+            let j = (rng.uniform() * n as f64) as usize % n;
+            if species[i] == species[j] {
+                continue;
+            }
+            species.swap(i, j);
+            let trial = deviation(&species);
+            if trial <= current {
+                current = trial;
+            } else {
+                species.swap(i, j);
+            }
+        }
+    }
+
+    let symbols: Vec<&String> = composition.keys().collect();
+    let mut alloy = data.clone();
+    for (&site, &k) in sites.iter().zip(&species) {
+        alloy.atoms[site].symbol = symbols[k].clone();
+        alloy.atoms[site].resolved_element = None;
+    }
+    Ok(alloy)
+}
+
+/// The length of the C–C bonds of graphene and nanotubes in Ångström.
+const GRAPHENE_BOND: f64 = 1.42;
+
+/// The spacing between the layers of graphite in Ångström.
+const GRAPHITE_SPACING: f64 = 3.35;
+
+/// A graphene sheet of `repeats` hexagonal cells of two atoms in the xy-plane, with C–C bonds of 1.42 Å
+/// and `vacuum` Ångström of vacuum on both sides along z. Without vacuum, the cell is 3.35 Å high along z,
+/// the interlayer spacing of graphite, so that the sheets stack like in bulk graphite.
+pub fn graphene(repeats: [usize; 2], vacuum: f64) -> Data {
+    let a = GRAPHENE_BOND * 3f64.sqrt();
+    let cell = Cell::new([a, 0.0, 0.0], [a / 2.0, a * 3f64.sqrt() / 2.0, 0.0], [0.0, 0.0, 1.0]);
+    let mut atoms = vec![];
+    for i in 0..repeats[0] {
+        for j in 0..repeats[1] {
+            for [x, y] in [[0.0, 0.0], [1.0 / 3.0, 1.0 / 3.0]] {
+                atoms.push(AtomData::new("C", cell.cartesian([i as f64 + x, j as f64 + y, 0.0])));
+            }
+        }
+    }
+
+    let [a, b, _] = cell.vectors;
+    let mut sheet = Data {
+        count: atoms.len(),
+        comment: String::new(),
+        atoms,
+        cell: None,
+    };
+    let repeated = Cell::new(a.map(|v| v * repeats[0] as f64), b.map(|v| v * repeats[1] as f64), [0.0; 3]);
+    let vacuum = if vacuum > 0.0 { vacuum } else { GRAPHITE_SPACING / 2.0 };
+    add_vacuum(&mut sheet, repeated, vacuum);
+    sheet
+}
+
+/// A single-walled carbon nanotube with chiral indices `(n, m)` along z, with C–C bonds of 1.42 Å,
+/// repeated `cells` times along its translational unit cell. The tube is centered in a periodic cell
+/// with `vacuum` Ångström of vacuum around it along x and y.
+pub fn nanotube(n: usize, m: usize, cells: usize, vacuum: f64) -> Result<Data, BuildError> {
+    if n + m == 0 {
+        return Err(BuildError::InvalidChiralIndices(n, m));
+    }
+    let a = GRAPHENE_BOND * 3f64.sqrt();
+    let (a1, a2) = ([a, 0.0], [a / 2.0, a * 3f64.sqrt() / 2.0]);
+    let point = |i: f64, j: f64| [i * a1[0] + j * a2[0], i * a1[1] + j * a2[1]];
+    let dot = |p: [f64; 2], q: [f64; 2]| p[0] * q[0] + p[1] * q[1];
+
+    // The chiral vector wraps around the circumference, and the translation vector is the shortest lattice
+    // vector perpendicular to it.
+    let (n, m) = (n as i64, m as i64);
+    let d = gcd(2 * m + n, 2 * n + m);
+    let (t1, t2) = ((2 * m + n) / d, -(2 * n + m) / d);
+    let chiral = point(n as f64, m as f64);
+    let translation = point(t1 as f64, t2 as f64);
+    let (circumference, length) = (dot(chiral, chiral).sqrt(), dot(translation, translation).sqrt());
+    let radius = circumference / (2.0 * std::f64::consts::PI);
+
+    let corners = [(0, 0), (n, m), (t1, t2), (n + t1, m + t2)];
+    let (i_min, i_max) = (corners.iter().map(|c| c.0).min().unwrap(), corners.iter().map(|c| c.0).max().unwrap());
+    let (j_min, j_max) = (corners.iter().map(|c| c.1).min().unwrap(), corners.iter().map(|c| c.1).max().unwrap());
+    let size = 2.0 * (radius + vacuum);
+    let eps = 1e-9;
+    let mut atoms = vec![];
+    for cell in 0..cells {
+        for i in i_min - 1..=i_max + 1 {
+            for j in j_min - 1..=j_max + 1 {
+                for [x, y] in [[0.0, 0.0], [1.0 / 3.0, 1.0 / 3.0]] {
+                    let p = point(i as f64 + x, j as f64 + y);
+                    let s = dot(p, chiral) / (circumference * circumference);
+                    let t = dot(p, translation) / (length * length);
+                    if (-eps..1.0 - eps).contains(&s) && (-eps..1.0 - eps).contains(&t) {
+                        let phi = 2.0 * std::f64::consts::PI * s;
+                        let z = (t.max(0.0) + cell as f64) * length;
+                        let position = [size / 2.0 + radius * phi.cos(), size / 2.0 + radius * phi.sin(), z];
+                        atoms.push(AtomData::new("C", position));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut tube = Data {
+        count: atoms.len(),
+        comment: String::new(),
+        atoms,
+        cell: None,
+    };
+    tube.set_cell(Some(Cell::new([size, 0.0, 0.0], [0.0, size, 0.0], [0.0, 0.0, length * cells as f64])));
+    Ok(tube)
+}
+
+/// The C60 fullerene centered at the origin, with bonds of 1.40 Å between hexagons and 1.45 Å in the pentagons.
+pub fn fullerene() -> Data {
+    // Every atom lies on an edge of an icosahedron, a fraction f of its length from the nearer vertex,
+    // so the pentagon bonds are f * e and the bonds between hexagons (1 - 2f) * e long.
+    let (pentagon, hexagon) = (1.45, 1.40);
+    let edge = hexagon + 2.0 * pentagon;
+    let f = pentagon / edge;
+    let phi = (1.0 + 5f64.sqrt()) / 2.0;
+    let mut vertices = vec![];
+    for (s1, s2) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+        let (u, v) = (s1 * edge / 2.0, s2 * phi * edge / 2.0);
+        vertices.extend([Position::new(0.0, u, v), Position::new(u, v, 0.0), Position::new(v, 0.0, u)]);
+    }
+
+    let mut atoms = vec![];
+    for &p in &vertices {
+        for &q in vertices.iter().filter(|&&q| (p.distance(q) - edge).abs() < 1e-6) {
+            atoms.push(AtomData::new("C", p + (q - p) * f));
+        }
+    }
+    Data {
+        count: atoms.len(),
+        comment: String::new(),
+        atoms,
+        cell: None,
+    }
+}
+
+/// Cleaves a bulk crystal along the plane with Miller indices `miller` (relative to the cell of `bulk`),
+/// and builds a slab of `layers` repetitions of the surface unit cell with `vacuum` Ångström of vacuum on both sides.
+///
+/// The surface cell has its first vector along x and the surface normal along z.
+pub fn slab(bulk: &Data, miller: [i32; 3], layers: usize, vacuum: f64) -> Result<Data, BuildError> {
+    let cell = bulk.cell.ok_or(BuildError::MissingCell)?;
+    let basis = surface_basis(&cell, miller)?;
+
+    // The basis is unimodular, so the surface cell contains exactly the atoms of the bulk cell.
+    let surface = Cell {
+        vectors: math::mul(&basis, &cell.vectors),
+    };
+    let inverse = math::inverse(&basis).ok_or(BuildError::DegenerateCell)?;
+    let fractional: Vec<[f64; 3]> = bulk
+        .atoms
+        .iter()
+        .map(|atom| math::vec_mul(cell.fractional(atom.position), &inverse).map(|v| v - (v + 1e-10).floor()))
+        .collect();
+
+    // Replace c by its component along the surface normal,
+    // and rotate the cell such that a lies along x and b in the xy-plane.
+    let [a, b, c] = surface.vectors.map(Position);
+    let normal = a.cross(b).normalized().ok_or(BuildError::DegenerateCell)?;
+    let height = c.dot(normal);
+    let perpendicular = Cell::new(a.0, b.0, (normal * height).0);
+    let bx = a.dot(b) / a.norm();
+    let rotated = Cell::new(
+        [a.norm(), 0.0, 0.0],
+        [bx, (b.norm().powi(2) - bx * bx).max(0.0).sqrt(), 0.0],
+        [0.0, 0.0, height],
+    );
+
+    let mut atoms = Vec::with_capacity(bulk.atoms.len() * layers);
+    for layer in 0..layers {
+        for (atom, f) in bulk.atoms.iter().zip(&fractional) {
+            let position = surface.cartesian([f[0], f[1], f[2] + layer as f64]);
+            let mut f = perpendicular.fractional(position);
+            f[0] -= f[0].floor();
+            f[1] -= f[1].floor();
+
+            let mut atom = atom.clone();
+            atom.position = rotated.cartesian(f);
+            atoms.push(atom);
+        }
+    }
+
+    let mut slab = Data {
+        count: atoms.len(),
+        comment: String::new(),
+        atoms,
+        cell: None,
+    };
+    add_vacuum(&mut slab, rotated, vacuum);
+    Ok(slab)
+}
+
+/// Repeats a periodic structure `repeats` times along its lattice vectors.
+pub fn supercell(bulk: &Data, repeats: [usize; 3]) -> Result<Data, BuildError> {
+    let cell = bulk.cell.ok_or(BuildError::MissingCell)?;
+
+    let mut atoms = Vec::with_capacity(bulk.atoms.len() * repeats.iter().product::<usize>());
+    for i in 0..repeats[0] {
+        for j in 0..repeats[1] {
+            for k in 0..repeats[2] {
+                let shift = cell.cartesian([i as f64, j as f64, k as f64]);
+                atoms.extend(bulk.atoms.iter().map(|atom| {
+                    let mut atom = atom.clone();
+                    atom.position += shift;
+                    atom
+                }));
+            }
+        }
+    }
+
+    let mut vectors = cell.vectors;
+    for (vector, n) in vectors.iter_mut().zip(repeats) {
+        *vector = vector.map(|v| v * n as f64);
+    }
+
+    let mut supercell = Data {
+        count: atoms.len(),
+        comment: bulk.comment.clone(),
+        atoms,
+        cell: None,
+    };
+    supercell.set_cell(Some(Cell { vectors }));
+    Ok(supercell)
+}
+
+/// Carves a spherical cluster of `radius` Ångström around `center` out of a periodic bulk structure,
+/// which is repeated as far as needed. The cluster is translated such that `center` lies at the origin,
+/// and has no cell.
+pub fn sphere_cut(bulk: &Data, center: Position, radius: f64) -> Result<Data, BuildError> {
+    let cell = bulk.cell.ok_or(BuildError::MissingCell)?;
+    let volume = cell.volume();
+    if volume == 0.0 {
+        return Err(BuildError::DegenerateCell);
+    }
+
+    // The number of repetitions needed along each lattice vector is the radius over the spacing of the lattice planes.
+    let [a, b, c] = cell.vectors.map(Position);
+    let spacings = [volume / b.cross(c).norm(), volume / c.cross(a).norm(), volume / a.cross(b).norm()];
+    let reach = spacings.map(|d| (radius / d).ceil() as i64 + 1);
+    let origin = cell.fractional(center).map(|f| f.floor() as i64);
+
+    let mut atoms = vec![];
+    for i in origin[0] - reach[0]..=origin[0] + reach[0] {
+        for j in origin[1] - reach[1]..=origin[1] + reach[1] {
+            for k in origin[2] - reach[2]..=origin[2] + reach[2] {
+                let shift = cell.cartesian([i as f64, j as f64, k as f64]);
+                for atom in &bulk.atoms {
+                    let position = atom.position + shift;
+                    if position.distance(center) <= radius {
+                        let mut atom = atom.clone();
+                        atom.position = position - center;
+                        atoms.push(atom);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut cluster = Data {
+        count: atoms.len(),
+        comment: bulk.comment.clone(),
+        atoms,
+        cell: bulk.cell,
+    };
+    cluster.set_cell(None);
+    Ok(cluster)
+}
+
+/// Sets the height of the cell to fit the atoms with `vacuum` Ångström on both sides along z, and centers them.
+fn add_vacuum(data: &mut Data, cell: Cell, vacuum: f64) {
+    let (min, max) = data
+        .atoms
+        .iter()
+        .map(|atom| atom.position[2])
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), z| (min.min(z), max.max(z)));
+    if min > max {
+        data.set_cell(Some(cell));
+        return;
+    }
+
+    for atom in &mut data.atoms {
+        atom.position[2] += vacuum - min;
+    }
+    let mut vectors = cell.vectors;
+    vectors[2] = [0.0, 0.0, max - min + 2.0 * vacuum];
+    data.set_cell(Some(Cell { vectors }));
+}
+
+/// Finds a unimodular integer basis whose first two vectors span the plane `miller`,
+/// following the construction used by ASE.
+fn surface_basis(cell: &Cell, miller: [i32; 3]) -> Result<Mat3, BuildError> {
+    let g = gcd(gcd(miller[0].into(), miller[1].into()), miller[2].into());
+    if g == 0 {
+        return Err(BuildError::InvalidMillerIndices(miller));
+    }
+    // Planes like (220) are the same as (110).
+    let [h, k, l] = miller.map(|i| i64::from(i) / g);
+
+    let basis: [[i64; 3]; 3] = match (h == 0, k == 0, l == 0) {
+        (false, true, true) => [[0, 1, 0], [0, 0, 1], [1, 0, 0]],
+        (true, false, true) => [[0, 0, 1], [1, 0, 0], [0, 1, 0]],
+        (true, true, false) => [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+        _ => {
+            let (mut p, mut q) = ext_gcd(k, l);
+            let [a1, a2, a3] = cell.vectors.map(Position);
+            let (hf, kf, lf) = (h as f64, k as f64, l as f64);
+
+            // The dot product of the first two basis vectors is k1 + i * k2 for integer i; pick the most orthogonal.
+            let c2 = a2 * lf - a3 * kf;
+            let k1 = ((a1 * kf - a2 * hf) * p as f64 + (a1 * lf - a3 * hf) * q as f64).dot(c2);
+            let k2 = ((a1 * kf - a2 * hf) * lf - (a1 * lf - a3 * hf) * kf).dot(c2);
+            if k2.abs() > 1e-10 {
+                let i = -(k1 / k2).round() as i64;
+                p += i * l;
+                q -= i * k;
+            }
+
+            let (a, b) = ext_gcd(p * k + q * l, h);
+            let g = gcd(l, k).abs();
+            [[p * k + q * l, -p * h, -q * h], [0, l / g, -k / g], [b, a * p, a * q]]
+        }
+    };
+
+    let mut basis = basis.map(|row| row.map(|v| v as f64));
+    if math::det(&basis).abs() < 0.5 {
+        return Err(BuildError::InvalidMillerIndices(miller));
+    }
+    // Keep the surface cell right-handed, so the third vector points out of the surface along the normal.
+    if math::det(&math::mul(&basis, &cell.vectors)) < 0.0 {
+        basis[2] = basis[2].map(|v| -v);
+    }
+    Ok(basis)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a.rem_euclid(b))
+    }
+}
+
+/// Extended Euclid's algorithm, returning `(x, y)` with `a * x + b * y = gcd(a, b)`.
+fn ext_gcd(a: i64, b: i64) -> (i64, i64) {
+    if b == 0 {
+        (1, 0)
+    } else if a.rem_euclid(b) == 0 {
+        (0, 1)
+    } else {
+        let (x, y) = ext_gcd(b, a.rem_euclid(b));
+        (y, x - y * a.div_euclid(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::analysis::nearest_neighbor_distances;
+    use crate::testing::frame;
+
+    /// A simple cubic crystal of one atom in a cubic cell of `a` Ångström.
+    fn simple_cubic(a: f64) -> Data {
+        let mut bulk = frame("", &[("Po", [0.0; 3])]);
+        bulk.cell = Some(Cell::new([a, 0.0, 0.0], [0.0, a, 0.0], [0.0, 0.0, a]));
+        bulk
+    }
+
+    #[test]
+    fn slab_of_simple_cubic_crystal() {
+        let slab = slab(&simple_cubic(2.0), [0, 0, 1], 3, 5.0).unwrap();
+        assert_eq!(slab.atoms.len(), 3);
+        let heights: Vec<f64> = slab.atoms.iter().map(|atom| atom.position[2]).collect();
+        assert_eq!(heights, [5.0, 7.0, 9.0]);
+        let cell = slab.cell.unwrap();
+        assert_eq!(cell.vectors[2], [0.0, 0.0, 14.0]);
+        assert!((cell.volume() - 56.0).abs() < 1e-9);
+
+        let cut = super::slab(&simple_cubic(2.0), [1, 1, 0], 2, 5.0).unwrap();
+        let [a, b, _] = cut.cell.unwrap().vectors;
+        assert!((Position(a).cross(Position(b)).norm() - 4.0 * 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(cut.atoms.len(), 2);
+    }
+
+    #[test]
+    fn slab_errors() {
+        let mut bulk = simple_cubic(2.0);
+        assert!(matches!(slab(&bulk, [0, 0, 0], 1, 5.0), Err(BuildError::InvalidMillerIndices(_))));
+        bulk.cell = None;
+        assert!(matches!(slab(&bulk, [0, 0, 1], 1, 5.0), Err(BuildError::MissingCell)));
+    }
+
+    #[test]
+    fn supercell_of_simple_cubic_crystal() {
+        let supercell = supercell(&simple_cubic(2.0), [2, 3, 1]).unwrap();
+        assert_eq!(supercell.atoms.len(), 6);
+        assert_eq!(supercell.cell.unwrap().vectors, [[4.0, 0.0, 0.0], [0.0, 6.0, 0.0], [0.0, 0.0, 2.0]]);
+        assert_eq!(supercell.atoms[5].position, Position::new(2.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn sphere_cut_of_simple_cubic_crystal() {
+        // The atom at the center, its 6 nearest neighbours at 2 Å, and then 12 at 2.83 Å.
+        let bulk = simple_cubic(2.0);
+        let cluster = sphere_cut(&bulk, Position::new(2.0, 2.0, 2.0), 2.1).unwrap();
+        assert_eq!(cluster.atoms.len(), 7);
+        assert!(cluster.cell.is_none());
+        assert!(cluster.atoms.iter().any(|atom| atom.position == Position::new(0.0, 0.0, 0.0)));
+        assert_eq!(sphere_cut(&bulk, Position::new(2.0, 2.0, 2.0), 2.9).unwrap().atoms.len(), 19);
+        assert!(matches!(sphere_cut(&frame("", &[]), Position::new(0.0, 0.0, 0.0), 1.0), Err(BuildError::MissingCell)));
+    }
+
+    #[test]
+    fn molecule_templates() {
+        let water = molecule(&Template::Water);
+        assert_eq!(water.formula(), "H2O");
+        let [o, h1, h2] = [0, 1, 2].map(|i| water.atoms[i].position);
+        assert!((o.distance(h1) - 0.9572).abs() < 1e-12);
+        let angle = (h1 - o).dot(h2 - o) / (0.9572 * 0.9572);
+        assert!((angle.acos().to_degrees() - 104.52).abs() < 1e-9);
+        assert!(water.center_of_mass().unwrap().norm() < 1e-12);
+
+        let methane = molecule(&Template::Methane);
+        assert_eq!(methane.atoms[0].symbol, "C");
+        assert!(methane.atoms[1..].iter().all(|atom| (atom.position.norm() - 1.087).abs() < 1e-12));
+        assert_eq!(molecule(&Template::Benzene).formula(), "C6H6");
+
+        let chain = molecule(&Template::Chain {
+            symbol: "C".to_string(),
+            length: 3,
+            bond_length: 1.5,
+        });
+        let xs: Vec<f64> = chain.atoms.iter().map(|atom| atom.position.0[0]).collect();
+        assert_eq!(xs, [-1.5, 0.0, 1.5]);
+        assert!(chain.cell.is_none());
+    }
+
+    #[test]
+    fn lattice_templates() {
+        let fcc = molecule(&Template::Fcc {
+            symbol: "Cu".to_string(),
+            a: 3.6,
+        });
+        assert_eq!(fcc.atoms.len(), 4);
+        assert_eq!(fcc.atoms[0].position, Position::new(0.0, 0.0, 0.0));
+        assert_eq!(supercell(&fcc, [2, 2, 2]).unwrap().atoms.len(), 32);
+
+        let bcc = molecule(&Template::Bcc {
+            symbol: "Fe".to_string(),
+            a: 2.87,
+        });
+        assert_eq!(bcc.cell.unwrap().volume(), 2.87f64.powi(3));
+
+        let a = 3.21;
+        let hcp = molecule(&Template::Hcp {
+            symbol: "Mg".to_string(),
+            a,
+            c: a * (8.0f64 / 3.0).sqrt(),
+        });
+        // With ideal packing, neighbours in adjacent layers are as far apart as within a layer.
+        let distance = hcp.atoms[0].position.distance(hcp.atoms[1].position);
+        assert!((distance - a).abs() < 1e-12, "{}", distance);
+    }
+
+    /// The number of neighbours of every atom within `cutoff` Ångström, under the minimum image convention.
+    fn coordination(data: &Data, cutoff: f64) -> Vec<usize> {
+        let distance = |a: Position, b: Position| match &data.cell {
+            Some(cell) => cell.cartesian(cell.fractional(b - a).map(|f| f - f.round())).norm(),
+            None => a.distance(b),
+        };
+        let positions: Vec<Position> = data.atoms.iter().map(|atom| atom.position).collect();
+        positions.iter().map(|&a| positions.iter().filter(|&&b| a != b && distance(a, b) < cutoff).count()).collect()
+    }
+
+    #[test]
+    fn graphene_sheets() {
+        let sheet = graphene([2, 3], 5.0);
+        assert_eq!(sheet.atoms.len(), 12);
+        assert_eq!(sheet.cell.unwrap().vectors[2], [0.0, 0.0, 10.0]);
+        assert!(sheet.atoms.iter().all(|atom| atom.position[2] == 5.0));
+        assert!(nearest_neighbor_distances(&sheet).iter().all(|d| (d - 1.42).abs() < 1e-9));
+        assert!(coordination(&sheet, 1.5).iter().all(|&n| n == 3));
+    }
+
+    #[test]
+    fn nanotubes() {
+        for ((n, m), atoms) in [((5, 5), 20), ((8, 0), 32), ((6, 3), 84)] {
+            let tube = nanotube(n, m, 2, 4.0).unwrap();
+            assert_eq!(tube.atoms.len(), 2 * atoms, "({}, {})", n, m);
+            assert!(coordination(&tube, 1.5).iter().all(|&k| k == 3), "({}, {})", n, m);
+            let cell = tube.cell.unwrap();
+            let center = Position::new(cell.vectors[0][0] / 2.0, cell.vectors[1][1] / 2.0, 0.0);
+            let radius = |atom: &AtomData| (atom.position - center).0[..2].iter().map(|v| v * v).sum::<f64>().sqrt();
+            let expected = 1.42 * 3f64.sqrt() * ((n * n + n * m + m * m) as f64).sqrt() / (2.0 * std::f64::consts::PI);
+            assert!(tube.atoms.iter().all(|atom| (radius(atom) - expected).abs() < 1e-9));
+        }
+        assert!(matches!(nanotube(0, 0, 1, 4.0), Err(BuildError::InvalidChiralIndices(0, 0))));
+    }
+
+    #[test]
+    fn c60() {
+        let c60 = fullerene();
+        assert_eq!(c60.atoms.len(), 60);
+        assert!(c60.center_of_mass().unwrap().norm() < 1e-9);
+        assert!(coordination(&c60, 1.5).iter().all(|&n| n == 3));
+        let distances = nearest_neighbor_distances(&c60);
+        assert!(distances.iter().all(|d| (d - 1.40).abs() < 1e-9), "{:?}", distances);
+    }
+
+    #[test]
+    fn water_boxes() {
+        let water = water_box(1.0, [20.0, 20.0, 15.0], 7);
+        // 6000 Å³ of water at 1 g/cm³ hold 200.6 molecules.
+        assert_eq!(water.atoms.len(), 3 * 201);
+        assert_eq!(water.cell.unwrap().volume(), 6000.0);
+        for molecule in water.atoms.chunks(3) {
+            assert_eq!(molecule[0].symbol, "O");
+            assert!(molecule[1..].iter().all(|h| (h.position.distance(molecule[0].position) - 0.9572).abs() < 1e-9));
+        }
+        let oxygens = Data {
+            count: 201,
+            comment: String::new(),
+            atoms: water.atoms.iter().step_by(3).cloned().collect(),
+            cell: water.cell,
+        };
+        assert!(nearest_neighbor_distances(&oxygens).iter().all(|&d| d > 2.5));
+        let positions = |data: &Data| data.atoms.iter().map(|atom| atom.position).collect::<Vec<Position>>();
+        assert_eq!(positions(&water_box(1.0, [20.0, 20.0, 15.0], 7)), positions(&water));
+        assert_ne!(positions(&water_box(1.0, [20.0, 20.0, 15.0], 8)), positions(&water));
+        assert!(water_box(0.0, [10.0; 3], 7).atoms.is_empty());
+    }
+
+    #[test]
+    fn surface_sites_of_square_and_triangular_layers() {
+        let square = supercell(&slab(&simple_cubic(2.0), [0, 0, 1], 2, 5.0).unwrap(), [3, 3, 1]).unwrap();
+        assert_eq!(square.atoms.len(), 18);
+        let top = surface_sites(&square, Site::Top);
+        assert_eq!(top.len(), 9);
+        assert!(top.iter().all(|p| p[2] == 7.0));
+        assert_eq!(surface_sites(&square, Site::Bridge).len(), 18);
+        assert_eq!(surface_sites(&square, Site::Hollow), []);
+
+        let mut triangular = frame("", &[("Pt", [0.0; 3])]);
+        triangular.cell = Some(Cell::new([2.0, 0.0, 0.0], [1.0, 3f64.sqrt(), 0.0], [0.0, 0.0, 10.0]));
+        let triangular = supercell(&triangular, [3, 3, 1]).unwrap();
+        assert_eq!(surface_sites(&triangular, Site::Bridge).len(), 27);
+        let hollow = surface_sites(&triangular, Site::Hollow);
+        assert_eq!(hollow.len(), 18);
+        let nearest =
+            |p: &Position| triangular.atoms.iter().map(|atom| atom.position.distance(*p)).fold(f64::MAX, f64::min);
+        assert!(hollow.iter().all(|p| (nearest(p) - 2.0 / 3f64.sqrt()).abs() < 1e-9));
+    }
+
+    #[test]
+    fn adsorb_molecules() {
+        let surface = supercell(&slab(&simple_cubic(2.0), [0, 0, 1], 2, 5.0).unwrap(), [3, 3, 1]).unwrap();
+        let co = frame("", &[("C", [0.0, 0.0, 0.0]), ("O", [1.13, 0.0, 0.0])]);
+        let upright = Orientation {
+            up: Position::new(1.0, 0.0, 0.0),
+            angle: 0.0,
+        };
+        let adsorbed = adsorb(&surface, &co, Site::Top, 1.8, upright).unwrap();
+        assert_eq!(adsorbed.atoms.len(), 20);
+        assert_eq!(adsorbed.atoms[18].position, Position::new(2.0, 2.0, 8.8));
+        assert!(adsorbed.atoms[19].position.distance(Position::new(2.0, 2.0, 9.93)) < 1e-12);
+
+        let turned = Orientation {
+            angle: 90.0,
+            ..Orientation::default()
+        };
+        let flat = adsorb(&surface, &co, Site::Bridge, 2.0, turned).unwrap();
+        let bond = flat.atoms[19].position - flat.atoms[18].position;
+        assert!(bond.distance(Position::new(0.0, 1.13, 0.0)) < 1e-12);
+
+        let error = adsorb(&surface, &co, Site::Hollow, 2.0, Orientation::default()).unwrap_err();
+        assert_eq!(error.to_string(), "The surface has no hollow site");
+        assert!(matches!(adsorb(&surface, &frame("", &[]), Site::Top, 2.0, upright), Err(BuildError::EmptyMolecule)));
+    }
+
+    fn composition(fractions: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        fractions.iter().map(|&(symbol, f)| (symbol.to_string(), f)).collect()
+    }
+
+    /// The number of nearest-neighbour pairs of different elements in a supercell of simple cubic cells of 2 Å.
+    fn unlike_pairs(data: &Data) -> usize {
+        let cell = data.cell.unwrap();
+        let pairs = (0..data.atoms.len()).flat_map(|i| (i + 1..data.atoms.len()).map(move |j| (i, j)));
+        pairs
+            .filter(|&(i, j)| {
+                let (a, b) = (&data.atoms[i], &data.atoms[j]);
+                a.symbol != b.symbol && minimum_image_distance(&cell, a.position, b.position) < 2.1
+            })
+            .count()
+    }
+
+    #[test]
+    fn random_alloys() {
+        let bulk = supercell(&simple_cubic(2.0), [4, 4, 4]).unwrap();
+        let brass = composition(&[("Cu", 2.0), ("Zn", 1.0)]);
+        let alloy = random_alloy(&bulk, &brass, 3).unwrap();
+        assert_eq!(alloy.formula(), "Cu43Zn21");
+        let symbols = |data: &Data| data.atoms.iter().map(|atom| atom.symbol.clone()).collect::<Vec<String>>();
+        assert_eq!(symbols(&random_alloy(&bulk, &brass, 3).unwrap()), symbols(&alloy));
+        assert_ne!(symbols(&random_alloy(&bulk, &brass, 4).unwrap()), symbols(&alloy));
+
+        // Ordering on the simple cubic lattice approaches the rock salt structure, where all neighbours differ.
+        let half = composition(&[("Cu", 0.5), ("Zn", 0.5)]);
+        let random = unlike_pairs(&random_alloy(&bulk, &half, 3).unwrap());
+        let options = AlloyOptions {
+            swaps: 3000,
+            short_range_order: -1.0,
+            ..AlloyOptions::default()
+        };
+        let ordered = unlike_pairs(&random_alloy_with(&bulk, &half, 3, &options).unwrap());
+        assert!(ordered > random + 20, "{} {}", ordered, random);
+    }
+
+    #[test]
+    fn alloy_sites_and_errors() {
+        let mut oxide = supercell(&simple_cubic(2.0), [2, 2, 2]).unwrap();
+        oxide.atoms.iter_mut().step_by(2).for_each(|atom| atom.symbol = "O".to_string());
+        let options = AlloyOptions {
+            sites: Some("Po".to_string()),
+            ..AlloyOptions::default()
+        };
+        let mixed = random_alloy_with(&oxide, &composition(&[("Mg", 1.0), ("Ni", 1.0)]), 1, &options).unwrap();
+        assert_eq!(mixed.formula(), "Mg2Ni2O4");
+
+        let invalid =
+            |fractions: &[(&str, f64)]| random_alloy(&oxide, &composition(fractions), 1).unwrap_err().to_string();
+        assert_eq!(invalid(&[]), "Invalid composition: the fractions must be non-negative");
+        assert_eq!(invalid(&[("Mg", -1.0), ("Ni", 2.0)]), "Invalid composition: the fractions must be non-negative");
+        let options = AlloyOptions {
+            sites: Some("Fe".to_string()),
+            ..AlloyOptions::default()
+        };
+        let error = random_alloy_with(&oxide, &composition(&[("Mg", 1.0)]), 1, &options).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid composition: there are no sites to replace");
+    }
+
+    #[test]
+    fn graphene_without_vacuum_stacks_like_graphite() {
+        let sheet = graphene([1, 1], 0.0);
+        assert_eq!(sheet.cell.unwrap().vectors[2], [0.0, 0.0, 3.35]);
+        assert!(sheet.atoms.iter().all(|atom| atom.position[2] == 1.675));
+    }
+}
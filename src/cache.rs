@@ -0,0 +1,315 @@
+//! A binary cache format (`.xyzb`) that stores frames as raw little-endian arrays,
+//! which loads much faster than parsing the text format again.
+//!
+//! Layout: the magic bytes `XYZB`, a `u32` version and a `u64` frame count, followed by every frame as
+//! the comment, an optional cell, a table of the distinct atomic symbols,
+//! one `u32` symbol index per atom, three `f64` coordinates per atom and the metadata of every atom:
+//! its name, group, index, velocity, force and charge, each as a `u8` that is 1 if present followed by the value,
+//! its tags as a `u64` count followed by layer and value pairs, and the atomic number of its resolved element,
+//! or 0 if there is none.
+//! Strings are stored as a `u64` byte length followed by UTF-8 bytes, and vectors as three `f64`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use super::cell::Cell;
+use super::data::{AtomData, Data, File};
+use super::element::Element;
+use super::position::Position;
+
+const MAGIC: &[u8; 4] = b"XYZB";
+const VERSION: u32 = 2;
+/// The size of a frame without atoms and without a cell: the comment length, the cell flag,
+/// the symbol count and the atom count.
+const MIN_FRAME_SIZE: usize = 8 + 1 + 8 + 8;
+
+impl File {
+    /// Writes the frames to a binary cache file.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
+    pub fn save_cache<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&VERSION.to_le_bytes());
+        put_u64(&mut buf, self.data.len() as u64);
+
+        for data in &self.data {
+            put_str(&mut buf, &data.comment);
+
+            match &data.cell {
+                Some(cell) => {
+                    buf.push(1);
+                    for v in cell.vectors.iter().flatten() {
+                        buf.extend_from_slice(&v.to_le_bytes());
+                    }
+                }
+                None => buf.push(0),
+            }
+
+            let mut symbols: Vec<&str> = vec![];
+            let mut indices: HashMap<&str, u32> = HashMap::new();
+            for atom in &data.atoms {
+                indices.entry(&atom.symbol).or_insert_with(|| {
+                    symbols.push(&atom.symbol);
+                    symbols.len() as u32 - 1
+                });
+            }
+            put_u64(&mut buf, symbols.len() as u64);
+            for symbol in symbols {
+                put_str(&mut buf, symbol);
+            }
+
+            put_u64(&mut buf, data.atoms.len() as u64);
+            for atom in &data.atoms {
+                buf.extend_from_slice(&indices[atom.symbol.as_str()].to_le_bytes());
+            }
+            for atom in &data.atoms {
+                put_vector(&mut buf, atom.position);
+            }
+            for atom in &data.atoms {
+                put_option(&mut buf, atom.name.as_deref(), put_str);
+                put_option(&mut buf, atom.group.as_deref(), put_str);
+                put_option(&mut buf, atom.index, |buf, index| put_u64(buf, index as u64));
+                put_option(&mut buf, atom.velocity, put_vector);
+                put_option(&mut buf, atom.force, put_vector);
+                put_option(&mut buf, atom.charge, |buf, charge| buf.extend_from_slice(&charge.to_le_bytes()));
+                put_u64(&mut buf, atom.tags.len() as u64);
+                for (layer, value) in &atom.tags {
+                    put_str(&mut buf, layer);
+                    put_str(&mut buf, value);
+                }
+                buf.push(atom.resolved_element.map_or(0, |element| element.atomic_number()));
+            }
+        }
+
+        fs::write(path, buf)
+    }
+
+    /// Reads the frames from a binary cache file written by [`File::save_cache`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
+    pub fn load_cache<P: AsRef<Path>>(path: P) -> Result<File> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader { bytes: &bytes };
+
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("not an xyzb cache file"));
+        }
+        let version = u32::from_le_bytes(reader.array()?);
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported xyzb version {}", version)));
+        }
+
+        let frames = reader.u64()? as usize;
+        let mut file = File {
+            data: Vec::with_capacity(frames.min(bytes.len() / MIN_FRAME_SIZE)),
+        };
+
+        for _ in 0..frames {
+            let comment = reader.string()?;
+
+            let cell = match reader.take(1)?[0] {
+                0 => None,
+                _ => {
+                    let mut vectors = [[0.0; 3]; 3];
+                    for v in vectors.iter_mut().flatten() {
+                        *v = reader.f64()?;
+                    }
+                    Some(Cell { vectors })
+                }
+            };
+
+            let symbol_count = reader.u64()? as usize;
+            let symbols = (0..symbol_count).map(|_| reader.string()).collect::<Result<Vec<String>>>()?;
+
+            let count = reader.u64()? as usize;
+            let indices = reader.take(count.checked_mul(4).ok_or_else(|| invalid("atom count overflow"))?)?;
+            let positions = reader.take(count.checked_mul(24).ok_or_else(|| invalid("atom count overflow"))?)?;
+
+            let mut atoms = indices
+                .chunks_exact(4)
+                .zip(positions.chunks_exact(24))
+                .map(|(index, position)| {
+                    let index = u32::from_le_bytes(index.try_into().unwrap()) as usize;
+                    let coordinate = |i: usize| f64::from_le_bytes(position[i * 8..i * 8 + 8].try_into().unwrap());
+                    Ok(AtomData::new(
+                        symbols.get(index).ok_or_else(|| invalid("symbol index out of range"))?.as_str(),
+                        [coordinate(0), coordinate(1), coordinate(2)],
+                    ))
+                })
+                .collect::<Result<Vec<AtomData>>>()?;
+            for atom in &mut atoms {
+                atom.name = reader.option(Reader::string)?;
+                atom.group = reader.option(Reader::string)?;
+                atom.index = reader.option(|reader| Ok(reader.u64()? as usize))?;
+                atom.velocity = reader.option(Reader::vector)?;
+                atom.force = reader.option(Reader::vector)?;
+                atom.charge = reader.option(Reader::f64)?;
+                for _ in 0..reader.u64()? {
+                    let layer = reader.string()?;
+                    atom.tags.insert(layer, reader.string()?);
+                }
+                atom.resolved_element = match reader.take(1)?[0] {
+                    0 => None,
+                    number => Some(Element::from_atomic_number(number).ok_or_else(|| invalid("unknown element"))?),
+                };
+            }
+
+            file.data.push(Data {
+                count,
+                comment,
+                atoms,
+                cell,
+            });
+        }
+
+        Ok(file)
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, value: &str) {
+    put_u64(buf, value.len() as u64);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn put_vector(buf: &mut Vec<u8>, value: Position) {
+    for v in value.0 {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+fn put_option<T>(buf: &mut Vec<u8>, value: Option<T>, put: impl FnOnce(&mut Vec<u8>, T)) {
+    match value {
+        Some(value) => {
+            buf.push(1);
+            put(buf, value);
+        }
+        None => buf.push(0),
+    }
+}
+
+/// Reads values from the front of a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.bytes.len() {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated xyzb cache file"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u64()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("invalid UTF-8 string"))
+    }
+
+    fn vector(&mut self) -> Result<Position> {
+        Ok(Position::new(self.f64()?, self.f64()?, self.f64()?))
+    }
+
+    fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<Option<T>> {
+        match self.take(1)?[0] {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            _ => Err(invalid("invalid optional value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Cell;
+    use crate::testing::{temp_path, trajectory};
+
+    #[test]
+    fn save_and_load() {
+        let mut file = trajectory(3);
+        file.data[1].cell = Some(Cell::new([5.0, 0.0, 0.0], [0.0, 6.0, 0.0], [0.0, 0.0, 7.0]));
+        let path = temp_path("cache.xyzb");
+        file.save_cache(&path).unwrap();
+        let loaded = File::load_cache(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.data.len(), 3);
+        for (a, b) in file.data.iter().zip(&loaded.data) {
+            assert_eq!(a.comment, b.comment);
+            assert_eq!(a.cell, b.cell);
+            for (a, b) in a.atoms.iter().zip(&b.atoms) {
+                assert_eq!(a.symbol, b.symbol);
+                assert_eq!(a.position, b.position);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_and_foreign_files() {
+        let path = temp_path("truncated.xyzb");
+        trajectory(2).save_cache(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 4]).unwrap();
+        assert_eq!(File::load_cache(&path).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        std::fs::write(&path, "3\nwater\n").unwrap();
+        assert_eq!(File::load_cache(&path).unwrap_err().kind(), ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn atom_metadata() {
+        let mut file = trajectory(1);
+        let atom = &mut file.data[0].atoms[1];
+        atom.name = Some("HW1".to_string());
+        atom.group = Some("SOL".to_string());
+        atom.index = Some(7);
+        atom.velocity = Some(Position::new(0.1, 0.2, 0.3));
+        atom.force = Some(Position::new(-1.0, 0.0, 1.0));
+        atom.charge = Some(0.41);
+        atom.resolved_element = Element::from_symbol("H");
+        file.data[0].set_tag(&[0, 1], "layer", "H");
+        let path = temp_path("metadata.xyzb");
+        file.save_cache(&path).unwrap();
+        let loaded = File::load_cache(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (a, b) = (&file.data[0].atoms[1], &loaded.data[0].atoms[1]);
+        assert_eq!((&a.name, &a.group, a.index, a.charge), (&b.name, &b.group, b.index, b.charge));
+        assert_eq!((a.velocity, a.force, a.resolved_element), (b.velocity, b.force, b.resolved_element));
+        assert_eq!(loaded.data[0].tagged("layer", "H"), [0, 1]);
+        assert_eq!(loaded.data[0].atoms[2].name, None);
+    }
+
+    #[test]
+    fn frame_count_is_not_trusted() {
+        let path = temp_path("count.xyzb");
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(u64::MAX.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        assert_eq!(File::load_cache(&path).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+use super::math;
+use super::position::Position;
+
+/// A periodic simulation cell, given by its three lattice vectors in Ångström.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    /// The lattice vectors `a`, `b` and `c`, one per row.
+    pub vectors: [[f64; 3]; 3],
+}
+impl Cell {
+    /// Creates a cell from its three lattice vectors.
+    pub fn new(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> Cell {
+        Cell { vectors: [a, b, c] }
+    }
+
+    /// Creates a cell from the lengths `[a, b, c]` in Ångström and the angles `[alpha, beta, gamma]` in degrees.
+    /// The `a` vector is placed along x and the `b` vector in the xy-plane.
+    pub fn from_lengths_angles(lengths: [f64; 3], angles: [f64; 3]) -> Cell {
+        let [a, b, c] = lengths;
+        // Right angles are common, so avoid the rounding error of cos(pi / 2).
+        let cos = |angle: f64| if angle == 90.0 { 0.0 } else { angle.to_radians().cos() };
+        let [cos_alpha, cos_beta, cos_gamma] = angles.map(cos);
+        let sin_gamma = angles[2].to_radians().sin();
+
+        let cx = c * cos_beta;
+        let cy = c * (cos_alpha - cos_beta * cos_gamma) / sin_gamma;
+        let cz = (c * c - cx * cx - cy * cy).max(0.0).sqrt();
+
+        Cell::new([a, 0.0, 0.0], [b * cos_gamma, b * sin_gamma, 0.0], [cx, cy, cz])
+    }
+
+    /// Reads the cell from an extended XYZ comment containing `Lattice="ax ay az bx by bz cx cy cz"`.
+    pub fn from_comment(comment: &str) -> Option<Cell> {
+        let start = comment.find("Lattice=\"")? + "Lattice=\"".len();
+        let end = start + comment[start..].find('"')?;
+
+        let values = comment[start..end]
+            .split_whitespace()
+            .map(|v| v.parse::<f64>().ok())
+            .collect::<Option<Vec<f64>>>()?;
+        if values.len() != 9 {
+            return None;
+        }
+
+        Some(Cell::new(
+            [values[0], values[1], values[2]],
+            [values[3], values[4], values[5]],
+            [values[6], values[7], values[8]],
+        ))
+    }
+
+    /// The `Lattice="..."` entry of an extended XYZ comment describing the cell.
+    pub fn to_comment(&self) -> String {
+        let values: Vec<String> = self.vectors.iter().flatten().map(f64::to_string).collect();
+        format!("Lattice=\"{}\"", values.join(" "))
+    }
+
+    /// Removes the `Lattice="..."` entry from an extended XYZ comment.
+    pub(crate) fn strip_comment(comment: &str) -> String {
+        let Some(start) = comment.find("Lattice=\"") else {
+            return comment.to_string();
+        };
+        let end = comment[start + "Lattice=\"".len()..]
+            .find('"')
+            .map_or(comment.len(), |i| start + "Lattice=\"".len() + i + 1);
+        format!("{} {}", comment[..start].trim_end(), comment[end..].trim_start()).trim().to_string()
+    }
+
+    /// The center of the cell, i.e. half the sum of the lattice vectors.
+    pub fn center(&self) -> Position {
+        (Position(self.vectors[0]) + Position(self.vectors[1]) + Position(self.vectors[2])) / 2.0
+    }
+
+    /// The fractional coordinates of a position, in units of the lattice vectors.
+    /// The coordinates are `NaN` if the cell is degenerate.
+    pub fn fractional(&self, position: Position) -> [f64; 3] {
+        let inverse = math::inverse(&self.vectors).unwrap_or([[f64::NAN; 3]; 3]);
+        math::vec_mul(position.0, &inverse)
+    }
+
+    /// The position at fractional coordinates, in units of the lattice vectors.
+    pub fn cartesian(&self, fractional: [f64; 3]) -> Position {
+        Position(math::vec_mul(fractional, &self.vectors))
+    }
+
+    /// Scales the lattice vectors by `factor`, e.g. `1.01` for a lattice constant 1% larger.
+    pub fn scale(&mut self, factor: f64) {
+        self.vectors = self.vectors.map(|v| v.map(|x| x * factor));
+    }
+
+    /// The volume of the cell in Å³.
+    pub fn volume(&self) -> f64 {
+        math::det(&self.vectors).abs()
+    }
+
+    /// The lengths `[a, b, c]` of the lattice vectors in Ångström,
+    /// and the angles `[alpha, beta, gamma]` between them in degrees.
+    pub fn lengths_angles(&self) -> ([f64; 3], [f64; 3]) {
+        let [a, b, c] = self.vectors;
+        let lengths = [norm(a), norm(b), norm(c)];
+
+        let angle = |u: [f64; 3], v: [f64; 3], lu: f64, lv: f64| {
+            (dot(u, v) / (lu * lv)).clamp(-1.0, 1.0).acos().to_degrees()
+        };
+        let angles = [
+            angle(b, c, lengths[1], lengths[2]),
+            angle(a, c, lengths[0], lengths[2]),
+            angle(a, b, lengths[0], lengths[1]),
+        ];
+
+        (lengths, angles)
+    }
+
+    /// The Niggli-reduced cell of the same lattice, found with the Křivý–Gruber algorithm with the tolerances of
+    /// Grosse-Kunstleve et al. (2004). Its vectors are the shortest possible and the angles between them all
+    /// acute or all non-acute, so equal lattices from different sources get the same cell.
+    /// The handedness of the vectors is kept. Returns `None` if the cell is degenerate.
+    pub fn niggli_reduce(&self) -> Option<Cell> {
+        let volume = self.volume();
+        if volume == 0.0 || !volume.is_finite() {
+            return None;
+        }
+        let eps = 1e-5 * volume.powf(2.0 / 3.0);
+        let sign = |x: f64| if x > eps { 1 } else if x < -eps { -1 } else { 0 };
+        let [mut a, mut b, mut c] = self.vectors;
+        let combine = |u: [f64; 3], v: [f64; 3], f: f64| [u[0] + f * v[0], u[1] + f * v[1], u[2] + f * v[2]];
+        let negate = |u: [f64; 3]| u.map(|x| -x);
+
+        for _ in 0..10000 {
+            let metric = |a: [f64; 3], b: [f64; 3], c: [f64; 3]| {
+                (dot(a, a), dot(b, b), dot(c, c), 2.0 * dot(b, c), 2.0 * dot(a, c), 2.0 * dot(a, b))
+            };
+
+            let (aa, bb, _, xi, eta, _) = metric(a, b, c);
+            if aa > bb + eps || ((aa - bb).abs() <= eps && xi.abs() > eta.abs() + eps) {
+                (a, b, c) = (negate(b), negate(a), negate(c));
+            }
+            let (_, bb, cc, _, eta, zeta) = metric(a, b, c);
+            if bb > cc + eps || ((bb - cc).abs() <= eps && eta.abs() > zeta.abs() + eps) {
+                (a, b, c) = (negate(a), negate(c), negate(b));
+                continue;
+            }
+
+            // Make the angles all acute or all non-acute, keeping the handedness.
+            let (_, _, _, xi, eta, zeta) = metric(a, b, c);
+            let (l, m, n) = (sign(xi), sign(eta), sign(zeta));
+            let mut flips = if l * m * n == 1 {
+                [l, m, n].map(|s| s as f64)
+            } else {
+                [l, m, n].map(|s| if s == 1 { -1.0 } else { 1.0 })
+            };
+            if flips.iter().product::<f64>() < 0.0 {
+                if let Some(zero) = [l, m, n].iter().position(|&s| s == 0) {
+                    flips[zero] = -1.0;
+                }
+            }
+            (a, b, c) = (a.map(|x| x * flips[0]), b.map(|x| x * flips[1]), c.map(|x| x * flips[2]));
+
+            let (aa, bb, _, xi, eta, zeta) = metric(a, b, c);
+            let eq = |x: f64, y: f64| (x - y).abs() <= eps;
+            let sum = xi + eta + zeta + aa + bb;
+            if xi.abs() > bb + eps || (eq(xi, bb) && 2.0 * eta < zeta - eps) || (eq(xi, -bb) && zeta < -eps) {
+                c = combine(c, b, -sign(xi) as f64);
+            } else if eta.abs() > aa + eps || (eq(eta, aa) && 2.0 * xi < zeta - eps) || (eq(eta, -aa) && zeta < -eps) {
+                c = combine(c, a, -sign(eta) as f64);
+            } else if zeta.abs() > aa + eps || (eq(zeta, aa) && 2.0 * xi < eta - eps) || (eq(zeta, -aa) && eta < -eps) {
+                b = combine(b, a, -sign(zeta) as f64);
+            } else if sum < -eps || (eq(sum, 0.0) && 2.0 * (aa + eta) + zeta > eps) {
+                c = combine(combine(c, a, 1.0), b, 1.0);
+            } else {
+                return Some(Cell::new(a, b, c));
+            }
+        }
+        None
+    }
+}
+
+fn dot(u: [f64; 3], v: [f64; 3]) -> f64 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+fn norm(u: [f64; 3]) -> f64 {
+    dot(u, u).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_and_angles_round_trip() {
+        let cell = Cell::from_lengths_angles([3.0, 4.0, 5.0], [80.0, 95.0, 110.0]);
+        let (lengths, angles) = cell.lengths_angles();
+        for i in 0..3 {
+            assert!((lengths[i] - [3.0, 4.0, 5.0][i]).abs() < 1e-9);
+            assert!((angles[i] - [80.0, 95.0, 110.0][i]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn right_angles_are_exact() {
+        let cell = Cell::from_lengths_angles([3.0, 4.0, 5.0], [90.0, 90.0, 90.0]);
+        assert_eq!(cell.vectors, [[3.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 5.0]]);
+        let hexagonal = Cell::from_lengths_angles([2.0, 2.0, 5.0], [90.0, 90.0, 120.0]);
+        assert_eq!(hexagonal.vectors[2], [0.0, 0.0, 5.0]);
+    }
+
+    #[test]
+    fn volume_of_orthorhombic_cell() {
+        assert!((Cell::new([2.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 4.0]).volume() - 24.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn from_comment() {
+        let cell = Cell::from_comment("energy=-1.0 Lattice=\"5 0 0 0 6 0 0 0 7\" pbc=\"T T T\"").unwrap();
+        assert_eq!(cell.vectors, [[5.0, 0.0, 0.0], [0.0, 6.0, 0.0], [0.0, 0.0, 7.0]]);
+        assert!(Cell::from_comment("Lattice=\"5 0 0\"").is_none());
+        assert!(Cell::from_comment("no lattice").is_none());
+    }
+
+    #[test]
+    fn comment_entry() {
+        let cell = Cell::new([5.0, 0.0, 0.0], [0.0, 6.5, 0.0], [0.0, 0.0, 7.0]);
+        assert_eq!(cell.to_comment(), "Lattice=\"5 0 0 0 6.5 0 0 0 7\"");
+        assert_eq!(Cell::from_comment(&cell.to_comment()), Some(cell));
+        assert_eq!(Cell::strip_comment("a=1 Lattice=\"5 0 0 0 6.5 0 0 0 7\" b=2"), "a=1 b=2");
+        assert_eq!(cell.center(), Position::new(2.5, 3.25, 3.5));
+    }
+
+    #[test]
+    fn fractional_and_cartesian() {
+        let cell = Cell::new([2.0, 0.0, 0.0], [1.0, 2.0, 0.0], [0.0, 0.0, 4.0]);
+        let position = cell.cartesian([0.5, 0.5, 0.25]);
+        assert_eq!(position, Position::new(1.5, 1.0, 1.0));
+        let fractional = cell.fractional(position);
+        assert!(fractional.iter().zip([0.5, 0.5, 0.25]).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert!(Cell::new([1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 0.0, 1.0]).fractional(position)[0].is_nan());
+    }
+
+    #[test]
+    fn niggli_reduction() {
+        let reduce = |cell: Cell| cell.niggli_reduce().unwrap().lengths_angles();
+        let close = |a: [f64; 3], b: [f64; 3]| a.iter().zip(&b).all(|(x, y)| (x - y).abs() < 1e-9);
+
+        let skewed = Cell::new([3.0, 0.0, 0.0], [6.0, 3.0, 0.0], [3.0, -3.0, 3.0]);
+        let (lengths, angles) = reduce(skewed);
+        assert!(close(lengths, [3.0; 3]) && close(angles, [90.0; 3]));
+        assert!(skewed.niggli_reduce().unwrap().volume() > 0.0);
+
+        let hexagonal = Cell::from_lengths_angles([2.0, 2.0, 5.0], [90.0, 90.0, 120.0]);
+        let [a, b, c] = hexagonal.vectors;
+        let sheared = Cell::new(a, [b[0] + 3.0 * a[0], b[1] + 3.0 * a[1], b[2]], [c[0] - a[0], c[1] - a[1], c[2]]);
+        let (expected, _) = reduce(hexagonal);
+        let (lengths, angles) = reduce(sheared);
+        assert!(close(lengths, expected));
+        assert!((angles[2] - 60.0).abs() < 1e-9 || (angles[2] - 120.0).abs() < 1e-9);
+
+        assert!(Cell::new([1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 0.0, 1.0]).niggli_reduce().is_none());
+    }
+}
@@ -1,3 +1,10 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use super::bonds::BOND_TOLERANCE;
+use super::cell::Cell;
+use super::element::Element;
+use super::error::ConsistencyError;
+use super::labels::LabelMap;
 use super::{AtomCount, AtomPosition};
 
 /// The data for each atom.
@@ -7,6 +14,65 @@ pub struct AtomData {
     pub symbol: String,
     /// The position of the atom as cartesion coordinates in Ångström (1e-10 m).
     pub position: AtomPosition,
+    /// An optional atom name, e.g. `CA` or `OW`, read from the `name` column of an extended XYZ file.
+    pub name: Option<String>,
+    /// An optional group the atom belongs to, such as a residue or ligand name,
+    /// read from the `group`, `residue` or `resname` column of an extended XYZ file.
+    pub group: Option<String>,
+    /// An optional index assigned by the program that wrote the file,
+    /// read from the `index` or `id` column of an extended XYZ file.
+    pub index: Option<usize>,
+    /// The force on the atom, read from the `forces` column of an extended XYZ file.
+    pub force: Option<AtomPosition>,
+    /// The velocity of the atom in the units of the file, typically Å/fs,
+    /// read from the `velo` or `velocities` column of an extended XYZ file.
+    pub velocity: Option<AtomPosition>,
+    /// The partial charge of the atom in elementary charges,
+    /// read from the `charge`, `charges` or `initial_charges` column of an extended XYZ file.
+    pub charge: Option<f64>,
+    /// Free-form annotations of the atom by layer, e.g. `layer` → `H` for the high layer of an ONIOM calculation.
+    /// Read from and written to the other string columns of extended XYZ files, see [`tags`](crate::tags).
+    pub tags: BTreeMap<String, String>,
+    /// The element of a label that is not an element symbol, e.g. `OW`,
+    /// resolved by the [`LabelMap`](crate::labels::LabelMap) used when reading the file.
+    pub resolved_element: Option<Element>,
+}
+impl AtomData {
+    /// Creates the data for an atom without metadata.
+    pub fn new<S: Into<String>, P: Into<AtomPosition>>(symbol: S, position: P) -> AtomData {
+        AtomData {
+            symbol: symbol.into(),
+            position: position.into(),
+            name: None,
+            group: None,
+            index: None,
+            force: None,
+            velocity: None,
+            charge: None,
+            tags: BTreeMap::new(),
+            resolved_element: None,
+        }
+    }
+
+    /// The element represented by the atomic symbol, if it is a known element symbol or a resolved label.
+    pub fn element(&self) -> Option<Element> {
+        self.resolved_element.or_else(|| Element::from_symbol(&self.symbol))
+    }
+
+    /// Replaces the symbol by the symbol of the element it resolves to with `labels` or names,
+    /// e.g. `OW`, `fe` or `carbon` by `O`, `Fe` or `C`.
+    /// Returns `false`, leaving the atom unchanged, if the symbol does not resolve.
+    pub fn fix_symbol(&mut self, labels: &LabelMap) -> bool {
+        let element = self.resolved_element.or_else(|| labels.resolve(&self.symbol));
+        match element.or_else(|| Element::from_name(&self.symbol)) {
+            Some(element) => {
+                self.symbol = element.symbol().to_string();
+                self.resolved_element = None;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 /// Represents the data in one block of a `.xyz` file.
@@ -20,12 +86,169 @@ pub struct Data {
     pub comment: String,
     /// The remaining lines of the file contain information about the positions of the atoms.
     pub atoms: Vec<AtomData>,
+    /// The periodic cell of the structure, if any.
+    /// This is read from the `Lattice="..."` entry of an extended XYZ comment.
+    pub cell: Option<Cell>,
+}
+impl Data {
+    /// An estimate of the memory used by the frame in bytes, including its strings and atoms.
+    pub fn estimated_memory(&self) -> usize {
+        let strings = |atom: &AtomData| {
+            let optional = [&atom.name, &atom.group].map(|s| s.as_ref().map_or(0, String::capacity));
+            let tags: usize = atom.tags.iter().map(|(k, v)| k.capacity() + v.capacity() + 3 * size_of::<usize>()).sum();
+            atom.symbol.capacity() + optional.iter().sum::<usize>() + tags
+        };
+        size_of::<Data>()
+            + self.comment.capacity()
+            + self.atoms.capacity() * size_of::<AtomData>()
+            + self.atoms.iter().map(strings).sum::<usize>()
+    }
+
+    /// The chemical formula in Hill notation, e.g. `CH4O` or `H2O`.
+    /// Carbon comes first and hydrogen second if carbon is present,
+    /// followed by the other symbols in alphabetical order.
+    /// Resolved labels are counted as their element.
+    pub fn formula(&self) -> String {
+        let mut counts: Vec<(&str, usize)> = vec![];
+        for atom in &self.atoms {
+            let symbol = atom.element().map_or(atom.symbol.as_str(), |e| e.symbol());
+            match counts.iter_mut().find(|(s, _)| *s == symbol) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((symbol, 1)),
+            }
+        }
+
+        let has_carbon = counts.iter().any(|(symbol, _)| *symbol == "C");
+        let rank = |symbol: &str| match symbol {
+            "C" if has_carbon => 0,
+            "H" if has_carbon => 1,
+            _ => 2,
+        };
+        counts.sort_by(|(a, _), (b, _)| rank(a).cmp(&rank(b)).then_with(|| a.cmp(b)));
+
+        counts
+            .into_iter()
+            .map(|(symbol, n)| if n == 1 { symbol.to_string() } else { format!("{}{}", symbol, n) })
+            .collect()
+    }
+
+    /// Sets the cell, and updates the `Lattice="..."` entry of the comment accordingly so it is kept when writing.
+    pub fn set_cell(&mut self, cell: Option<Cell>) {
+        let comment = Cell::strip_comment(&self.comment);
+        self.comment = match &cell {
+            Some(cell) if comment.is_empty() => cell.to_comment(),
+            Some(cell) => format!("{} {}", cell.to_comment(), comment),
+            None => comment,
+        };
+        self.cell = cell;
+    }
+
+    /// The total mass of the atoms in unified atomic mass units (Da).
+    /// Returns `None` if any atomic symbol is not a known element.
+    pub fn mass(&self) -> Option<f64> {
+        self.atoms.iter().map(|atom| atom.element().map(|e| e.mass())).sum()
+    }
+
+    /// The density of the structure in g/cm³, computed from the atomic masses and the cell volume.
+    /// Returns `None` if there is no cell or any atomic symbol is not a known element.
+    pub fn density(&self) -> Option<f64> {
+        // 1 Da/Å³ = 1.66053906660e-24 g / 1e-24 cm³
+        const DA_PER_CUBIC_ANGSTROM: f64 = 1.66053906660;
+
+        let volume = self.cell.as_ref()?.volume();
+        Some(self.mass()? / volume * DA_PER_CUBIC_ANGSTROM)
+    }
+
+    /// An aligned table of the atoms for terminal inspection, with a header line followed by one line per atom:
+    /// the index, the atomic symbol and the coordinates,
+    /// plus the name, group, index and force columns if any atom has them.
+    pub fn display_table(&self) -> String {
+        let any = |f: fn(&AtomData) -> bool| self.atoms.iter().any(f);
+        let (names, groups, ids, forces) = (
+            any(|atom| atom.name.is_some()),
+            any(|atom| atom.group.is_some()),
+            any(|atom| atom.index.is_some()),
+            any(|atom| atom.force.is_some()),
+        );
+
+        let mut rows: Vec<Vec<String>> = vec![];
+        let mut header = vec!["#", "symbol", "x", "y", "z"];
+        if names {
+            header.push("name");
+        }
+        if groups {
+            header.push("group");
+        }
+        if ids {
+            header.push("id");
+        }
+        if forces {
+            header.extend(["fx", "fy", "fz"]);
+        }
+        rows.push(header.into_iter().map(String::from).collect());
+
+        for (i, atom) in self.atoms.iter().enumerate() {
+            let mut row = vec![i.to_string(), atom.symbol.clone()];
+            row.extend(atom.position.0.iter().map(|v| format!("{:.5}", v)));
+            if names {
+                row.push(atom.name.clone().unwrap_or_default());
+            }
+            if groups {
+                row.push(atom.group.clone().unwrap_or_default());
+            }
+            if ids {
+                row.push(atom.index.map(|id| id.to_string()).unwrap_or_default());
+            }
+            if forces {
+                match atom.force {
+                    Some(force) => row.extend(force.0.iter().map(|v| format!("{:.5}", v))),
+                    None => row.extend([String::new(), String::new(), String::new()]),
+                }
+            }
+            rows.push(row);
+        }
+
+        let mut widths = vec![0; rows[0].len()];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut table = String::new();
+        for row in &rows {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .zip(&rows[0])
+                .map(|((cell, &width), column)| match column.as_str() {
+                    "symbol" | "name" | "group" => format!("{:<width$}", cell),
+                    _ => format!("{:>width$}", cell),
+                })
+                .collect();
+            table.push_str(cells.join("  ").trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    /// Whether any atom position has a NaN or infinite coordinate.
+    pub fn has_invalid_coords(&self) -> bool {
+        self.atoms.iter().any(|atom| !atom.position.is_finite())
+    }
+
+    /// Replaces the atom symbols by element symbols using `labels`, see [`AtomData::fix_symbol`].
+    /// Returns the indices of the atoms whose symbols could not be resolved.
+    pub fn fix_symbols(&mut self, labels: &LabelMap) -> Vec<usize> {
+        (0..self.atoms.len()).filter(|&i| !self.atoms[i].fix_symbol(labels)).collect()
+    }
 }
 impl std::fmt::Display for Data {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut string = String::new();
 
-        let head = format!("{}\n{}", self.count, self.comment);
+        // A line break in the comment would shift every following line of the frame.
+        let head = format!("{}\n{}", self.count, self.comment.replace(['\n', '\r'], " "));
         string.push_str(&head);
 
         for atom in &self.atoms {
@@ -39,12 +262,149 @@ impl std::fmt::Display for Data {
     }
 }
 
+/// How strictly the frames of two trajectories must agree before they are joined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksPolicy {
+    /// Only the atom counts must match.
+    Count,
+    /// The atom counts and the order of the atomic symbols must match.
+    Order,
+    /// The atom counts and compositions must match.
+    /// Atoms are reordered to follow the order of the existing frames.
+    Remap,
+}
+
 /// Represents the data in a `.xyz` file.
 #[derive(Debug, Clone)]
 pub struct File {
     /// Vec of data blocks.
     pub data: Vec<Data>,
 }
+impl File {
+    /// Appends the frames of another trajectory, if they are consistent with the frames already present.
+    /// Every frame of `other` is checked against the last frame of `self`,
+    /// or the first frame of `other` if `self` is empty. On error nothing is appended,
+    /// and the error reports the first frame of `other` and atom where the trajectories diverge.
+    pub fn extend_checked(&mut self, other: File, policy: ChecksPolicy) -> Result<(), ConsistencyError> {
+        let reference = match self.data.last().or(other.data.first()) {
+            Some(data) => data.clone(),
+            None => return Ok(()),
+        };
+
+        let mut frames = Vec::with_capacity(other.data.len());
+        for (frame, mut data) in other.data.into_iter().enumerate() {
+            if data.atoms.len() != reference.atoms.len() {
+                return Err(ConsistencyError::AtomCount {
+                    frame,
+                    expected: reference.atoms.len(),
+                    found: data.atoms.len(),
+                });
+            }
+
+            match policy {
+                ChecksPolicy::Count => {}
+                ChecksPolicy::Order => {
+                    let mismatch = reference.atoms.iter().zip(&data.atoms).position(|(r, a)| r.symbol != a.symbol);
+                    if let Some(atom) = mismatch {
+                        return Err(ConsistencyError::AtomSymbol {
+                            frame,
+                            atom,
+                            expected: reference.atoms[atom].symbol.clone(),
+                            found: data.atoms[atom].symbol.clone(),
+                        });
+                    }
+                }
+                ChecksPolicy::Remap => data.atoms = remap_atoms(&reference.atoms, data.atoms, frame)?,
+            }
+
+            frames.push(data);
+        }
+
+        self.data.append(&mut frames);
+        Ok(())
+    }
+
+    /// Checks that the topology is the same in every frame, as analysis tools that assume a fixed topology require:
+    /// every frame has the atom count and element sequence of the first frame, and, with a `bond_tolerance`
+    /// in Ångström, the bonds of the first frame, see [`Data::bonds`]. Only bonds that form or break by more than
+    /// the tolerance, beyond the bond cutoff, count as changes, so bonds vibrating around the cutoff do not.
+    /// The error reports the first frame and atom where the topology differs from the first frame.
+    pub fn check_topology_consistency(&self, bond_tolerance: Option<f64>) -> Result<(), ConsistencyError> {
+        let Some(reference) = self.data.first() else {
+            return Ok(());
+        };
+        let reference_bonds = bond_tolerance.map(|_| reference.bonds());
+        for (frame, data) in self.data.iter().enumerate().skip(1) {
+            if data.atoms.len() != reference.atoms.len() {
+                return Err(ConsistencyError::AtomCount {
+                    frame,
+                    expected: reference.atoms.len(),
+                    found: data.atoms.len(),
+                });
+            }
+            // Atoms are compared by element, so labels resolved to the same element match.
+            let same = |r: &AtomData, a: &AtomData| match (r.element(), a.element()) {
+                (Some(r), Some(a)) => r == a,
+                _ => r.symbol == a.symbol,
+            };
+            if let Some(atom) = reference.atoms.iter().zip(&data.atoms).position(|(r, a)| !same(r, a)) {
+                return Err(ConsistencyError::AtomSymbol {
+                    frame,
+                    atom,
+                    expected: reference.atoms[atom].symbol.clone(),
+                    found: data.atoms[atom].symbol.clone(),
+                });
+            }
+
+            let (Some(tolerance), Some(reference_bonds)) = (bond_tolerance, &reference_bonds) else {
+                continue;
+            };
+            let bonds = data.bonds();
+            // The distance of a pair of atoms beyond their bond cutoff, negative if they are bonded.
+            let excess = |(i, j): (usize, usize)| {
+                let radius = |i: usize| data.atoms[i].element().and_then(|e| e.covalent_radius()).unwrap_or(0.0);
+                data.atoms[i].position.distance(data.atoms[j].position) - radius(i) - radius(j) - BOND_TOLERANCE
+            };
+            let missing = |bonds: &[(usize, usize)], b: &(usize, usize)| bonds.binary_search(b).is_err();
+            let broken = reference_bonds.iter().find(|&&b| missing(&bonds, &b) && excess(b) > tolerance);
+            if let Some(&(first, second)) = broken {
+                return Err(ConsistencyError::BondBroken { frame, first, second });
+            }
+            let formed = bonds.iter().find(|&&b| missing(reference_bonds, &b) && -excess(b) > tolerance);
+            if let Some(&(first, second)) = formed {
+                return Err(ConsistencyError::BondFormed { frame, first, second });
+            }
+        }
+        Ok(())
+    }
+
+    /// Reverses the order of the frames.
+    pub fn reverse(&mut self) {
+        self.data.reverse();
+    }
+
+    /// Sorts the frames by a key, for example an energy parsed from the comment.
+    /// The sort is stable, and frames with keys that cannot be compared (such as `NaN`) are moved to the end.
+    pub fn sort_frames_by<K: PartialOrd, F: FnMut(&Data) -> K>(&mut self, mut key: F) {
+        let (mut keyed, unordered): (Vec<_>, Vec<_>) = self
+            .data
+            .drain(..)
+            .map(|data| (key(&data), data))
+            .partition(|(k, _)| k.partial_cmp(k).is_some());
+        keyed.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        self.data = keyed.into_iter().chain(unordered).map(|(_, data)| data).collect();
+    }
+
+    /// Rotates the frames `n` places to the left, so that frame `n` becomes the first frame.
+    /// `n` wraps around the number of frames.
+    pub fn rotate_frames(&mut self, n: usize) {
+        if !self.data.is_empty() {
+            let n = n % self.data.len();
+            self.data.rotate_left(n);
+        }
+    }
+}
+
 impl std::fmt::Display for File {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f)?;
@@ -55,3 +415,142 @@ impl std::fmt::Display for File {
     }
 }
 
+/// Reorders `atoms` so that the atomic symbols follow the order of `reference`,
+/// keeping the relative order of atoms with the same symbol.
+fn remap_atoms(reference: &[AtomData], atoms: Vec<AtomData>, frame: usize) -> Result<Vec<AtomData>, ConsistencyError> {
+    let mut by_symbol: HashMap<String, VecDeque<AtomData>> = HashMap::new();
+    for atom in atoms {
+        by_symbol.entry(atom.symbol.clone()).or_default().push_back(atom);
+    }
+
+    reference
+        .iter()
+        .enumerate()
+        .map(|(atom, r)| {
+            by_symbol
+                .get_mut(&r.symbol)
+                .and_then(VecDeque::pop_front)
+                .ok_or_else(|| ConsistencyError::Composition {
+                    frame,
+                    atom,
+                    symbol: r.symbol.clone(),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::LabelMap;
+    use crate::testing::frame;
+    use crate::testing::trajectory;
+    use crate::testing::water;
+
+    #[test]
+    fn mass_and_density() {
+        let mut data = water();
+        assert!((data.mass().unwrap() - 18.015).abs() < 1e-2);
+        assert!(data.density().is_none());
+
+        // One molecule in 29.9 Å³ has about the density of liquid water.
+        let side = 29.9f64.cbrt();
+        data.cell = Some(Cell::new([side, 0.0, 0.0], [0.0, side, 0.0], [0.0, 0.0, side]));
+        assert!((data.density().unwrap() - 1.0).abs() < 1e-2);
+
+        data.atoms[0].symbol = "Xx".to_string();
+        assert!(data.mass().is_none());
+    }
+
+    #[test]
+    fn extend_checked_policies() {
+        let mut file = File { data: vec![water()] };
+        let reordered = frame("", &[("H", [0.96, 0.0, 0.0]), ("O", [0.0, 0.0, 0.0]), ("H", [-0.24, 0.93, 0.0])]);
+
+        let error = file.extend_checked(File { data: vec![reordered.clone()] }, ChecksPolicy::Order).unwrap_err();
+        assert!(matches!(error, ConsistencyError::AtomSymbol { frame: 0, atom: 0, .. }));
+        assert_eq!(file.data.len(), 1);
+
+        file.extend_checked(File { data: vec![reordered] }, ChecksPolicy::Remap).unwrap();
+        let symbols: Vec<&str> = file.data[1].atoms.iter().map(|atom| atom.symbol.as_str()).collect();
+        assert_eq!(symbols, ["O", "H", "H"]);
+        assert_eq!(file.data[1].atoms[1].position[0], 0.96);
+
+        let hydrogen = frame("", &[("H", [0.0; 3]), ("H", [0.74, 0.0, 0.0])]);
+        let error = file.extend_checked(File { data: vec![hydrogen] }, ChecksPolicy::Count).unwrap_err();
+        assert!(matches!(error, ConsistencyError::AtomCount { expected: 3, found: 2, .. }));
+    }
+
+    fn comments(file: &File) -> Vec<&str> {
+        file.data.iter().map(|data| data.comment.as_str()).collect()
+    }
+
+    #[test]
+    fn reverse_sort_and_rotate_frames() {
+        let mut file = trajectory(4);
+        file.reverse();
+        assert_eq!(comments(&file), ["step=3", "step=2", "step=1", "step=0"]);
+
+        file.data[1].comment = "step=nan".to_string();
+        file.sort_frames_by(|data| data.comment[5..].parse::<f64>().unwrap());
+        assert_eq!(comments(&file), ["step=0", "step=1", "step=3", "step=nan"]);
+
+        file.rotate_frames(5);
+        assert_eq!(comments(&file), ["step=1", "step=3", "step=nan", "step=0"]);
+    }
+
+    #[test]
+    fn hill_formula() {
+        assert_eq!(water().formula(), "H2O");
+        let methanol = [("C", [0.0; 3]), ("O", [1.4, 0.0, 0.0]), ("H", [0.0; 3]), ("H", [0.0; 3]), ("H", [0.0; 3])];
+        let mut methanol = frame("", &methanol);
+        methanol.atoms.push(methanol.atoms[2].clone());
+        assert_eq!(methanol.formula(), "CH4O");
+        assert_eq!(frame("", &[]).formula(), "");
+    }
+
+    #[test]
+    fn display_table_columns() {
+        let mut data = water();
+        assert_eq!(
+            data.display_table(),
+            "#  symbol         x        y        z\n\
+             0  O        0.00000  0.00000  0.00000\n\
+             1  H        0.96000  0.00000  0.00000\n\
+             2  H       -0.24000  0.93000  0.00000\n"
+        );
+        data.atoms[1].name = Some("HW1".to_string());
+        data.atoms[2].index = Some(12);
+        let table = data.display_table();
+        assert!(table.starts_with("#  symbol         x        y        z  name  id\n"), "{}", table);
+        assert!(table.ends_with("  0.93000  0.00000        12\n"), "{}", table);
+    }
+
+    #[test]
+    fn fix_symbols_with_labels_and_names() {
+        let mut data = frame("", &[("OW", [0.0; 3]), ("hydrogen", [1.0, 0.0, 0.0]), ("Xq", [2.0, 0.0, 0.0])]);
+        assert_eq!(data.fix_symbols(&LabelMap::common()), [2]);
+        let symbols: Vec<&str> = data.atoms.iter().map(|atom| atom.symbol.as_str()).collect();
+        assert_eq!(symbols, ["O", "H", "Xq"]);
+    }
+
+    #[test]
+    fn topology_consistency() {
+        let mut file = trajectory(3);
+        assert!(file.check_topology_consistency(Some(0.2)).is_ok());
+
+        file.data[2].atoms[1].position[0] += 0.55;
+        assert!(file.check_topology_consistency(Some(0.2)).is_ok());
+        file.data[2].atoms[1].position[0] += 0.6;
+        assert!(file.check_topology_consistency(None).is_ok());
+        let error = file.check_topology_consistency(Some(0.2)).unwrap_err();
+        assert!(matches!(error, ConsistencyError::BondBroken { frame: 2, first: 0, second: 1 }));
+
+        file.data[1].atoms[2].symbol = "F".to_string();
+        let error = file.check_topology_consistency(None).unwrap_err();
+        assert!(matches!(error, ConsistencyError::AtomSymbol { frame: 1, atom: 2, .. }));
+        file.data[1].atoms.pop();
+        let error = file.check_topology_consistency(None).unwrap_err();
+        assert!(matches!(error, ConsistencyError::AtomCount { frame: 1, expected: 3, found: 2 }));
+    }
+}
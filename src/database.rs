@@ -0,0 +1,325 @@
+//! Random access to the frames of a large `.xyz` file of concatenated structures with varying atom counts,
+//! such as the QM9 or ANI machine-learning datasets, with their comment properties as typed columns.
+//!
+//! ```no_run
+//! use rust_xyz_chem::database::Database;
+//!
+//! let database = Database::open("dataset.xyz")?;
+//! let molecule = database.get(1234).expect("frame exists")?;
+//! let energies = database.column("energy").numbers();
+//! println!("{} of {} frames, {:?}", molecule.atoms.len(), database.len(), database.summary().properties);
+//! # Ok::<(), rust_xyz_chem::error::FileParseError>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::analysis::Series;
+use super::data::{Data, File};
+use super::metadata::parse_properties;
+use super::{error, frames_with, Frames, ParseOptions};
+
+/// An index of the frames of a `.xyz` file, which reads single frames on demand.
+///
+/// Opening the database reads the file once, keeping the position, atom count and comment of every frame,
+/// and the number of atoms of every element, but not the atoms themselves.
+#[derive(Debug, Clone)]
+pub struct Database {
+    path: PathBuf,
+    options: ParseOptions,
+    offsets: Vec<u64>,
+    atom_counts: Vec<usize>,
+    comments: Vec<String>,
+    elements: BTreeMap<String, usize>,
+}
+
+/// The values of a comment property across the frames of a [`Database`], typed by the values found.
+/// Frames without the property have `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    /// Every value present is an integer.
+    Integer(Vec<Option<i64>>),
+    /// Every value present is a number, and some are not integers.
+    Float(Vec<Option<f64>>),
+    /// Some values present are not numbers.
+    Text(Vec<Option<String>>),
+}
+impl Column {
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Integer(values) => values.len(),
+            Column::Float(values) => values.len(),
+            Column::Text(values) => values.len(),
+        }
+    }
+
+    /// Whether the column has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values as numbers, with `NaN` for missing values, or `None` for a text column.
+    pub fn numbers(&self) -> Option<Vec<f64>> {
+        match self {
+            Column::Integer(values) => Some(values.iter().map(|v| v.map_or(f64::NAN, |v| v as f64)).collect()),
+            Column::Float(values) => Some(values.iter().map(|v| v.unwrap_or(f64::NAN)).collect()),
+            Column::Text(_) => None,
+        }
+    }
+}
+
+/// Statistics of the frames of a [`Database`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// The number of frames.
+    pub frames: usize,
+    /// The number of atoms in all frames.
+    pub atoms: usize,
+    /// The smallest and largest number of atoms in a frame, if there are frames.
+    pub atom_range: Option<(usize, usize)>,
+    /// The number of atoms of every element in all frames, by symbol. Unknown symbols are counted as they are.
+    pub elements: BTreeMap<String, usize>,
+    /// The statistics of every numeric comment property, in the order they first appear.
+    pub properties: Vec<PropertySummary>,
+}
+
+/// Statistics of a numeric comment property across the frames of a [`Database`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertySummary {
+    pub name: String,
+    /// The number of frames with the property.
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// The sample standard deviation, or `NaN` for fewer than two values.
+    pub std: f64,
+}
+
+/// The frames of a file, in memory or indexed on disk, read by [`read_stored`](crate::read_stored).
+#[derive(Debug, Clone)]
+pub enum FrameStore {
+    /// The frames fit into the memory limit.
+    Memory(File),
+    /// The frames exceed the memory limit, and are read from the file on demand.
+    Disk(Database),
+}
+impl FrameStore {
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        match self {
+            FrameStore::Memory(file) => file.data.len(),
+            FrameStore::Disk(database) => database.len(),
+        }
+    }
+
+    /// Whether there are no frames.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The frame at `index`, or `None` if there is no such frame. Frames in memory are cloned.
+    pub fn get(&self, index: usize) -> Option<error::Result<Data>> {
+        match self {
+            FrameStore::Memory(file) => file.data.get(index).cloned().map(Ok),
+            FrameStore::Disk(database) => database.get(index),
+        }
+    }
+
+    /// Iterates over the frames in order.
+    pub fn iter(&self) -> impl Iterator<Item = error::Result<Data>> + '_ {
+        (0..self.len()).map(|index| self.get(index).expect("index is in range"))
+    }
+}
+
+impl Database {
+    /// Indexes the frames of a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> error::Result<Database> {
+        Database::open_with(path, ParseOptions::default())
+    }
+
+    /// Indexes the frames of a file, parsed with `options`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
+    pub fn open_with<P: AsRef<Path>>(path: P, options: ParseOptions) -> error::Result<Database> {
+        let mut database = Database::empty(path, options);
+        let mut frames = frames_with(&database.path, &database.options)?;
+        let mut offset = 0;
+        while let Some(data) = frames.next() {
+            database.push(offset, &data?);
+            offset = frames.byte_offset();
+        }
+        Ok(database)
+    }
+
+    /// A database of the file without frames, which are added by [`Database::push`] while reading the file.
+    pub(crate) fn empty<P: AsRef<Path>>(path: P, options: ParseOptions) -> Database {
+        Database {
+            path: path.as_ref().to_path_buf(),
+            options,
+            offsets: vec![],
+            atom_counts: vec![],
+            comments: vec![],
+            elements: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the frame at byte `offset` of the file to the index.
+    pub(crate) fn push(&mut self, offset: u64, data: &Data) {
+        for atom in &data.atoms {
+            let symbol = atom.element().map_or(atom.symbol.as_str(), |e| e.symbol());
+            *self.elements.entry(symbol.to_string()).or_default() += 1;
+        }
+        self.offsets.push(offset);
+        self.atom_counts.push(data.atoms.len());
+        self.comments.push(data.comment.clone());
+    }
+
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the file has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// The number of atoms of every frame.
+    pub fn atom_counts(&self) -> &[usize] {
+        &self.atom_counts
+    }
+
+    /// The comment of every frame.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Reads the frame at `index`, or returns `None` if there is no such frame.
+    /// Line numbers in errors count from the start of the frame.
+    pub fn get(&self, index: usize) -> Option<error::Result<Data>> {
+        let offset = *self.offsets.get(index)?;
+        let read = || {
+            let mut file = fs::File::open(&self.path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut frames = Frames::with_options(BufReader::new(file), self.options.clone());
+            frames.next().unwrap_or_else(|| Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()))
+        };
+        Some(read())
+    }
+
+    /// The names of the comment properties, in the order they first appear.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = vec![];
+        for comment in &self.comments {
+            for (key, _) in parse_properties(comment) {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+
+    /// The values of the comment property `key` of every frame, compared case-insensitively.
+    pub fn column(&self, key: &str) -> Column {
+        let values: Vec<Option<String>> = self
+            .comments
+            .iter()
+            .map(|comment| {
+                let mut properties = parse_properties(comment).into_iter();
+                properties.find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v)
+            })
+            .collect();
+        if let Some(integers) = parse_all(&values) {
+            Column::Integer(integers)
+        } else if let Some(floats) = parse_all(&values) {
+            Column::Float(floats)
+        } else {
+            Column::Text(values)
+        }
+    }
+
+    /// Statistics of the atoms and numeric comment properties of all frames.
+    pub fn summary(&self) -> Summary {
+        let properties = self
+            .keys()
+            .into_iter()
+            .filter_map(|name| {
+                let values = self.column(&name).numbers()?;
+                let series = Series::new(name, values);
+                let values = series.values.iter().copied().filter(|v| !v.is_nan());
+                Some(PropertySummary {
+                    count: values.clone().count(),
+                    min: values.clone().fold(f64::INFINITY, f64::min),
+                    max: values.fold(f64::NEG_INFINITY, f64::max),
+                    mean: series.mean(),
+                    std: series.std(),
+                    name: series.name,
+                })
+            })
+            .collect();
+        Summary {
+            frames: self.len(),
+            atoms: self.atom_counts.iter().sum(),
+            atom_range: self.atom_counts.iter().min().zip(self.atom_counts.iter().max()).map(|(&a, &b)| (a, b)),
+            elements: self.elements.clone(),
+            properties,
+        }
+    }
+}
+
+/// Parses every value present, or returns `None` if any fails to parse.
+fn parse_all<T: std::str::FromStr>(values: &[Option<String>]) -> Option<Vec<Option<T>>> {
+    values.iter().map(|v| v.as_deref().map(str::parse).transpose().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    const DATASET: &str = "1\nenergy=-0.5 id=1 source=a\nH 0 0 0\n\n\
+                           3\nenergy=-76 id=2 source=b\nO 0 0 0\nH 0.96 0 0\nH -0.24 0.93 0\n\n\
+                           2\nid=3 source=c\nH 0 0 0\nH 0.74 0 0\n";
+
+    fn database(name: &str) -> Database {
+        let path = temp_path(name);
+        fs::write(&path, DATASET).unwrap();
+        let database = Database::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        database
+    }
+
+    #[test]
+    fn indexed_access() {
+        let path = temp_path("database.xyz");
+        fs::write(&path, DATASET).unwrap();
+        let database = Database::open(&path).unwrap();
+        assert_eq!(database.len(), 3);
+        assert_eq!(database.atom_counts(), [1, 3, 2]);
+        assert_eq!(database.get(1).unwrap().unwrap().formula(), "H2O");
+        assert_eq!(database.get(2).unwrap().unwrap().comment, "id=3 source=c");
+        assert!(database.get(3).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn property_columns_and_summary() {
+        let database = database("database-columns.xyz");
+        assert_eq!(database.keys(), ["energy", "id", "source"]);
+        assert_eq!(database.column("ID"), Column::Integer(vec![Some(1), Some(2), Some(3)]));
+        assert_eq!(database.column("energy").numbers().unwrap()[..2], [-0.5, -76.0]);
+        assert!(matches!(database.column("source"), Column::Text(_)));
+        assert_eq!(database.column("missing").len(), 3);
+
+        let summary = database.summary();
+        assert_eq!((summary.frames, summary.atoms, summary.atom_range), (3, 6, Some((1, 3))));
+        assert_eq!(summary.elements, BTreeMap::from([("H".to_string(), 5), ("O".to_string(), 1)]));
+        let energy = &summary.properties[0];
+        assert_eq!((energy.name.as_str(), energy.count, energy.min, energy.max), ("energy", 2, -76.0, -0.5));
+        assert_eq!(summary.properties.len(), 2);
+    }
+}
@@ -0,0 +1,185 @@
+//! Iterates over the frames of many `.xyz` files and directories, filtered by composition, size and energy,
+//! e.g. to build training and validation sets for machine-learning potentials.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::data::{Data, File};
+use super::element::Element;
+use super::random::Rng;
+use super::{error, frames_with, ParseOptions};
+
+/// Conditions that frames of a [`Dataset`] have to fulfil.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only frames consisting solely of these elements are kept.
+    pub only_elements: Option<Vec<Element>>,
+    /// Only frames containing all of these elements are kept.
+    pub required_elements: Vec<Element>,
+    /// Only frames with at most this many atoms are kept.
+    pub max_atoms: Option<usize>,
+    /// Only frames with an `energy` property in this inclusive range are kept.
+    pub energy: Option<(f64, f64)>,
+}
+impl Filter {
+    /// Whether a frame fulfils all conditions.
+    pub fn matches(&self, data: &Data) -> bool {
+        if self.max_atoms.is_some_and(|max| data.atoms.len() > max) {
+            return false;
+        }
+        if let Some((min, max)) = self.energy {
+            match data.energy() {
+                Some(energy) if (min..=max).contains(&energy) => {}
+                _ => return false,
+            }
+        }
+
+        let elements: Vec<Option<Element>> = data.atoms.iter().map(|atom| atom.element()).collect();
+        if let Some(only) = &self.only_elements {
+            if !elements.iter().all(|element| element.is_some_and(|e| only.contains(&e))) {
+                return false;
+            }
+        }
+        self.required_elements.iter().all(|required| elements.contains(&Some(*required)))
+    }
+}
+
+/// A collection of `.xyz` files whose frames are read lazily and filtered.
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    /// The files, in reading order.
+    pub paths: Vec<PathBuf>,
+    /// Options for parsing the files.
+    pub options: ParseOptions,
+    /// Conditions that frames have to fulfil.
+    pub filter: Filter,
+}
+impl Dataset {
+    /// Creates a dataset from files and directories.
+    /// Directories are searched recursively for `.xyz` and `.extxyz` files, which are read in natural order.
+    pub fn new<I, P>(paths: I) -> error::Result<Dataset>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut dataset = Dataset::default();
+        for path in paths {
+            let path = path.as_ref();
+            if path.is_dir() {
+                let mut found = vec![];
+                find_files(path, &mut found)?;
+                found.sort_by(|a, b| super::natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+                dataset.paths.extend(found);
+            } else {
+                dataset.paths.push(path.to_path_buf());
+            }
+        }
+        Ok(dataset)
+    }
+
+    /// Iterates over the frames that match the filter, one frame at a time.
+    /// Each file is only opened once the previous one is exhausted.
+    pub fn frames(&self) -> impl Iterator<Item = error::Result<Data>> + '_ {
+        self.paths
+            .iter()
+            .flat_map(move |path| match frames_with(path, &self.options) {
+                Ok(frames) => Box::new(frames) as Box<dyn Iterator<Item = error::Result<Data>>>,
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            })
+            .filter(move |frame| frame.as_ref().map_or(true, |data| self.filter.matches(data)))
+    }
+
+    /// Reads the frames that match the filter, shuffled reproducibly with `seed`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(files = self.paths.len()), err))]
+    pub fn shuffled(&self, seed: u64) -> error::Result<File> {
+        let mut data = self.frames().collect::<error::Result<Vec<Data>>>()?;
+        Rng::new(seed).shuffle(&mut data);
+        Ok(File { data })
+    }
+
+    /// Reads the frames that match the filter, shuffled with `seed`, and splits them into
+    /// a training set and a validation set holding `validation_fraction` of the frames.
+    pub fn split(&self, validation_fraction: f64, seed: u64) -> error::Result<(File, File)> {
+        let mut training = self.shuffled(seed)?;
+        let validation_count = (training.data.len() as f64 * validation_fraction.clamp(0.0, 1.0)).round() as usize;
+        let validation = training.data.split_off(training.data.len() - validation_count);
+        Ok((training, File { data: validation }))
+    }
+}
+
+fn find_files(dir: &Path, found: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_files(&path, found)?;
+        } else if path.extension().is_some_and(|ext| ext == "xyz" || ext == "extxyz") {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{frame, temp_path, water};
+
+    /// Writes single-frame files into a fresh directory.
+    fn directory(name: &str) -> PathBuf {
+        let dir = temp_path(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let mut data = water();
+        data.comment = "energy=-1.5".to_string();
+        let frames = [
+            ("a.xyz", data),
+            ("sub/b10.xyz", frame("energy=-0.5", &[("H", [0.0; 3]), ("H", [0.74, 0.0, 0.0])])),
+            ("sub/b9.xyz", frame("", &[("O", [0.0; 3])])),
+            ("notes.txt", frame("", &[])),
+        ];
+        for (path, data) in frames {
+            crate::write(dir.join(path), File { data: vec![data] }).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn files_in_natural_order_and_filters() {
+        let dir = directory("dataset");
+        let mut dataset = Dataset::new([&dir]).unwrap();
+        let names: Vec<_> = dataset.paths.iter().map(|p| p.strip_prefix(&dir).unwrap().to_path_buf()).collect();
+        assert_eq!(names, [Path::new("a.xyz"), Path::new("sub/b9.xyz"), Path::new("sub/b10.xyz")]);
+        assert_eq!(dataset.frames().count(), 3);
+
+        let hydrogen = Element::from_symbol("H").unwrap();
+        dataset.filter.required_elements = vec![hydrogen];
+        assert_eq!(dataset.frames().count(), 2);
+        dataset.filter.only_elements = Some(vec![hydrogen]);
+        assert_eq!(dataset.frames().next().unwrap().unwrap().atoms.len(), 2);
+        dataset.filter = Filter {
+            max_atoms: Some(2),
+            energy: Some((-1.0, 0.0)),
+            ..Filter::default()
+        };
+        assert_eq!(dataset.frames().count(), 1);
+
+        dataset.paths.push(dir.join("missing.xyz"));
+        assert!(dataset.frames().last().unwrap().is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reproducible_shuffle_and_split() {
+        let dir = directory("dataset-split");
+        let dataset = Dataset::new([&dir]).unwrap();
+        let counts = |file: &File| file.data.iter().map(|data| data.atoms.len()).collect::<Vec<_>>();
+        assert_eq!(counts(&dataset.shuffled(3).unwrap()), counts(&dataset.shuffled(3).unwrap()));
+        let mut shuffled = counts(&dataset.shuffled(3).unwrap());
+        shuffled.sort();
+        assert_eq!(shuffled, [1, 2, 3]);
+
+        let (training, validation) = dataset.split(0.34, 3).unwrap();
+        assert_eq!((training.data.len(), validation.data.len()), (2, 1));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Reads binary DCD trajectories, as written by CHARMM, NAMD and LAMMPS.
+//!
+//! DCD files only store coordinates, so the atomic symbols have to be supplied separately,
+//! e.g. from a companion `.xyz` frame:
+//!
+//! ```no_run
+//! let topology = rust_xyz_chem::read("first_frame.xyz")?;
+//! let symbols: Vec<&str> = topology.data[0].atoms.iter().map(|atom| atom.symbol.as_str()).collect();
+//! let trajectory = rust_xyz_chem::dcd::read("trajectory.dcd", &symbols)?;
+//! rust_xyz_chem::write("trajectory.xyz", trajectory)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use super::cell::Cell;
+use super::data::{AtomData, Data, File};
+
+/// Reads a `.dcd` file, labelling the atoms with `symbols` in order.
+pub fn read<P: AsRef<Path>, S: AsRef<str>>(path: P, symbols: &[S]) -> Result<File> {
+    let bytes = fs::read(path)?;
+    let mut reader = RecordReader::new(&bytes)?;
+
+    let header = reader.record()?;
+    if header.len() != 84 || &header[..4] != b"CORD" {
+        return Err(invalid("not a dcd file"));
+    }
+    let control = |i: usize| reader.i32_at(header, 4 + 4 * i);
+    let frames = control(0).max(0) as usize;
+    let first_step = control(1) as i64;
+    let step_interval = control(2) as i64;
+    let fixed_atoms = control(8);
+    let has_cell = control(10) != 0;
+    let has_4d = control(11) != 0;
+    if fixed_atoms != 0 {
+        return Err(invalid("dcd files with fixed atoms are not supported"));
+    }
+
+    // Title record, which is not needed.
+    reader.record()?;
+
+    let atoms = reader.record()?;
+    if atoms.len() != 4 {
+        return Err(invalid("invalid atom count record"));
+    }
+    let count = reader.i32_at(atoms, 0).max(0) as usize;
+    if count != symbols.len() {
+        return Err(invalid(&format!(
+            "dcd file has {} atoms, but {} symbols were given",
+            count,
+            symbols.len()
+        )));
+    }
+
+    // The header is not trusted for the reservation, which cannot exceed the frames the remaining bytes hold.
+    let frame_bytes = 3 * (4 * count + 8) + if has_cell { 56 } else { 0 } + if has_4d { 4 * count + 8 } else { 0 };
+    let mut file = File {
+        data: Vec::with_capacity(frames.min(reader.remaining() / frame_bytes)),
+    };
+    // The frame count in the header is unreliable for trajectories that were still being written,
+    // so read until the end.
+    for frame in 0.. {
+        if reader.is_empty() {
+            break;
+        }
+
+        let cell = if has_cell { Some(reader.cell()?) } else { None };
+        let x = reader.coordinates(count)?;
+        let y = reader.coordinates(count)?;
+        let z = reader.coordinates(count)?;
+        if has_4d {
+            reader.record()?;
+        }
+
+        let mut comment = String::new();
+        if let Some(cell) = &cell {
+            comment.push_str(&cell.to_comment());
+            comment.push(' ');
+        }
+        comment.push_str(&format!("step={}", first_step + frame * step_interval));
+
+        file.data.push(Data {
+            count,
+            comment,
+            atoms: symbols
+                .iter()
+                .enumerate()
+                .map(|(i, symbol)| AtomData::new(symbol.as_ref(), [x[i] as f64, y[i] as f64, z[i] as f64]))
+                .collect(),
+            cell,
+        });
+    }
+
+    Ok(file)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Reads Fortran unformatted records, detecting the byte order from the first record marker.
+struct RecordReader<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+impl<'a> RecordReader<'a> {
+    fn new(bytes: &'a [u8]) -> Result<RecordReader<'a>> {
+        let marker: [u8; 4] = bytes.get(..4).ok_or_else(|| invalid("not a dcd file"))?.try_into().unwrap();
+        let big_endian = match (i32::from_le_bytes(marker), i32::from_be_bytes(marker)) {
+            (84, _) => false,
+            (_, 84) => true,
+            _ => return Err(invalid("not a dcd file")),
+        };
+        Ok(RecordReader { bytes, big_endian })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The number of bytes not read yet.
+    fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn i32_at(&self, bytes: &[u8], offset: usize) -> i32 {
+        let b: [u8; 4] = bytes[offset..offset + 4].try_into().unwrap();
+        if self.big_endian {
+            i32::from_be_bytes(b)
+        } else {
+            i32::from_le_bytes(b)
+        }
+    }
+
+    fn record(&mut self) -> Result<&'a [u8]> {
+        let truncated = || Error::new(ErrorKind::UnexpectedEof, "truncated dcd file");
+        if self.bytes.len() < 4 {
+            return Err(truncated());
+        }
+        let len = self.i32_at(self.bytes, 0);
+        let len = usize::try_from(len).map_err(|_| invalid("negative record length"))?;
+        if self.bytes.len() < len + 8 {
+            return Err(truncated());
+        }
+
+        let record = &self.bytes[4..4 + len];
+        if self.i32_at(self.bytes, 4 + len) as usize != len {
+            return Err(invalid("mismatched record markers"));
+        }
+        self.bytes = &self.bytes[len + 8..];
+        Ok(record)
+    }
+
+    fn coordinates(&mut self, count: usize) -> Result<Vec<f32>> {
+        let record = self.record()?;
+        if record.len() != count * 4 {
+            return Err(invalid("invalid coordinate record"));
+        }
+        Ok(record
+            .chunks_exact(4)
+            .map(|b| {
+                let b: [u8; 4] = b.try_into().unwrap();
+                if self.big_endian {
+                    f32::from_be_bytes(b)
+                } else {
+                    f32::from_le_bytes(b)
+                }
+            })
+            .collect())
+    }
+
+    /// Reads a CHARMM unit cell record `[a, gamma, b, beta, alpha, c]`.
+    /// Angles are stored either in degrees or, by newer NAMD versions, as cosines.
+    fn cell(&mut self) -> Result<Cell> {
+        let record = self.record()?;
+        if record.len() != 48 {
+            return Err(invalid("invalid unit cell record"));
+        }
+        let values: Vec<f64> = record
+            .chunks_exact(8)
+            .map(|b| {
+                let b: [u8; 8] = b.try_into().unwrap();
+                if self.big_endian {
+                    f64::from_be_bytes(b)
+                } else {
+                    f64::from_le_bytes(b)
+                }
+            })
+            .collect();
+
+        let mut angles = [values[4], values[3], values[1]];
+        if angles.iter().all(|a| a.abs() <= 1.0) {
+            angles = angles.map(|a| a.acos().to_degrees());
+        }
+        Ok(Cell::from_lengths_angles([values[0], values[2], values[5]], angles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    fn record(bytes: &mut Vec<u8>, payload: &[u8]) {
+        bytes.extend((payload.len() as i32).to_le_bytes());
+        bytes.extend(payload);
+        bytes.extend((payload.len() as i32).to_le_bytes());
+    }
+
+    /// A little-endian DCD file of two atoms, with a cell and a frame count of `frames` in the header.
+    fn dcd(frames: i32, coordinates: &[[[f32; 2]; 3]]) -> Vec<u8> {
+        let mut header = b"CORD".to_vec();
+        let mut control = [0i32; 20];
+        control[0] = frames;
+        (control[1], control[2], control[10]) = (100, 10, 1);
+        header.extend(control.iter().flat_map(|v| v.to_le_bytes()));
+        let mut bytes = vec![];
+        record(&mut bytes, &header);
+        record(&mut bytes, &[0; 84]);
+        record(&mut bytes, &2i32.to_le_bytes());
+        for frame in coordinates {
+            let cell = [10.0f64, 90.0, 11.0, 90.0, 90.0, 12.0];
+            record(&mut bytes, &cell.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>());
+            for axis in frame {
+                record(&mut bytes, &axis.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>());
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn read_frames_and_cells() {
+        let path = temp_path("frames.dcd");
+        let frames = [[[0.0, 1.0], [0.0, 0.0], [0.0, 0.5]], [[0.0, 1.5], [0.0, 0.0], [0.0, 0.5]]];
+        fs::write(&path, dcd(2, &frames)).unwrap();
+        let file = read(&path, &["O", "H"]).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(file.data.len(), 2);
+        assert_eq!(file.data[1].comment.split_whitespace().last(), Some("step=110"));
+        assert_eq!(file.data[1].atoms[1].symbol, "H");
+        assert_eq!(file.data[1].atoms[1].position[0], 1.5);
+        let (lengths, angles) = file.data[0].cell.unwrap().lengths_angles();
+        assert_eq!(lengths.map(f64::round), [10.0, 11.0, 12.0]);
+        assert!(angles.iter().all(|angle| (angle - 90.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn wrong_symbol_count_and_truncated_file() {
+        let path = temp_path("truncated.dcd");
+        let bytes = dcd(1, &[[[0.0, 1.0], [0.0, 0.0], [0.0, 0.5]]]);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(read(&path, &["O"]).unwrap_err().kind(), ErrorKind::InvalidData);
+        fs::write(&path, &bytes[..bytes.len() - 6]).unwrap();
+        assert_eq!(read(&path, &["O", "H"]).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn header_frame_count_is_not_trusted() {
+        let path = temp_path("corrupt.dcd");
+        fs::write(&path, dcd(i32::MAX, &[[[0.0, 1.0], [0.0, 0.0], [0.0, 0.5]]])).unwrap();
+        let file = read(&path, &["O", "H"]);
+        fs::remove_file(&path).unwrap();
+        let file = file.unwrap();
+        assert_eq!(file.data.len(), 1);
+        assert_eq!(file.data[0].atoms[1].position.0, [1.0, 0.0, 0.5]);
+    }
+}
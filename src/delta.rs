@@ -0,0 +1,290 @@
+//! Delta encoding of trajectories, storing only the atoms that moved since the previous frame,
+//! with a compact binary representation for archiving trajectories of mostly static systems.
+//!
+//! Only the atomic symbols, positions, comments and cells of the frames are stored.
+
+use std::io::{self, Error, ErrorKind, Read, Write};
+
+use super::cell::Cell;
+use super::data::{AtomData, Data, File};
+use super::position::Position;
+
+const MAGIC: &[u8; 4] = b"XYZD";
+const VERSION: u32 = 1;
+
+/// A frame of a delta-encoded trajectory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delta {
+    /// A complete frame, used for the first frame and whenever the atoms change.
+    Keyframe {
+        comment: String,
+        cell: Option<Cell>,
+        atoms: Vec<(String, Position)>,
+    },
+    /// The changes from the previous frame: the new positions of the moved atoms, by index.
+    Changes {
+        comment: String,
+        cell: Option<Cell>,
+        moved: Vec<(usize, Position)>,
+    },
+}
+
+impl File {
+    /// Encodes the frames as deltas from their previous frame, keeping the atoms displaced by more than `tol`
+    /// Ångström. Positions are compared to the decoded previous frame, so the error of every decoded position
+    /// stays within `tol` instead of accumulating. A `tol` of zero encodes the trajectory losslessly.
+    pub fn diff_frames(&self, tol: f64) -> Vec<Delta> {
+        let mut deltas = Vec::with_capacity(self.data.len());
+        let mut previous: Option<Vec<(String, Position)>> = None;
+
+        for data in &self.data {
+            let atoms: Vec<(String, Position)> =
+                data.atoms.iter().map(|atom| (atom.symbol.clone(), atom.position)).collect();
+            let same_atoms = previous.as_ref().is_some_and(|previous| {
+                previous.len() == atoms.len() && previous.iter().zip(&atoms).all(|(a, b)| a.0 == b.0)
+            });
+
+            match previous.as_mut().filter(|_| same_atoms) {
+                Some(previous) => {
+                    let mut moved = vec![];
+                    for (i, (_, position)) in atoms.iter().enumerate() {
+                        if position.distance(previous[i].1) > tol || (tol == 0.0 && *position != previous[i].1) {
+                            moved.push((i, *position));
+                            previous[i].1 = *position;
+                        }
+                    }
+                    deltas.push(Delta::Changes { comment: data.comment.clone(), cell: data.cell, moved });
+                }
+                None => {
+                    deltas.push(Delta::Keyframe {
+                        comment: data.comment.clone(),
+                        cell: data.cell,
+                        atoms: atoms.clone(),
+                    });
+                    previous = Some(atoms);
+                }
+            }
+        }
+        deltas
+    }
+}
+
+/// Decodes delta-encoded frames, see [`File::diff_frames`].
+/// Fails with [`ErrorKind::InvalidData`] if the first delta is not a keyframe or an atom index is out of range.
+pub fn apply_deltas(deltas: &[Delta]) -> io::Result<File> {
+    let mut file = File { data: Vec::with_capacity(deltas.len()) };
+    for (frame, delta) in deltas.iter().enumerate() {
+        let data = match delta {
+            Delta::Keyframe { comment, cell, atoms } => Data {
+                count: atoms.len(),
+                comment: comment.clone(),
+                atoms: atoms.iter().map(|(symbol, position)| AtomData::new(symbol.as_str(), *position)).collect(),
+                cell: *cell,
+            },
+            Delta::Changes { comment, cell, moved } => {
+                let previous = file.data.last().ok_or_else(|| invalid(format!("frame {} has no keyframe", frame)))?;
+                let mut data = Data { comment: comment.clone(), cell: *cell, ..previous.clone() };
+                for &(i, position) in moved {
+                    let atom = data
+                        .atoms
+                        .get_mut(i)
+                        .ok_or_else(|| invalid(format!("atom index {} is out of range in frame {}", i, frame)))?;
+                    atom.position = position;
+                }
+                data
+            }
+        };
+        file.data.push(data);
+    }
+    Ok(file)
+}
+
+/// Writes delta-encoded frames in a compact little-endian binary format.
+pub fn write_deltas<W: Write>(writer: &mut W, deltas: &[Delta]) -> io::Result<()> {
+    let mut buf = MAGIC.to_vec();
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+    buf.extend_from_slice(&(deltas.len() as u64).to_le_bytes());
+
+    for delta in deltas {
+        let (kind, comment, cell) = match delta {
+            Delta::Keyframe { comment, cell, .. } => (0u8, comment, cell),
+            Delta::Changes { comment, cell, .. } => (1u8, comment, cell),
+        };
+        buf.push(kind);
+        put_string(&mut buf, comment);
+        match cell {
+            Some(cell) => {
+                buf.push(1);
+                cell.vectors.iter().flatten().for_each(|&v| buf.extend_from_slice(&v.to_le_bytes()));
+            }
+            None => buf.push(0),
+        }
+
+        match delta {
+            Delta::Keyframe { atoms, .. } => {
+                buf.extend_from_slice(&(atoms.len() as u32).to_le_bytes());
+                for (symbol, position) in atoms {
+                    put_string(&mut buf, symbol);
+                    position.0.iter().for_each(|&v| buf.extend_from_slice(&v.to_le_bytes()));
+                }
+            }
+            Delta::Changes { moved, .. } => {
+                buf.extend_from_slice(&(moved.len() as u32).to_le_bytes());
+                for (i, position) in moved {
+                    buf.extend_from_slice(&(*i as u32).to_le_bytes());
+                    position.0.iter().for_each(|&v| buf.extend_from_slice(&v.to_le_bytes()));
+                }
+            }
+        }
+    }
+    writer.write_all(&buf)
+}
+
+/// Reads delta-encoded frames written by [`write_deltas`].
+pub fn read_deltas<R: Read>(reader: &mut R) -> io::Result<Vec<Delta>> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a delta-encoded trajectory".to_string()));
+    }
+    let version = u32::from_le_bytes(take(reader)?);
+    if version != VERSION {
+        return Err(invalid(format!("unsupported version {}", version)));
+    }
+
+    // The frame count is only checked at the end, so a corrupt count cannot make the reader run past the data.
+    let frames = u64::from_le_bytes(take(reader)?);
+    let mut deltas = vec![];
+    loop {
+        let mut kind = [0];
+        if reader.read(&mut kind)? == 0 {
+            break;
+        }
+        let [kind] = kind;
+        let comment = get_string(reader)?;
+        let cell = match take(reader)? {
+            [0] => None,
+            _ => {
+                let mut vectors = [[0.0; 3]; 3];
+                for v in vectors.iter_mut().flatten() {
+                    *v = f64::from_le_bytes(take(reader)?);
+                }
+                Some(Cell { vectors })
+            }
+        };
+
+        let entries = u32::from_le_bytes(take(reader)?);
+        deltas.push(match kind {
+            0 => Delta::Keyframe {
+                comment,
+                cell,
+                atoms: (0..entries)
+                    .map(|_| Ok((get_string(reader)?, get_position(reader)?)))
+                    .collect::<io::Result<_>>()?,
+            },
+            1 => Delta::Changes {
+                comment,
+                cell,
+                moved: (0..entries)
+                    .map(|_| Ok((u32::from_le_bytes(take(reader)?) as usize, get_position(reader)?)))
+                    .collect::<io::Result<_>>()?,
+            },
+            _ => return Err(invalid(format!("unknown frame kind {}", kind))),
+        });
+    }
+    if deltas.len() as u64 != frames {
+        return Err(invalid(format!("expected {} frames, but found {}", frames, deltas.len())));
+    }
+    Ok(deltas)
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn take<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn get_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = u32::from_le_bytes(take(reader)?) as u64;
+    // Reading through `take` only allocates for the bytes actually present, whatever the stored length.
+    let mut bytes = vec![];
+    if reader.take(len).read_to_end(&mut bytes)? as u64 != len {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    String::from_utf8(bytes).map_err(|_| invalid("invalid UTF-8 in string".to_string()))
+}
+
+fn get_position<R: Read>(reader: &mut R) -> io::Result<Position> {
+    Ok(Position([
+        f64::from_le_bytes(take(reader)?),
+        f64::from_le_bytes(take(reader)?),
+        f64::from_le_bytes(take(reader)?),
+    ]))
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind};
+
+    use super::*;
+    use crate::testing::trajectory;
+
+    fn encoded() -> Vec<u8> {
+        let mut buf = vec![];
+        write_deltas(&mut buf, &trajectory(3).diff_frames(0.0)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn lossless_and_lossy_deltas() {
+        let deltas = trajectory(3).diff_frames(0.0);
+        assert!(matches!(&deltas[0], Delta::Keyframe { atoms, .. } if atoms.len() == 3));
+        assert!(matches!(&deltas[1], Delta::Changes { moved, .. } if moved.len() == 3));
+        let file = apply_deltas(&deltas).unwrap();
+        assert_eq!(file.data[2].comment, "step=2");
+        assert_eq!(file.data[2].atoms[0].position.0, trajectory(3).data[2].atoms[0].position.0);
+
+        let deltas = trajectory(3).diff_frames(0.15);
+        assert!(matches!(&deltas[1], Delta::Changes { moved, .. } if moved.is_empty()));
+        assert!(matches!(&deltas[2], Delta::Changes { moved, .. } if moved.len() == 3));
+        assert_eq!(apply_deltas(&deltas).unwrap().data[1].atoms[0].position.0, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let deltas = read_deltas(&mut Cursor::new(encoded())).unwrap();
+        assert_eq!(deltas, trajectory(3).diff_frames(0.0));
+        assert_eq!(read_deltas(&mut Cursor::new(b"XYZ?".to_vec())).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn deltas_without_keyframe() {
+        let deltas = &trajectory(2).diff_frames(0.0)[1..];
+        assert_eq!(apply_deltas(deltas).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn corrupt_string_length() {
+        let mut bytes = encoded();
+        // The length of the comment of the first frame, after the magic, version, frame count and frame kind.
+        bytes[17..21].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = read_deltas(&mut Cursor::new(bytes)).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::UnexpectedEof | ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn corrupt_frame_count() {
+        let mut bytes = encoded();
+        bytes[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(read_deltas(&mut Cursor::new(bytes)).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
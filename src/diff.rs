@@ -0,0 +1,330 @@
+use std::io::{self, Write};
+
+use super::data::{AtomData, Data, File};
+use super::error::ConsistencyError;
+use super::writer::{extended_comment, format_number};
+use super::AtomPosition;
+
+/// The differences between two frames, atom by atom.
+#[derive(Debug, Clone)]
+pub struct Diff {
+    /// Displacement vector of each atom present in both frames, from the first frame to the second.
+    pub displacements: Vec<AtomPosition>,
+    /// The largest displacement in Ångström.
+    pub max_displacement: f64,
+    /// The index of the atom with the largest displacement, if any atoms are compared.
+    pub max_displacement_atom: Option<usize>,
+    /// Indices of the atoms displaced by more than the tolerance.
+    pub moved: Vec<usize>,
+    /// Indices of the atoms whose atomic symbol differs between the frames.
+    pub changed_symbols: Vec<usize>,
+    /// The atom counts of the first and second frame.
+    pub atom_counts: (usize, usize),
+    /// Whether both frames contain the same atomic symbols, irrespective of order.
+    pub same_composition: bool,
+}
+impl Diff {
+    /// Whether the frames are identical within the tolerance.
+    pub fn is_empty(&self) -> bool {
+        self.moved.is_empty() && self.changed_symbols.is_empty() && self.atom_counts.0 == self.atom_counts.1
+    }
+
+    /// Whether the atoms of the frames only differ in order, i.e. the compositions match but some symbols differ.
+    pub fn is_reordered(&self) -> bool {
+        self.same_composition && !self.changed_symbols.is_empty()
+    }
+}
+
+/// The displacement of every atom between two frames, e.g. to visualize strain.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DisplacementField {
+    /// The displacement vector of each atom in Ångström, from the first frame to the second.
+    pub vectors: Vec<AtomPosition>,
+    /// The length of each displacement vector in Ångström.
+    pub magnitudes: Vec<f64>,
+}
+
+impl Data {
+    /// The displacement of every atom from this frame to `other`, which must have the same number of atoms.
+    /// With `periodic`, displacements follow the minimum image convention in the cell of `other`,
+    /// or of this frame if `other` has none, so atoms wrapped back into the cell do not jump.
+    pub fn displacement_field(&self, other: &Data, periodic: bool) -> Result<DisplacementField, ConsistencyError> {
+        if self.atoms.len() != other.atoms.len() {
+            return Err(ConsistencyError::AtomCount {
+                frame: 0,
+                expected: self.atoms.len(),
+                found: other.atoms.len(),
+            });
+        }
+        let cell = other.cell.or(self.cell).filter(|_| periodic);
+        let vectors: Vec<AtomPosition> = self
+            .atoms
+            .iter()
+            .zip(&other.atoms)
+            .map(|(a, b)| {
+                let d = b.position - a.position;
+                cell.map_or(d, |cell| cell.cartesian(cell.fractional(d).map(|f| f - f.round())))
+            })
+            .collect();
+        let magnitudes = vectors.iter().map(AtomPosition::norm).collect();
+        Ok(DisplacementField { vectors, magnitudes })
+    }
+
+    /// Compares this frame to another, atom by atom.
+    /// Atoms displaced by more than `tol` Ångström are reported as moved.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(atoms = self.atoms.len())))]
+    pub fn diff(&self, other: &Data, tol: f64) -> Diff {
+        let mut diff = Diff {
+            displacements: Vec::with_capacity(self.atoms.len().min(other.atoms.len())),
+            max_displacement: 0.0,
+            max_displacement_atom: None,
+            moved: vec![],
+            changed_symbols: vec![],
+            atom_counts: (self.atoms.len(), other.atoms.len()),
+            same_composition: composition(self) == composition(other),
+        };
+
+        for (i, (a, b)) in self.atoms.iter().zip(&other.atoms).enumerate() {
+            let displacement = b.position - a.position;
+            let distance = displacement.norm();
+
+            if diff.max_displacement_atom.is_none() || distance > diff.max_displacement {
+                diff.max_displacement = distance;
+                diff.max_displacement_atom = Some(i);
+            }
+            if distance > tol {
+                diff.moved.push(i);
+            }
+            if a.symbol != b.symbol {
+                diff.changed_symbols.push(i);
+            }
+            diff.displacements.push(displacement);
+        }
+
+        diff
+    }
+
+    /// Whether this periodic frame and `other` are the same crystal up to a shift of the origin and the order
+    /// of the atoms, e.g. duplicate frames from different databases.
+    /// The lattice vectors must agree within `tol` Ångström, and every atom, shifted by the translation that maps
+    /// one atom onto an atom of the same symbol in `other`, must lie within `tol` Ångström of a distinct atom
+    /// with its symbol, modulo lattice translations. Frames without a cell are never equivalent.
+    pub fn equivalent_under_translation(&self, other: &Data, tol: f64) -> bool {
+        let (Some(cell), Some(other_cell)) = (self.cell, other.cell) else {
+            return false;
+        };
+        if self.atoms.len() != other.atoms.len() || composition(self) != composition(other) {
+            return false;
+        }
+        if (0..3).any(|i| AtomPosition::from(cell.vectors[i]).distance(other_cell.vectors[i].into()) > tol) {
+            return false;
+        }
+
+        // Anchoring on the rarest symbol keeps the number of trial translations small.
+        let count = |symbol: &str| self.atoms.iter().filter(|atom| atom.symbol == symbol).count();
+        let Some(anchor) = self.atoms.iter().min_by_key(|atom| count(&atom.symbol)) else {
+            return true;
+        };
+        let distance = |a: AtomPosition, b: AtomPosition| {
+            let fractional = other_cell.fractional(b - a).map(|f| f - f.round());
+            other_cell.cartesian(fractional).norm()
+        };
+
+        other.atoms.iter().filter(|atom| atom.symbol == anchor.symbol).any(|image| {
+            let shift = image.position - anchor.position;
+            let mut used = vec![false; other.atoms.len()];
+            self.atoms.iter().all(|atom| {
+                let target = atom.position + shift;
+                let found = (0..other.atoms.len()).find(|&j| {
+                    !used[j] && other.atoms[j].symbol == atom.symbol && distance(target, other.atoms[j].position) <= tol
+                });
+                found.map(|j| used[j] = true).is_some()
+            })
+        })
+    }
+
+    /// Reorders the atoms to match the order of the atoms of `reference`, which must have the same composition,
+    /// e.g. to compare the output of a program that reorders atoms with its input index by index.
+    /// Atoms are matched to the nearest unmatched reference atom of the same element, closest pairs first,
+    /// so the frames must be in the same orientation.
+    /// Returns the permutation applied: the atom at index `i` afterwards was at index `permutation[i]` before.
+    pub fn match_ordering(&mut self, reference: &Data) -> Result<Vec<usize>, ConsistencyError> {
+        if self.atoms.len() != reference.atoms.len() {
+            return Err(ConsistencyError::AtomCount {
+                frame: 0,
+                expected: reference.atoms.len(),
+                found: self.atoms.len(),
+            });
+        }
+        let element = |atom: &AtomData| atom.element().map_or_else(|| atom.symbol.clone(), |e| e.symbol().to_string());
+        let elements: Vec<String> = self.atoms.iter().map(element).collect();
+
+        let mut pairs = vec![];
+        for (i, r) in reference.atoms.iter().enumerate() {
+            let symbol = element(r);
+            for j in (0..self.atoms.len()).filter(|&j| elements[j] == symbol) {
+                pairs.push((r.position.distance(self.atoms[j].position), i, j));
+            }
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut permutation = vec![None; reference.atoms.len()];
+        let mut used = vec![false; self.atoms.len()];
+        for (_, i, j) in pairs {
+            if permutation[i].is_none() && !used[j] {
+                permutation[i] = Some(j);
+                used[j] = true;
+            }
+        }
+        let permutation = permutation
+            .into_iter()
+            .enumerate()
+            .map(|(atom, j)| {
+                j.ok_or_else(|| ConsistencyError::Composition {
+                    frame: 0,
+                    atom,
+                    symbol: reference.atoms[atom].symbol.clone(),
+                })
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+
+        self.atoms = permutation.iter().map(|&j| self.atoms[j].clone()).collect();
+        Ok(permutation)
+    }
+}
+
+/// Writes the frames as extended XYZ with the displacement of every atom from `reference` (see
+/// [`Data::displacement_field`]) in the extra columns `displacement:R:3` and `displacement_magnitude:R:1`,
+/// e.g. to color atoms by displacement in OVITO. The `key=value` properties of the comments are kept.
+/// Fails if a frame does not have as many atoms as `reference`.
+pub fn write_displacements<W: Write>(writer: &mut W, file: &File, reference: &Data, periodic: bool) -> io::Result<()> {
+    for (i, data) in file.data.iter().enumerate() {
+        let field = reference.displacement_field(data, periodic).map_err(|_| {
+            let error = ConsistencyError::AtomCount {
+                frame: i,
+                expected: reference.atoms.len(),
+                found: data.atoms.len(),
+            };
+            io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+        })?;
+
+        let comment = extended_comment(data, "species:S:1:pos:R:3:displacement:R:3:displacement_magnitude:R:1");
+
+        let mut buf = format!("{}\n{}\n", data.atoms.len(), comment);
+        for ((atom, vector), magnitude) in data.atoms.iter().zip(&field.vectors).zip(&field.magnitudes) {
+            buf.push_str(&atom.symbol);
+            for v in atom.position.0.into_iter().chain(vector.0).chain([*magnitude]) {
+                buf.push(' ');
+                format_number(&mut buf, v, None);
+            }
+            buf.push('\n');
+        }
+        writer.write_all(buf.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn composition(data: &Data) -> Vec<&str> {
+    let mut symbols: Vec<&str> = data.atoms.iter().map(|atom| atom.symbol.as_str()).collect();
+    symbols.sort_unstable();
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Cell;
+    use crate::error::ConsistencyError;
+    use crate::testing::trajectory;
+    use crate::testing::{frame, water};
+
+    #[test]
+    fn identical_frames() {
+        let diff = water().diff(&water(), 1e-6);
+        assert!(diff.is_empty());
+        assert_eq!(diff.max_displacement, 0.0);
+        assert_eq!(diff.atom_counts, (3, 3));
+    }
+
+    #[test]
+    fn moved_and_reordered_atoms() {
+        let mut moved = water();
+        moved.atoms[2].position[1] += 0.5;
+        let diff = water().diff(&moved, 0.1);
+        assert_eq!(diff.moved, [2]);
+        assert_eq!(diff.max_displacement_atom, Some(2));
+        assert!((diff.max_displacement - 0.5).abs() < 1e-12);
+        assert!((diff.displacements[2][1] - 0.5).abs() < 1e-12);
+        assert!(!diff.is_reordered());
+
+        let reordered = frame("", &[("H", [0.96, 0.0, 0.0]), ("O", [0.0, 0.0, 0.0]), ("H", [-0.24, 0.93, 0.0])]);
+        let diff = water().diff(&reordered, 0.1);
+        assert_eq!(diff.changed_symbols, [0, 1]);
+        assert!(diff.is_reordered());
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn equivalent_under_translation() {
+        let cell = Some(Cell::new([4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]));
+        let mut crystal = frame("", &[("Na", [0.0, 0.0, 0.0]), ("Cl", [2.0, 0.0, 0.0]), ("Cl", [0.0, 2.0, 0.0])]);
+        crystal.set_cell(cell);
+        let mut shifted = frame("", &[("Cl", [3.5, 1.0, 1.0]), ("Na", [1.5, 1.0, 1.0]), ("Cl", [1.5, 3.0, 1.0])]);
+        shifted.set_cell(cell);
+        assert!(crystal.equivalent_under_translation(&shifted, 1e-6));
+
+        shifted.atoms[2].position[2] += 0.1;
+        assert!(!crystal.equivalent_under_translation(&shifted, 1e-6));
+        assert!(crystal.equivalent_under_translation(&shifted, 0.2));
+        assert!(!water().equivalent_under_translation(&water(), 1e-6));
+    }
+
+    #[test]
+    fn match_ordering_to_reference() {
+        let mut reordered = frame("", &[("H", [-0.2, 0.9, 0.0]), ("H", [1.0, 0.0, 0.0]), ("O", [0.0, 0.0, 0.1])]);
+        assert_eq!(reordered.match_ordering(&water()).unwrap(), [2, 1, 0]);
+        assert!(!water().diff(&reordered, 0.2).is_reordered());
+
+        let mut fluoride = frame("", &[("H", [0.0; 3]), ("F", [1.0, 0.0, 0.0]), ("H", [2.0, 0.0, 0.0])]);
+        let error = fluoride.match_ordering(&water()).unwrap_err();
+        assert!(matches!(error, ConsistencyError::Composition { atom: 0, .. }));
+        assert!(matches!(frame("", &[]).match_ordering(&water()), Err(ConsistencyError::AtomCount { .. })));
+    }
+
+    #[test]
+    fn displacement_fields() {
+        let file = trajectory(3);
+        let field = file.data[0].displacement_field(&file.data[2], false).unwrap();
+        assert!(field.vectors.iter().all(|v| (v.0[0] - 0.2).abs() < 1e-12 && v.0[1] == 0.0));
+        assert!(field.magnitudes.iter().all(|m| (m - 0.2).abs() < 1e-12));
+
+        let mut wrapped = frame("", &[("He", [9.5, 0.0, 0.0])]);
+        wrapped.cell = Some(Cell::new([10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]));
+        let start = frame("", &[("He", [0.5, 0.0, 0.0])]);
+        assert!((start.displacement_field(&wrapped, true).unwrap().magnitudes[0] - 1.0).abs() < 1e-12);
+        assert_eq!(start.displacement_field(&wrapped, false).unwrap().magnitudes[0], 9.0);
+        assert!(matches!(
+            start.displacement_field(&water(), false),
+            Err(ConsistencyError::AtomCount { expected: 1, found: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn write_displacement_columns() {
+        let mut file = trajectory(2);
+        file.data[1].comment = "step=1 note=\"two words\"".to_string();
+        let mut buf = vec![];
+        write_displacements(&mut buf, &file, &file.data[0], false).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        let properties = "Properties=species:S:1:pos:R:3:displacement:R:3:displacement_magnitude:R:1";
+        assert_eq!(lines[1], format!("{} step=0 pbc=\"F F F\"", properties));
+        assert_eq!(lines[2], "O 0 0 0 0 0 0 0");
+        assert_eq!(lines[6], format!("{} step=1 note=\"two words\" pbc=\"F F F\"", properties));
+        assert_eq!(lines[7], "O 0.1 0 0 0.1 0 0 0.1");
+
+        let error = write_displacements(&mut vec![], &file, &frame("", &[]), false).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Expected 0 atoms, but found 3 in frame 0");
+    }
+}
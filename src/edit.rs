@@ -0,0 +1,427 @@
+//! Sets bond lengths, angles and dihedrals by moving one side of the bond graph, e.g. for building scans,
+//! and creates point defects.
+
+use std::collections::VecDeque;
+
+use super::analysis::minimum_image_distance;
+use super::data::{AtomData, Data};
+use super::element::Element;
+use super::error::EditError;
+use super::position::Position;
+
+/// Atoms closer than this fraction of the sum of their covalent radii clash, see [`interstitial`].
+pub const CLASH_FACTOR: f64 = 0.7;
+
+/// The atoms moved when setting an internal coordinate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveGroup {
+    /// The last atom together with everything bonded to it, on its side of the central bond.
+    #[default]
+    Fragment,
+    /// Only the last atom.
+    Atom,
+    /// The fragments on both sides, each moved half of the way.
+    Both,
+}
+
+/// Replaces atom `atom` of `data`, typically a hydrogen, with `fragment`, bonded through its atom `attach`.
+/// The fragment is placed along the bond from the nearest bonded neighbour of the replaced atom, at the sum of
+/// the covalent radii, and rotated so the direction from `attach` to the centroid of its other atoms points
+/// away from that neighbour. The fragment atoms are appended after the remaining atoms of `data`.
+pub fn substitute(data: &Data, atom: usize, fragment: &Data, attach: usize) -> Result<Data, EditError> {
+    data.check_indices(&[atom])?;
+    if attach >= fragment.atoms.len() {
+        return Err(EditError::InvalidIndex(attach));
+    }
+
+    let position = data.atoms[atom].position;
+    let parent = data
+        .bonds()
+        .into_iter()
+        .filter_map(|(a, b)| match (a == atom, b == atom) {
+            (true, _) => Some(b),
+            (_, true) => Some(a),
+            _ => None,
+        })
+        .min_by(|&a, &b| {
+            let distance = |n: usize| position.distance(data.atoms[n].position);
+            distance(a).total_cmp(&distance(b))
+        })
+        .ok_or(EditError::Unbonded(atom))?;
+    let anchor = data.atoms[parent].position;
+    let direction = (position - anchor).normalized().ok_or(EditError::Degenerate)?;
+
+    let radius = |atom: &super::data::AtomData| atom.element().and_then(|e| e.covalent_radius());
+    let length = match (radius(&data.atoms[parent]), radius(&fragment.atoms[attach])) {
+        (Some(a), Some(b)) => a + b,
+        _ => anchor.distance(position),
+    };
+
+    let origin = fragment.atoms[attach].position;
+    let others: Vec<Position> = (0..fragment.atoms.len())
+        .filter(|&i| i != attach)
+        .map(|i| fragment.atoms[i].position - origin)
+        .collect();
+    let inward = (others.iter().copied().sum::<Position>() / others.len().max(1) as f64).normalized();
+    let rotate = |v: Position| match inward {
+        Some(inward) => rotate_onto(v, inward, direction),
+        None => v,
+    };
+
+    let placed = anchor + direction * length;
+    let mut result = data.clone();
+    result.atoms.remove(atom);
+    for fragment_atom in &fragment.atoms {
+        let mut new = fragment_atom.clone();
+        new.position = placed + rotate(new.position - origin);
+        result.atoms.push(new);
+    }
+    result.count = result.atoms.len();
+    Ok(result)
+}
+
+/// Removes atom `index` of `data`, creating a vacancy.
+pub fn vacancy(data: &Data, index: usize) -> Result<Data, EditError> {
+    data.check_indices(&[index])?;
+    let mut result = data.clone();
+    result.atoms.remove(index);
+    result.count = result.atoms.len();
+    Ok(result)
+}
+
+/// Replaces atom `index` of `data` by an atom of `element` at the same position, creating a substitutional defect.
+/// Fails if the new atom clashes with another atom, see [`interstitial`].
+pub fn substitute_element(data: &Data, index: usize, element: Element) -> Result<Data, EditError> {
+    data.check_indices(&[index])?;
+    let position = data.atoms[index].position;
+    data.check_clash(position, element, Some(index))?;
+    let mut result = data.clone();
+    let atom = &mut result.atoms[index];
+    atom.symbol = element.symbol().to_string();
+    atom.resolved_element = None;
+    Ok(result)
+}
+
+/// Adds an atom of `element` at `position` to `data`, creating an interstitial defect.
+/// Fails if the new atom is closer to another atom than [`CLASH_FACTOR`] times the sum of their covalent radii,
+/// under the minimum image convention if the frame has a cell. Atoms without a covalent radius never clash.
+pub fn interstitial(data: &Data, position: Position, element: Element) -> Result<Data, EditError> {
+    data.check_clash(position, element, None)?;
+    let mut result = data.clone();
+    result.atoms.push(AtomData::new(element.symbol(), position));
+    result.count = result.atoms.len();
+    Ok(result)
+}
+
+/// Rotates `v` by the rotation that turns unit vector `from` onto unit vector `to`.
+pub(crate) fn rotate_onto(v: Position, from: Position, to: Position) -> Position {
+    let cos = from.dot(to).clamp(-1.0, 1.0);
+    let axis = match from.cross(to).normalized() {
+        Some(axis) => axis,
+        None if cos > 0.0 => return v,
+        // Antiparallel: turn half way around any axis perpendicular to `from`.
+        None => {
+            let helper = if from[0].abs() < 0.9 { Position::new(1.0, 0.0, 0.0) } else { Position::new(0.0, 1.0, 0.0) };
+            from.cross(helper).normalized().unwrap()
+        }
+    };
+    let sin = (1.0 - cos * cos).sqrt();
+    v * cos + axis.cross(v) * sin + axis * (axis.dot(v) * (1.0 - cos))
+}
+
+impl Data {
+    /// Sets the distance between atoms `i` and `j` to `length` Ångström by moving along the bond.
+    pub fn set_bond_length(&mut self, i: usize, j: usize, length: f64, group: MoveGroup) -> Result<(), EditError> {
+        self.check_indices(&[i, j])?;
+        let (pi, pj) = (self.atoms[i].position, self.atoms[j].position);
+        let direction = (pj - pi).normalized().ok_or(EditError::Degenerate)?;
+        let shift = direction * (length - pi.distance(pj));
+
+        let [first, second] = self.sides(i, j, group)?;
+        self.translate(&first, -shift / 2.0);
+        let factor = if group == MoveGroup::Both { 0.5 } else { 1.0 };
+        self.translate(&second, shift * factor);
+        Ok(())
+    }
+
+    /// Sets the angle `i-j-k` to `degrees` by rotating about the normal of the plane of the atoms, through `j`.
+    pub fn set_angle(&mut self, i: usize, j: usize, k: usize, degrees: f64, group: MoveGroup) -> Result<(), EditError> {
+        self.check_indices(&[i, j, k])?;
+        let (pi, pj, pk) = (self.atoms[i].position, self.atoms[j].position, self.atoms[k].position);
+        let (u, v) = (pi - pj, pk - pj);
+        let axis = u.cross(v).normalized().ok_or(EditError::Degenerate)?;
+        let current = u.dot(v) / (u.norm() * v.norm());
+        let change = (degrees - current.clamp(-1.0, 1.0).acos().to_degrees()).to_radians();
+
+        let [first, second] = match group {
+            MoveGroup::Atom => [vec![], vec![k]],
+            MoveGroup::Fragment => [vec![], self.side(j, k, &[i, j], group)?],
+            MoveGroup::Both => [self.side(j, i, &[j, k], group)?, self.side(j, k, &[i, j], group)?],
+        };
+        let factor = if group == MoveGroup::Both { 0.5 } else { 1.0 };
+        self.rotate(&first, pj, axis, -change / 2.0);
+        self.rotate(&second, pj, axis, change * factor);
+        Ok(())
+    }
+
+    /// Sets the dihedral angle `i-j-k-l` to `degrees` by rotating about the `j-k` bond.
+    pub fn set_dihedral(
+        &mut self,
+        i: usize,
+        j: usize,
+        k: usize,
+        l: usize,
+        degrees: f64,
+        group: MoveGroup,
+    ) -> Result<(), EditError> {
+        self.check_indices(&[i, j, k, l])?;
+        let current = self.dihedral(i, j, k, l).ok_or(EditError::Degenerate)?;
+        let (pj, pk) = (self.atoms[j].position, self.atoms[k].position);
+        let axis = (pk - pj).normalized().ok_or(EditError::Degenerate)?;
+        let change = (degrees - current).to_radians();
+
+        let [first, second] = match group {
+            MoveGroup::Atom => [vec![], vec![l]],
+            MoveGroup::Fragment => [vec![], self.side(j, k, &[j], group)?],
+            MoveGroup::Both => [self.side(k, j, &[k], group)?, self.side(j, k, &[j], group)?],
+        };
+        let factor = if group == MoveGroup::Both { 0.5 } else { 1.0 };
+        self.rotate(&first, pj, axis, -change / 2.0);
+        self.rotate(&second, pj, axis, change * factor);
+        Ok(())
+    }
+
+    /// The distance between atoms `i` and `j` in Ångström, or `None` if an index is out of range.
+    pub fn distance(&self, i: usize, j: usize) -> Option<f64> {
+        Some(self.atoms.get(i)?.position.distance(self.atoms.get(j)?.position))
+    }
+
+    /// The angle `i-j-k` in degrees, from 0° to 180°,
+    /// or `None` if an index is out of range or `i` or `k` coincides with `j`.
+    pub fn angle(&self, i: usize, j: usize, k: usize) -> Option<f64> {
+        let position = |n: usize| self.atoms.get(n).map(|atom| atom.position);
+        let (u, v) = (position(i)? - position(j)?, position(k)? - position(j)?);
+        if u.norm() == 0.0 || v.norm() == 0.0 {
+            return None;
+        }
+        Some((u.dot(v) / (u.norm() * v.norm())).clamp(-1.0, 1.0).acos().to_degrees())
+    }
+
+    /// The dihedral angle `i-j-k-l` in degrees, from -180° to 180°,
+    /// or `None` if an index is out of range or three consecutive atoms are collinear.
+    pub fn dihedral(&self, i: usize, j: usize, k: usize, l: usize) -> Option<f64> {
+        let position = |n: usize| self.atoms.get(n).map(|atom| atom.position);
+        let (b1, b2, b3) = (position(j)? - position(i)?, position(k)? - position(j)?, position(l)? - position(k)?);
+        let (n1, n2) = (b1.cross(b2), b2.cross(b3));
+        if n1.norm() == 0.0 || n2.norm() == 0.0 {
+            return None;
+        }
+        Some((b2.norm() * b1.dot(n2)).atan2(n1.dot(n2)).to_degrees())
+    }
+
+    /// Fails if an atom of `element` at `position` would clash with an atom other than `skip`.
+    fn check_clash(&self, position: Position, element: Element, skip: Option<usize>) -> Result<(), EditError> {
+        let Some(radius) = element.covalent_radius() else {
+            return Ok(());
+        };
+        for (i, atom) in self.atoms.iter().enumerate().filter(|&(i, _)| Some(i) != skip) {
+            let Some(other) = atom.element().and_then(|e| e.covalent_radius()) else {
+                continue;
+            };
+            let distance = match &self.cell {
+                Some(cell) => minimum_image_distance(cell, position, atom.position),
+                None => position.distance(atom.position),
+            };
+            if distance < CLASH_FACTOR * (radius + other) {
+                return Err(EditError::Clash { atom: i, distance });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_indices(&self, indices: &[usize]) -> Result<(), EditError> {
+        match indices.iter().find(|&&i| i >= self.atoms.len()) {
+            Some(&i) => Err(EditError::InvalidIndex(i)),
+            None => Ok(()),
+        }
+    }
+
+    /// The atoms moved on the side of `i` and of `j` when changing the bond `i-j`.
+    fn sides(&self, i: usize, j: usize, group: MoveGroup) -> Result<[Vec<usize>; 2], EditError> {
+        Ok(match group {
+            MoveGroup::Atom => [vec![], vec![j]],
+            MoveGroup::Fragment => [vec![], self.side(i, j, &[i], group)?],
+            MoveGroup::Both => [self.side(j, i, &[j], group)?, self.side(i, j, &[i], group)?],
+        })
+    }
+
+    /// The atoms connected to `start` in the bond graph without crossing the bond from `pivot` to `start`,
+    /// or an error if any of the `fixed` atoms is among them.
+    fn side(&self, pivot: usize, start: usize, fixed: &[usize], group: MoveGroup) -> Result<Vec<usize>, EditError> {
+        if group == MoveGroup::Atom {
+            return Ok(vec![start]);
+        }
+        let mut neighbours = vec![vec![]; self.atoms.len()];
+        for (a, b) in self.bonds() {
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+        }
+
+        let mut visited = vec![false; self.atoms.len()];
+        visited[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(a) = queue.pop_front() {
+            for &b in &neighbours[a] {
+                if (a == start && b == pivot) || visited[b] {
+                    continue;
+                }
+                if fixed.contains(&b) {
+                    return Err(EditError::Ring(pivot, start));
+                }
+                visited[b] = true;
+                queue.push_back(b);
+            }
+        }
+        Ok((0..self.atoms.len()).filter(|&a| visited[a]).collect())
+    }
+
+    fn translate(&mut self, atoms: &[usize], shift: Position) {
+        for &a in atoms {
+            self.atoms[a].position += shift;
+        }
+    }
+
+    /// Rotates atoms by `angle` radians about an axis through `origin`, using Rodrigues' formula.
+    fn rotate(&mut self, atoms: &[usize], origin: Position, axis: Position, angle: f64) {
+        let (sin, cos) = angle.sin_cos();
+        for &a in atoms {
+            let v = self.atoms[a].position - origin;
+            let rotated = v * cos + axis.cross(v) * sin + axis * (axis.dot(v) * (1.0 - cos));
+            self.atoms[a].position = origin + rotated;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Cell;
+    use crate::testing::frame;
+    use crate::testing::water;
+
+    /// Hydrogen peroxide with the hydrogen atoms on the same side, at a dihedral angle of 0°.
+    fn peroxide() -> Data {
+        frame("", &[("O", [0.0; 3]), ("O", [1.45, 0.0, 0.0]), ("H", [-0.3, 0.92, 0.0]), ("H", [1.75, 0.92, 0.0])])
+    }
+
+    fn distance(data: &Data, i: usize, j: usize) -> f64 {
+        data.atoms[i].position.distance(data.atoms[j].position)
+    }
+
+    #[test]
+    fn set_bond_length_moves_fragment() {
+        let mut data = peroxide();
+        data.set_bond_length(0, 1, 1.5, MoveGroup::Fragment).unwrap();
+        assert!((distance(&data, 0, 1) - 1.5).abs() < 1e-12);
+        assert_eq!(data.atoms[0].position, peroxide().atoms[0].position);
+        assert!((distance(&data, 1, 3) - distance(&peroxide(), 1, 3)).abs() < 1e-12);
+
+        let mut data = peroxide();
+        data.set_bond_length(0, 1, 1.55, MoveGroup::Both).unwrap();
+        assert!((distance(&data, 0, 1) - 1.55).abs() < 1e-12);
+        assert!((data.atoms[0].position[0] + 0.05).abs() < 1e-12);
+        let mut data = peroxide();
+        data.set_bond_length(1, 3, 1.0, MoveGroup::Atom).unwrap();
+        assert!((distance(&data, 1, 3) - 1.0).abs() < 1e-12);
+        assert_eq!(data.atoms[2].position, peroxide().atoms[2].position);
+    }
+
+    #[test]
+    fn set_angle_and_dihedral() {
+        let mut data = peroxide();
+        assert_eq!(data.dihedral(2, 0, 1, 3), Some(0.0));
+        data.set_dihedral(2, 0, 1, 3, 120.0, MoveGroup::Fragment).unwrap();
+        assert!((data.dihedral(2, 0, 1, 3).unwrap() - 120.0).abs() < 1e-9);
+        assert!((distance(&data, 1, 3) - distance(&peroxide(), 1, 3)).abs() < 1e-12);
+        data.set_dihedral(2, 0, 1, 3, -90.0, MoveGroup::Both).unwrap();
+        assert!((data.dihedral(2, 0, 1, 3).unwrap() + 90.0).abs() < 1e-9);
+
+        let mut data = peroxide();
+        data.set_angle(0, 1, 3, 90.0, MoveGroup::Fragment).unwrap();
+        let (u, v) = (data.atoms[0].position - data.atoms[1].position, data.atoms[3].position - data.atoms[1].position);
+        assert!(u.dot(v).abs() < 1e-12);
+        assert_eq!(data.atoms[2].position, peroxide().atoms[2].position);
+    }
+
+    #[test]
+    fn edit_errors() {
+        let mut data = peroxide();
+        assert!(matches!(data.set_bond_length(0, 4, 1.0, MoveGroup::Fragment), Err(EditError::InvalidIndex(4))));
+        assert!(matches!(data.set_angle(0, 1, 0, 90.0, MoveGroup::Fragment), Err(EditError::Degenerate)));
+        assert_eq!(data.dihedral(0, 1, 0, 1), None);
+
+        let mut ring = frame("", &[("C", [0.0; 3]), ("C", [1.5, 0.0, 0.0]), ("C", [0.75, 1.3, 0.0])]);
+        assert!(matches!(ring.set_bond_length(0, 1, 1.6, MoveGroup::Fragment), Err(EditError::Ring(0, 1))));
+        ring.set_bond_length(0, 1, 1.6, MoveGroup::Atom).unwrap();
+    }
+
+    #[test]
+    fn substitute_hydrogen_with_hydroxyl() {
+        let water = crate::testing::water();
+        let hydroxyl = frame("", &[("H", [0.0, 0.0, 0.97]), ("O", [0.0; 3])]);
+        let peroxide = substitute(&water, 1, &hydroxyl, 1).unwrap();
+        assert_eq!(peroxide.count, 4);
+        let symbols: Vec<&str> = peroxide.atoms.iter().map(|atom| atom.symbol.as_str()).collect();
+        assert_eq!(symbols, ["O", "H", "H", "O"]);
+        // The oxygen atoms are at the sum of their covalent radii, with the hydrogen atom pointing away.
+        assert!(peroxide.atoms[3].position.distance(Position::new(1.32, 0.0, 0.0)) < 1e-12);
+        assert!(peroxide.atoms[2].position.distance(Position::new(2.29, 0.0, 0.0)) < 1e-12);
+
+        assert!(matches!(substitute(&water, 1, &hydroxyl, 2), Err(EditError::InvalidIndex(2))));
+        assert!(matches!(substitute(&water, 3, &hydroxyl, 1), Err(EditError::InvalidIndex(3))));
+        let lone = frame("", &[("H", [0.0; 3])]);
+        assert!(matches!(substitute(&lone, 0, &hydroxyl, 1), Err(EditError::Unbonded(0))));
+    }
+
+    #[test]
+    fn rotate_onto_parallel_and_antiparallel() {
+        let (x, y) = (Position::new(1.0, 0.0, 0.0), Position::new(0.0, 1.0, 0.0));
+        assert!(rotate_onto(x, x, y).distance(y) < 1e-12);
+        assert_eq!(rotate_onto(y, x, x), y);
+        assert!(rotate_onto(x, x, -x).distance(-x) < 1e-12);
+    }
+
+    #[test]
+    fn distances_and_angles() {
+        let data = peroxide();
+        assert_eq!(data.distance(0, 1), Some(1.45));
+        assert_eq!(data.distance(0, 4), None);
+        let water = crate::testing::frame("", &[("O", [0.0; 3]), ("H", [1.0, 0.0, 0.0]), ("H", [0.0, 2.0, 0.0])]);
+        assert!((water.angle(1, 0, 2).unwrap() - 90.0).abs() < 1e-12);
+        assert_eq!(water.angle(0, 0, 2), None);
+        assert!(data.dihedral(2, 0, 1, 3).unwrap().abs() < 1e-9);
+    }
+
+    #[test]
+    fn point_defects() {
+        let element = |symbol| Element::from_symbol(symbol).unwrap();
+        let mut crystal = frame("", &[("Si", [0.0; 3]), ("Si", [1.3575, 1.3575, 1.3575])]);
+        crystal.cell = Some(Cell::new([5.43, 0.0, 0.0], [0.0, 5.43, 0.0], [0.0, 0.0, 5.43]));
+
+        let vacant = vacancy(&crystal, 0).unwrap();
+        assert_eq!((vacant.count, vacant.atoms[0].position), (1, crystal.atoms[1].position));
+        assert!(matches!(vacancy(&crystal, 2), Err(EditError::InvalidIndex(2))));
+
+        let doped = substitute_element(&crystal, 1, element("P")).unwrap();
+        assert_eq!(doped.atoms[1].symbol, "P");
+        assert_eq!(doped.formula(), "PSi");
+
+        let filled = interstitial(&crystal, Position::new(2.715, 2.715, 2.715), element("Si")).unwrap();
+        assert_eq!(filled.count, 3);
+        // Across the cell boundary, the atom at the corner is 0.1 Å away.
+        let error = interstitial(&crystal, Position::new(5.33, 0.0, 0.0), element("Si")).unwrap_err();
+        assert!(matches!(error, EditError::Clash { atom: 0, .. }));
+        assert_eq!(error.to_string(), "The new atom clashes with atom 0, 0.100 Å away");
+        assert!(interstitial(&water(), Position::new(0.0, 0.0, 0.1), element("Bk")).is_ok());
+    }
+}
@@ -0,0 +1,283 @@
+/// A chemical element, identified by its atomic number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Element(u8);
+
+/// Element symbols, indexed by atomic number minus one.
+const SYMBOLS: [&str; 118] = [
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+    "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+    "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk",
+    "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh",
+    "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// Element names in lowercase, indexed by atomic number minus one.
+const NAMES: [&str; 118] = [
+    "hydrogen", "helium", "lithium", "beryllium", "boron", "carbon", "nitrogen", "oxygen", "fluorine",
+    "neon", "sodium", "magnesium", "aluminium", "silicon", "phosphorus", "sulfur", "chlorine", "argon",
+    "potassium", "calcium", "scandium", "titanium", "vanadium", "chromium", "manganese", "iron", "cobalt",
+    "nickel", "copper", "zinc", "gallium", "germanium", "arsenic", "selenium", "bromine", "krypton",
+    "rubidium", "strontium", "yttrium", "zirconium", "niobium", "molybdenum", "technetium", "ruthenium",
+    "rhodium", "palladium", "silver", "cadmium", "indium", "tin", "antimony", "tellurium", "iodine", "xenon",
+    "caesium", "barium", "lanthanum", "cerium", "praseodymium", "neodymium", "promethium", "samarium",
+    "europium", "gadolinium", "terbium", "dysprosium", "holmium", "erbium", "thulium", "ytterbium",
+    "lutetium", "hafnium", "tantalum", "tungsten", "rhenium", "osmium", "iridium", "platinum", "gold",
+    "mercury", "thallium", "lead", "bismuth", "polonium", "astatine", "radon", "francium", "radium",
+    "actinium", "thorium", "protactinium", "uranium", "neptunium", "plutonium", "americium", "curium",
+    "berkelium", "californium", "einsteinium", "fermium", "mendelevium", "nobelium", "lawrencium",
+    "rutherfordium", "dubnium", "seaborgium", "bohrium", "hassium", "meitnerium", "darmstadtium",
+    "roentgenium", "copernicium", "nihonium", "flerovium", "moscovium", "livermorium", "tennessine",
+    "oganesson",
+];
+
+/// Standard atomic weights in unified atomic mass units (Da), indexed by atomic number minus one.
+/// For elements without a stable isotope the mass number of the longest-lived isotope is used.
+const MASSES: [f64; 118] = [
+    1.008, 4.002602, 6.94, 9.0121831, 10.81, 12.011, 14.007, 15.999, 18.998403163, 20.1797,
+    22.98976928, 24.305, 26.9815385, 28.085, 30.973761998, 32.06, 35.45, 39.948, 39.0983, 40.078,
+    44.955908, 47.867, 50.9415, 51.9961, 54.938044, 55.845, 58.933194, 58.6934, 63.546, 65.38,
+    69.723, 72.630, 74.921595, 78.971, 79.904, 83.798, 85.4678, 87.62, 88.90584, 91.224,
+    92.90637, 95.95, 98.0, 101.07, 102.90550, 106.42, 107.8682, 112.414, 114.818, 118.710,
+    121.760, 127.60, 126.90447, 131.293, 132.90545196, 137.327, 138.90547, 140.116, 140.90766,
+    144.242, 145.0, 150.36, 151.964, 157.25, 158.92535, 162.500, 164.93033, 167.259, 168.93422,
+    173.045, 174.9668, 178.49, 180.94788, 183.84, 186.207, 190.23, 192.217, 195.084, 196.966569,
+    200.592, 204.38, 207.2, 208.98040, 209.0, 210.0, 222.0, 223.0, 226.0, 227.0, 232.0377,
+    231.03588, 238.02891, 237.0, 244.0, 243.0, 247.0, 247.0, 251.0, 252.0, 257.0, 258.0, 259.0,
+    262.0, 267.0, 270.0, 269.0, 270.0, 270.0, 278.0, 281.0, 281.0, 285.0, 286.0, 289.0, 289.0,
+    293.0, 293.0, 294.0,
+];
+
+/// Covalent radii in Ångström from Cordero et al. (2008), indexed by atomic number minus one.
+/// Low-spin radii are used for Mn, Fe and Co, and the sp3 radius for carbon.
+const COVALENT_RADII: [f64; 96] = [
+    0.31, 0.28, 1.28, 0.96, 0.84, 0.76, 0.71, 0.66, 0.57, 0.58, 1.66, 1.41, 1.21, 1.11, 1.07, 1.05,
+    1.02, 1.06, 2.03, 1.76, 1.70, 1.60, 1.53, 1.39, 1.39, 1.32, 1.26, 1.24, 1.32, 1.22, 1.22, 1.20,
+    1.19, 1.20, 1.20, 1.16, 2.20, 1.95, 1.90, 1.75, 1.64, 1.54, 1.47, 1.46, 1.42, 1.39, 1.45, 1.44,
+    1.42, 1.39, 1.39, 1.38, 1.39, 1.40, 2.44, 2.15, 2.07, 2.04, 2.03, 2.01, 1.99, 1.98, 1.98, 1.96,
+    1.94, 1.92, 1.92, 1.89, 1.90, 1.87, 1.87, 1.75, 1.70, 1.62, 1.51, 1.44, 1.41, 1.36, 1.36, 1.32,
+    1.45, 1.46, 1.48, 1.40, 1.50, 1.50, 2.60, 2.21, 2.15, 2.06, 2.00, 1.96, 1.90, 1.87, 1.80, 1.69,
+];
+
+/// Jmol CPK colors as RGB, indexed by atomic number minus one.
+const CPK_COLORS: [[u8; 3]; 109] = [
+    [0xff, 0xff, 0xff], [0xd9, 0xff, 0xff], [0xcc, 0x80, 0xff], [0xc2, 0xff, 0x00], [0xff, 0xb5, 0xb5], [0x90, 0x90, 0x90],
+    [0x30, 0x50, 0xf8], [0xff, 0x0d, 0x0d], [0x90, 0xe0, 0x50], [0xb3, 0xe3, 0xf5], [0xab, 0x5c, 0xf2], [0x8a, 0xff, 0x00],
+    [0xbf, 0xa6, 0xa6], [0xf0, 0xc8, 0xa0], [0xff, 0x80, 0x00], [0xff, 0xff, 0x30], [0x1f, 0xf0, 0x1f], [0x80, 0xd1, 0xe3],
+    [0x8f, 0x40, 0xd4], [0x3d, 0xff, 0x00], [0xe6, 0xe6, 0xe6], [0xbf, 0xc2, 0xc7], [0xa6, 0xa6, 0xab], [0x8a, 0x99, 0xc7],
+    [0x9c, 0x7a, 0xc7], [0xe0, 0x66, 0x33], [0xf0, 0x90, 0xa0], [0x50, 0xd0, 0x50], [0xc8, 0x80, 0x33], [0x7d, 0x80, 0xb0],
+    [0xc2, 0x8f, 0x8f], [0x66, 0x8f, 0x8f], [0xbd, 0x80, 0xe3], [0xff, 0xa1, 0x00], [0xa6, 0x29, 0x29], [0x5c, 0xb8, 0xd1],
+    [0x70, 0x2e, 0xb0], [0x00, 0xff, 0x00], [0x94, 0xff, 0xff], [0x94, 0xe0, 0xe0], [0x73, 0xc2, 0xc9], [0x54, 0xb5, 0xb5],
+    [0x3b, 0x9e, 0x9e], [0x24, 0x8f, 0x8f], [0x0a, 0x7d, 0x8c], [0x00, 0x69, 0x85], [0xc0, 0xc0, 0xc0], [0xff, 0xd9, 0x8f],
+    [0xa6, 0x75, 0x73], [0x66, 0x80, 0x80], [0x9e, 0x63, 0xb5], [0xd4, 0x7a, 0x00], [0x94, 0x00, 0x94], [0x42, 0x9e, 0xb0],
+    [0x57, 0x17, 0x8f], [0x00, 0xc9, 0x00], [0x70, 0xd4, 0xff], [0xff, 0xff, 0xc7], [0xd9, 0xff, 0xc7], [0xc7, 0xff, 0xc7],
+    [0xa3, 0xff, 0xc7], [0x8f, 0xff, 0xc7], [0x61, 0xff, 0xc7], [0x45, 0xff, 0xc7], [0x30, 0xff, 0xc7], [0x1f, 0xff, 0xc7],
+    [0x00, 0xff, 0x9c], [0x00, 0xe6, 0x75], [0x00, 0xd4, 0x52], [0x00, 0xbf, 0x38], [0x00, 0xab, 0x24], [0x4d, 0xc2, 0xff],
+    [0x4d, 0xa6, 0xff], [0x21, 0x94, 0xd6], [0x26, 0x7d, 0xab], [0x26, 0x66, 0x96], [0x17, 0x54, 0x87], [0xd0, 0xd0, 0xe0],
+    [0xff, 0xd1, 0x23], [0xb8, 0xb8, 0xd0], [0xa6, 0x54, 0x4d], [0x57, 0x59, 0x61], [0x9e, 0x4f, 0xb5], [0xab, 0x5c, 0x00],
+    [0x75, 0x4f, 0x45], [0x42, 0x82, 0x96], [0x42, 0x00, 0x66], [0x00, 0x7d, 0x00], [0x70, 0xab, 0xfa], [0x00, 0xba, 0xff],
+    [0x00, 0xa1, 0xff], [0x00, 0x8f, 0xff], [0x00, 0x80, 0xff], [0x00, 0x6b, 0xff], [0x54, 0x5c, 0xf2], [0x78, 0x5c, 0xe3],
+    [0x8a, 0x4f, 0xe3], [0xa1, 0x36, 0xd4], [0xb3, 0x1f, 0xd4], [0xb3, 0x1f, 0xba], [0xb3, 0x0d, 0xa6], [0xbd, 0x0d, 0x87],
+    [0xc7, 0x00, 0x66], [0xcc, 0x00, 0x59], [0xd1, 0x00, 0x4f], [0xd9, 0x00, 0x45], [0xe0, 0x00, 0x38], [0xe6, 0x00, 0x2e],
+    [0xeb, 0x00, 0x26],
+];
+
+/// Van der Waals radii in Ångström, indexed by atomic number minus one: Mantina et al. (2009) for the main group elements
+/// and Bondi (1964) for the others where available. Elements without a tabulated radius use 2.0 Å.
+const VDW_RADII: [f64; 118] = [
+    1.10, 1.40, 1.81, 1.53, 1.92, 1.70, 1.55, 1.52, 1.47, 1.54, 2.27, 1.73, 1.84, 2.10, 1.80, 1.80,
+    1.75, 1.88, 2.75, 2.31, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 1.63, 1.40, 1.39, 1.87, 2.11,
+    1.85, 1.90, 1.83, 2.02, 3.03, 2.49, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 1.63, 1.72, 1.58,
+    1.93, 2.17, 2.06, 2.06, 1.98, 2.16, 3.43, 2.68, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00,
+    2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 1.72, 1.66, 1.55,
+    1.96, 2.02, 2.07, 1.97, 2.02, 2.20, 3.48, 2.83, 2.00, 2.00, 2.00, 1.86, 2.00, 2.00, 2.00, 2.00,
+    2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00, 2.00,
+    2.00, 2.00, 2.00, 2.00, 2.00, 2.00,
+];
+
+impl Element {
+    /// Looks up an element by its atomic number.
+    pub fn from_atomic_number(number: u8) -> Option<Element> {
+        if (1..=SYMBOLS.len()).contains(&(number as usize)) {
+            Some(Element(number))
+        } else {
+            None
+        }
+    }
+
+    /// Looks up an element by its symbol, ignoring case (e.g. `"Fe"`, `"fe"`, `"FE"`).
+    pub fn from_symbol(symbol: &str) -> Option<Element> {
+        SYMBOLS
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(symbol))
+            .map(|i| Element(i as u8 + 1))
+    }
+
+    /// Looks up an element by its English name, ignoring case (e.g. `"carbon"`).
+    /// The alternative spellings `aluminum`, `sulphur` and `cesium` are accepted as well.
+    pub fn from_name(name: &str) -> Option<Element> {
+        let name = name.to_ascii_lowercase();
+        let name = match name.as_str() {
+            "aluminum" => "aluminium",
+            "sulphur" => "sulfur",
+            "cesium" => "caesium",
+            name => name,
+        };
+        NAMES.iter().position(|n| *n == name).map(|i| Element(i as u8 + 1))
+    }
+
+    /// The element that an unknown label most likely stands for, to suggest as a correction.
+    ///
+    /// Tries the label as an element name, then as a symbol with digits and punctuation removed
+    /// (e.g. `Cl2` or `Fe+`), and finally the symbol or name closest by edit distance, if it is close enough.
+    pub fn closest(label: &str) -> Option<Element> {
+        if let Some(element) = Element::from_name(label) {
+            return Some(element);
+        }
+        let letters: String = label.chars().filter(char::is_ascii_alphabetic).collect();
+        if letters.is_empty() {
+            return None;
+        }
+        if let Some(element) = Element::from_symbol(&letters) {
+            return Some(element);
+        }
+
+        let letters = letters.to_ascii_lowercase();
+        let limit = (letters.len() / 3).max(1);
+        (0..SYMBOLS.len())
+            .map(|i| {
+                let symbol = edit_distance(&letters, &SYMBOLS[i].to_ascii_lowercase());
+                (symbol.min(edit_distance(&letters, NAMES[i])), i)
+            })
+            .filter(|(distance, _)| *distance <= limit)
+            .min()
+            .map(|(_, i)| Element(i as u8 + 1))
+    }
+
+    /// The atomic number of the element.
+    pub fn atomic_number(&self) -> u8 {
+        self.0
+    }
+
+    /// The symbol of the element, as found in the periodic table.
+    pub fn symbol(&self) -> &'static str {
+        SYMBOLS[self.index()]
+    }
+
+    /// The English name of the element in lowercase, with IUPAC spelling (e.g. `"aluminium"`).
+    pub fn name(&self) -> &'static str {
+        NAMES[self.index()]
+    }
+
+    /// The standard atomic weight of the element in unified atomic mass units (Da).
+    pub fn mass(&self) -> f64 {
+        MASSES[self.index()]
+    }
+
+    /// The covalent radius of the element in Ångström, or `None` for elements heavier than curium.
+    pub fn covalent_radius(&self) -> Option<f64> {
+        COVALENT_RADII.get(self.index()).copied()
+    }
+
+    /// The Jmol CPK color of the element as RGB, or deep pink for elements heavier than meitnerium.
+    pub fn cpk_color(&self) -> [u8; 3] {
+        CPK_COLORS.get(self.index()).copied().unwrap_or([0xff, 0x14, 0x93])
+    }
+
+    /// The van der Waals radius of the element in Ångström, or 2 Å for elements without a tabulated radius.
+    pub fn vdw_radius(&self) -> f64 {
+        VDW_RADII[self.index()]
+    }
+
+    /// The radius in Ångström to draw the element with in space-filling pictures, its van der Waals radius.
+    pub fn display_radius(&self) -> f64 {
+        self.vdw_radius()
+    }
+
+    fn index(&self) -> usize {
+        self.0 as usize - 1
+    }
+}
+impl std::fmt::Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// The Levenshtein distance between two strings, counting the single-character insertions, deletions and
+/// substitutions that turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_and_numbers() {
+        let iron = Element::from_symbol("fe").unwrap();
+        assert_eq!(iron.atomic_number(), 26);
+        assert_eq!(iron.symbol(), "Fe");
+        assert_eq!(Element::from_atomic_number(26), Some(iron));
+        assert!(Element::from_atomic_number(0).is_none());
+        assert!(Element::from_atomic_number(119).is_none());
+        assert!(Element::from_symbol("Xx").is_none());
+        assert!((Element::from_symbol("C").unwrap().mass() - 12.011).abs() < 1e-3);
+    }
+
+    #[test]
+    fn covalent_radii() {
+        let radius = |symbol| Element::from_symbol(symbol).unwrap().covalent_radius();
+        assert_eq!(radius("H"), Some(0.31));
+        assert_eq!(radius("C"), Some(0.76));
+        assert_eq!(radius("Cm"), Some(1.69));
+        assert_eq!(radius("Bk"), None);
+    }
+
+    #[test]
+    fn cpk_colors_and_display_radii() {
+        let element = |symbol| Element::from_symbol(symbol).unwrap();
+        assert_eq!(element("O").cpk_color(), [0xff, 0x0d, 0x0d]);
+        assert_eq!(element("C").cpk_color(), [0x90, 0x90, 0x90]);
+        assert_eq!(element("Og").cpk_color(), [0xff, 0x14, 0x93]);
+        assert_eq!(element("H").display_radius(), 1.1);
+        assert_eq!(element("C").display_radius(), 1.7);
+    }
+
+    #[test]
+    fn names_and_closest_elements() {
+        assert_eq!(Element::from_name("Carbon").map(|e| e.symbol()), Some("C"));
+        assert_eq!(Element::from_name("aluminum").map(|e| e.name()), Some("aluminium"));
+        assert_eq!(Element::from_name("C"), None);
+        let closest = |label| Element::closest(label).map(|e| e.symbol());
+        assert_eq!(closest("oxygen"), Some("O"));
+        assert_eq!(closest("Cl2"), Some("Cl"));
+        assert_eq!(closest("Fe+"), Some("Fe"));
+        assert_eq!(closest("carbn"), Some("C"));
+        assert_eq!(closest("123"), None);
+        assert_eq!(closest("Qqqqq"), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn vdw_radii() {
+        let element = |symbol| Element::from_symbol(symbol).unwrap();
+        assert_eq!(element("C").vdw_radius(), 1.70);
+        assert_eq!(element("Fe").vdw_radius(), 2.0);
+        assert_eq!(element("O").display_radius(), element("O").vdw_radius());
+    }
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use super::element::Element;
+
 /// Result type with dynamic error
 pub type Result<T> = std::result::Result<T, FileParseError>;
 
@@ -26,6 +28,147 @@ pub enum FileParseError {
         source: std::io::Error,
     },
 
-    #[error("Expected empty line, found data at line {0}")]
-    UnexpectedData(usize),
+    #[error("Expected {0} column data, but found none at line {1}")]
+    NoColumnData(String, usize),
+
+    #[error("Could not parse data as {0} column at line {1}")]
+    InvalidColumnData(String, usize),
+
+    #[error("Invalid glob pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Atom position is not finite at line {0}")]
+    NonFinitePosition(usize),
+
+    #[error("Expected atom count, but found empty line at line {0}")]
+    BlankLine(usize),
+
+    #[error("Expected end of atom data, but found more columns at line {0}")]
+    ExtraColumns(usize),
+
+    #[error("Unknown element symbol {0} at line {1}{}", suggestion(.0))]
+    UnknownElement(String, usize),
+
+    #[error("Checksum does not match the atoms of the frame with the comment at line {0}")]
+    ChecksumMismatch(usize),
+
+    #[error("Expected the 17 QM9 properties, but found other data at line {0}")]
+    Qm9Properties(usize),
+
+    #[error("The frames need more than the memory limit of {0} bytes")]
+    MemoryLimit(usize),
+}
+
+/// Describes where two trajectories, or the frames of one, diverge when they are required to be consistent.
+#[derive(Debug, Error)]
+pub enum ConsistencyError {
+    #[error("Expected {expected} atoms, but found {found} in frame {frame}")]
+    AtomCount {
+        frame: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Expected atom symbol {expected}, but found {found} for atom {atom} in frame {frame}")]
+    AtomSymbol {
+        frame: usize,
+        atom: usize,
+        expected: String,
+        found: String,
+    },
+
+    #[error("Could not remap atoms, no atom {symbol} left to match atom {atom} in frame {frame}")]
+    Composition {
+        frame: usize,
+        atom: usize,
+        symbol: String,
+    },
+
+    #[error("The bond between atoms {first} and {second} is broken in frame {frame}")]
+    BondBroken {
+        frame: usize,
+        first: usize,
+        second: usize,
+    },
+
+    #[error("A bond between atoms {first} and {second} is formed in frame {frame}")]
+    BondFormed {
+        frame: usize,
+        first: usize,
+        second: usize,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Unclosed placeholder starting at byte {0}")]
+    UnclosedPlaceholder(usize),
+
+    #[error("Unmatched closing brace at byte {0}")]
+    UnmatchedBrace(usize),
+
+    #[error("Unknown placeholder {{{0}}}")]
+    UnknownPlaceholder(String),
+
+    #[error("Invalid number format {0:?}")]
+    InvalidFormat(String),
+}
+
+#[derive(Debug, Error)]
+pub enum SelectionError {
+    #[error("Unexpected end of selection")]
+    UnexpectedEnd,
+
+    #[error("Unexpected {0:?} in selection")]
+    UnexpectedToken(String),
+
+    #[error("Could not parse {0:?} as a number in selection")]
+    InvalidNumber(String),
+}
+
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("The structure has no cell")]
+    MissingCell,
+
+    #[error("The cell is degenerate")]
+    DegenerateCell,
+
+    #[error("Invalid Miller indices {0:?}")]
+    InvalidMillerIndices([i32; 3]),
+
+    #[error("Invalid chiral indices ({0}, {1})")]
+    InvalidChiralIndices(usize, usize),
+
+    #[error("The surface has no {0} site")]
+    MissingSite(crate::build::Site),
+
+    #[error("The molecule has no atoms")]
+    EmptyMolecule,
+
+    #[error("Invalid composition: {0}")]
+    InvalidComposition(String),
+}
+
+#[derive(Debug, Error)]
+pub enum EditError {
+    #[error("Atom index {0} is out of range")]
+    InvalidIndex(usize),
+
+    #[error("Atoms {0} and {1} are part of a ring, so one side cannot be moved alone")]
+    Ring(usize, usize),
+
+    #[error("The atoms are coincident or collinear")]
+    Degenerate,
+
+    #[error("Atom {0} is not bonded to any atom")]
+    Unbonded(usize),
+
+    #[error("The new atom clashes with atom {atom}, {distance:.3} Å away")]
+    Clash { atom: usize, distance: f64 },
+}
+
+/// Suggests the element an unknown symbol most likely stands for, to append to an error message.
+pub(crate) fn suggestion(symbol: &str) -> String {
+    Element::closest(symbol).map_or_else(String::new, |element| format!(", did you mean {}?", element))
 }
@@ -0,0 +1,168 @@
+//! Support for the per-atom columns of extended XYZ files,
+//! described by `Properties=name:type:width:...` in the comment line.
+
+use super::data::AtomData;
+use super::error::{FileParseError, Result};
+use super::position::Position;
+
+/// A per-atom column of an extended XYZ file.
+#[derive(Debug, Clone, PartialEq)]
+enum Column {
+    Species,
+    Position,
+    Name,
+    Group,
+    Index,
+    Force,
+    Velocity,
+    Charge,
+    /// A string column stored as a tag layer of the same name.
+    Tag(String),
+    /// A column that is not read, with its width.
+    Other(usize),
+}
+
+/// The layout of the atom lines of an extended XYZ frame.
+#[derive(Debug, Clone)]
+pub(crate) struct Layout {
+    columns: Vec<Column>,
+}
+impl Layout {
+    /// Reads the layout from the `Properties=...` entry of a comment.
+    /// Returns `None` if there is no such entry, or it does not contain both species and positions.
+    pub(crate) fn from_comment(comment: &str) -> Option<Layout> {
+        let start = comment.find("Properties=")? + "Properties=".len();
+        let properties = comment[start..].split_whitespace().next()?.trim_matches('"');
+
+        let fields: Vec<&str> = properties.split(':').collect();
+        let columns = fields
+            .chunks(3)
+            .map(|field| {
+                let [name, kind, width] = field else { return None };
+                let width: usize = width.parse().ok()?;
+                Some(match (name.to_ascii_lowercase().as_str(), width) {
+                    ("species" | "element", 1) => Column::Species,
+                    ("pos" | "positions", 3) => Column::Position,
+                    ("name" | "atom_name", 1) => Column::Name,
+                    ("group" | "residue" | "resname", 1) => Column::Group,
+                    ("index" | "id", 1) => Column::Index,
+                    ("forces" | "force", 3) => Column::Force,
+                    ("velo" | "vel" | "velocities" | "velocity", 3) => Column::Velocity,
+                    ("charge" | "charges" | "initial_charges", 1) if kind.eq_ignore_ascii_case("r") => Column::Charge,
+                    (_, 1) if kind.eq_ignore_ascii_case("s") => Column::Tag(name.to_string()),
+                    _ => Column::Other(width),
+                })
+            })
+            .collect::<Option<Vec<Column>>>()?;
+
+        if columns.contains(&Column::Species) && columns.contains(&Column::Position) {
+            Some(Layout { columns })
+        } else {
+            None
+        }
+    }
+
+    /// Whether the layout has a velocity column.
+    pub(crate) fn has_velocities(&self) -> bool {
+        self.columns.contains(&Column::Velocity)
+    }
+
+    /// Parses an atom line following the layout.
+    pub(crate) fn parse_atom(&self, line: &str, line_count: usize) -> Result<AtomData> {
+        let mut tokens = line.split_whitespace();
+        let mut atom = AtomData::new(String::new(), [0.0; 3]);
+
+        for column in &self.columns {
+            match column {
+                Column::Species => {
+                    atom.symbol = tokens.next().ok_or(FileParseError::NoAtomSymbol(line_count))?.to_string();
+                }
+                Column::Position => {
+                    for v in atom.position.0.iter_mut() {
+                        let token = tokens.next().ok_or(FileParseError::NoPositionData(line_count))?;
+                        *v = token.parse().map_err(|_| FileParseError::InvalidPositionData(line_count))?;
+                    }
+                }
+                Column::Force => atom.force = Some(vector_column(&mut tokens, "forces", line_count)?),
+                Column::Velocity => atom.velocity = Some(vector_column(&mut tokens, "velocities", line_count)?),
+                Column::Name => atom.name = Some(column_token(&mut tokens, "name", line_count)?.to_string()),
+                Column::Group => atom.group = Some(column_token(&mut tokens, "group", line_count)?.to_string()),
+                Column::Charge => {
+                    let token = column_token(&mut tokens, "charge", line_count)?;
+                    let charge = token
+                        .parse()
+                        .map_err(|_| FileParseError::InvalidColumnData("charge".to_string(), line_count))?;
+                    atom.charge = Some(charge);
+                }
+                Column::Tag(layer) => {
+                    let value = column_token(&mut tokens, layer, line_count)?;
+                    if value != crate::tags::UNTAGGED {
+                        atom.tags.insert(layer.clone(), value.to_string());
+                    }
+                }
+                Column::Index => {
+                    let token = column_token(&mut tokens, "index", line_count)?;
+                    let index = token
+                        .parse()
+                        .map_err(|_| FileParseError::InvalidColumnData("index".to_string(), line_count))?;
+                    atom.index = Some(index);
+                }
+                Column::Other(width) => {
+                    for _ in 0..*width {
+                        tokens.next();
+                    }
+                }
+            }
+        }
+
+        Ok(atom)
+    }
+}
+
+fn column_token<'a>(tokens: &mut impl Iterator<Item = &'a str>, column: &str, line_count: usize) -> Result<&'a str> {
+    tokens.next().ok_or_else(|| FileParseError::NoColumnData(column.to_string(), line_count))
+}
+
+fn vector_column<'a>(tokens: &mut impl Iterator<Item = &'a str>, column: &str, line_count: usize) -> Result<Position> {
+    let mut vector = Position::default();
+    for v in vector.0.iter_mut() {
+        let token = column_token(tokens, column, line_count)?;
+        *v = token.parse().map_err(|_| FileParseError::InvalidColumnData(column.to_string(), line_count))?;
+    }
+    Ok(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_columns() {
+        let comment = "Properties=species:S:1:pos:R:3:resname:S:1:charge:R:1:id:I:1 step=1";
+        let layout = Layout::from_comment(comment).unwrap();
+        let atom = layout.parse_atom("C 1.0 2.0 3.0 LIG -0.2 7", 3).unwrap();
+        assert_eq!(atom.symbol, "C");
+        assert_eq!(atom.position.0, [1.0, 2.0, 3.0]);
+        assert_eq!(atom.group.as_deref(), Some("LIG"));
+        assert_eq!(atom.index, Some(7));
+        assert!(matches!(layout.parse_atom("C 1.0 2.0 3.0 LIG", 3), Err(FileParseError::NoColumnData(_, 3))));
+        assert!(Layout::from_comment("Properties=species:S:1").is_none());
+        assert!(Layout::from_comment("no properties").is_none());
+    }
+
+    #[test]
+    fn velocity_and_force_columns() {
+        let layout = Layout::from_comment("Properties=species:S:1:pos:R:3:velo:R:3:forces:R:3").unwrap();
+        let atom = layout.parse_atom("H 0 0 0 0.01 0.02 -0.03 1 2 3", 3).unwrap();
+        assert_eq!(atom.velocity, Some(Position::new(0.01, 0.02, -0.03)));
+        assert_eq!(atom.force, Some(Position::new(1.0, 2.0, 3.0)));
+        assert!(matches!(layout.parse_atom("H 0 0 0 0.01 x 0 1 2 3", 3), Err(FileParseError::InvalidColumnData(_, 3))));
+    }
+
+    #[test]
+    fn charge_column() {
+        let layout = Layout::from_comment("Properties=species:S:1:pos:R:3:initial_charges:R:1").unwrap();
+        assert_eq!(layout.parse_atom("Na 0 0 0 1.0", 3).unwrap().charge, Some(1.0));
+        assert!(matches!(layout.parse_atom("Na 0 0 0 +", 3), Err(FileParseError::InvalidColumnData(_, 3))));
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::data::Data;
+use super::error;
+use super::reader::{Frames, ParseOptions};
+
+/// Options for [`follow`].
+#[derive(Debug, Clone)]
+pub struct FollowOptions {
+    /// How the frames are parsed.
+    pub parse: ParseOptions,
+    /// How long to wait before checking the file for new data again.
+    pub poll_interval: Duration,
+    /// Skips the frames already complete in the file, only yielding the frames completed later.
+    pub from_end: bool,
+    /// Stops once no new data was appended for this long. Without a timeout, the iterator waits forever.
+    pub timeout: Option<Duration>,
+}
+impl Default for FollowOptions {
+    fn default() -> FollowOptions {
+        FollowOptions {
+            parse: ParseOptions::default(),
+            poll_interval: Duration::from_millis(500),
+            from_end: false,
+            timeout: None,
+        }
+    }
+}
+
+/// Iterator over the frames of a `.xyz` file that is still being written, see [`follow`].
+pub struct Follow {
+    frames: Frames<BufReader<Tail>>,
+    waited: Arc<AtomicBool>,
+    from_end: bool,
+}
+impl Iterator for Follow {
+    type Item = error::Result<Data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.frames.next()?;
+            // The reader only waits once everything in the file when it was opened has been read.
+            if !self.from_end || self.waited.load(Ordering::Relaxed) || frame.is_err() {
+                return Some(frame);
+            }
+        }
+    }
+}
+
+/// Follows a `.xyz` file that is appended to, e.g. by a running simulation, like `tail -f`:
+/// yields the frames in the file, and then waits for new frames and yields them as they are completed.
+///
+/// A frame is yielded once its last atom line is written.
+pub fn follow<P: AsRef<Path>>(path: P, options: FollowOptions) -> io::Result<Follow> {
+    let waited = Arc::new(AtomicBool::new(false));
+    let tail = Tail {
+        file: fs::File::open(path)?,
+        poll_interval: options.poll_interval,
+        timeout: options.timeout,
+        last_data: Instant::now(),
+        waited: waited.clone(),
+    };
+    Ok(Follow {
+        frames: Frames::with_options(BufReader::new(tail), options.parse),
+        waited,
+        from_end: options.from_end,
+    })
+}
+
+/// A reader that waits for more data at the end of the file, until the timeout.
+struct Tail {
+    file: fs::File,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+    last_data: Instant,
+    waited: Arc<AtomicBool>,
+}
+impl Read for Tail {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let bytes = self.file.read(buf)?;
+            if bytes > 0 || buf.is_empty() {
+                self.last_data = Instant::now();
+                return Ok(bytes);
+            }
+            self.waited.store(true, Ordering::Relaxed);
+            if self.timeout.is_some_and(|t| self.last_data.elapsed() >= t) {
+                return Ok(0);
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::testing::temp_path;
+
+    const WATER: &str = "3\nwater\nO 0.0 0.0 0.0\nH 0.96 0.0 0.0\nH -0.24 0.93 0.0\n";
+
+    fn comments(frames: Follow) -> Vec<String> {
+        frames.map(|data| data.unwrap().comment).collect()
+    }
+
+    #[test]
+    fn frames_appended_while_following() {
+        let path = temp_path("follow.xyz");
+        fs::write(&path, format!("{}\n{}\n", WATER, WATER.replace("water", "second"))).unwrap();
+        let options = FollowOptions {
+            poll_interval: Duration::from_millis(5),
+            timeout: Some(Duration::from_millis(300)),
+            ..FollowOptions::default()
+        };
+
+        let appended = std::thread::scope(|scope| {
+            let frames = follow(&path, options.clone()).unwrap();
+            let tail = follow(&path, FollowOptions { from_end: true, ..options }).unwrap();
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+                file.write_all(WATER.replace("water", "third").as_bytes()).unwrap();
+            });
+            let tail = scope.spawn(|| comments(tail));
+            assert_eq!(comments(frames), ["water", "second", "third"]);
+            tail.join().unwrap()
+        });
+        assert_eq!(appended, ["third"]);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,489 @@
+use super::cell::Cell;
+use super::data::Data;
+use super::error::BuildError;
+use super::math;
+use super::position::Position;
+use super::random::Rng;
+
+/// A cartesian axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+impl Axis {
+    /// The index of the axis in a position, 0 for x to 2 for z.
+    pub fn index(&self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+}
+
+/// Spherical coordinates of a position relative to an origin, with the polar axis along z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spherical {
+    /// The distance from the origin in Ångström.
+    pub r: f64,
+    /// The polar angle from the z axis in degrees, from 0 to 180.
+    pub theta: f64,
+    /// The azimuthal angle from the x axis towards the y axis in degrees, from -180 to 180.
+    pub phi: f64,
+}
+
+/// Cylindrical coordinates of a position relative to an axis through an origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cylindrical {
+    /// The distance from the axis in Ångström.
+    pub rho: f64,
+    /// The azimuthal angle in degrees, from -180 to 180, measured from the next axis in cyclic order
+    /// (y for the x axis, z for the y axis and x for the z axis) towards the one after it.
+    pub phi: f64,
+    /// The coordinate along the axis relative to the origin, in Ångström.
+    pub height: f64,
+}
+
+/// An axis-aligned box, given by its minimum and maximum corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Position,
+    pub max: Position,
+}
+impl BoundingBox {
+    /// Whether a position lies inside the box, including its surface.
+    pub fn contains(&self, position: Position) -> bool {
+        (0..3).all(|i| self.min[i] <= position[i] && position[i] <= self.max[i])
+    }
+
+    /// The edge lengths of the box.
+    pub fn size(&self) -> Position {
+        self.max - self.min
+    }
+
+    /// The center of the box.
+    pub fn center(&self) -> Position {
+        (self.min + self.max) / 2.0
+    }
+}
+
+impl Data {
+    /// The smallest axis-aligned box containing all atoms, or `None` if there are no atoms.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let first = self.atoms.first()?.position;
+        Some(self.atoms.iter().fold(BoundingBox { min: first, max: first }, |b, atom| BoundingBox {
+            min: Position(std::array::from_fn(|i| b.min[i].min(atom.position[i]))),
+            max: Position(std::array::from_fn(|i| b.max[i].max(atom.position[i]))),
+        }))
+    }
+
+    /// Sets an orthorhombic cell that fits the atoms with `padding` Ångström of vacuum on every side,
+    /// and centers the atoms in it. Does nothing if there are no atoms.
+    pub fn fit_cell(&mut self, padding: f64) {
+        let Some(bbox) = self.bounding_box() else { return };
+        let size = bbox.size() + Position::new(2.0, 2.0, 2.0) * padding;
+        self.set_cell(Some(Cell::new([size[0], 0.0, 0.0], [0.0, size[1], 0.0], [0.0, 0.0, size[2]])));
+        self.center_in_cell();
+    }
+
+    /// Translates the atoms such that the center of their bounding box lies at the center of the cell.
+    /// Does nothing if there is no cell or there are no atoms.
+    pub fn center_in_cell(&mut self) {
+        let (Some(cell), Some(bbox)) = (&self.cell, self.bounding_box()) else { return };
+        let shift = cell.center() - bbox.center();
+        for atom in &mut self.atoms {
+            atom.position += shift;
+        }
+    }
+
+    /// Replaces the cell by its Niggli-reduced cell (see [`Cell::niggli_reduce`]) and wraps the atoms into it,
+    /// so their fractional coordinates lie between 0 and 1.
+    pub fn reduce_cell(&mut self) -> Result<(), BuildError> {
+        let cell = self.cell.ok_or(BuildError::MissingCell)?;
+        let reduced = cell.niggli_reduce().ok_or(BuildError::DegenerateCell)?;
+        for atom in &mut self.atoms {
+            let fractional = reduced.fractional(atom.position).map(|f| f.rem_euclid(1.0));
+            atom.position = reduced.cartesian(fractional);
+        }
+        self.set_cell(Some(reduced));
+        Ok(())
+    }
+
+    /// Deforms the cell and the atom positions by a strain tensor `strain` (ε),
+    /// mapping every position `r` to `(I + ε) r`, so fractional coordinates are preserved.
+    pub fn apply_strain(&mut self, strain: [[f64; 3]; 3]) {
+        let mut deformation = strain;
+        for (i, row) in deformation.iter_mut().enumerate() {
+            row[i] += 1.0;
+        }
+        let deformation = math::transpose(&deformation);
+
+        for atom in &mut self.atoms {
+            atom.position = Position(math::vec_mul(atom.position.0, &deformation));
+        }
+        if let Some(cell) = self.cell {
+            self.set_cell(Some(Cell {
+                vectors: cell.vectors.map(|v| math::vec_mul(v, &deformation)),
+            }));
+        }
+    }
+
+    /// Scales the cell and the atom positions by `factor`, e.g. for an equation-of-state scan of the lattice constant.
+    pub fn scale_cell(&mut self, factor: f64) {
+        for atom in &mut self.atoms {
+            atom.position = atom.position * factor;
+        }
+        if let Some(mut cell) = self.cell {
+            cell.scale(factor);
+            self.set_cell(Some(cell));
+        }
+    }
+
+    /// Displaces every coordinate by a normally distributed random amount with standard deviation `stddev` Ångström.
+    /// The same `seed` always gives the same displacements.
+    pub fn rattle(&mut self, stddev: f64, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for atom in &mut self.atoms {
+            for v in atom.position.0.iter_mut() {
+                *v += stddev * rng.normal();
+            }
+        }
+    }
+
+    /// Rounds every coordinate to `decimals` decimal places, so values like 1e-17 become zero,
+    /// and negative zeros are written without a sign.
+    pub fn round_coords(&mut self, decimals: i32) {
+        let scale = 10f64.powi(decimals);
+        for atom in &mut self.atoms {
+            for v in atom.position.0.iter_mut() {
+                *v = (*v * scale).round() / scale + 0.0;
+            }
+        }
+    }
+
+    /// Snaps coordinates that are symmetric up to `tol` Ångström to exactly symmetric values, e.g. before
+    /// detecting the symmetry of an optimized geometry. Without a cell, coordinates within `tol` of zero
+    /// become zero, and pairs of atoms with the same symbol that are mirror images through a coordinate plane
+    /// up to `tol` are moved to exact mirror images around their mean, which makes mirror planes exact for frames
+    /// in the [standard orientation](Data::to_standard_orientation). With a cell, fractional coordinates are snapped
+    /// to the nearest multiple of 1/24, which covers the halves, thirds, quarters, sixths and eighths.
+    pub fn snap_to_symmetry(&mut self, tol: f64) {
+        if let Some(cell) = self.cell {
+            let lengths = cell.vectors.map(|v| Position(v).norm());
+            for atom in &mut self.atoms {
+                let mut fractional = cell.fractional(atom.position);
+                for (f, length) in fractional.iter_mut().zip(lengths) {
+                    let snapped = (*f * 24.0).round() / 24.0 + 0.0;
+                    if (snapped - *f).abs() * length <= tol {
+                        *f = snapped;
+                    }
+                }
+                atom.position = cell.cartesian(fractional);
+            }
+            return;
+        }
+
+        for atom in &mut self.atoms {
+            atom.position.0.iter_mut().filter(|v| v.abs() <= tol).for_each(|v| *v = 0.0);
+        }
+        for axis in 0..3 {
+            let mirror = |mut position: Position| {
+                position.0[axis] = -position.0[axis];
+                position
+            };
+            let mut paired = vec![false; self.atoms.len()];
+            for i in 0..self.atoms.len() {
+                if paired[i] || self.atoms[i].position[axis] == 0.0 {
+                    continue;
+                }
+                let image = mirror(self.atoms[i].position);
+                let partner = (i + 1..self.atoms.len())
+                    .filter(|&j| !paired[j] && self.atoms[j].symbol == self.atoms[i].symbol)
+                    .map(|j| (j, self.atoms[j].position.distance(image)))
+                    .filter(|&(_, distance)| distance <= tol)
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+                let Some((j, _)) = partner else {
+                    continue;
+                };
+                let mean = (self.atoms[i].position + mirror(self.atoms[j].position)) / 2.0;
+                self.atoms[i].position = mean;
+                self.atoms[j].position = mirror(mean);
+                paired[i] = true;
+                paired[j] = true;
+            }
+        }
+    }
+
+    /// The frame in the standard orientation used by Gaussian, to compare geometries from different programs:
+    /// translated so the center of nuclear charge is at the origin, and rotated onto the principal axes of the
+    /// charge-weighted moment of inertia tensor, with x, y and z along the axes of increasing moment.
+    /// Each axis points to where the charge-weighted third moment is positive, and the axes are right-handed.
+    /// Symmetric tops have no unique orientation around their degenerate axes.
+    /// The cell, if any, is rotated along. Returns `None` if there are no atoms or an atom is not a known element.
+    pub fn to_standard_orientation(&self) -> Option<Data> {
+        let charges: Vec<f64> = self
+            .atoms
+            .iter()
+            .map(|atom| atom.element().map(|e| e.atomic_number() as f64))
+            .collect::<Option<_>>()?;
+        let total: f64 = charges.iter().sum();
+        if total == 0.0 {
+            return None;
+        }
+        let center = self.atoms.iter().zip(&charges).map(|(atom, &z)| atom.position * z).sum::<Position>() / total;
+        let relative: Vec<Position> = self.atoms.iter().map(|atom| atom.position - center).collect();
+
+        let mut inertia = vec![vec![0.0; 3]; 3];
+        for (r, &z) in relative.iter().zip(&charges) {
+            for (i, row) in inertia.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value += z * (if i == j { r.dot(*r) } else { 0.0 } - r[i] * r[j]);
+                }
+            }
+        }
+        let (moments, vectors) = math::symmetric_eigen(inertia);
+        let mut order = [0, 1, 2];
+        order.sort_by(|&a, &b| moments[a].total_cmp(&moments[b]));
+        let mut axes = order.map(|k| Position::new(vectors[0][k], vectors[1][k], vectors[2][k]));
+
+        for axis in axes.iter_mut() {
+            let third: f64 = relative.iter().zip(&charges).map(|(r, &z)| z * r.dot(*axis).powi(3)).sum();
+            if third < -1e-8 {
+                *axis = -*axis;
+            }
+        }
+        if axes[0].cross(axes[1]).dot(axes[2]) < 0.0 {
+            axes[2] = -axes[2];
+        }
+
+        let rotation: math::Mat3 = math::transpose(&axes.map(|axis| axis.0));
+        let mut data = self.clone();
+        for (atom, r) in data.atoms.iter_mut().zip(relative) {
+            atom.position = Position(math::vec_mul(r.0, &rotation));
+        }
+        if let Some(cell) = self.cell {
+            data.set_cell(Some(Cell {
+                vectors: cell.vectors.map(|v| math::vec_mul(v, &rotation)),
+            }));
+        }
+        Some(data)
+    }
+
+    /// The spherical coordinates of every atom relative to `origin`, e.g. the center of a nanoparticle.
+    pub fn spherical_coords(&self, origin: Position) -> Vec<Spherical> {
+        self.atoms
+            .iter()
+            .map(|atom| {
+                let p = atom.position - origin;
+                let r = p.norm();
+                let theta = if r > 0.0 { (p[2] / r).clamp(-1.0, 1.0).acos().to_degrees() } else { 0.0 };
+                Spherical { r, theta, phi: p[1].atan2(p[0]).to_degrees() }
+            })
+            .collect()
+    }
+
+    /// The cylindrical coordinates of every atom relative to the line along `axis` through `origin`,
+    /// e.g. the axis of a nanotube.
+    pub fn cylindrical_coords(&self, origin: Position, axis: Axis) -> Vec<Cylindrical> {
+        let d = axis.index();
+        let (h, v) = ((d + 1) % 3, (d + 2) % 3);
+        self.atoms
+            .iter()
+            .map(|atom| {
+                let p = atom.position - origin;
+                Cylindrical {
+                    rho: p[h].hypot(p[v]),
+                    phi: p[v].atan2(p[h]).to_degrees(),
+                    height: p[d],
+                }
+            })
+            .collect()
+    }
+
+    /// The indices of the atoms within `radius` Ångström of `center`, in ascending order.
+    pub fn atoms_within_sphere(&self, center: Position, radius: f64) -> Vec<usize> {
+        self.atom_indices(|p| p.distance(center) <= radius)
+    }
+
+    /// The indices of the atoms with a coordinate along `axis` from `min` (inclusive) to `max` (exclusive),
+    /// in ascending order.
+    pub fn atoms_in_slab(&self, axis: Axis, min: f64, max: f64) -> Vec<usize> {
+        self.atom_indices(|p| min <= p[axis.index()] && p[axis.index()] < max)
+    }
+
+    /// The indices of the atoms outside a box, in ascending order.
+    pub fn atoms_outside_box(&self, bbox: &BoundingBox) -> Vec<usize> {
+        self.atom_indices(|p| !bbox.contains(p))
+    }
+
+    fn atom_indices<F: Fn(Position) -> bool>(&self, predicate: F) -> Vec<usize> {
+        self.atoms
+            .iter()
+            .enumerate()
+            .filter(|(_, atom)| predicate(atom.position))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BuildError;
+    use crate::testing::frame;
+    use crate::testing::water;
+
+    #[test]
+    fn bounding_box_and_regions() {
+        let data = water();
+        let bbox = data.bounding_box().unwrap();
+        assert_eq!(bbox.min, Position::new(-0.24, 0.0, 0.0));
+        assert_eq!(bbox.max, Position::new(0.96, 0.93, 0.0));
+        assert!((bbox.size()[0] - 1.2).abs() < 1e-12);
+        assert!((bbox.center()[0] - 0.36).abs() < 1e-12);
+        assert!(bbox.contains(Position::new(0.0, 0.93, 0.0)));
+
+        assert_eq!(data.atoms_within_sphere(Position::default(), 0.96), [0, 1]);
+        assert_eq!(data.atoms_within_sphere(Position::new(1.0, 0.0, 0.0), 0.5), [1]);
+        assert_eq!(data.atoms_in_slab(Axis::X, -1.0, 0.96), [0, 2]);
+        assert_eq!(data.atoms_in_slab(Axis::Y, 0.5, 1.0), [2]);
+        let inner = BoundingBox {
+            min: Position::new(-0.1, -0.1, -0.1),
+            max: Position::new(1.0, 0.1, 0.1),
+        };
+        assert_eq!(data.atoms_outside_box(&inner), [2]);
+        assert!(crate::testing::frame("", &[]).bounding_box().is_none());
+    }
+
+    #[test]
+    fn fit_cell_with_padding() {
+        let mut data = water();
+        data.comment = "energy=-76.4".to_string();
+        data.fit_cell(5.0);
+        let cell = data.cell.unwrap();
+        assert!((cell.vectors[0][0] - 11.2).abs() < 1e-12);
+        assert!((cell.vectors[2][2] - 10.0).abs() < 1e-12);
+        assert_eq!(data.comment, format!("{} energy=-76.4", cell.to_comment()));
+        let bbox = data.bounding_box().unwrap();
+        assert!((bbox.min[0] - 5.0).abs() < 1e-12);
+        assert!((bbox.max[1] - 5.93).abs() < 1e-12);
+    }
+
+    #[test]
+    fn strain_and_scaling_preserve_fractional_coordinates() {
+        let mut data = water();
+        data.set_cell(Some(Cell::new([4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0])));
+        let fractional =
+            |data: &Data| data.atoms.iter().map(|atom| data.cell.unwrap().fractional(atom.position)).collect();
+        let before: Vec<[f64; 3]> = fractional(&data);
+
+        data.apply_strain([[0.1, 0.05, 0.0], [0.05, 0.0, 0.0], [0.0, 0.0, -0.02]]);
+        assert_eq!(data.cell.unwrap().vectors[0], [4.4, 0.2, 0.0]);
+        let after: Vec<[f64; 3]> = fractional(&data);
+        assert!(before.iter().flatten().zip(after.iter().flatten()).all(|(a, b)| (a - b).abs() < 1e-12));
+
+        data.scale_cell(2.0);
+        assert_eq!(data.cell.unwrap().vectors[2], [0.0, 0.0, 7.84]);
+        let scaled: Vec<[f64; 3]> = fractional(&data);
+        assert!(before.iter().flatten().zip(scaled.iter().flatten()).all(|(a, b)| (a - b).abs() < 1e-12));
+    }
+
+    #[test]
+    fn rattle_is_reproducible() {
+        let positions = |data: &Data| data.atoms.iter().map(|atom| atom.position).collect::<Vec<_>>();
+        let (mut a, mut b) = (water(), water());
+        a.rattle(0.1, 7);
+        b.rattle(0.1, 7);
+        assert_eq!(positions(&a), positions(&b));
+        assert_ne!(positions(&a), positions(&water()));
+        b.rattle(0.1, 8);
+        assert_ne!(positions(&a), positions(&b));
+    }
+
+    #[test]
+    fn standard_orientation_is_unique() {
+        let oriented = water().to_standard_orientation().unwrap();
+        // The molecule lies in the plane of the two smaller moments, centered on its nuclear charge.
+        assert!(oriented.atoms.iter().all(|atom| atom.position[2].abs() < 1e-12));
+        let charge: Position = oriented.atoms.iter().zip([8.0, 1.0, 1.0]).map(|(atom, z)| atom.position * z).sum();
+        assert!(charge.norm() < 1e-12);
+
+        let mut moved = water();
+        for atom in &mut moved.atoms {
+            let [x, y, z] = atom.position.0;
+            atom.position = Position::new(5.0 - z, 2.0 + y, 1.0 + x);
+        }
+        let again = moved.to_standard_orientation().unwrap();
+        for (a, b) in oriented.atoms.iter().zip(&again.atoms) {
+            assert!(a.position.distance(b.position) < 1e-9, "{:?} {:?}", a.position, b.position);
+        }
+        assert!(crate::testing::frame("", &[("Xx", [0.0; 3])]).to_standard_orientation().is_none());
+    }
+
+    #[test]
+    fn spherical_and_cylindrical_coordinates() {
+        let data = crate::testing::frame("", &[("C", [1.0, 1.0, 1.0]), ("C", [1.0, 2.0, 1.0]), ("C", [0.0, 1.0, 3.0])]);
+        let origin = Position::new(1.0, 1.0, 1.0);
+        let spherical = data.spherical_coords(origin);
+        assert_eq!(spherical[0], Spherical { r: 0.0, theta: 0.0, phi: 0.0 });
+        assert_eq!(spherical[1], Spherical { r: 1.0, theta: 90.0, phi: 90.0 });
+        assert!((spherical[2].theta - 0.5f64.atan().to_degrees()).abs() < 1e-12);
+        assert_eq!(spherical[2].phi, 180.0);
+
+        let cylindrical = data.cylindrical_coords(origin, Axis::X);
+        assert_eq!(cylindrical[1], Cylindrical { rho: 1.0, phi: 0.0, height: 0.0 });
+        assert_eq!(cylindrical[2], Cylindrical { rho: 2.0, phi: 90.0, height: -1.0 });
+        assert_eq!(data.cylindrical_coords(origin, Axis::Z)[2].height, 2.0);
+    }
+
+    #[test]
+    fn reduce_cell_wraps_atoms() {
+        let mut data = water();
+        assert!(matches!(data.reduce_cell(), Err(BuildError::MissingCell)));
+
+        data.set_cell(Some(Cell::new([3.0, 0.0, 0.0], [6.0, 3.0, 0.0], [0.0, 0.0, 3.0])));
+        data.reduce_cell().unwrap();
+        let cell = data.cell.unwrap();
+        assert!((cell.volume() - 27.0).abs() < 1e-9);
+        for atom in &data.atoms {
+            assert!(cell.fractional(atom.position).iter().all(|f| (0.0..1.0).contains(f)));
+        }
+    }
+
+    #[test]
+    fn round_coordinates() {
+        let mut data = frame("", &[("C", [1e-17, -0.00004, 1.23456])]);
+        data.round_coords(4);
+        assert_eq!(data.atoms[0].position.0, [0.0, 0.0, 1.2346]);
+        assert!(data.atoms[0].position[1].is_sign_positive());
+    }
+
+    #[test]
+    fn snap_to_mirror_symmetry() {
+        let atoms = [("O", [1e-5, 0.0, -0.1173]), ("H", [0.7572, 3e-6, 0.4692]), ("H", [-0.75721, 0.0, 0.4692])];
+        let mut water = frame("", &atoms);
+        water.snap_to_symmetry(1e-3);
+        assert_eq!(water.atoms[0].position.0, [0.0, 0.0, -0.1173]);
+        assert_eq!(water.atoms[1].position[0], -water.atoms[2].position[0]);
+        assert_eq!(water.atoms[1].position[1], 0.0);
+        assert_eq!(water.atoms[1].position[2], water.atoms[2].position[2]);
+
+        // Only atoms with the same symbol at mirrored positions are paired.
+        let atoms = [("C", [1.0, 0.5, 0.0]), ("N", [-1.0004, 0.5, 0.0]), ("C", [-1.0004, -0.5, 0.0])];
+        let mut molecule = frame("", &atoms);
+        molecule.snap_to_symmetry(1e-3);
+        let positions: Vec<[f64; 3]> = molecule.atoms.iter().map(|atom| atom.position.0).collect();
+        assert_eq!(positions, atoms.map(|(_, position)| position));
+
+        let mut crystal = frame("", &[("Na", [0.0001, 2.8199, 1.9])]);
+        crystal.cell = Some(Cell::new([5.64, 0.0, 0.0], [0.0, 5.64, 0.0], [0.0, 0.0, 5.64]));
+        crystal.snap_to_symmetry(1e-3);
+        let position = crystal.atoms[0].position;
+        assert_eq!(position[0], 0.0);
+        assert!((position[1] - 2.82).abs() < 1e-12);
+        // The z coordinate is 0.02 Å from a third, farther than the tolerance.
+        assert!((position[2] - 1.9).abs() < 1e-12);
+    }
+}
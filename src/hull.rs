@@ -0,0 +1,267 @@
+//! Bounding spheres and convex hulls of the atoms of a frame, to estimate the size of molecules and check for
+//! collisions when packing them, more tightly than with [bounding boxes](crate::geometry::BoundingBox).
+
+use std::collections::HashSet;
+
+use super::data::Data;
+use super::position::Position;
+use super::random::Rng;
+
+/// A sphere, given by its center and radius in Ångström.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Position,
+    pub radius: f64,
+}
+impl BoundingSphere {
+    /// Whether a position lies inside the sphere, including its surface.
+    pub fn contains(&self, position: Position) -> bool {
+        self.center.distance(position) <= self.radius
+    }
+
+    /// Whether this sphere and `other` overlap.
+    pub fn intersects(&self, other: &BoundingSphere) -> bool {
+        self.center.distance(other.center) <= self.radius + other.radius
+    }
+
+    fn point(p: Position) -> BoundingSphere {
+        BoundingSphere { center: p, radius: 0.0 }
+    }
+
+    fn diameter(a: Position, b: Position) -> BoundingSphere {
+        BoundingSphere {
+            center: (a + b) / 2.0,
+            radius: a.distance(b) / 2.0,
+        }
+    }
+
+    /// The smallest sphere with the three positions on its surface, or the sphere over the farthest pair
+    /// if they are collinear.
+    fn circumscribed3(a: Position, b: Position, c: Position) -> BoundingSphere {
+        let (u, v) = (b - a, c - a);
+        let w = u.cross(v);
+        let w2 = w.dot(w);
+        if w2 <= 1e-12 * u.dot(u) * v.dot(v) {
+            return [(a, b), (a, c), (b, c)]
+                .into_iter()
+                .map(|(p, q)| BoundingSphere::diameter(p, q))
+                .max_by(|s, t| s.radius.total_cmp(&t.radius))
+                .unwrap();
+        }
+        let offset = (v.cross(w) * u.dot(u) + w.cross(u) * v.dot(v)) / (2.0 * w2);
+        BoundingSphere {
+            center: a + offset,
+            radius: offset.norm(),
+        }
+    }
+
+    /// The sphere with the four positions on its surface, or the smallest sphere containing them
+    /// if they are coplanar.
+    fn circumscribed4(a: Position, b: Position, c: Position, d: Position) -> BoundingSphere {
+        let (u, v, w) = (b - a, c - a, d - a);
+        let det = u.dot(v.cross(w));
+        if det.abs() <= 1e-12 * u.norm() * v.norm() * w.norm() {
+            let points = [a, b, c, d];
+            let candidates = [(0, 1, 2), (0, 1, 3), (0, 2, 3), (1, 2, 3)]
+                .map(|(i, j, k)| BoundingSphere::circumscribed3(points[i], points[j], points[k]));
+            return candidates
+                .into_iter()
+                .filter(|s| points.iter().all(|&p| s.contains_approximately(p)))
+                .min_by(|s, t| s.radius.total_cmp(&t.radius))
+                .unwrap_or(candidates[0]);
+        }
+        let offset = (v.cross(w) * u.dot(u) + w.cross(u) * v.dot(v) + u.cross(v) * w.dot(w)) / (2.0 * det);
+        BoundingSphere {
+            center: a + offset,
+            radius: offset.norm(),
+        }
+    }
+
+    /// Whether a position lies inside the sphere, allowing for rounding errors.
+    fn contains_approximately(&self, position: Position) -> bool {
+        self.center.distance(position) <= self.radius * (1.0 + 1e-9) + 1e-12
+    }
+}
+
+/// The convex hull of the atoms of a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvexHull {
+    /// The indices of the atoms at the corners of the hull, sorted.
+    pub vertices: Vec<usize>,
+    /// The triangular facets as atom indices, in counterclockwise order seen from outside the hull.
+    pub facets: Vec<[usize; 3]>,
+    positions: Vec<Position>,
+}
+impl ConvexHull {
+    /// The unit normal of a facet, pointing out of the hull.
+    pub fn normal(&self, facet: [usize; 3]) -> Position {
+        let [a, b, c] = facet.map(|i| self.positions[i]);
+        (b - a).cross(c - a).normalized().unwrap_or_default()
+    }
+
+    /// The surface area in Å².
+    pub fn area(&self) -> f64 {
+        self.facets
+            .iter()
+            .map(|facet| {
+                let [a, b, c] = facet.map(|i| self.positions[i]);
+                (b - a).cross(c - a).norm() / 2.0
+            })
+            .sum()
+    }
+
+    /// The enclosed volume in Å³.
+    pub fn volume(&self) -> f64 {
+        self.facets
+            .iter()
+            .map(|facet| {
+                let [a, b, c] = facet.map(|i| self.positions[i]);
+                a.dot(b.cross(c)) / 6.0
+            })
+            .sum()
+    }
+
+    /// Whether a position lies inside the hull, including its surface.
+    pub fn contains(&self, position: Position) -> bool {
+        self.facets
+            .iter()
+            .all(|&facet| self.normal(facet).dot(position - self.positions[facet[0]]) <= 1e-9)
+    }
+}
+
+impl Data {
+    /// The smallest sphere containing all atom positions, or `None` if there are no atoms.
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        let mut points: Vec<Position> = self.atoms.iter().map(|atom| atom.position).collect();
+        // Welzl's algorithm takes expected linear time for points in random order.
+        Rng::new(0).shuffle(&mut points);
+
+        let mut sphere = BoundingSphere::point(*points.first()?);
+        for i in 1..points.len() {
+            if sphere.contains_approximately(points[i]) {
+                continue;
+            }
+            sphere = BoundingSphere::point(points[i]);
+            for j in 0..i {
+                if sphere.contains_approximately(points[j]) {
+                    continue;
+                }
+                sphere = BoundingSphere::diameter(points[i], points[j]);
+                for k in 0..j {
+                    if sphere.contains_approximately(points[k]) {
+                        continue;
+                    }
+                    sphere = BoundingSphere::circumscribed3(points[i], points[j], points[k]);
+                    for l in 0..k {
+                        if !sphere.contains_approximately(points[l]) {
+                            sphere = BoundingSphere::circumscribed4(points[i], points[j], points[k], points[l]);
+                        }
+                    }
+                }
+            }
+        }
+        Some(sphere)
+    }
+
+    /// The convex hull of the atom positions, or `None` if there are fewer than four atoms
+    /// or all atoms lie in a plane.
+    pub fn convex_hull(&self) -> Option<ConvexHull> {
+        let positions: Vec<Position> = self.atoms.iter().map(|atom| atom.position).collect();
+        let bbox = self.bounding_box()?;
+        let eps = 1e-9 * bbox.size().norm().max(1e-9);
+
+        // Start from a tetrahedron of atoms far apart.
+        let farthest = |score: &dyn Fn(Position) -> f64| {
+            (0..positions.len()).max_by(|&i, &j| score(positions[i]).total_cmp(&score(positions[j])))
+        };
+        let a = 0;
+        let b = farthest(&|p| p.distance(positions[a]))?;
+        let ab = positions[b] - positions[a];
+        let c = farthest(&|p| ab.cross(p - positions[a]).norm())?;
+        let normal = ab.cross(positions[c] - positions[a]).normalized()?;
+        let d = farthest(&|p| normal.dot(p - positions[a]).abs())?;
+        if positions[a].distance(positions[b]) <= eps || normal.dot(positions[d] - positions[a]).abs() <= eps {
+            return None;
+        }
+        let mut facets = if normal.dot(positions[d] - positions[a]) < 0.0 {
+            vec![[a, b, c], [a, d, b], [b, d, c], [c, d, a]]
+        } else {
+            vec![[a, c, b], [a, b, d], [b, c, d], [c, a, d]]
+        };
+
+        let outside = |facet: &[usize; 3], p: Position| {
+            let [a, b, c] = facet.map(|i| positions[i]);
+            (b - a).cross(c - a).normalized().is_some_and(|n| n.dot(p - a) > eps)
+        };
+        for p in (0..positions.len()).filter(|&p| ![a, b, c, d].contains(&p)) {
+            let (visible, hidden): (Vec<[usize; 3]>, Vec<[usize; 3]>) =
+                facets.iter().partition(|facet| outside(facet, positions[p]));
+            if visible.is_empty() {
+                continue;
+            }
+            // The edges of the visible facets that are not shared by two of them form the horizon,
+            // which is connected to the new vertex.
+            let edges: HashSet<(usize, usize)> =
+                visible.iter().flat_map(|&[a, b, c]| [(a, b), (b, c), (c, a)]).collect();
+            facets = hidden;
+            for &(i, j) in edges.iter().filter(|&&(i, j)| !edges.contains(&(j, i))) {
+                facets.push([i, j, p]);
+            }
+        }
+
+        let mut vertices: Vec<usize> = facets.iter().flatten().copied().collect();
+        vertices.sort_unstable();
+        vertices.dedup();
+        Some(ConvexHull {
+            vertices,
+            facets,
+            positions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{frame, water};
+
+    fn cube() -> Data {
+        let mut atoms = vec![("X", [0.5, 0.5, 0.5])];
+        for i in 0..8 {
+            let corner = [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|bit| bit as f64);
+            atoms.push(("C", corner));
+        }
+        frame("cube", &atoms)
+    }
+
+    #[test]
+    fn bounding_spheres() {
+        let sphere = cube().bounding_sphere().unwrap();
+        assert!(sphere.center.distance(Position([0.5, 0.5, 0.5])) < 1e-9);
+        assert!((sphere.radius - 3f64.sqrt() / 2.0).abs() < 1e-9);
+
+        let line = frame("", &[("C", [0.0, 0.0, 0.0]), ("C", [1.0, 0.0, 0.0]), ("C", [4.0, 0.0, 0.0])]);
+        let sphere = line.bounding_sphere().unwrap();
+        assert!((sphere.radius - 2.0).abs() < 1e-9);
+        assert!(sphere.contains(Position([2.0, 0.0, 0.0])));
+
+        let water = water().bounding_sphere().unwrap();
+        assert!(water.intersects(&sphere));
+        assert!(frame("", &[]).bounding_sphere().is_none());
+    }
+
+    #[test]
+    fn convex_hulls() {
+        let hull = cube().convex_hull().unwrap();
+        assert_eq!(hull.vertices, (1..9).collect::<Vec<_>>());
+        assert_eq!(hull.facets.len(), 12);
+        assert!((hull.area() - 6.0).abs() < 1e-9);
+        assert!((hull.volume() - 1.0).abs() < 1e-9);
+        assert!(hull.contains(Position([0.2, 0.9, 0.5])));
+        assert!(!hull.contains(Position([1.2, 0.5, 0.5])));
+        let top = hull.facets.iter().find(|facet| facet.iter().all(|&i| i >= 5)).unwrap();
+        assert!((hull.normal(*top).0[2] - 1.0).abs() < 1e-9);
+
+        assert!(water().convex_hull().is_none());
+    }
+}
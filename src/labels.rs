@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use super::element::Element;
+
+/// Resolves nonstandard atom labels, such as the force field labels `CA`, `HW1` or `OW`, to elements.
+///
+/// Labels are resolved by looking them up in the alias map first, then as element symbols,
+/// and finally as element symbols followed by digits (e.g. `C12`).
+#[derive(Debug, Clone, Default)]
+pub struct LabelMap {
+    aliases: HashMap<String, Element>,
+}
+impl LabelMap {
+    /// Creates an empty map, which only resolves element symbols.
+    pub fn new() -> LabelMap {
+        LabelMap::default()
+    }
+
+    /// Creates a map with common labels of water models and protein force fields.
+    pub fn common() -> LabelMap {
+        let mut map = LabelMap::new();
+        let aliases = [
+            ("OW", "O"),
+            ("HW", "H"),
+            ("HW1", "H"),
+            ("HW2", "H"),
+            ("CA", "C"),
+            ("CB", "C"),
+            ("CG", "C"),
+            ("CD", "C"),
+            ("CE", "C"),
+            ("CZ", "C"),
+            ("HA", "H"),
+            ("HB", "H"),
+            ("HN", "H"),
+            ("NZ", "N"),
+            ("OG", "O"),
+            ("OH", "O"),
+            ("SG", "S"),
+            ("SD", "S"),
+        ];
+        for (label, symbol) in aliases {
+            map.insert(label, Element::from_symbol(symbol).unwrap());
+        }
+        map
+    }
+
+    /// Adds an alias, replacing any previous alias for the label.
+    pub fn insert<S: Into<String>>(&mut self, label: S, element: Element) {
+        self.aliases.insert(label.into(), element);
+    }
+
+    /// Resolves a label to an element.
+    pub fn resolve(&self, label: &str) -> Option<Element> {
+        if let Some(element) = self.aliases.get(label) {
+            return Some(*element);
+        }
+        Element::from_symbol(label).or_else(|| {
+            let stripped = label.trim_end_matches(|c: char| c.is_ascii_digit());
+            if stripped.is_empty() || stripped == label {
+                None
+            } else {
+                Element::from_symbol(stripped)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_labels() {
+        let mut map = LabelMap::common();
+        let element = |symbol| Element::from_symbol(symbol);
+        assert_eq!(map.resolve("OW"), element("O"));
+        assert_eq!(map.resolve("CA"), element("C"));
+        assert_eq!(map.resolve("Fe"), element("Fe"));
+        assert_eq!(map.resolve("C12"), element("C"));
+        assert_eq!(map.resolve("XX1"), None);
+        assert_eq!(map.resolve("12"), None);
+        map.insert("CA", element("Ca").unwrap());
+        assert_eq!(map.resolve("CA"), element("Ca"));
+        assert_eq!(LabelMap::new().resolve("OW"), None);
+    }
+}
@@ -1,93 +1,234 @@
+pub mod analysis;
+pub mod anomaly;
+pub mod bonds;
+pub mod build;
+pub mod cache;
+pub mod cell;
 pub mod data;
-mod error;
+pub mod database;
+pub mod dataset;
+#[cfg(feature = "dcd")]
+pub mod dcd;
+pub mod delta;
+pub mod diff;
+pub mod edit;
+pub mod element;
+pub mod error;
+pub mod geometry;
+pub mod hull;
+pub mod labels;
+pub mod metadata;
+pub mod ml;
+pub mod neb;
+pub mod position;
+pub mod provenance;
+pub mod qmmm;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod render;
+pub mod scan;
+pub mod select;
+pub mod shared;
+pub mod single;
+pub mod sniff;
+pub mod stereo;
+pub mod tags;
+pub mod template;
+pub mod units;
 
+mod extended;
+mod follow;
+mod math;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod random;
+mod reader;
+#[cfg(test)]
+mod testing;
+mod trajectory;
+mod voronoi;
+mod writer;
+
+use std::cmp::Ordering;
 use std::fs;
-use std::path::Path;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub use follow::{follow, Follow, FollowOptions};
+pub use reader::{Dialect, Frames, ParseOptions, Skipped};
+pub use trajectory::{TrajectoryOptions, TrajectoryWriter};
+pub use writer::{
+    write_file, write_frame, write_frame_at, CommentField, CommentFn, CommentPolicy, NonAsciiPolicy, WriteOptions,
+};
 
 type AtomCount = usize;
-type AtomPosition = [f64; 3];
+type AtomPosition = position::Position;
 
 /// Reads a `.xyz` file to a `file` struct.
+/// With the `remote` feature, the path can also be an HTTP(S) or S3 URL, see [`remote`].
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
 pub fn read<P: AsRef<Path>>(path: P) -> error::Result<data::File> {
-    let contents = fs::read_to_string(path)?;
-    let mut lines = contents.lines();
-    let mut line = lines.next();
-    let mut line_count = 1;
+    read_with(path, &ParseOptions::default())
+}
 
-    let mut file = data::File {
-        data: vec![],
-    };
+/// Reads a `.xyz` file to a `file` struct, parsed with `options`.
+/// With the `remote` feature, the path can also be an HTTP(S) or S3 URL, see [`remote`].
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
+pub fn read_with<P: AsRef<Path>>(path: P, options: &ParseOptions) -> error::Result<data::File> {
+    #[cfg(feature = "remote")]
+    if let Some(url) = path.as_ref().to_str().filter(|path| remote::is_url(path)) {
+        return collect_limited(remote::frames_with(url, options)?, options.max_memory);
+    }
+    collect_limited(frames_with(path, options)?, options.max_memory)
+}
 
-    while line.is_some() {
-        let count: AtomCount = match line.ok_or(error::FileParseError::InvalidAtomCount(line_count))?.parse() {
-            Ok(n) => n,
-            Err(_) => return Err(error::FileParseError::InvalidAtomCount(line_count)),
-        };
+/// Collects frames, failing once their estimated memory exceeds `limit` bytes.
+fn collect_limited(
+    frames: impl Iterator<Item = error::Result<data::Data>>,
+    limit: Option<usize>,
+) -> error::Result<data::File> {
+    let mut data = vec![];
+    let mut memory = 0;
+    for frame in frames {
+        let frame = frame?;
+        if let Some(limit) = limit {
+            memory += frame.estimated_memory();
+            if memory > limit {
+                return Err(error::FileParseError::MemoryLimit(limit));
+            }
+        }
+        data.push(frame);
+    }
+    Ok(data::File { data })
+}
 
-        line = lines.next();
-        line_count += 1;
+/// Reads a `.xyz` file into memory like [`read_with`] if its frames fit into [`ParseOptions::max_memory`],
+/// or indexes it as a [`Database`](database::Database) that reads frames from the file on demand otherwise,
+/// e.g. to analyze trajectories of any size without running out of memory.
+/// The file is read once: the index is built while reading, and the frames read so far are dropped once they
+/// exceed the limit. URLs are only read into memory, failing with [`error::FileParseError::MemoryLimit`]
+/// if they do not fit.
+pub fn read_stored<P: AsRef<Path>>(path: P, options: &ParseOptions) -> error::Result<database::FrameStore> {
+    #[cfg(feature = "remote")]
+    if path.as_ref().to_str().is_some_and(remote::is_url) {
+        return read_with(path, options).map(database::FrameStore::Memory);
+    }
+    let mut database = database::Database::empty(&path, options.clone());
+    let mut frames = frames_with(&path, options)?;
+    let (mut data, mut memory, mut offset) = (Some(vec![]), 0, 0);
+    while let Some(frame) = frames.next() {
+        let frame = frame?;
+        database.push(offset, &frame);
+        offset = frames.byte_offset();
+        memory += frame.estimated_memory();
+        if options.max_memory.is_some_and(|limit| memory > limit) {
+            data = None;
+        }
+        if let Some(data) = &mut data {
+            data.push(frame);
+        }
+    }
+    Ok(match data {
+        Some(data) => database::FrameStore::Memory(data::File { data }),
+        None => database::FrameStore::Disk(database),
+    })
+}
 
-        let comment: String = line.unwrap_or("").to_string();
+/// Reads a `.xyz` file to a `file` struct, parsed with the options recommended by [`sniff::sniff`]
+/// for the start of the file, e.g. for files of unknown origin.
+pub fn read_auto<P: AsRef<Path>>(path: P) -> error::Result<data::File> {
+    let report = sniff::sniff(&path)?;
+    read_with(path, &report.options)
+}
 
-        let mut data = data::Data {
-            count,
-            comment,
-            atoms: vec![],
-        };
+/// Reads the frames of a `.xyz` file that could be parsed, resynchronizing after corrupted regions
+/// with the [`Dialect::Permissive`] dialect, and returns them with the skipped regions.
+pub fn read_recovering<P: AsRef<Path>>(path: P) -> error::Result<(data::File, Vec<Skipped>)> {
+    let options = ParseOptions {
+        dialect: Dialect::Permissive,
+        ..ParseOptions::default()
+    };
+    let mut frames = frames_with(path, &options)?;
+    let data = frames.by_ref().collect::<error::Result<Vec<data::Data>>>()?;
+    Ok((data::File { data }, frames.skipped().to_vec()))
+}
+
+/// Opens a `.xyz` file for reading one frame at a time, without loading the whole file into memory.
+pub fn frames<P: AsRef<Path>>(path: P) -> error::Result<Frames<BufReader<fs::File>>> {
+    frames_with(path, &ParseOptions::default())
+}
 
-        for _ in 0..count {
-            line = lines.next();
-            line_count += 1;
+/// Opens a `.xyz` file for reading one frame at a time, parsed with `options`.
+pub fn frames_with<P: AsRef<Path>>(path: P, options: &ParseOptions) -> error::Result<Frames<BufReader<fs::File>>> {
+    Ok(Frames::with_options(BufReader::new(fs::File::open(path)?), options.clone()))
+}
 
-            let mut data_line = match line {
-                Some(s) => s.split_whitespace(),
-                None => return Err(error::FileParseError::EmptyLine(line_count)),
-            };
+/// Reads every `.xyz` file matching a glob pattern, e.g. `"frames/*.xyz"`, into a single `file` struct.
+/// The files are read in natural order, so `frame_2.xyz` comes before `frame_10.xyz`.
+#[cfg_attr(feature = "tracing", tracing::instrument(err))]
+pub fn read_glob(pattern: &str) -> error::Result<data::File> {
+    Ok(data::File {
+        data: frames_glob(pattern)?.collect::<error::Result<Vec<data::Data>>>()?,
+    })
+}
 
-            data.atoms.push(data::AtomData {
-                symbol: data_line.next().ok_or(error::FileParseError::NoAtomSymbol(line_count))?.to_string(),
-                position: splitwhitespace_to_position(&mut data_line, &line_count)?,
-            });
-        }
+/// Iterates over the frames of every `.xyz` file matching a glob pattern, one frame at a time.
+/// The files are read in natural order, and each file is only opened once the previous one is exhausted.
+pub fn frames_glob(pattern: &str) -> error::Result<impl Iterator<Item = error::Result<data::Data>>> {
+    let mut paths = glob::glob(pattern)
+        .map_err(|e| error::FileParseError::InvalidPattern(e.to_string()))?
+        .collect::<Result<Vec<PathBuf>, glob::GlobError>>()
+        .map_err(std::io::Error::from)?;
+    paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
 
-        file.data.push(data);
+    Ok(paths.into_iter().flat_map(|path| match frames(path) {
+        Ok(frames) => Box::new(frames) as Box<dyn Iterator<Item = error::Result<data::Data>>>,
+        Err(e) => Box::new(std::iter::once(Err(e))),
+    }))
+}
 
-        line = lines.next();
-        line_count += 1;
+/// Compares strings such that runs of digits are compared by their numeric value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
 
-        match line {
-            Some(s) => if !s.is_empty() {
-                return Err(error::FileParseError::UnexpectedData(line_count));
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_digits(&mut a);
+                let y = take_digits(&mut b);
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
             }
-            None => {
-                break
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
             }
         }
-        line = lines.next();
-        line_count += 1;
     }
-
-    Ok(file)
 }
 
-fn splitwhitespace_to_position(data: &mut std::str::SplitWhitespace, line: &usize) -> error::Result<AtomPosition> {
-    Ok([
-        match data.next().ok_or(error::FileParseError::NoPositionData(*line))?.parse() {
-            Ok(n) => n,
-            Err(_) => return Err(error::FileParseError::InvalidPositionData(*line)),
-        },
-        match data.next().ok_or(error::FileParseError::NoPositionData(*line))?.parse() {
-            Ok(n) => n,
-            Err(_) => return Err(error::FileParseError::InvalidPositionData(*line)),
-        },
-        match data.next().ok_or(error::FileParseError::NoPositionData(*line))?.parse() {
-            Ok(n) => n,
-            Err(_) => return Err(error::FileParseError::InvalidPositionData(*line)),
-        },
-    ])
+/// Takes a run of digits, without leading zeros.
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        if !(digits.is_empty() && c == '0') {
+            digits.push(c);
+        }
+    }
+    digits
 }
 
 /// Writes a `.xyz` file from a `file` struct.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
 pub fn write<P: AsRef<Path>>(path: P, file: data::File) -> std::io::Result<()> {
     let mut contents = String::new();
 
@@ -97,3 +238,134 @@ pub fn write<P: AsRef<Path>>(path: P, file: data::File) -> std::io::Result<()> {
 
     fs::write(path, contents)
 }
+
+/// Writes a `.xyz` file from a `file` struct, formatted with `options`.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
+pub fn write_with<P: AsRef<Path>>(path: P, file: &data::File, options: &WriteOptions) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    write_file(&mut writer, file, options)?;
+    writer.flush()
+}
+
+/// Writes a `.xyz` file like [`write_with`] with the default options, but safely replacing an existing file.
+pub fn write_atomic<P: AsRef<Path>>(path: P, file: &data::File) -> std::io::Result<()> {
+    write_atomic_with(path, file, &WriteOptions::default())
+}
+
+/// Writes a `.xyz` file formatted with `options` to a temporary file in the same directory,
+/// and renames it to `path` once it is completely written and stored on disk.
+/// An interrupted write leaves any existing file at `path` untouched instead of truncated.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %path.as_ref().display()), err))]
+pub fn write_atomic_with<P: AsRef<Path>>(path: P, file: &data::File, options: &WriteOptions) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let name = path.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+    let temporary = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+
+    let result = (|| {
+        let mut writer = BufWriter::new(fs::File::create(&temporary)?);
+        write_file(&mut writer, file, options)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temporary, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+    use crate::testing::trajectory;
+
+    #[test]
+    fn read_glob_in_natural_order() {
+        let directory = temp_path("glob");
+        fs::create_dir_all(&directory).unwrap();
+        for step in [1, 2, 10] {
+            let frame = format!("1\nstep={}\nH 0.0 0.0 {}.0\n", step, step);
+            fs::write(directory.join(format!("frame_{}.xyz", step)), frame).unwrap();
+        }
+        let file = read_glob(&format!("{}/frame_*.xyz", directory.display())).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        let comments: Vec<&str> = file.data.iter().map(|data| data.comment.as_str()).collect();
+        assert_eq!(comments, ["step=1", "step=2", "step=10"]);
+    }
+
+    #[test]
+    fn write_atomic_replaces_files() {
+        let directory = temp_path("atomic");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("out.xyz");
+        fs::write(&path, "old").unwrap();
+        write_atomic(&path, &trajectory(1)).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("3\nstep=0\n"));
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+
+        assert!(write_atomic(directory.join("missing").join("out.xyz"), &trajectory(1)).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn read_recovering_reports_skipped_regions() {
+        let path = temp_path("recovering.xyz");
+        fs::write(&path, "1\nfirst\nHe 0 0 0\n2\ncut off\nHe 0 0 0\n1\nlast\nHe 1 0 0\n").unwrap();
+        let (file, skipped) = read_recovering(&path).unwrap();
+        let comments: Vec<&str> = file.data.iter().map(|data| data.comment.as_str()).collect();
+        assert_eq!(comments, ["first", "last"]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].lines, (4, 6));
+        assert!(read(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn memory_limits_and_stored_frames() {
+        let path = temp_path("stored.xyz");
+        let text: String = (0..20).map(|i| format!("3\nstep={}\nO 0 0 0\nH 0.96 0 0\nH -0.24 0.93 0\n\n", i)).collect();
+        fs::write(&path, text).unwrap();
+        let frame = read(&path).unwrap().data.swap_remove(0);
+        let memory = frame.estimated_memory();
+        assert!(memory > size_of::<data::Data>() + 3 * size_of::<data::AtomData>());
+
+        let limited = |max_memory| ParseOptions {
+            max_memory: Some(max_memory),
+            ..ParseOptions::default()
+        };
+        assert!(matches!(read_with(&path, &limited(10 * memory)), Err(error::FileParseError::MemoryLimit(_))));
+        assert_eq!(read_with(&path, &limited(100 * memory)).unwrap().data.len(), 20);
+
+        let stored = read_stored(&path, &limited(100 * memory)).unwrap();
+        assert!(matches!(stored, database::FrameStore::Memory(_)));
+        let stored = read_stored(&path, &limited(10 * memory)).unwrap();
+        assert!(matches!(stored, database::FrameStore::Disk(_)));
+        assert_eq!(stored.len(), 20);
+        assert_eq!(stored.get(19).unwrap().unwrap().comment, "step=19");
+        let comments: Vec<String> = stored.iter().map(|data| data.unwrap().comment).collect();
+        assert_eq!(comments[..2], ["step=0", "step=1"]);
+        assert!(stored.get(20).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stored_frames_are_indexed_while_reading() {
+        let path = temp_path("indexed.xyz");
+        let text: String = (1..=6).map(|n| format!("{}\nn={}\n{}\n", n, n, "He 0 0 0\n".repeat(n))).collect();
+        fs::write(&path, text).unwrap();
+        let memory: usize = read(&path).unwrap().data.iter().map(|data| data.estimated_memory()).sum();
+        let limited = |max_memory| ParseOptions {
+            max_memory: Some(max_memory),
+            ..ParseOptions::default()
+        };
+        let database::FrameStore::Disk(database) = read_stored(&path, &limited(memory / 2)).unwrap() else {
+            panic!("the frames exceed the limit");
+        };
+        assert_eq!(database.atom_counts(), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(database.comments()[5], "n=6");
+        assert_eq!(database.get(4).unwrap().unwrap().atoms.len(), 5);
+        assert!(matches!(read_stored(&path, &limited(memory)).unwrap(), database::FrameStore::Memory(_)));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+//! Small dense linear algebra helpers.
+
+/// A 3×3 matrix, stored by rows.
+pub(crate) type Mat3 = [[f64; 3]; 3];
+
+pub(crate) fn det(m: &Mat3) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// The inverse of a matrix, or `None` if it is singular.
+pub(crate) fn inverse(m: &Mat3) -> Option<Mat3> {
+    let d = det(m);
+    if d == 0.0 || !d.is_finite() {
+        return None;
+    }
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    Some([
+        [cofactor(1, 2, 1, 2) / d, -cofactor(0, 2, 1, 2) / d, cofactor(0, 1, 1, 2) / d],
+        [-cofactor(1, 2, 0, 2) / d, cofactor(0, 2, 0, 2) / d, -cofactor(0, 1, 0, 2) / d],
+        [cofactor(1, 2, 0, 1) / d, -cofactor(0, 2, 0, 1) / d, cofactor(0, 1, 0, 1) / d],
+    ])
+}
+
+pub(crate) fn transpose(m: &Mat3) -> Mat3 {
+    std::array::from_fn(|i| std::array::from_fn(|j| m[j][i]))
+}
+
+/// The matrix product `a · b`.
+pub(crate) fn mul(a: &Mat3, b: &Mat3) -> Mat3 {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+/// The product `v · m` of a row vector and a matrix.
+pub(crate) fn vec_mul(v: [f64; 3], m: &Mat3) -> [f64; 3] {
+    std::array::from_fn(|j| (0..3).map(|k| v[k] * m[k][j]).sum())
+}
+
+/// The eigenvalues of a symmetric matrix, in no particular order, computed with cyclic Jacobi rotations.
+pub(crate) fn symmetric_eigenvalues(a: Vec<Vec<f64>>) -> Vec<f64> {
+    symmetric_eigen(a).0
+}
+
+/// The eigenvalues of a symmetric matrix, in no particular order, and the matching unit eigenvectors
+/// as the columns of the second matrix, computed with cyclic Jacobi rotations.
+pub(crate) fn symmetric_eigen(mut a: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    for _ in 0..100 {
+        let off: f64 = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).map(|(i, j)| a[i][j] * a[i][j]).sum();
+        let scale: f64 = (0..n).map(|i| a[i][i] * a[i][i]).sum();
+        if off <= f64::EPSILON * f64::EPSILON * scale || off == 0.0 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in a.iter_mut().chain(v.iter_mut()) {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (head, tail) = a.split_at_mut(q);
+                for (pk, qk) in head[p].iter_mut().zip(tail[0].iter_mut()) {
+                    (*pk, *qk) = (c * *pk - s * *qk, s * *pk + c * *qk);
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i][i]).collect(), v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eigenvalues_of_symmetric_matrix() {
+        let matrix = vec![vec![2.0, 1.0, 0.0], vec![1.0, 2.0, 0.0], vec![0.0, 0.0, 5.0]];
+        let mut eigenvalues = symmetric_eigenvalues(matrix);
+        eigenvalues.sort_by(f64::total_cmp);
+        assert!(eigenvalues.iter().zip([1.0, 3.0, 5.0]).all(|(a, b)| (a - b).abs() < 1e-12), "{:?}", eigenvalues);
+    }
+
+    #[test]
+    fn eigenvectors_of_symmetric_matrix() {
+        let a = vec![vec![4.0, 1.0, 2.0], vec![1.0, 3.0, 0.0], vec![2.0, 0.0, 5.0]];
+        let (values, vectors) = symmetric_eigen(a.clone());
+        for (k, value) in values.iter().enumerate() {
+            let v: Vec<f64> = (0..3).map(|i| vectors[i][k]).collect();
+            assert!((v.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-12);
+            for (row, x) in a.iter().zip(&v) {
+                let av: f64 = row.iter().zip(&v).map(|(a, b)| a * b).sum();
+                assert!((av - value * x).abs() < 1e-9);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Reads `key=value` metadata from comment lines, as written by extended XYZ files and many simulation codes,
+//! e.g. `energy=-76.4 step=100 Lattice="3.1 0 0 0 3.1 0 0 0 3.1"`.
+
+use super::data::{Data, File};
+
+impl Data {
+    /// The `key=value` pairs of the comment, in order. Values may be quoted with double quotes to contain spaces.
+    pub fn properties(&self) -> Vec<(String, String)> {
+        parse_properties(&self.comment)
+    }
+
+    /// The value of the comment property `key`, compared case-insensitively.
+    pub fn property(&self, key: &str) -> Option<String> {
+        self.properties()
+            .into_iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    /// The comment property `key` parsed as a number.
+    pub fn numeric_property(&self, key: &str) -> Option<f64> {
+        self.property(key)?.parse().ok()
+    }
+
+    /// The energy from the `energy` property of the comment.
+    pub fn energy(&self) -> Option<f64> {
+        self.numeric_property("energy")
+    }
+
+    /// The simulation step or time of the frame, from the first of the `step`, `timestep` or `time` properties.
+    pub fn step(&self) -> Option<f64> {
+        ["step", "timestep", "time"].iter().find_map(|key| self.numeric_property(key))
+    }
+}
+
+impl File {
+    /// Drops frames repeated at the restart boundaries of a simulation, keeping the later occurrence:
+    /// when the step of a frame is not greater than that of the frames before it, those frames are dropped.
+    /// Frames without a step (see [`Data::step`]) are kept, and end the search for repeated frames before them.
+    /// Returns the number of dropped frames.
+    pub fn dedup_by_step(&mut self) -> usize {
+        let before = self.data.len();
+        let mut kept: Vec<(Option<f64>, Data)> = Vec::with_capacity(before);
+        for data in self.data.drain(..) {
+            let step = data.step();
+            if let Some(step) = step {
+                while kept.last().is_some_and(|(last, _)| last.is_some_and(|last| last >= step)) {
+                    kept.pop();
+                }
+            }
+            kept.push((step, data));
+        }
+        self.data = kept.into_iter().map(|(_, data)| data).collect();
+        before - self.data.len()
+    }
+}
+
+/// Splits a comment into `key=value` pairs, skipping words without `=`.
+pub(crate) fn parse_properties(comment: &str) -> Vec<(String, String)> {
+    let mut properties = vec![];
+    let mut rest = comment.trim_start();
+
+    while !rest.is_empty() {
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let Some(eq) = rest[..word_end].find('=') else {
+            rest = rest[word_end..].trim_start();
+            continue;
+        };
+
+        let key = rest[..eq].to_string();
+        let after = &rest[eq + 1..];
+        let (value, remainder) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+
+        properties.push((key, value.to_string()));
+        rest = remainder.trim_start();
+    }
+
+    properties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_properties() {
+        let comment = r#"Lattice="3.1 0 0 0 3.1 0 0 0 3.1" Energy=-76.4 step=100 converged name="" tag"#;
+        assert_eq!(
+            parse_properties(comment),
+            [("Lattice", "3.1 0 0 0 3.1 0 0 0 3.1"), ("Energy", "-76.4"), ("step", "100"), ("name", "")]
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+        let data = crate::testing::frame(comment, &[]);
+        assert_eq!(data.energy(), Some(-76.4));
+        assert_eq!(data.numeric_property("STEP"), Some(100.0));
+        assert_eq!(data.property("converged"), None);
+    }
+
+    #[test]
+    fn dedup_restarted_trajectory() {
+        let steps = ["step=0", "step=10", "step=20", "timestep=10", "time=20", "step=30", "no step", "step=5"];
+        let mut file = File {
+            data: steps.iter().map(|comment| crate::testing::frame(comment, &[])).collect(),
+        };
+        assert_eq!(file.data[3].step(), Some(10.0));
+        assert_eq!(file.dedup_by_step(), 2);
+        let comments: Vec<&str> = file.data.iter().map(|data| data.comment.as_str()).collect();
+        assert_eq!(comments, ["step=0", "timestep=10", "time=20", "step=30", "no step", "step=5"]);
+    }
+}
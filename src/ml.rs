@@ -0,0 +1,415 @@
+//! Exports trajectories with energies and forces as datasets for training machine-learning potentials,
+//! and computes simple structure descriptors.
+//!
+//! Energies are read from the `energy` property of the comment and forces from the `forces` column
+//! of extended XYZ files, in the units given by the `energy_unit` and `force_unit` properties,
+//! see [`units`](crate::units).
+//! They are exported in eV and eV/Å, or in the units passed to the `_in` functions.
+
+use std::fs;
+use std::io::{self, Error, ErrorKind, Write};
+use std::path::Path;
+
+use super::data::{Data, File};
+use super::math;
+use super::position::Position;
+use super::tags::UNTAGGED;
+use super::units::{EnergyUnit, ForceUnit};
+use super::writer::format_number;
+
+/// Writes the frames as ASE-compatible extended XYZ, with the energy and forces where present.
+/// Other `key=value` properties of the comments are kept, and partial charges and atom tags are written as columns.
+pub fn write_extxyz<W: Write>(writer: &mut W, file: &File) -> io::Result<()> {
+    write_extxyz_in(writer, file, EnergyUnit::default(), ForceUnit::default())
+}
+
+/// Writes the frames as extended XYZ like [`write_extxyz`], with the energies and forces converted to
+/// `energy_unit` and `force_unit`. Units other than eV and eV/Å are stated in the comments.
+pub fn write_extxyz_in<W: Write>(
+    writer: &mut W,
+    file: &File,
+    energy_unit: EnergyUnit,
+    force_unit: ForceUnit,
+) -> io::Result<()> {
+    for (i, data) in file.data.iter().enumerate() {
+        let forces = forces_in(data, i, force_unit)?;
+        let has_forces = !data.atoms.is_empty() && forces.iter().all(Option::is_some);
+        let has_charges = !data.atoms.is_empty() && data.atoms.iter().all(|atom| atom.charge.is_some());
+        let layers = data.tag_layers();
+
+        let mut comment = String::new();
+        if let Some(cell) = &data.cell {
+            comment.push_str(&cell.to_comment());
+            comment.push(' ');
+        }
+        comment.push_str("Properties=species:S:1:pos:R:3");
+        if has_forces {
+            comment.push_str(":forces:R:3");
+        }
+        if has_charges {
+            comment.push_str(":charges:R:1");
+        }
+        for layer in &layers {
+            comment.push_str(&format!(":{}:S:1", layer));
+        }
+        if let Some(energy) = energy_in(data, i, energy_unit)? {
+            comment.push_str(&format!(" energy={}", energy));
+        }
+        if energy_unit != EnergyUnit::default() {
+            comment.push_str(&format!(" energy_unit={}", energy_unit));
+        }
+        if force_unit != ForceUnit::default() && has_forces {
+            comment.push_str(&format!(" force_unit={}", force_unit));
+        }
+        for (key, value) in data.properties() {
+            let skipped = ["lattice", "properties", "energy", "energy_unit", "force_unit", "pbc"];
+            if skipped.contains(&key.to_ascii_lowercase().as_str()) {
+                continue;
+            }
+            if value.contains(char::is_whitespace) || value.is_empty() {
+                comment.push_str(&format!(" {}=\"{}\"", key, value));
+            } else {
+                comment.push_str(&format!(" {}={}", key, value));
+            }
+        }
+        comment.push_str(if data.cell.is_some() { " pbc=\"T T T\"" } else { " pbc=\"F F F\"" });
+
+        let mut buf = format!("{}\n{}\n", data.atoms.len(), comment);
+        for (atom, force) in data.atoms.iter().zip(&forces) {
+            buf.push_str(&atom.symbol);
+            let force = force.filter(|_| has_forces);
+            for v in atom.position.0.into_iter().chain(force.into_iter().flat_map(|f| f.0)) {
+                buf.push(' ');
+                format_number(&mut buf, v, None);
+            }
+            if let Some(charge) = atom.charge.filter(|_| has_charges) {
+                buf.push(' ');
+                format_number(&mut buf, charge, None);
+            }
+            for layer in &layers {
+                buf.push(' ');
+                buf.push_str(atom.tag(layer).unwrap_or(UNTAGGED));
+            }
+            buf.push('\n');
+        }
+        writer.write_all(buf.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes the frames as a NumPy `.npz` archive in the layout used by NequIP, with the arrays
+/// `pos` (frames × atoms × 3), `z` (frames × atoms) and, if present for every frame,
+/// `energy` (frames), `forces` (frames × atoms × 3) and `cell` (frames × 3 × 3).
+///
+/// All frames must have the same number of atoms, and all atomic symbols must be known elements.
+pub fn write_npz<P: AsRef<Path>>(path: P, file: &File) -> io::Result<()> {
+    write_npz_in(path, file, EnergyUnit::default(), ForceUnit::default())
+}
+
+/// Writes the frames as a NumPy `.npz` archive like [`write_npz`], with the energies and forces converted to
+/// `energy_unit` and `force_unit`.
+pub fn write_npz_in<P: AsRef<Path>>(
+    path: P,
+    file: &File,
+    energy_unit: EnergyUnit,
+    force_unit: ForceUnit,
+) -> io::Result<()> {
+    let frames = file.data.len();
+    let atoms = file.data.first().map_or(0, |data| data.atoms.len());
+    if let Some(i) = file.data.iter().position(|data| data.atoms.len() != atoms) {
+        return Err(invalid(&format!("frame {} has {} atoms, expected {}", i, file.data[i].atoms.len(), atoms)));
+    }
+
+    let mut numbers = Vec::with_capacity(frames * atoms);
+    for (i, data) in file.data.iter().enumerate() {
+        for atom in &data.atoms {
+            let element = atom
+                .element()
+                .ok_or_else(|| invalid(&format!("unknown element {} in frame {}", atom.symbol, i)))?;
+            numbers.push(element.atomic_number() as i64);
+        }
+    }
+
+    let positions: Vec<f64> = file.data.iter().flat_map(|d| d.atoms.iter().flat_map(|a| a.position.0)).collect();
+    let mut arrays = vec![
+        ("pos", npy_f64(&positions, &[frames, atoms, 3])),
+        ("z", npy_i64(&numbers, &[frames, atoms])),
+    ];
+
+    let energies = file.data.iter().enumerate().map(|(i, d)| energy_in(d, i, energy_unit));
+    let energies: Option<Vec<f64>> = energies.collect::<io::Result<Vec<Option<f64>>>>()?.into_iter().collect();
+    if let Some(energies) = energies.filter(|_| frames > 0) {
+        arrays.push(("energy", npy_f64(&energies, &[frames])));
+    }
+
+    let mut forces = Some(Vec::with_capacity(frames * atoms * 3));
+    for (i, data) in file.data.iter().enumerate() {
+        for force in forces_in(data, i, force_unit)? {
+            match (&mut forces, force) {
+                (Some(forces), Some(force)) => forces.extend(force.0),
+                _ => forces = None,
+            }
+        }
+    }
+    if let Some(forces) = forces.filter(|_| frames > 0 && atoms > 0) {
+        arrays.push(("forces", npy_f64(&forces, &[frames, atoms, 3])));
+    }
+
+    let cells: Option<Vec<f64>> = file
+        .data
+        .iter()
+        .map(|d| d.cell.map(|c| c.vectors))
+        .collect::<Option<Vec<_>>>()
+        .map(|cells| cells.iter().flatten().flatten().copied().collect());
+    if let Some(cells) = cells.filter(|_| frames > 0) {
+        arrays.push(("cell", npy_f64(&cells, &[frames, 3, 3])));
+    }
+
+    let entries: Vec<(String, Vec<u8>)> =
+        arrays.into_iter().map(|(name, data)| (format!("{}.npy", name), data)).collect();
+    fs::write(path, zip_stored(&entries)?)
+}
+
+/// The energy of frame `i` in `unit`, or an error if the frame states an unknown unit.
+fn energy_in(data: &Data, i: usize, unit: EnergyUnit) -> io::Result<Option<f64>> {
+    if data.energy().is_none() {
+        return Ok(None);
+    }
+    let energy = data.energy_in(unit).ok_or_else(|| invalid(&format!("unknown energy unit in frame {}", i)))?;
+    Ok(Some(energy))
+}
+
+/// The forces on the atoms of frame `i` in `unit`, or an error if the frame states an unknown unit.
+fn forces_in(data: &Data, i: usize, unit: ForceUnit) -> io::Result<Vec<Option<Position>>> {
+    if data.force_unit().is_none() && data.atoms.iter().any(|atom| atom.force.is_some()) {
+        return Err(invalid(&format!("unknown force unit in frame {}", i)));
+    }
+    Ok(data.forces_in(unit))
+}
+
+/// The Coulomb matrix of a frame, with `0.5 Z_i^2.4` on the diagonal and `Z_i Z_j / |R_i - R_j|` elsewhere,
+/// where distances are in Ångström.
+/// Returns `None` if an atom is not a known element.
+pub fn coulomb_matrix(data: &Data) -> Option<Vec<Vec<f64>>> {
+    let charges = data
+        .atoms
+        .iter()
+        .map(|atom| atom.element().map(|element| element.atomic_number() as f64))
+        .collect::<Option<Vec<f64>>>()?;
+
+    Some(
+        data.atoms
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                data.atoms
+                    .iter()
+                    .enumerate()
+                    .map(|(j, b)| {
+                        if i == j {
+                            0.5 * charges[i].powf(2.4)
+                        } else {
+                            charges[i] * charges[j] / a.position.distance(b.position)
+                        }
+                    })
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+/// The Coulomb matrix with its rows and columns sorted by descending row norm,
+/// which makes it invariant to the order of the atoms.
+pub fn sorted_coulomb_matrix(data: &Data) -> Option<Vec<Vec<f64>>> {
+    let matrix = coulomb_matrix(data)?;
+    let norms: Vec<f64> = matrix.iter().map(|row| row.iter().map(|v| v * v).sum::<f64>().sqrt()).collect();
+    let mut order: Vec<usize> = (0..matrix.len()).collect();
+    order.sort_by(|&i, &j| norms[j].total_cmp(&norms[i]));
+
+    Some(order.iter().map(|&i| order.iter().map(|&j| matrix[i][j]).collect()).collect())
+}
+
+/// The eigenvalues of the Coulomb matrix, sorted by descending absolute value.
+pub fn coulomb_eigenvalues(data: &Data) -> Option<Vec<f64>> {
+    let mut eigenvalues = math::symmetric_eigenvalues(coulomb_matrix(data)?);
+    eigenvalues.sort_by(|a, b| b.abs().total_cmp(&a.abs()));
+    Some(eigenvalues)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+/// Serializes an array in the `.npy` format, version 1.0.
+fn npy(descr: &str, shape: &[usize], data: Vec<u8>) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    // The magic string, version and header length take 10 bytes,
+    // and the header ends with a newline on a 64 byte boundary.
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    npy.extend_from_slice(header.as_bytes());
+    npy.extend(data);
+    npy
+}
+
+fn npy_f64(values: &[f64], shape: &[usize]) -> Vec<u8> {
+    npy("<f8", shape, values.iter().flat_map(|v| v.to_le_bytes()).collect())
+}
+
+fn npy_i64(values: &[i64], shape: &[usize]) -> Vec<u8> {
+    npy("<i8", shape, values.iter().flat_map(|v| v.to_le_bytes()).collect())
+}
+
+/// Packs files into an uncompressed zip archive.
+fn zip_stored(entries: &[(String, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    let too_large = || invalid("npz archive larger than 4 GiB is not supported");
+    let mut archive = vec![];
+    let mut central = vec![];
+
+    for (name, data) in entries {
+        let offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let crc = crc32(data);
+
+        // Version needed, flags, compression (stored), modification time and date.
+        let common = [20u16, 0, 0, 0, 0x21].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        let sizes = [crc, size, size].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+
+        archive.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(&sizes);
+        archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&0u16.to_le_bytes());
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&sizes);
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // Extra field length, comment length, disk number, internal and external attributes.
+        central.extend_from_slice(&[0u8; 12]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+    let central_size = central.len() as u32;
+    archive.extend(central);
+    archive.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0u8; 4]);
+    archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&central_size.to_le_bytes());
+    archive.extend_from_slice(&central_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+
+    Ok(archive)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let table: Vec<u32> = (0..256u32)
+        .map(|mut c| {
+            for _ in 0..8 {
+                c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            }
+            c
+        })
+        .collect();
+    !data.iter().fold(!0u32, |crc, &b| table[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Frames;
+    use crate::position::Position;
+    use crate::testing::{frame, temp_path, trajectory};
+
+    #[test]
+    fn extxyz_round_trips_energy_and_forces() {
+        let mut file = trajectory(3);
+        for (step, data) in file.data.iter_mut().enumerate() {
+            data.comment = format!("energy={} step={}", -76.4 - 0.01 * step as f64, step);
+            for (i, atom) in data.atoms.iter_mut().enumerate() {
+                atom.force = Some(Position::new(0.5 * i as f64, -0.25, 0.01 * step as f64));
+            }
+        }
+        let mut buf = vec![];
+        write_extxyz(&mut buf, &file).unwrap();
+        let read: Vec<Data> = Frames::new(Cursor::new(buf)).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(read.len(), file.data.len());
+        for (original, read) in file.data.iter().zip(&read) {
+            assert_eq!(read.energy(), original.energy());
+            assert_eq!(read.property("step"), original.property("step"));
+            for (a, b) in original.atoms.iter().zip(&read.atoms) {
+                assert_eq!(a.symbol, b.symbol);
+                assert_eq!(a.position, b.position);
+                assert_eq!(a.force, b.force);
+            }
+        }
+    }
+
+    #[test]
+    fn npz_requires_equal_atom_counts_and_known_elements() {
+        let path = temp_path("dataset.npz");
+        write_npz(&path, &trajectory(2)).unwrap();
+        assert_eq!(&fs::read(&path).unwrap()[..4], b"PK\x03\x04");
+
+        let mut file = trajectory(2);
+        file.data.push(frame("", &[("O", [0.0; 3])]));
+        assert_eq!(write_npz(&path, &file).unwrap_err().kind(), ErrorKind::InvalidInput);
+        let file = File {
+            data: vec![frame("", &[("Xx", [0.0; 3])])],
+        };
+        assert_eq!(write_npz(&path, &file).unwrap_err().kind(), ErrorKind::InvalidInput);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn coulomb_matrix_descriptors() {
+        let hydrogen = frame("", &[("H", [0.0; 3]), ("H", [1.0, 0.0, 0.0])]);
+        assert_eq!(coulomb_matrix(&hydrogen).unwrap(), [[0.5, 1.0], [1.0, 0.5]]);
+        let eigenvalues = coulomb_eigenvalues(&hydrogen).unwrap();
+        assert!((eigenvalues[0] - 1.5).abs() < 1e-12 && (eigenvalues[1] + 0.5).abs() < 1e-12);
+
+        let water = crate::testing::water();
+        let mut reordered = water.clone();
+        reordered.atoms.rotate_left(1);
+        assert_ne!(coulomb_matrix(&water), coulomb_matrix(&reordered));
+        assert_eq!(sorted_coulomb_matrix(&water), sorted_coulomb_matrix(&reordered));
+        assert_eq!(coulomb_matrix(&frame("", &[("Xx", [0.0; 3])])), None);
+    }
+
+    #[test]
+    fn extxyz_in_other_units() {
+        let mut data = frame("energy=-1 energy_unit=Ha force_unit=Ha/Bohr", &[("H", [0.0; 3])]);
+        data.atoms[0].force = Some(Position::new(0.0, 0.0, 1.0));
+        let file = File { data: vec![data] };
+        let kcal = EnergyUnit::KcalPerMol;
+        let mut buf = vec![];
+        write_extxyz_in(&mut buf, &file, kcal, ForceUnit::new(kcal, crate::units::LengthUnit::Angstrom)).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let comment = text.lines().nth(1).unwrap();
+        assert!(comment.contains(" energy=-627.509"));
+        assert!(comment.contains(" energy_unit=kcal/mol force_unit=kcal/mol/Ang"));
+        assert!(!comment.contains("=Ha"));
+
+        let mut file = file;
+        file.data[0].comment = "energy=-1 force_unit=Ry/Bohr".to_string();
+        assert_eq!(write_extxyz(&mut vec![], &file).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
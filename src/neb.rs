@@ -0,0 +1,211 @@
+//! Writes the images of a reaction path as nudged elastic band (NEB) inputs for ORCA, VASP and CP2K.
+//!
+//! The images are the frames of a [`File`], from the reactant to the product, which must all have the same atoms
+//! in the same order.
+
+use std::fs;
+use std::io::{self, Error, ErrorKind, Write};
+use std::path::Path;
+
+use super::data::{ChecksPolicy, Data, File};
+
+/// Options for [`write_orca_neb`].
+#[derive(Debug, Clone)]
+pub struct OrcaNebOptions {
+    /// The simple input line, e.g. `! B3LYP def2-SVP NEB-CI`.
+    pub keywords: String,
+    /// The total charge.
+    pub charge: i32,
+    /// The spin multiplicity.
+    pub multiplicity: u32,
+}
+impl Default for OrcaNebOptions {
+    fn default() -> OrcaNebOptions {
+        OrcaNebOptions {
+            keywords: "! XTB2 NEB-CI".to_string(),
+            charge: 0,
+            multiplicity: 1,
+        }
+    }
+}
+
+/// Writes an ORCA NEB calculation to the directory `dir`: the input `neb.inp` with the reactant as its geometry,
+/// the product as `product.xyz`, and every image as the initial path `path.allxyz`.
+/// The number of images in the input is that of the path without its end points.
+pub fn write_orca_neb<P: AsRef<Path>>(dir: P, file: &File, options: &OrcaNebOptions) -> io::Result<()> {
+    check_images(file)?;
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    let (reactant, product) = (&file.data[0], file.data.last().unwrap());
+
+    let mut input = format!("{}\n\n", options.keywords.trim_end());
+    input.push_str("%neb\n");
+    input.push_str("  NEB_End_XYZFile \"product.xyz\"\n  Restart_ALLXYZFile \"path.allxyz\"\n");
+    input.push_str(&format!("  NImages {}\nend\n\n", file.data.len() - 2));
+    input.push_str(&format!("* xyz {} {}\n", options.charge, options.multiplicity));
+    for atom in &reactant.atoms {
+        let p = atom.position;
+        input.push_str(&format!("  {:<4} {:14.8} {:14.8} {:14.8}\n", symbol(atom), p[0], p[1], p[2]));
+    }
+    input.push_str("*\n");
+    fs::write(dir.join("neb.inp"), input)?;
+    fs::write(dir.join("product.xyz"), xyz(product))?;
+
+    // Images in `.allxyz` files are separated by lines holding only `>`.
+    let path: Vec<String> = file.data.iter().map(xyz).collect();
+    fs::write(dir.join("path.allxyz"), path.join(">\n"))
+}
+
+/// Writes a VASP NEB calculation to the directory `dir`: one subdirectory per image, `00` for the reactant
+/// up to e.g. `07` for the product, each with a `POSCAR` in cartesian coordinates.
+/// Atoms are grouped by element, in the order the elements first appear, as VASP requires.
+/// Every image must have a cell. The `INCAR` needs `IMAGES` set to the number of images without the end points.
+pub fn write_vasp_neb<P: AsRef<Path>>(dir: P, file: &File) -> io::Result<()> {
+    check_images(file)?;
+    if let Some(i) = file.data.iter().position(|data| data.cell.is_none()) {
+        return Err(invalid(format!("image {} has no cell", i)));
+    }
+
+    let dir = dir.as_ref();
+    let width = (file.data.len() - 1).to_string().len().max(2);
+    for (i, data) in file.data.iter().enumerate() {
+        let image = dir.join(format!("{:0width$}", i, width = width));
+        fs::create_dir_all(&image)?;
+        fs::write(image.join("POSCAR"), poscar(data))?;
+    }
+    Ok(())
+}
+
+/// Writes the `&BAND` section of a CP2K `&MOTION` section, with one `&REPLICA` holding the coordinates of every image.
+/// The atoms of the `&SUBSYS` section must be in the same order as in the images.
+pub fn write_cp2k_neb<W: Write>(writer: &mut W, file: &File) -> io::Result<()> {
+    check_images(file)?;
+
+    let mut band = String::from("&BAND\n");
+    band.push_str("  BAND_TYPE CI-NEB\n");
+    band.push_str(&format!("  NUMBER_OF_REPLICA {}\n", file.data.len()));
+    band.push_str("  &OPTIMIZE_BAND\n    OPT_TYPE DIIS\n  &END OPTIMIZE_BAND\n");
+    for data in &file.data {
+        band.push_str("  &REPLICA\n    &COORD\n");
+        for atom in &data.atoms {
+            let p = atom.position;
+            band.push_str(&format!("      {:<4} {:14.8} {:14.8} {:14.8}\n", symbol(atom), p[0], p[1], p[2]));
+        }
+        band.push_str("    &END COORD\n  &END REPLICA\n");
+    }
+    band.push_str("&END BAND\n");
+    writer.write_all(band.as_bytes())
+}
+
+/// Checks that there are at least two images, with the same atomic symbols in the same order.
+fn check_images(file: &File) -> io::Result<()> {
+    if file.data.len() < 2 {
+        return Err(invalid(format!("a band needs at least 2 images, found {}", file.data.len())));
+    }
+    File { data: vec![] }
+        .extend_checked(file.clone(), ChecksPolicy::Order)
+        .map_err(|e| invalid(e.to_string()))
+}
+
+/// A frame as a plain XYZ block, as read by ORCA.
+fn xyz(data: &Data) -> String {
+    let mut xyz = format!("{}\n{}\n", data.atoms.len(), data.comment.trim());
+    for atom in &data.atoms {
+        let p = atom.position;
+        xyz.push_str(&format!("{:<4} {:14.8} {:14.8} {:14.8}\n", symbol(atom), p[0], p[1], p[2]));
+    }
+    xyz
+}
+
+/// A frame as a VASP `POSCAR` in cartesian coordinates, with the atoms grouped by element.
+fn poscar(data: &Data) -> String {
+    let mut species: Vec<&str> = vec![];
+    for atom in &data.atoms {
+        if !species.contains(&symbol(atom)) {
+            species.push(symbol(atom));
+        }
+    }
+
+    let title = data.comment.trim();
+    let mut poscar = format!("{}\n1.0\n", if title.is_empty() { data.formula() } else { title.to_string() });
+    for v in data.cell.unwrap().vectors {
+        poscar.push_str(&format!("  {:14.8} {:14.8} {:14.8}\n", v[0], v[1], v[2]));
+    }
+    let counts: Vec<String> =
+        species.iter().map(|&s| data.atoms.iter().filter(|a| symbol(a) == s).count().to_string()).collect();
+    poscar.push_str(&format!("  {}\n  {}\nCartesian\n", species.join("  "), counts.join("  ")));
+    for &s in &species {
+        for atom in data.atoms.iter().filter(|a| symbol(a) == s) {
+            let p = atom.position;
+            poscar.push_str(&format!("  {:14.8} {:14.8} {:14.8}\n", p[0], p[1], p[2]));
+        }
+    }
+    poscar
+}
+
+/// The element symbol of an atom, or its label if it is not a known element.
+fn symbol(atom: &super::data::AtomData) -> &str {
+    atom.element().map_or(atom.symbol.as_str(), |e| e.symbol())
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Cell;
+    use crate::testing::{frame, temp_path, trajectory};
+
+    #[test]
+    fn orca_neb_files() {
+        let dir = temp_path("orca-neb");
+        write_orca_neb(&dir, &trajectory(4), &OrcaNebOptions::default()).unwrap();
+        let input = fs::read_to_string(dir.join("neb.inp")).unwrap();
+        assert!(input.starts_with("! XTB2 NEB-CI\n\n%neb\n"));
+        assert!(input.contains("  NImages 2\nend\n\n* xyz 0 1\n  O        0.00000000     0.00000000     0.00000000\n"));
+        assert!(input.ends_with("*\n"));
+        let product = fs::read_to_string(dir.join("product.xyz")).unwrap();
+        assert!(product.starts_with("3\nstep=3\nO        0.30000000 "));
+        let path = fs::read_to_string(dir.join("path.allxyz")).unwrap();
+        assert_eq!(path.matches(">\n").count(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn vasp_neb_directories() {
+        let mut file = trajectory(3);
+        for data in &mut file.data {
+            data.atoms.swap(0, 1);
+            data.cell = Some(Cell::new([5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 5.0]));
+        }
+        let dir = temp_path("vasp-neb");
+        write_vasp_neb(&dir, &file).unwrap();
+        let poscar = fs::read_to_string(dir.join("02").join("POSCAR")).unwrap();
+        let lines: Vec<&str> = poscar.lines().collect();
+        assert_eq!(lines[..2], ["step=2", "1.0"]);
+        assert_eq!(lines[5..8], ["  H  O", "  2  1", "Cartesian"]);
+        assert_eq!(lines[10], "      0.20000000     0.00000000     0.00000000");
+        assert!(!dir.join("03").exists());
+
+        file.data[1].cell = None;
+        assert_eq!(write_vasp_neb(&dir, &file).unwrap_err().kind(), ErrorKind::InvalidData);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cp2k_band_and_invalid_images() {
+        let mut band = vec![];
+        write_cp2k_neb(&mut band, &trajectory(2)).unwrap();
+        let band = String::from_utf8(band).unwrap();
+        assert!(band.starts_with("&BAND\n  BAND_TYPE CI-NEB\n  NUMBER_OF_REPLICA 2\n"));
+        assert_eq!(band.matches("&REPLICA").count(), 2);
+        assert!(band.ends_with("    &END COORD\n  &END REPLICA\n&END BAND\n"));
+
+        assert_eq!(write_cp2k_neb(&mut vec![], &trajectory(1)).unwrap_err().kind(), ErrorKind::InvalidData);
+        let mut file = trajectory(2);
+        file.data[1] = frame("", &[("H", [0.0; 3]), ("O", [0.0; 3]), ("H", [0.0; 3])]);
+        assert_eq!(write_cp2k_neb(&mut vec![], &file).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,160 @@
+//! Parallel map and fold over frames, and parallel writing, using rayon.
+
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc;
+
+use rayon::prelude::*;
+
+use super::data::{Data, File};
+use super::shared::SharedTrajectory;
+use super::{error, write_frame_at, Frames, WriteOptions};
+
+impl SharedTrajectory {
+    /// Iterates over the frames in parallel.
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, Data> {
+        self.frames().par_iter()
+    }
+}
+
+impl File {
+    /// Applies `f` to every frame in parallel, returning the results in frame order.
+    pub fn par_map_frames<T, F>(&self, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&Data) -> T + Sync + Send,
+    {
+        self.data.par_iter().map(f).collect()
+    }
+
+    /// Folds the frames in parallel: every thread folds its frames into a value starting from `identity()`,
+    /// and the values are then combined with `reduce`, which should be associative.
+    pub fn par_fold<T, I, F, R>(&self, identity: I, fold: F, reduce: R) -> T
+    where
+        T: Send,
+        I: Fn() -> T + Sync + Send,
+        F: Fn(T, &Data) -> T + Sync + Send,
+        R: Fn(T, T) -> T + Sync + Send,
+    {
+        self.data.par_iter().fold(&identity, fold).reduce(&identity, reduce)
+    }
+
+    /// Writes the frames like [`write_file`](crate::write_file), formatting batches of frames on the rayon
+    /// threads while a separate thread writes the formatted batches in order.
+    /// At most two formatted batches wait to be written, which bounds the memory used.
+    pub fn par_write<W: Write + Send>(&self, writer: &mut W, options: &WriteOptions) -> io::Result<()> {
+        let size = 64 * rayon::current_num_threads();
+        std::thread::scope(|scope| {
+            let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(2);
+            let output = scope.spawn(move || receiver.into_iter().try_for_each(|batch| writer.write_all(&batch)));
+
+            for (n, batch) in self.data.chunks(size).enumerate() {
+                let formatted: Vec<Vec<u8>> = batch
+                    .par_iter()
+                    .enumerate()
+                    .map(|(i, data)| {
+                        let mut buf = vec![];
+                        write_frame_at(&mut buf, data, n * size + i, options).map(|_| buf)
+                    })
+                    .collect::<io::Result<_>>()?;
+                // Sending only fails once the writing thread stopped on an error, which is returned below.
+                if sender.send(formatted.concat()).is_err() {
+                    break;
+                }
+            }
+            drop(sender);
+            output.join().unwrap()
+        })
+    }
+}
+
+impl<R: BufRead> Frames<R> {
+    /// Applies `f` to every frame in parallel, returning the results in frame order.
+    /// Frames are parsed in batches, so only a few frames per thread are held in memory at a time.
+    /// Stops at the first parse error.
+    pub fn par_map_frames<T, F>(self, f: F) -> error::Result<Vec<T>>
+    where
+        T: Send,
+        F: Fn(&Data) -> T + Sync + Send,
+    {
+        let mut results = vec![];
+        self.par_batches(|batch| results.par_extend(batch.par_iter().map(&f)))?;
+        Ok(results)
+    }
+
+    /// Folds the frames in parallel like [`File::par_fold`], parsing them in batches.
+    /// Stops at the first parse error.
+    pub fn par_fold<T, I, F, Re>(self, identity: I, fold: F, reduce: Re) -> error::Result<T>
+    where
+        T: Send,
+        I: Fn() -> T + Sync + Send,
+        F: Fn(T, &Data) -> T + Sync + Send,
+        Re: Fn(T, T) -> T + Sync + Send,
+    {
+        let mut total = Some(identity());
+        self.par_batches(|batch| {
+            let value = batch.par_iter().fold(&identity, &fold).reduce(&identity, &reduce);
+            total = total.take().map(|total| reduce(total, value));
+        })?;
+        Ok(total.unwrap())
+    }
+
+    fn par_batches<G: FnMut(&[Data])>(mut self, mut process: G) -> error::Result<()> {
+        let size = 4 * rayon::current_num_threads();
+        loop {
+            let batch = self.by_ref().take(size).collect::<error::Result<Vec<Data>>>()?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            process(&batch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::shared::SharedTrajectory;
+    use crate::testing::trajectory;
+    use crate::writer::{write_file, CommentField, CommentPolicy};
+
+    #[test]
+    fn map_and_fold_in_frame_order() {
+        let file = trajectory(50);
+        let x = |data: &Data| data.atoms[0].position[0];
+        let expected: Vec<f64> = file.data.iter().map(x).collect();
+        assert_eq!(file.par_map_frames(x), expected);
+        assert_eq!(file.par_fold(|| 0, |n, data| n + data.atoms.len(), |a, b| a + b), 150);
+
+        let text = file.data.iter().map(|data| data.to_string()).collect::<Vec<_>>().join("\n");
+        assert_eq!(Frames::new(Cursor::new(text.clone())).par_map_frames(x).unwrap(), expected);
+        let count = Frames::new(Cursor::new(text)).par_fold(|| 0, |n, _| n + 1, |a, b| a + b);
+        assert_eq!(count.unwrap(), 50);
+        assert!(Frames::new(Cursor::new("2\n\nH 0 0 0\n")).par_map_frames(x).is_err());
+    }
+
+    #[test]
+    fn par_write_matches_write_file() {
+        let file = trajectory(1000);
+        let options = WriteOptions {
+            comment: CommentPolicy::Fields(vec![CommentField::Index]),
+            ..WriteOptions::default()
+        };
+        let (mut expected, mut written) = (vec![], vec![]);
+        write_file(&mut expected, &file, &options).unwrap();
+        file.par_write(&mut written, &options).unwrap();
+        assert_eq!(written, expected);
+
+        let mut full = [0u8; 100];
+        let error = file.par_write(&mut full.as_mut_slice(), &options).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn shared_trajectories_iterate_in_parallel() {
+        let trajectory = SharedTrajectory::from(trajectory(50));
+        let atoms: usize = trajectory.par_iter().map(|data| data.atoms.len()).sum();
+        assert_eq!(atoms, 150);
+    }
+}
@@ -0,0 +1,189 @@
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+
+/// A position or displacement in cartesian coordinates, in Ångström.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Position(pub [f64; 3]);
+impl Position {
+    /// Creates a position from its coordinates.
+    pub fn new(x: f64, y: f64, z: f64) -> Position {
+        Position([x, y, z])
+    }
+
+    /// Creates a position from its coordinates, or `None` if any coordinate is NaN or infinite.
+    pub fn try_new(x: f64, y: f64, z: f64) -> Option<Position> {
+        Some(Position::new(x, y, z)).filter(Position::is_finite)
+    }
+
+    /// Whether all coordinates are neither NaN nor infinite.
+    pub fn is_finite(&self) -> bool {
+        self.0.iter().all(|v| v.is_finite())
+    }
+
+    /// The x coordinate.
+    pub fn x(&self) -> f64 {
+        self.0[0]
+    }
+
+    /// The y coordinate.
+    pub fn y(&self) -> f64 {
+        self.0[1]
+    }
+
+    /// The z coordinate.
+    pub fn z(&self) -> f64 {
+        self.0[2]
+    }
+
+    /// The dot product with another vector.
+    pub fn dot(&self, other: Position) -> f64 {
+        self.0[0] * other.0[0] + self.0[1] * other.0[1] + self.0[2] * other.0[2]
+    }
+
+    /// The cross product with another vector.
+    pub fn cross(&self, other: Position) -> Position {
+        let [a, b] = [self.0, other.0];
+        Position([
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ])
+    }
+
+    /// The length of the vector.
+    pub fn norm(&self) -> f64 {
+        self.dot(*self).sqrt()
+    }
+
+    /// The distance to another position.
+    pub fn distance(&self, other: Position) -> f64 {
+        (*self - other).norm()
+    }
+
+    /// The vector scaled to unit length, or `None` for the zero vector.
+    pub fn normalized(&self) -> Option<Position> {
+        let norm = self.norm();
+        if norm > 0.0 {
+            Some(*self / norm)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<[f64; 3]> for Position {
+    fn from(coordinates: [f64; 3]) -> Position {
+        Position(coordinates)
+    }
+}
+impl From<Position> for [f64; 3] {
+    fn from(position: Position) -> [f64; 3] {
+        position.0
+    }
+}
+
+impl Index<usize> for Position {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        &self.0[index]
+    }
+}
+impl IndexMut<usize> for Position {
+    fn index_mut(&mut self, index: usize) -> &mut f64 {
+        &mut self.0[index]
+    }
+}
+
+impl Add for Position {
+    type Output = Position;
+
+    fn add(self, other: Position) -> Position {
+        Position([self.0[0] + other.0[0], self.0[1] + other.0[1], self.0[2] + other.0[2]])
+    }
+}
+impl AddAssign for Position {
+    fn add_assign(&mut self, other: Position) {
+        *self = *self + other;
+    }
+}
+impl Sub for Position {
+    type Output = Position;
+
+    fn sub(self, other: Position) -> Position {
+        Position([self.0[0] - other.0[0], self.0[1] - other.0[1], self.0[2] - other.0[2]])
+    }
+}
+impl SubAssign for Position {
+    fn sub_assign(&mut self, other: Position) {
+        *self = *self - other;
+    }
+}
+impl Mul<f64> for Position {
+    type Output = Position;
+
+    fn mul(self, factor: f64) -> Position {
+        Position(self.0.map(|v| v * factor))
+    }
+}
+impl Mul<Position> for f64 {
+    type Output = Position;
+
+    fn mul(self, position: Position) -> Position {
+        position * self
+    }
+}
+impl Div<f64> for Position {
+    type Output = Position;
+
+    fn div(self, divisor: f64) -> Position {
+        Position(self.0.map(|v| v / divisor))
+    }
+}
+impl Neg for Position {
+    type Output = Position;
+
+    fn neg(self) -> Position {
+        Position(self.0.map(|v| -v))
+    }
+}
+impl std::iter::Sum for Position {
+    fn sum<I: Iterator<Item = Position>>(iter: I) -> Position {
+        iter.fold(Position::default(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_arithmetic() {
+        let a = Position::new(1.0, 2.0, 2.0);
+        let b = Position::from([0.0, 1.0, 0.0]);
+        assert_eq!(a + b, Position::new(1.0, 3.0, 2.0));
+        assert_eq!(a - b, Position::new(1.0, 1.0, 2.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(-a / 2.0, Position::new(-0.5, -1.0, -1.0));
+        assert_eq!(a.norm(), 3.0);
+        assert_eq!(a.dot(b), 2.0);
+        assert_eq!(Position::new(1.0, 0.0, 0.0).cross(b), Position::new(0.0, 0.0, 1.0));
+        assert_eq!(a.distance(a), 0.0);
+        assert_eq!([a, b].into_iter().sum::<Position>(), a + b);
+        assert!(Position::default().normalized().is_none());
+        assert!((a.normalized().unwrap().norm() - 1.0).abs() < 1e-12);
+
+        let mut c = a;
+        c[2] = 5.0;
+        c += b;
+        c -= a;
+        assert_eq!(<[f64; 3]>::from(c), [0.0, 1.0, 3.0]);
+        assert_eq!((c.x(), c.y(), c.z()), (0.0, 1.0, 3.0));
+    }
+
+    #[test]
+    fn finite_positions() {
+        assert_eq!(Position::try_new(1.0, 2.0, 3.0), Some(Position::new(1.0, 2.0, 3.0)));
+        assert_eq!(Position::try_new(1.0, f64::NAN, 3.0), None);
+        assert!(!Position::new(f64::NEG_INFINITY, 0.0, 0.0).is_finite());
+    }
+}
@@ -0,0 +1,239 @@
+//! Provenance entries in comment lines, tracing the origin of structure files:
+//! `tool=rust-xyz-chem/0.0.2 created=2024-01-31T12:00:00Z sha256=...`, written with
+//! [`WriteOptions::provenance`](crate::WriteOptions) and verified with [`File::verify_checksums`] or
+//! [`ParseOptions::verify_checksums`](crate::ParseOptions).
+//!
+//! The checksum is the SHA-256 of the atoms, one `symbol x y z` line each, with the coordinates in the shortest
+//! representation that reads back to the same value. It is computed from the coordinates as written,
+//! so it matches after reading the file back, whatever the precision.
+//!
+//! [`Data::geometry_hash`] instead fingerprints the rounded geometry, e.g. to skip structures that were
+//! already computed.
+
+use super::data::{Data, File};
+use super::writer::format_number;
+
+/// The keys of the provenance entries.
+pub(crate) const KEYS: [&str; 3] = ["tool", "created", "sha256"];
+
+impl Data {
+    /// Whether the `sha256` checksum of the comment matches the atoms, or `None` if the comment has no checksum.
+    pub fn verify_checksum(&self) -> Option<bool> {
+        let expected = self.property("sha256")?;
+        let atoms = self.atoms.iter().map(|atom| (atom.symbol.as_str(), atom.position.0));
+        Some(expected.eq_ignore_ascii_case(&checksum(atoms)))
+    }
+
+    /// A hexadecimal SHA-256 fingerprint of the geometry, to recognize structures that were already computed:
+    /// the element symbols and the coordinates rounded to `precision` decimals, and the cell vectors if any.
+    /// The comment and atom metadata are ignored. Coordinates close to a rounding boundary may round differently
+    /// for nearly identical structures, so the precision should be well above the noise of the coordinates.
+    pub fn geometry_hash(&self, precision: usize) -> String {
+        self.hash_lines(precision, false)
+    }
+
+    /// A fingerprint of the geometry like [`Data::geometry_hash`], which does not depend on the order of the atoms.
+    pub fn geometry_hash_unordered(&self, precision: usize) -> String {
+        self.hash_lines(precision, true)
+    }
+
+    fn hash_lines(&self, precision: usize, sorted: bool) -> String {
+        let round = |v: f64| {
+            let text = format!("{:.*}", precision, v);
+            // Negative values that round to zero hash like zero.
+            match text.strip_prefix('-') {
+                Some(rest) if rest.bytes().all(|b| b == b'0' || b == b'.') => rest.to_string(),
+                _ => text,
+            }
+        };
+        let mut lines: Vec<String> = self
+            .atoms
+            .iter()
+            .map(|atom| {
+                let symbol = atom.element().map_or(atom.symbol.as_str(), |e| e.symbol());
+                format!("{} {}", symbol, atom.position.0.map(round).join(" "))
+            })
+            .collect();
+        if sorted {
+            lines.sort_unstable();
+        }
+        if let Some(cell) = &self.cell {
+            lines.extend(cell.vectors.iter().map(|v| format!("cell {}", v.map(round).join(" "))));
+        }
+        let text: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        sha256(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl File {
+    /// The indices of the frames whose `sha256` checksum does not match their atoms.
+    /// Frames without a checksum are not reported.
+    pub fn verify_checksums(&self) -> Vec<usize> {
+        (0..self.data.len()).filter(|&i| self.data[i].verify_checksum() == Some(false)).collect()
+    }
+}
+
+/// The provenance entries for atoms given as symbols and coordinates, created at `timestamp`.
+pub(crate) fn entries<'a>(atoms: impl Iterator<Item = (&'a str, [f64; 3])>, timestamp: &str) -> String {
+    format!(
+        "tool={}/{} created={} sha256={}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        timestamp,
+        checksum(atoms)
+    )
+}
+
+/// The hexadecimal SHA-256 of the atoms, see the module documentation.
+pub(crate) fn checksum<'a>(atoms: impl Iterator<Item = (&'a str, [f64; 3])>) -> String {
+    let mut text = String::new();
+    for (symbol, position) in atoms {
+        text.push_str(symbol);
+        for v in position {
+            text.push(' ');
+            format_number(&mut text, v, None);
+        }
+        text.push('\n');
+    }
+    sha256(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Removes `key=value` entries with the provenance keys from a comment.
+pub(crate) fn strip(comment: &str) -> String {
+    comment
+        .split(' ')
+        .filter(|word| !KEYS.iter().any(|key| word.strip_prefix(key).is_some_and(|rest| rest.starts_with('='))))
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .trim()
+        .to_string()
+}
+
+pub(crate) fn sha256(message: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+        0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+        0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+        0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+        0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+        0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+        0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut h: [u32; 8] =
+        [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+    // Padding: a one bit, zeros up to 56 bytes modulo 64, and the message length in bits.
+    let mut data = message.to_vec();
+    data.push(0x80);
+    while data.len() % 64 != 56 {
+        data.push(0);
+    }
+    data.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+
+    for block in data.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(choice).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            (hh, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::error::FileParseError;
+    use crate::testing::frame;
+    use crate::testing::water;
+    use crate::writer::{write_frame, WriteOptions};
+    use crate::{Frames, ParseOptions};
+
+    fn hex(digest: [u8; 32]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn sha256_test_vectors() {
+        assert_eq!(hex(sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hex(sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let long = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        assert_eq!(hex(sha256(long)), "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    }
+
+    #[test]
+    fn checksums_round_trip_and_detect_changes() {
+        let mut buf = vec![];
+        let options = WriteOptions {
+            provenance: true,
+            precision: Some(3),
+            ..WriteOptions::default()
+        };
+        write_frame(&mut buf, &water(), &options).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let comment = text.lines().nth(1).unwrap();
+        assert!(comment.starts_with("water tool=rust-xyz-chem/"));
+        assert_eq!(strip(comment), "water");
+
+        let verified = ParseOptions {
+            verify_checksums: true,
+            ..ParseOptions::default()
+        };
+        let frames = Frames::with_options(Cursor::new(text.as_str()), verified.clone());
+        let frames: Vec<Data> = frames.collect::<Result<_, _>>().unwrap();
+        assert_eq!(frames[0].verify_checksum(), Some(true));
+        assert_eq!(water().verify_checksum(), None);
+
+        let tampered = text.replace("0.960", "0.961");
+        let mut frames = Frames::with_options(Cursor::new(tampered.as_str()), verified);
+        assert!(matches!(frames.next(), Some(Err(FileParseError::ChecksumMismatch(2)))));
+        let file = File { data: Frames::new(Cursor::new(tampered.as_str())).collect::<Result<_, _>>().unwrap() };
+        assert_eq!(file.verify_checksums(), [0]);
+    }
+
+    #[test]
+    fn geometry_hashes() {
+        let hash = water().geometry_hash(3);
+        assert_eq!(hash.len(), 64);
+        let mut moved = water();
+        moved.comment = "another comment".to_string();
+        moved.atoms[1].position.0[0] = 0.9601;
+        assert_eq!(moved.geometry_hash(3), hash);
+        assert_ne!(moved.geometry_hash(4), water().geometry_hash(4));
+
+        let zero = frame("", &[("C", [0.0, 0.0, 0.0])]);
+        let negative_zero = frame("", &[("C", [-0.0001, 0.0, 0.0])]);
+        assert_eq!(negative_zero.geometry_hash(3), zero.geometry_hash(3));
+
+        let mut reordered = water();
+        reordered.atoms.swap(0, 2);
+        assert_ne!(reordered.geometry_hash(3), hash);
+        assert_eq!(reordered.geometry_hash_unordered(3), water().geometry_hash_unordered(3));
+    }
+}
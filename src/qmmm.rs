@@ -0,0 +1,226 @@
+//! Writes inputs for multi-layer QM/MM calculations, with the layers of the atoms taken from their tags
+//! (see [`tags`](crate::tags)), and point charges for electrostatic embedding.
+
+use std::io::{self, Error, ErrorKind, Write};
+
+use super::data::Data;
+use super::position::Position;
+
+/// Options for [`write_oniom`].
+#[derive(Debug, Clone)]
+pub struct OniomOptions {
+    /// The Link 0 commands and route section, e.g. `%chk=job.chk` and `#p ONIOM(B3LYP/6-31G(d):UFF)`.
+    pub route: String,
+    /// The title section, or the comment of the frame if empty.
+    pub title: String,
+    /// The charge and spin multiplicity pairs, in Gaussian's order: the real system at the low level,
+    /// then the model systems, e.g. three pairs for a two-layer calculation.
+    pub charges: Vec<(i32, u32)>,
+    /// The tag layer holding the ONIOM layer of each atom, `H`, `M` or `L` (case-insensitive).
+    pub layer_tag: String,
+    /// The ONIOM layer of atoms without a tag.
+    pub default_layer: char,
+    /// The element of the link atoms that cap the bonds from a lower layer to a higher one.
+    pub link_atom: String,
+}
+impl Default for OniomOptions {
+    fn default() -> OniomOptions {
+        OniomOptions {
+            route: "#p ONIOM(B3LYP/6-31G(d):UFF)".to_string(),
+            title: String::new(),
+            charges: vec![(0, 1); 3],
+            layer_tag: "layer".to_string(),
+            default_layer: 'L',
+            link_atom: "H".to_string(),
+        }
+    }
+}
+
+/// Writes a frame as a Gaussian ONIOM input with the layer designation of every atom.
+/// An atom bonded to an atom of a higher layer (see [`Data::bonds`]) is replaced by a link atom in the higher
+/// layer calculations, bonded to the neighbour of the highest layer, or the nearest one of them.
+/// Fails with [`ErrorKind::InvalidData`] if a layer is not `H`, `M` or `L`.
+pub fn write_oniom<W: Write>(writer: &mut W, data: &Data, options: &OniomOptions) -> io::Result<()> {
+    let layers = data
+        .atoms
+        .iter()
+        .enumerate()
+        .map(|(i, atom)| {
+            let layer = atom.tag(&options.layer_tag).map_or(options.default_layer.to_string(), str::to_uppercase);
+            match layer.as_str() {
+                "H" => Ok('H'),
+                "M" => Ok('M'),
+                "L" => Ok('L'),
+                _ => Err(Error::new(ErrorKind::InvalidData, format!("invalid ONIOM layer {:?} of atom {}", layer, i))),
+            }
+        })
+        .collect::<io::Result<Vec<char>>>()?;
+    let rank = |layer: char| "LMH".find(layer).unwrap();
+
+    let mut links: Vec<Option<usize>> = vec![None; data.atoms.len()];
+    for (i, j) in data.bonds() {
+        for (low, high) in [(i, j), (j, i)] {
+            if rank(layers[high]) <= rank(layers[low]) {
+                continue;
+            }
+            let better = links[low].is_none_or(|current| {
+                let distance = |k: usize| data.atoms[low].position.distance(data.atoms[k].position);
+                (rank(layers[high]), -distance(high)) > (rank(layers[current]), -distance(current))
+            });
+            if better {
+                links[low] = Some(high);
+            }
+        }
+    }
+
+    let title = if options.title.trim().is_empty() { data.comment.trim() } else { options.title.trim() };
+    let mut buf = format!("{}\n\n{}\n\n", options.route.trim_end(), if title.is_empty() { "ONIOM" } else { title });
+    let charges: Vec<String> = options.charges.iter().map(|(c, m)| format!("{} {}", c, m)).collect();
+    buf.push_str(&charges.join(" "));
+    buf.push('\n');
+
+    for (i, atom) in data.atoms.iter().enumerate() {
+        let symbol = atom.element().map_or(atom.symbol.as_str(), |e| e.symbol());
+        let p = atom.position;
+        buf.push_str(&format!(" {:<4} 0 {:14.8} {:14.8} {:14.8} {}", symbol, p[0], p[1], p[2], layers[i]));
+        if let Some(high) = links[i] {
+            buf.push_str(&format!(" {} {}", options.link_atom, high + 1));
+        }
+        buf.push('\n');
+    }
+    buf.push('\n');
+    writer.write_all(buf.as_bytes())
+}
+
+/// An external point charge for electrostatic embedding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointCharge {
+    /// The position in Ångström.
+    pub position: Position,
+    /// The charge in elementary charges.
+    pub charge: f64,
+}
+
+/// The layout of the point charges written by [`write_point_charges`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointChargeFormat {
+    /// An ORCA point charge file, for `%pointcharges "file.pc"`: the number of charges,
+    /// then one `charge x y z` line per charge.
+    #[default]
+    Orca,
+    /// The block following the molecule specification of a Gaussian input with the `Charge` keyword:
+    /// one `x y z charge` line per charge, ended by a blank line.
+    Gaussian,
+}
+
+/// Splits a frame into the QM region, the atoms at `qm` (e.g. from [`Data::tagged`] or [`Data::select`]),
+/// and point charges at the other atoms, with their partial charges from [`AtomData::charge`](crate::data::AtomData).
+/// Fails with [`ErrorKind::InvalidData`] if an index is out of range or an atom outside the QM region has no charge.
+pub fn split_embedding(data: &Data, qm: &[usize]) -> io::Result<(Data, Vec<PointCharge>)> {
+    if let Some(&i) = qm.iter().find(|&&i| i >= data.atoms.len()) {
+        return Err(Error::new(ErrorKind::InvalidData, format!("atom index {} is out of range", i)));
+    }
+
+    let mut region = data.clone();
+    region.atoms = qm.iter().map(|&i| data.atoms[i].clone()).collect();
+    region.count = region.atoms.len();
+
+    let charges = (0..data.atoms.len())
+        .filter(|i| !qm.contains(i))
+        .map(|i| {
+            let atom = &data.atoms[i];
+            let charge = atom
+                .charge
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("atom {} has no partial charge", i)))?;
+            Ok(PointCharge { position: atom.position, charge })
+        })
+        .collect::<io::Result<Vec<PointCharge>>>()?;
+    Ok((region, charges))
+}
+
+/// Writes point charges in the layout of `format`.
+pub fn write_point_charges<W: Write>(
+    writer: &mut W,
+    charges: &[PointCharge],
+    format: PointChargeFormat,
+) -> io::Result<()> {
+    let mut buf = String::new();
+    if format == PointChargeFormat::Orca {
+        buf.push_str(&format!("{}\n", charges.len()));
+    }
+    for c in charges {
+        let [x, y, z] = c.position.0;
+        let line = match format {
+            PointChargeFormat::Orca => format!("{:10.6} {:14.8} {:14.8} {:14.8}\n", c.charge, x, y, z),
+            PointChargeFormat::Gaussian => format!("{:14.8} {:14.8} {:14.8} {:10.6}\n", x, y, z, c.charge),
+        };
+        buf.push_str(&line);
+    }
+    if format == PointChargeFormat::Gaussian {
+        buf.push('\n');
+    }
+    writer.write_all(buf.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::water;
+
+    fn oniom(data: &Data, options: &OniomOptions) -> io::Result<String> {
+        let mut buf = vec![];
+        write_oniom(&mut buf, data, options)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    #[test]
+    fn layers_and_link_atoms() {
+        let mut data = water();
+        data.set_tag(&[0], "layer", "h");
+        let input = oniom(&data, &OniomOptions::default()).unwrap();
+        let lines: Vec<&str> = input.lines().collect();
+        assert_eq!(lines[..5], ["#p ONIOM(B3LYP/6-31G(d):UFF)", "", "water", "", "0 1 0 1 0 1"]);
+        assert_eq!(lines[5], " O    0     0.00000000     0.00000000     0.00000000 H");
+        assert_eq!(lines[6], " H    0     0.96000000     0.00000000     0.00000000 L H 1");
+        assert!(lines[7].ends_with(" L H 1"));
+        assert!(input.ends_with("\n\n"));
+
+        data.set_tag(&[1, 2], "layer", "M");
+        let options = OniomOptions {
+            title: "scan".to_string(),
+            ..OniomOptions::default()
+        };
+        let input = oniom(&data, &options).unwrap();
+        assert_eq!(input.lines().nth(2), Some("scan"));
+        assert!(input.lines().nth(6).unwrap().ends_with(" M H 1"));
+
+        data.set_tag(&[2], "layer", "X");
+        assert_eq!(oniom(&data, &options).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn embedding_split_and_point_charges() {
+        let mut data = water();
+        for (atom, charge) in data.atoms.iter_mut().zip([-0.834, 0.417, 0.417]) {
+            atom.charge = Some(charge);
+        }
+        let (region, charges) = split_embedding(&data, &[0]).unwrap();
+        assert_eq!((region.count, region.atoms[0].symbol.as_str()), (1, "O"));
+        assert_eq!(charges.len(), 2);
+        assert_eq!(charges[1], PointCharge { position: Position::new(-0.24, 0.93, 0.0), charge: 0.417 });
+
+        let mut orca = vec![];
+        write_point_charges(&mut orca, &charges, PointChargeFormat::Orca).unwrap();
+        let orca = String::from_utf8(orca).unwrap();
+        assert_eq!(orca.lines().next(), Some("2"));
+        assert_eq!(orca.lines().nth(1), Some("  0.417000     0.96000000     0.00000000     0.00000000"));
+        let mut gaussian = vec![];
+        write_point_charges(&mut gaussian, &charges[..1], PointChargeFormat::Gaussian).unwrap();
+        assert_eq!(String::from_utf8(gaussian).unwrap(), "    0.96000000     0.00000000     0.00000000   0.417000\n\n");
+
+        assert_eq!(split_embedding(&data, &[3]).unwrap_err().kind(), ErrorKind::InvalidData);
+        data.atoms[2].charge = None;
+        assert_eq!(split_embedding(&data, &[0]).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert!(split_embedding(&data, &[0, 2]).is_ok());
+    }
+}
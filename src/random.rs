@@ -0,0 +1,76 @@
+//! A small seeded random number generator, so results are reproducible across platforms and crate versions.
+
+/// The xoshiro256** generator, seeded with SplitMix64.
+pub(crate) struct Rng {
+    state: [u64; 4],
+}
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        let mut s = seed;
+        let mut splitmix = || {
+            s = s.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = s;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Rng {
+            state: [splitmix(), splitmix(), splitmix(), splitmix()],
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// A uniformly distributed number in `[0, 1)`.
+    pub(crate) fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A normally distributed number with mean 0 and standard deviation 1, using the Box-Muller transform.
+    pub(crate) fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.uniform();
+        let v = self.uniform();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// Shuffles a slice with the Fisher-Yates algorithm.
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_and_normally_distributed() {
+        let mut rng = Rng::new(0);
+        // The first output of SplitMix64 seeded with 0.
+        assert_eq!(rng.state[0], 0xe220a8397b1dcdaf);
+        assert_eq!(Rng::new(42).next_u64(), Rng::new(42).next_u64());
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+
+        let n = 100_000;
+        let samples: Vec<f64> = (0..n).map(|_| rng.normal()).collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+        assert!(mean.abs() < 0.02, "{}", mean);
+        assert!((variance - 1.0).abs() < 0.02, "{}", variance);
+        assert!((0..1000).map(|_| rng.uniform()).all(|u| (0.0..1.0).contains(&u)));
+    }
+}
@@ -0,0 +1,619 @@
+use std::io::BufRead;
+
+use super::extended::Layout;
+use super::labels::LabelMap;
+use super::{data, error, AtomCount, AtomPosition};
+
+/// Options for parsing `.xyz` files.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Resolves atom labels that are not element symbols, such as `OW` or `CA`, to elements.
+    /// The original labels are kept as the atomic symbols.
+    pub labels: Option<LabelMap>,
+    /// Rejects atom positions with NaN or infinite coordinates, which broken jobs sometimes write,
+    /// with [`error::FileParseError::NonFinitePosition`].
+    pub reject_non_finite: bool,
+    /// How strictly the file format is enforced.
+    pub dialect: Dialect,
+    /// Rejects frames whose `sha256` provenance checksum does not match their atoms,
+    /// with [`error::FileParseError::ChecksumMismatch`]. Frames without a checksum are accepted.
+    pub verify_checksums: bool,
+    /// Replaces atom labels that resolve to an element by the element symbol, e.g. `OW` by `O` and `FE` by `Fe`,
+    /// see [`AtomData::fix_symbol`](data::AtomData::fix_symbol). Labels are resolved with `labels`,
+    /// or with [`LabelMap::common`] without.
+    pub fix_symbols: bool,
+    /// The largest estimated memory in bytes of the frames read by [`read_with`](crate::read_with), which fails
+    /// with [`error::FileParseError::MemoryLimit`] instead of running out of memory when it is exceeded.
+    /// [`read_stored`](crate::read_stored) keeps the frames on disk instead. Unlimited if `None`.
+    pub max_memory: Option<usize>,
+}
+
+/// How strictly the file format is enforced when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Frames follow each other, possibly separated by blank lines, and atom lines may contain extra columns
+    /// after the position.
+    #[default]
+    Default,
+    /// Frames follow each other without blank lines, atom lines contain exactly an element symbol and
+    /// three coordinates (or the columns of an extended XYZ `Properties=` entry), and symbols must be elements,
+    /// possibly after resolving labels.
+    Strict,
+    /// Frames may be separated by blank lines or arbitrary other lines, such as log output.
+    /// Parsing resynchronizes on the next line holding only an atom count, and frames that fail to parse are skipped.
+    Permissive,
+    /// The layout of the QM9 dataset: the comment holds the 17 properties `tag index A B C mu alpha homo lumo gap
+    /// r2 zpve U0 U H G Cv`, atom lines have the Mulliken charge as a fifth column, and the atoms are followed by
+    /// lines with the harmonic frequencies, the SMILES and the InChI of the molecule. Numbers may be written
+    /// like `1.5*^-6`. The comment is replaced by `key=value` properties, see
+    /// [`Data::properties`](data::Data::properties), with the frequencies as `frequencies`, and the SMILES and InChI
+    /// as `smiles`, `smiles_relaxed`, `inchi` and `inchi_relaxed`. Frames may be separated by blank lines.
+    Qm9,
+}
+
+/// A region of the input that was skipped to resynchronize on the next frame, in the
+/// [`Dialect::Permissive`] dialect, such as corrupted or truncated frames or log output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skipped {
+    /// The byte offset of the first skipped line.
+    pub start: u64,
+    /// The byte offset after the last skipped line, where the next frame or the end of the input starts.
+    pub end: u64,
+    /// The line numbers of the first and the last skipped line, counting from 1.
+    pub lines: (usize, usize),
+}
+
+/// Iterator over the frames of a `.xyz` file, parsing one frame at a time.
+pub struct Frames<R> {
+    reader: R,
+    options: ParseOptions,
+    line: String,
+    line_count: usize,
+    /// The byte offset at which `line` starts.
+    line_offset: usize,
+    skipped: Vec<Skipped>,
+    /// Whether `line` holds a line that still has to be parsed, in the permissive dialect.
+    pending: bool,
+    frame_count: usize,
+    byte_count: usize,
+    done: bool,
+}
+impl<R: BufRead> Frames<R> {
+    /// Creates an iterator over the frames read from `reader`.
+    pub fn new(reader: R) -> Frames<R> {
+        Frames::with_options(reader, ParseOptions::default())
+    }
+
+    /// Creates an iterator over the frames read from `reader`, parsed with `options`.
+    pub fn with_options(reader: R, options: ParseOptions) -> Frames<R> {
+        let mut options = options;
+        if options.fix_symbols && options.labels.is_none() {
+            options.labels = Some(LabelMap::common());
+        }
+        Frames {
+            reader,
+            options,
+            line: String::new(),
+            line_count: 0,
+            line_offset: 0,
+            skipped: vec![],
+            pending: false,
+            frame_count: 0,
+            byte_count: 0,
+            done: false,
+        }
+    }
+
+    /// The number of bytes read so far. After a frame is returned, this is the offset at which the next frame
+    /// starts, so reading can later resume from a reader positioned there.
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_count as u64
+    }
+
+    /// The regions skipped so far to resynchronize on the next frame, in the [`Dialect::Permissive`] dialect.
+    /// Blank lines between frames are not reported.
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+
+    /// Reads the next line into `self.line`, without the line ending.
+    /// Returns `false` at the end of the input.
+    fn next_line(&mut self) -> error::Result<bool> {
+        self.line.clear();
+        self.line_offset = self.byte_count;
+        let bytes = self.reader.read_line(&mut self.line)?;
+        if bytes == 0 {
+            return Ok(false);
+        }
+        self.line_count += 1;
+        self.byte_count += bytes;
+
+        if self.line.ends_with('\n') {
+            self.line.pop();
+            if self.line.ends_with('\r') {
+                self.line.pop();
+            }
+        }
+        Ok(true)
+    }
+
+    /// Parses the next frame. Returns `None` at the end of the input.
+    fn next_frame(&mut self) -> error::Result<Option<data::Data>> {
+        if self.options.dialect == Dialect::Permissive {
+            return self.next_frame_permissive();
+        }
+
+        if !self.next_line()? {
+            return Ok(None);
+        }
+        if self.options.dialect == Dialect::Qm9 {
+            while self.line.trim().is_empty() {
+                if !self.next_line()? {
+                    return Ok(None);
+                }
+            }
+            return self.frame_qm9().map(Some);
+        }
+        if self.options.dialect == Dialect::Strict && self.line.is_empty() {
+            return Err(error::FileParseError::BlankLine(self.line_count));
+        }
+        while self.line.trim().is_empty() {
+            if !self.next_line()? {
+                return Ok(None);
+            }
+        }
+
+        let count: AtomCount = match self.line.trim().parse() {
+            Ok(n) => n,
+            Err(_) => return Err(error::FileParseError::InvalidAtomCount(self.line_count)),
+        };
+
+        self.frame_body(count).map(Some)
+    }
+
+    /// Parses the next frame, skipping lines up to the next atom count followed by a frame that parses,
+    /// and recording the skipped region.
+    fn next_frame_permissive(&mut self) -> error::Result<Option<data::Data>> {
+        // The byte offset and line number of the first skipped line.
+        let mut skipped_from: Option<(usize, usize)> = None;
+        loop {
+            if !self.pending && !self.next_line()? {
+                self.skip(skipped_from, self.byte_count, self.line_count);
+                return Ok(None);
+            }
+            self.pending = false;
+            let start = (self.line_offset, self.line_count);
+
+            let Ok(count) = self.line.trim().parse::<AtomCount>() else {
+                if !self.line.trim().is_empty() {
+                    skipped_from.get_or_insert(start);
+                }
+                continue;
+            };
+            match self.frame_body(count) {
+                Ok(data) => {
+                    self.skip(skipped_from, start.0, start.1 - 1);
+                    return Ok(Some(data));
+                }
+                Err(e @ error::FileParseError::Io { .. }) => return Err(e),
+                // The offending line may start the next frame.
+                Err(_) => {
+                    skipped_from.get_or_insert(start);
+                    self.pending = true;
+                }
+            }
+        }
+    }
+
+    /// Records the region from `from` up to the byte offset `end` and the line `last` as skipped.
+    fn skip(&mut self, from: Option<(usize, usize)>, end: usize, last: usize) {
+        let Some((start, first)) = from else { return };
+        #[cfg(feature = "tracing")]
+        tracing::warn!(start, end, first, last, "skipped unparsable lines");
+        self.skipped.push(Skipped {
+            start: start as u64,
+            end: end as u64,
+            lines: (first, last),
+        });
+    }
+
+    /// Parses a frame in the [`Dialect::Qm9`] layout, whose atom count line has been read.
+    fn frame_qm9(&mut self) -> error::Result<data::Data> {
+        let count: AtomCount = match self.line.trim().parse() {
+            Ok(n) => n,
+            Err(_) => return Err(error::FileParseError::InvalidAtomCount(self.line_count)),
+        };
+        let mut data = self.frame_body(count)?;
+        let comment_line = self.line_count - count;
+
+        let mut trailing = vec![];
+        for _ in 0..3 {
+            if !self.next_line()? {
+                return Err(error::FileParseError::EmptyLine(self.line_count + 1));
+            }
+            trailing.push(self.line.clone());
+        }
+        let values: Vec<&str> = data.comment.split_whitespace().collect();
+        let is_number = |value: &&str| value.replace("*^", "e").parse::<f64>().is_ok();
+        if values.len() != QM9_PROPERTIES.len() || !values[1..].iter().all(is_number) {
+            return Err(error::FileParseError::Qm9Properties(comment_line));
+        }
+
+        let number = |value: &str| value.replace("*^", "e");
+        let mut properties: Vec<(&str, String)> =
+            QM9_PROPERTIES.iter().copied().zip(values.into_iter().map(number)).collect();
+        let frequencies: Vec<String> = trailing[0].split_whitespace().map(number).collect();
+        properties.push(("frequencies", frequencies.join(" ")));
+        let identifiers = [(&trailing[1], "smiles", "smiles_relaxed"), (&trailing[2], "inchi", "inchi_relaxed")];
+        for (line, key, relaxed) in identifiers {
+            let mut values = line.split_whitespace();
+            properties.push((key, values.next().unwrap_or_default().to_string()));
+            if let Some(value) = values.next() {
+                properties.push((relaxed, value.to_string()));
+            }
+        }
+        // Values with spaces or `=`, such as the frequencies and InChIs, are quoted.
+        let property = |(key, value): &(&str, String)| match value.contains([' ', '=']) {
+            true => format!("{}=\"{}\"", key, value),
+            false => format!("{}={}", key, value),
+        };
+        data.comment = properties.iter().map(property).collect::<Vec<String>>().join(" ");
+        Ok(data)
+    }
+
+    /// Parses the comment and atom lines of a frame with `count` atoms.
+    fn frame_body(&mut self, count: AtomCount) -> error::Result<data::Data> {
+        let comment = if self.next_line()? { self.line.clone() } else { String::new() };
+        let comment_line = self.line_count;
+
+        let layout = Layout::from_comment(&comment);
+        let mut data = data::Data {
+            count,
+            cell: crate::cell::Cell::from_comment(&comment),
+            comment,
+            atoms: vec![],
+        };
+
+        for _ in 0..count {
+            if !self.next_line()? {
+                return Err(error::FileParseError::EmptyLine(self.line_count + 1));
+            }
+
+            let mut atom = match &layout {
+                Some(layout) => layout.parse_atom(&self.line, self.line_count)?,
+                None => {
+                    if self.options.dialect == Dialect::Qm9 {
+                        self.line = self.line.replace("*^", "e");
+                    }
+                    let mut data_line = self.line.split_whitespace();
+                    let symbol = data_line.next().ok_or(error::FileParseError::NoAtomSymbol(self.line_count))?;
+                    let position = splitwhitespace_to_position(&mut data_line, &self.line_count)?;
+                    if self.options.dialect == Dialect::Strict && data_line.next().is_some() {
+                        return Err(error::FileParseError::ExtraColumns(self.line_count));
+                    }
+                    let mut atom = data::AtomData::new(symbol, position);
+                    if self.options.dialect == Dialect::Qm9 {
+                        let charge = data_line.next().map(str::parse);
+                        atom.charge = Some(charge.and_then(Result::ok).ok_or_else(|| {
+                            error::FileParseError::InvalidColumnData("charge".to_string(), self.line_count)
+                        })?);
+                    }
+                    atom
+                }
+            };
+            if self.options.reject_non_finite && !atom.position.is_finite() {
+                return Err(error::FileParseError::NonFinitePosition(self.line_count));
+            }
+            if let Some(labels) = &self.options.labels {
+                atom.resolved_element = labels.resolve(&atom.symbol);
+            }
+            if let Some(labels) = self.options.labels.as_ref().filter(|_| self.options.fix_symbols) {
+                atom.fix_symbol(labels);
+            }
+            if self.options.dialect == Dialect::Strict && atom.element().is_none() {
+                return Err(error::FileParseError::UnknownElement(atom.symbol, self.line_count));
+            }
+            data.atoms.push(atom);
+        }
+        if self.options.verify_checksums && data.verify_checksum() == Some(false) {
+            return Err(error::FileParseError::ChecksumMismatch(comment_line));
+        }
+
+        Ok(data)
+    }
+}
+impl<R: BufRead> Iterator for Frames<R> {
+    type Item = error::Result<data::Data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let frame = self.next_frame();
+        if matches!(frame, Ok(Some(_))) {
+            self.frame_count += 1;
+        } else {
+            self.done = true;
+        }
+
+        #[cfg(feature = "tracing")]
+        if self.done {
+            match &frame {
+                Err(e) => {
+                    tracing::warn!(frames = self.frame_count, line = self.line_count, error = %e, "parsing failed")
+                }
+                _ => tracing::debug!(
+                    frames = self.frame_count,
+                    lines = self.line_count,
+                    bytes = self.byte_count,
+                    "finished parsing"
+                ),
+            }
+        }
+        frame.transpose()
+    }
+}
+
+/// The names of the properties on the comment line of a QM9 frame.
+const QM9_PROPERTIES: [&str; 17] =
+    ["tag", "index", "A", "B", "C", "mu", "alpha", "homo", "lumo", "gap", "r2", "zpve", "U0", "U", "H", "G", "Cv"];
+
+fn splitwhitespace_to_position(data: &mut std::str::SplitWhitespace, line: &usize) -> error::Result<AtomPosition> {
+    Ok(AtomPosition::from([
+        match data.next().ok_or(error::FileParseError::NoPositionData(*line))?.parse() {
+            Ok(n) => n,
+            Err(_) => return Err(error::FileParseError::InvalidPositionData(*line)),
+        },
+        match data.next().ok_or(error::FileParseError::NoPositionData(*line))?.parse() {
+            Ok(n) => n,
+            Err(_) => return Err(error::FileParseError::InvalidPositionData(*line)),
+        },
+        match data.next().ok_or(error::FileParseError::NoPositionData(*line))?.parse() {
+            Ok(n) => n,
+            Err(_) => return Err(error::FileParseError::InvalidPositionData(*line)),
+        },
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::error::FileParseError;
+    use crate::labels::LabelMap;
+    use crate::testing::water;
+
+    const WATER: &str = "3\nwater\nO 0.0 0.0 0.0\nH 0.96 0.0 0.0\nH -0.24 0.93 0.0\n";
+
+    const HYDROGEN: &str = "2\nhydrogen\nH 0.0 0.0 0.0\nH 0.74 0.0 0.0\n";
+
+    fn parse(input: &str) -> error::Result<Vec<data::Data>> {
+        Frames::new(Cursor::new(input)).collect()
+    }
+
+    #[test]
+    fn single_frame() {
+        let frames = parse(WATER).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].comment, "water");
+        assert_eq!(frames[0].atoms.len(), 3);
+        assert_eq!(frames[0].atoms[2].position.0, [-0.24, 0.93, 0.0]);
+    }
+
+    #[test]
+    fn frames_separated_by_blank_lines() {
+        let frames = parse(&format!("{}\n{}", WATER, HYDROGEN)).unwrap();
+        assert_eq!(frames.iter().map(|data| data.comment.as_str()).collect::<Vec<_>>(), ["water", "hydrogen"]);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let frames = parse(&format!("{}\n{}", WATER, HYDROGEN).replace('\n', "\r\n")).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].comment, "hydrogen");
+        assert_eq!(frames[1].atoms[1].position.0, [0.74, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let mut frames = Frames::new(Cursor::new(format!("{}\n3\ncut off\nO 0.0 0.0 0.0\n", WATER)));
+        assert!(frames.next().unwrap().is_ok());
+        assert!(matches!(frames.next(), Some(Err(FileParseError::EmptyLine(_)))));
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn invalid_atom_count() {
+        let result = parse(&format!("{}\nnot a count\n", WATER));
+        assert!(matches!(result, Err(FileParseError::InvalidAtomCount(7))));
+    }
+
+    #[test]
+    fn labels_resolved_while_parsing() {
+        let options = ParseOptions {
+            labels: Some(LabelMap::common()),
+            ..ParseOptions::default()
+        };
+        let input = "3\nspc\nOW 0.0 0.0 0.0\nHW1 1.0 0.0 0.0\nHW2 0.0 1.0 0.0\n";
+        let frames = Frames::with_options(Cursor::new(input), options).collect::<Result<Vec<_>, _>>().unwrap();
+        let atom = &frames[0].atoms[1];
+        assert_eq!(atom.symbol, "HW1");
+        assert_eq!(atom.element().map(|element| element.symbol()), Some("H"));
+        assert_eq!(frames[0].mass(), water().mass());
+        assert_eq!(frames[0].formula(), "H2O");
+    }
+
+    #[test]
+    fn non_finite_positions() {
+        let input = "2\nbroken\nH 0.0 NaN 0.0\nH inf 0.0 0.0\n";
+        let frames = parse(input).unwrap();
+        assert!(frames[0].has_invalid_coords());
+        let options = ParseOptions {
+            reject_non_finite: true,
+            ..ParseOptions::default()
+        };
+        let mut frames = Frames::with_options(Cursor::new(input), options);
+        assert!(matches!(frames.next(), Some(Err(error::FileParseError::NonFinitePosition(3)))));
+        assert!(!parse(WATER).unwrap()[0].has_invalid_coords());
+    }
+
+    #[test]
+    fn counts_frames_and_bytes() {
+        let input = format!("{}\n{}", WATER, WATER);
+        let mut frames = Frames::new(Cursor::new(input.as_str()));
+        assert_eq!(frames.by_ref().count(), 2);
+        assert_eq!((frames.frame_count, frames.byte_count), (2, input.len()));
+    }
+
+    fn parse_strict(input: &str) -> error::Result<Vec<data::Data>> {
+        let options = ParseOptions {
+            dialect: Dialect::Strict,
+            ..ParseOptions::default()
+        };
+        Frames::with_options(Cursor::new(input), options).collect()
+    }
+
+    #[test]
+    fn strict_dialect() {
+        let frames = parse_strict(&format!("{}{}", WATER, HYDROGEN)).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert!(matches!(
+            parse_strict(&format!("{}\n{}", WATER, HYDROGEN)),
+            Err(error::FileParseError::BlankLine(6))
+        ));
+        assert!(matches!(parse_strict("1\n\nH 0 0 0 1.0\n"), Err(error::FileParseError::ExtraColumns(3))));
+        assert!(matches!(
+            parse_strict("1\n\nOW 0 0 0\n"),
+            Err(error::FileParseError::UnknownElement(symbol, 3)) if symbol == "OW"
+        ));
+        assert_eq!(parse("1\n\nOW 0 0 0 1.0\n").unwrap()[0].atoms[0].symbol, "OW");
+    }
+
+    #[test]
+    fn permissive_dialect() {
+        let options = ParseOptions {
+            dialect: Dialect::Permissive,
+            ..ParseOptions::default()
+        };
+        let input = format!("step 1 converged\n{}{}3\nbroken\nO 0 0\n{}-- done --\n", WATER, HYDROGEN, HYDROGEN);
+        let frames: Vec<_> = Frames::with_options(Cursor::new(input), options).collect::<Result<_, _>>().unwrap();
+        let comments: Vec<&str> = frames.iter().map(|data| data.comment.as_str()).collect();
+        assert_eq!(comments, ["water", "hydrogen", "hydrogen"]);
+    }
+
+    #[test]
+    fn byte_offset_of_next_frame() {
+        let input = format!("{}\n{}", WATER, HYDROGEN);
+        let mut frames = Frames::new(Cursor::new(input.as_str()));
+        frames.next().unwrap().unwrap();
+        let rest = &input[frames.byte_offset() as usize..];
+        assert_eq!(parse(rest).unwrap()[0].comment, "hydrogen");
+    }
+
+    #[test]
+    fn fix_symbols_while_parsing() {
+        let options = ParseOptions {
+            fix_symbols: true,
+            ..ParseOptions::default()
+        };
+        let input = "3\nspc\nOW 0.0 0.0 0.0\nHW1 1.0 0.0 0.0\nHW2 0.0 1.0 0.0\n";
+        let frames = Frames::with_options(Cursor::new(input), options).collect::<Result<Vec<_>, _>>().unwrap();
+        let symbols: Vec<&str> = frames[0].atoms.iter().map(|atom| atom.symbol.as_str()).collect();
+        assert_eq!(symbols, ["O", "H", "H"]);
+
+        let error = parse_strict("1\n\nCl2 0.0 0.0 0.0\n").unwrap_err();
+        assert_eq!(error.to_string(), "Unknown element symbol Cl2 at line 3, did you mean Cl?");
+    }
+
+    const METHANE: &str = "5\n\
+    gdb 1\t157.7118\t157.70997\t157.70699\t0.\t13.21\t-0.3877\t0.1171\t0.5048\t35.3641\t0.044749\t-40.47893\t\
+    -40.476062\t-40.475117\t-40.498597\t6.469\t\n\
+    C\t-0.0126981359\t 1.0858041578\t 0.0080009958\t-0.535689\n\
+    H\t 0.002150416\t-0.0060313176\t 0.0019761204\t 0.133921\n\
+    H\t 1.0117308433\t 1.4637511618\t 0.0002765748\t 0.133922\n\
+    H\t-0.540815069\t 1.4475266138\t-0.8766437152\t 0.133923\n\
+    H\t-0.5238136345\t 1.4379326443\t 0.9063972942\t 1.5*^-6\n\
+    1341.307\t1341.3284\t1341.365\t1562.6731\t1562.7453\t3038.3205\t3151.6034\t3151.6788\t3151.7078\n\
+    C\tC\t\n\
+    InChI=1S/CH4/h1H4\tInChI=1S/CH4/h1H4\n";
+
+    fn parse_qm9(input: &str) -> error::Result<Vec<data::Data>> {
+        let options = ParseOptions {
+            dialect: Dialect::Qm9,
+            ..ParseOptions::default()
+        };
+        Frames::with_options(Cursor::new(input), options).collect()
+    }
+
+    #[test]
+    fn qm9_dialect() {
+        let frames = parse_qm9(&format!("{}\n{}", METHANE, METHANE)).unwrap();
+        assert_eq!(frames.len(), 2);
+        let methane = &frames[1];
+        assert_eq!(methane.formula(), "CH4");
+        assert_eq!(methane.atoms[0].charge, Some(-0.535689));
+        assert_eq!(methane.atoms[4].charge, Some(1.5e-6));
+        assert!(methane.comment.starts_with("tag=gdb index=1 A=157.7118 "));
+        assert!(methane.comment.contains(" U0=-40.47893 "));
+        assert!(methane.comment.contains(" frequencies=\"1341.307 1341.3284 "));
+        let identifiers = r#" smiles=C smiles_relaxed=C inchi="InChI=1S/CH4/h1H4" inchi_relaxed="InChI=1S/CH4/h1H4""#;
+        assert!(methane.comment.ends_with(identifiers));
+
+        let missing = METHANE.replace("\t6.469", "");
+        assert!(matches!(parse_qm9(&missing), Err(error::FileParseError::Qm9Properties(2))));
+        let truncated = METHANE.split_inclusive('\n').take(8).collect::<String>();
+        assert!(matches!(parse_qm9(&truncated), Err(error::FileParseError::EmptyLine(9))));
+    }
+
+    #[test]
+    fn skipped_regions() {
+        let options = ParseOptions {
+            dialect: Dialect::Permissive,
+            ..ParseOptions::default()
+        };
+        let input = format!("step 1 converged\n{}{}3\nbroken\nO 0 0\n{}-- done --\n", WATER, HYDROGEN, HYDROGEN);
+        let mut frames = Frames::with_options(Cursor::new(input.as_str()), options);
+        assert_eq!(frames.by_ref().count(), 3);
+        let broken = input.find("3\nbroken").unwrap() as u64;
+        let done = input.find("-- done").unwrap() as u64;
+        let skipped = [
+            Skipped {
+                start: 0,
+                end: 17,
+                lines: (1, 1),
+            },
+            Skipped {
+                start: broken,
+                end: broken + 15,
+                lines: (11, 13),
+            },
+            Skipped {
+                start: done,
+                end: input.len() as u64,
+                lines: (18, 18),
+            },
+        ];
+        assert_eq!(frames.skipped(), skipped);
+    }
+
+    #[test]
+    fn frames_back_to_back() {
+        let frames = parse(&format!("{}{}{}", WATER, HYDROGEN, WATER)).unwrap();
+        assert_eq!(frames.iter().map(|data| data.atoms.len()).collect::<Vec<_>>(), [3, 2, 3]);
+        let frames = parse(&format!("{}\n{}{}", WATER, HYDROGEN, WATER).replace('\n', "\r\n")).unwrap();
+        assert_eq!(frames[1].comment, "hydrogen");
+    }
+
+    #[test]
+    fn trailing_blank_lines() {
+        let frames = parse(&format!("{}{}\n\n  \n", WATER, HYDROGEN)).unwrap();
+        assert_eq!(frames.len(), 2);
+    }
+}
@@ -0,0 +1,197 @@
+//! Reads `.xyz` files from HTTP(S) and S3 URLs, streaming the frames as they are downloaded.
+//!
+//! [`read`](crate::read) and [`read_with`](crate::read_with) also accept such URLs when this feature is enabled:
+//!
+//! ```no_run
+//! let file = rust_xyz_chem::read("https://example.com/water.xyz")?;
+//! for frame in rust_xyz_chem::remote::frames("s3://bucket/trajectories/run1.xyz")? {
+//!     println!("{}", frame?.atoms.len());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! S3 requests are signed with the credentials in the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+//! optionally `AWS_SESSION_TOKEN` environment variables, or sent unsigned for public buckets without them.
+//! The region is read from `AWS_REGION` or `AWS_DEFAULT_REGION`, and defaults to `us-east-1`.
+//! S3-compatible stores such as MinIO are used by setting `AWS_ENDPOINT_URL` to their address.
+
+use std::io::{self, BufReader, Read};
+
+use super::error;
+use super::provenance::sha256;
+use super::reader::{Frames, ParseOptions};
+use super::writer::timestamp;
+
+/// Whether a path is a URL that can be read with this module.
+pub fn is_url(path: &str) -> bool {
+    ["http://", "https://", "s3://"].iter().any(|scheme| path.starts_with(scheme))
+}
+
+/// Opens a URL for reading one frame at a time.
+pub fn frames(url: &str) -> error::Result<Frames<BufReader<Box<dyn Read + Send + Sync>>>> {
+    frames_with(url, &ParseOptions::default())
+}
+
+/// Opens a URL for reading one frame at a time, parsed with `options`.
+pub fn frames_with(url: &str, options: &ParseOptions) -> error::Result<Frames<BufReader<Box<dyn Read + Send + Sync>>>> {
+    Ok(Frames::with_options(BufReader::new(open(url)?), options.clone()))
+}
+
+/// Opens a URL for reading its contents as they are downloaded.
+#[cfg_attr(feature = "tracing", tracing::instrument(err))]
+pub fn open(url: &str) -> io::Result<Box<dyn Read + Send + Sync>> {
+    let request = match url.strip_prefix("s3://") {
+        Some(location) => s3_request(location)?,
+        None if is_url(url) => ureq::get(url),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("not a URL: {}", url))),
+    };
+    match request.call() {
+        Ok(response) => Ok(response.into_reader()),
+        Err(ureq::Error::Status(status, response)) => Err(io::Error::other(format!(
+            "{} responded with status {} {}",
+            url,
+            status,
+            response.status_text()
+        ))),
+        Err(e) => Err(io::Error::other(e)),
+    }
+}
+
+/// A request for the object at `bucket/key`, signed if there are credentials.
+fn s3_request(location: &str) -> io::Result<ureq::Request> {
+    let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let (bucket, key) = location
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("not an S3 object: s3://{}", location)))?;
+    let region = env("AWS_REGION").or_else(|| env("AWS_DEFAULT_REGION")).unwrap_or_else(|| "us-east-1".into());
+    let key = key.split('/').map(encode).collect::<Vec<String>>().join("/");
+
+    // Custom endpoints are addressed by path, AWS by virtual host.
+    let (url, host, path) = match env("AWS_ENDPOINT_URL") {
+        Some(endpoint) => {
+            let endpoint = endpoint.trim_end_matches('/');
+            let host = endpoint.split_once("://").map_or(endpoint, |(_, rest)| rest);
+            let path = format!("/{}/{}", encode(bucket), key);
+            (format!("{}{}", endpoint, path), host.to_string(), path)
+        }
+        None => {
+            let host = format!("{}.s3.{}.amazonaws.com", bucket, region);
+            (format!("https://{}/{}", host, key), host, format!("/{}", key))
+        }
+    };
+
+    let request = ureq::get(&url);
+    let (Some(access_key), Some(secret_key)) = (env("AWS_ACCESS_KEY_ID"), env("AWS_SECRET_ACCESS_KEY")) else {
+        return Ok(request);
+    };
+    let date = timestamp(std::time::SystemTime::now()).replace(['-', ':'], "");
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", "UNSIGNED-PAYLOAD".to_string()),
+        ("x-amz-date", date.clone()),
+    ];
+    if let Some(token) = env("AWS_SESSION_TOKEN") {
+        headers.push(("x-amz-security-token", token));
+    }
+    let authorization = authorization(&access_key, &secret_key, &region, &date, &path, &headers);
+
+    // The host header is set by the client.
+    Ok(headers[1..]
+        .iter()
+        .fold(request, |request, (name, value)| request.set(name, value))
+        .set("Authorization", &authorization))
+}
+
+/// The AWS Signature Version 4 `Authorization` header of an S3 `GET` request without a query,
+/// at `date` formatted like `20240131T120000Z`, with `headers` sorted by their lowercase names.
+fn authorization(
+    access_key: &str,
+    secret_key: &str,
+    region: &str,
+    date: &str,
+    path: &str,
+    headers: &[(&str, String)],
+) -> String {
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<&str>>().join(";");
+    let canonical_headers: String =
+        headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let payload = headers.iter().find(|(name, _)| *name == "x-amz-content-sha256").map_or("", |(_, v)| v.as_str());
+    let canonical_request = format!("GET\n{}\n\n{}\n{}\n{}", path, canonical_headers, signed_headers, payload);
+
+    let scope = format!("{}/{}/s3/aws4_request", &date[..8], region);
+    let hash = hex(&sha256(canonical_request.as_bytes()));
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", date, scope, hash);
+    let key = [&date[..8], region, "s3", "aws4_request"]
+        .iter()
+        .fold(format!("AWS4{}", secret_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()).to_vec());
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key,
+        scope,
+        signed_headers,
+        hex(&hmac(&key, string_to_sign.as_bytes()))
+    )
+}
+
+/// The HMAC-SHA256 of a message.
+fn hmac(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<u8>>();
+    let inner = sha256(&[pad(0x36), message.to_vec()].concat());
+    sha256(&[pad(0x5c), inner.to_vec()].concat())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Percent-encodes a path segment, keeping the unreserved characters.
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_test_vectors() {
+        // RFC 4231, test cases 2 and 6.
+        let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+        assert_eq!(hex(&hmac(b"Jefe", b"what do ya want for nothing?")), expected);
+        let expected = "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54";
+        assert_eq!(hex(&hmac(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")), expected);
+    }
+
+    #[test]
+    fn signed_header_and_encoding() {
+        let date = "20240131T120000Z";
+        let headers = [("host", "bucket.s3.us-east-1.amazonaws.com".to_string()), ("x-amz-date", date.to_string())];
+        let authorization = authorization("AKID", "secret", "us-east-1", date, "/a.xyz", &headers);
+        let prefix = "AWS4-HMAC-SHA256 Credential=AKID/20240131/us-east-1/s3/aws4_request, \
+                      SignedHeaders=host;x-amz-date, Signature=";
+        assert!(authorization.starts_with(prefix));
+        assert_eq!(authorization.len(), prefix.len() + 64);
+        assert_eq!(encode("run 1+2~.xyz"), "run%201%2B2~.xyz");
+    }
+
+    #[test]
+    fn urls() {
+        assert!(is_url("s3://bucket/key.xyz") && is_url("https://example.com/a.xyz"));
+        assert!(!is_url("data/https.xyz"));
+        assert_eq!(open("ftp://example.com/a.xyz").err().unwrap().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(open("s3://bucket").err().unwrap().kind(), io::ErrorKind::InvalidInput);
+    }
+}
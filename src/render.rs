@@ -0,0 +1,315 @@
+//! Draws frames as simple ball-and-stick pictures, and exports them as scenes for POV-Ray and 3D software.
+
+use std::fmt::Write;
+
+use super::data::Data;
+use super::geometry::Axis;
+use super::position::Position;
+
+/// Options for [`to_svg`].
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    /// The axis to look down, from its positive end.
+    pub axis: Axis,
+    /// The width of the picture in pixels. The height follows from the extent of the structure.
+    pub width: f64,
+    /// The radius of the atoms as a fraction of their covalent radius.
+    pub atom_scale: f64,
+    /// The width of the bonds in Ångström.
+    pub bond_width: f64,
+    /// The background color, e.g. `"white"`, or `None` for a transparent background.
+    pub background: Option<String>,
+}
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions {
+            axis: Axis::Z,
+            width: 400.0,
+            atom_scale: 0.6,
+            bond_width: 0.12,
+            background: None,
+        }
+    }
+}
+
+/// Draws a frame as an SVG picture of circles for the atoms and lines for the perceived bonds, see [`Data::bonds`].
+/// Atoms and bonds closer to the viewer are drawn on top.
+pub fn to_svg(data: &Data, options: &SvgOptions) -> String {
+    let [h, v, d] = view_axes(options.axis);
+    let radii = radii(data, options.atom_scale);
+
+    let extent = |axis: usize, sign: f64| {
+        data.atoms
+            .iter()
+            .zip(&radii)
+            .map(|(atom, r)| sign * atom.position[axis] + r)
+            .fold(f64::NEG_INFINITY, f64::max)
+            * sign
+    };
+    let (min_h, max_h, min_v, max_v) = if data.atoms.is_empty() {
+        (0.0, 1.0, 0.0, 1.0)
+    } else {
+        (extent(h, -1.0), extent(h, 1.0), extent(v, -1.0), extent(v, 1.0))
+    };
+    let scale = options.width / (max_h - min_h).max(f64::EPSILON);
+    let height = (max_v - min_v) * scale;
+    let x = |i: usize| (data.atoms[i].position[h] - min_h) * scale;
+    let y = |i: usize| (max_v - data.atoms[i].position[v]) * scale;
+
+    // Bonds are drawn as two halves in the colors of their atoms, each just below its atom.
+    enum Shape {
+        Atom(usize),
+        HalfBond(usize, usize),
+    }
+    let mut shapes: Vec<(f64, Shape)> =
+        (0..data.atoms.len()).map(|i| (data.atoms[i].position[d], Shape::Atom(i))).collect();
+    for (i, j) in data.bonds() {
+        let depth = |k: usize| data.atoms[k].position[d] - radii[k];
+        shapes.push((depth(i), Shape::HalfBond(i, j)));
+        shapes.push((depth(j), Shape::HalfBond(j, i)));
+    }
+    shapes.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.1}\" height=\"{:.1}\" viewBox=\"0 0 {:.1} {:.1}\">\n",
+        options.width, height, options.width, height
+    );
+    if let Some(background) = &options.background {
+        writeln!(svg, "  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", background).unwrap();
+    }
+    for (_, shape) in shapes {
+        match shape {
+            Shape::Atom(i) => writeln!(
+                svg,
+                "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\" stroke=\"black\" stroke-width=\"1\"/>",
+                x(i),
+                y(i),
+                radii[i] * scale,
+                hex(color(data, i))
+            )
+            .unwrap(),
+            Shape::HalfBond(i, j) => writeln!(
+                svg,
+                "  <line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\" stroke-width=\"{:.2}\"/>",
+                x(i),
+                y(i),
+                (x(i) + x(j)) / 2.0,
+                (y(i) + y(j)) / 2.0,
+                hex(color(data, i)),
+                options.bond_width * scale
+            )
+            .unwrap(),
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Options for the three-dimensional scenes of [`to_povray`] and [`to_obj`].
+#[derive(Debug, Clone)]
+pub struct SceneOptions {
+    /// The axis the camera looks down, from its positive end. Only used by [`to_povray`].
+    pub axis: Axis,
+    /// The radius of the atoms as a fraction of their covalent radius.
+    pub atom_scale: f64,
+    /// The radius of the bonds in Ångström.
+    pub bond_radius: f64,
+}
+impl Default for SceneOptions {
+    fn default() -> SceneOptions {
+        SceneOptions {
+            axis: Axis::Z,
+            atom_scale: 0.6,
+            bond_radius: 0.1,
+        }
+    }
+}
+
+/// Writes a frame as a POV-Ray scene of spheres for the atoms and cylinders for the perceived bonds,
+/// with an orthographic camera and a light looking down the axis.
+/// Render it with e.g. `povray +W800 +H600 +A scene.pov`.
+pub fn to_povray(data: &Data, options: &SceneOptions) -> String {
+    let [h, v, d] = view_axes(options.axis);
+    let radii = radii(data, options.atom_scale);
+    // POV-Ray is left-handed, so the depth axis is flipped to keep the picture unmirrored.
+    let point = |p: Position| format!("<{:.4}, {:.4}, {:.4}>", p[h], p[v], 0.0 - p[d]);
+
+    let (center, size) = match data.bounding_box() {
+        Some(b) => (b.center(), b.size()),
+        None => (Position::default(), Position::default()),
+    };
+    let padding = 2.0 * radii.iter().fold(0.0, |a: f64, &b| a.max(b)) + 1.0;
+    let (width, height) = (size[h] + padding, size[v] + padding);
+    let camera = center + Position(std::array::from_fn(|i| if i == d { size[d] + 10.0 } else { 0.0 }));
+
+    let mut pov = String::from("#version 3.7;\nglobal_settings { assumed_gamma 1.0 }\n");
+    writeln!(pov, "background {{ color rgb <1, 1, 1> }}\n").unwrap();
+    writeln!(pov, "camera {{").unwrap();
+    writeln!(pov, "  orthographic").unwrap();
+    writeln!(pov, "  location {}", point(camera)).unwrap();
+    writeln!(pov, "  look_at {}", point(center)).unwrap();
+    writeln!(pov, "  right x * {:.4}", width.max(height * 4.0 / 3.0)).unwrap();
+    writeln!(pov, "  up y * {:.4}", height.max(width * 3.0 / 4.0)).unwrap();
+    writeln!(pov, "}}").unwrap();
+    writeln!(pov, "light_source {{ {} color rgb <1, 1, 1> }}", point(camera)).unwrap();
+    writeln!(pov, "#declare Finish = finish {{ phong 0.7 specular 0.3 }};\n").unwrap();
+
+    let pigment = |i: usize| {
+        let [r, g, b] = color(data, i).map(|c| c as f64 / 255.0);
+        format!("pigment {{ color rgb <{:.3}, {:.3}, {:.3}> }} finish {{ Finish }}", r, g, b)
+    };
+    for (i, atom) in data.atoms.iter().enumerate() {
+        writeln!(pov, "sphere {{ {}, {:.4} {} }}", point(atom.position), radii[i], pigment(i)).unwrap();
+    }
+    for (i, j) in data.bonds() {
+        let middle = (data.atoms[i].position + data.atoms[j].position) / 2.0;
+        for (k, end) in [(i, middle), (j, middle)] {
+            let start = point(data.atoms[k].position);
+            let end = point(end);
+            writeln!(pov, "cylinder {{ {}, {}, {:.4} {} }}", start, end, options.bond_radius, pigment(k)).unwrap();
+        }
+    }
+    pov
+}
+
+/// Writes a frame as a Wavefront OBJ mesh of spheres for the atoms and cylinders for the perceived bonds,
+/// which can be imported into Blender and other 3D software.
+/// Each atom and bond half is a separate object, using a material named after its element, e.g. `usemtl O`.
+pub fn to_obj(data: &Data, options: &SceneOptions) -> String {
+    const SLICES: usize = 16;
+    const STACKS: usize = 8;
+
+    let radii = radii(data, options.atom_scale);
+    let material = |i: usize| data.atoms[i].element().map_or("X", |e| e.symbol());
+    let mut obj = String::new();
+    let mut vertices = 0;
+
+    for (i, atom) in data.atoms.iter().enumerate() {
+        writeln!(obj, "o atom{}_{}\nusemtl {}", i, atom.symbol, material(i)).unwrap();
+        for stack in 0..=STACKS {
+            let theta = std::f64::consts::PI * stack as f64 / STACKS as f64;
+            for slice in 0..SLICES {
+                let phi = 2.0 * std::f64::consts::PI * slice as f64 / SLICES as f64;
+                let direction = Position::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+                let p = atom.position + direction * radii[i];
+                writeln!(obj, "v {:.4} {:.4} {:.4}", p[0], p[1], p[2]).unwrap();
+            }
+        }
+        for stack in 0..STACKS {
+            for slice in 0..SLICES {
+                let index = |st: usize, sl: usize| vertices + st * SLICES + sl % SLICES + 1;
+                let (a, b) = (index(stack, slice), index(stack, slice + 1));
+                let (c, d) = (index(stack + 1, slice + 1), index(stack + 1, slice));
+                writeln!(obj, "f {} {} {} {}", a, d, c, b).unwrap();
+            }
+        }
+        vertices += (STACKS + 1) * SLICES;
+    }
+
+    for (i, j) in data.bonds() {
+        let middle = (data.atoms[i].position + data.atoms[j].position) / 2.0;
+        for (k, end) in [(i, middle), (j, middle)] {
+            let start = data.atoms[k].position;
+            let Some(axis) = (end - start).normalized() else {
+                continue;
+            };
+            // Two unit vectors perpendicular to the bond span the circles at its ends.
+            let helper = if axis[0].abs() < 0.9 { Position::new(1.0, 0.0, 0.0) } else { Position::new(0.0, 1.0, 0.0) };
+            let u = axis.cross(helper).normalized().unwrap();
+            let w = axis.cross(u);
+
+            writeln!(obj, "o bond{}_{}\nusemtl {}", k, if k == i { j } else { i }, material(k)).unwrap();
+            for center in [start, end] {
+                for slice in 0..SLICES {
+                    let phi = 2.0 * std::f64::consts::PI * slice as f64 / SLICES as f64;
+                    let p = center + (u * phi.cos() + w * phi.sin()) * options.bond_radius;
+                    writeln!(obj, "v {:.4} {:.4} {:.4}", p[0], p[1], p[2]).unwrap();
+                }
+            }
+            for slice in 0..SLICES {
+                let next = (slice + 1) % SLICES;
+                let [a, b] = [slice, next].map(|s| vertices + s + 1);
+                let [c, d] = [next, slice].map(|s| vertices + SLICES + s + 1);
+                writeln!(obj, "f {} {} {} {}", a, b, c, d).unwrap();
+            }
+            vertices += 2 * SLICES;
+        }
+    }
+    obj
+}
+
+/// The horizontal, vertical and depth coordinate indices when looking down an axis from its positive end.
+fn view_axes(axis: Axis) -> [usize; 3] {
+    match axis {
+        Axis::X => [1, 2, 0],
+        Axis::Y => [2, 0, 1],
+        Axis::Z => [0, 1, 2],
+    }
+}
+
+/// The drawn radius of every atom, a fraction of its covalent radius, or of 1.5 Å for unknown elements.
+fn radii(data: &Data, atom_scale: f64) -> Vec<f64> {
+    data.atoms
+        .iter()
+        .map(|atom| atom.element().and_then(|e| e.covalent_radius()).unwrap_or(1.5) * atom_scale)
+        .collect()
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// The CPK color of an atom, or gray for unknown elements.
+fn color(data: &Data, atom: usize) -> [u8; 3] {
+    data.atoms[atom].element().map_or([0x80, 0x80, 0x80], |e| e.cpk_color())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::water;
+
+    #[test]
+    fn svg_atoms_and_bonds() {
+        let options = SvgOptions {
+            background: Some("white".to_string()),
+            ..SvgOptions::default()
+        };
+        let svg = to_svg(&water(), &options);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400.0\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches("<line").count(), 4);
+        assert_eq!(svg.matches("fill=\"#ff0d0d\"").count(), 1);
+        assert!(svg.contains("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>"));
+        let side = to_svg(&water(), &SvgOptions { axis: Axis::Y, ..SvgOptions::default() });
+        assert_eq!(side.matches("<circle").count(), 3);
+    }
+
+    #[test]
+    fn povray_scene() {
+        let pov = to_povray(&water(), &SceneOptions::default());
+        assert!(pov.starts_with("#version 3.7;\n"));
+        assert_eq!(pov.matches("\nsphere {").count(), 3);
+        assert_eq!(pov.matches("\ncylinder {").count(), 4);
+        assert!(pov.contains("sphere { <-0.2400, 0.9300, 0.0000>, "));
+        // POV-Ray is left-handed, so the depth is flipped.
+        let side = to_povray(&water(), &SceneOptions { axis: Axis::X, ..SceneOptions::default() });
+        assert!(side.contains("sphere { <0.0000, 0.0000, -0.9600>, "));
+        assert!(pov.contains("pigment { color rgb <1.000, 0.051, 0.051> }"));
+    }
+
+    #[test]
+    fn obj_mesh() {
+        let obj = to_obj(&water(), &SceneOptions::default());
+        let count = |prefix: &str| obj.lines().filter(|line| line.starts_with(prefix)).count();
+        // Spheres of 9 rings of 16 vertices, and bond halves of 2 rings.
+        assert_eq!(count("o "), 7);
+        assert_eq!(count("v "), 3 * 9 * 16 + 4 * 2 * 16);
+        assert_eq!(count("f "), 3 * 8 * 16 + 4 * 16);
+        assert!(obj.starts_with("o atom0_O\nusemtl O\n"));
+        assert!(obj.contains("o bond1_0\nusemtl H\n"));
+        let last = obj.lines().rfind(|line| line.starts_with("f ")).unwrap();
+        assert!(last.split(' ').skip(1).all(|index| index.parse::<usize>().unwrap() <= count("v ")));
+    }
+}
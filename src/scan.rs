@@ -0,0 +1,161 @@
+//! Fast scans of large `.xyz` files that only read the atom count and comment lines of every frame,
+//! skipping over the atom lines without parsing them, e.g. to count the frames or plot the energies
+//! of a multi-gigabyte trajectory.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use super::error::{self, FileParseError};
+use super::metadata::parse_properties;
+
+/// The atom count and comment line of a frame, found by [`scan_headers`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// The byte offset of the atom count line in the file.
+    pub offset: u64,
+    /// The number of atoms.
+    pub count: usize,
+    pub comment: String,
+}
+impl Header {
+    /// The value of the comment property `key`, compared case-insensitively.
+    pub fn property(&self, key: &str) -> Option<String> {
+        parse_properties(&self.comment)
+            .into_iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    /// The energy from the `energy` property of the comment.
+    pub fn energy(&self) -> Option<f64> {
+        self.property("energy")?.parse().ok()
+    }
+}
+
+/// Reads the header of every frame of a file. Frames may be separated by blank lines.
+pub fn scan_headers<P: AsRef<Path>>(path: P) -> error::Result<Vec<Header>> {
+    scan_headers_reader(BufReader::new(fs::File::open(path)?))
+}
+
+/// Reads the header of every frame read from `reader`, like [`scan_headers`].
+pub fn scan_headers_reader<R: BufRead>(reader: R) -> error::Result<Vec<Header>> {
+    let mut headers = vec![];
+    Scanner::new(reader).run(true, |offset, count, comment| {
+        headers.push(Header {
+            offset,
+            count,
+            comment: comment.map(str::to_string).unwrap_or_default(),
+        })
+    })?;
+    Ok(headers)
+}
+
+/// The number of frames and the total number of atoms of a file, skipping the comment lines as well.
+pub fn count_frames<P: AsRef<Path>>(path: P) -> error::Result<(usize, usize)> {
+    let (mut frames, mut atoms) = (0, 0);
+    Scanner::new(BufReader::new(fs::File::open(path)?)).run(false, |_, count, _| {
+        frames += 1;
+        atoms += count;
+    })?;
+    Ok((frames, atoms))
+}
+
+/// Reads the headers line by line, and skips the atom lines without decoding them.
+struct Scanner<R> {
+    reader: R,
+    line: String,
+    line_count: usize,
+    byte_count: u64,
+}
+impl<R: BufRead> Scanner<R> {
+    fn new(reader: R) -> Scanner<R> {
+        Scanner {
+            reader,
+            line: String::new(),
+            line_count: 0,
+            byte_count: 0,
+        }
+    }
+
+    /// Reads the next line into `self.line`, with the line ending. Returns `false` at the end of the input.
+    fn next_line(&mut self) -> error::Result<bool> {
+        self.line.clear();
+        let bytes = self.reader.read_line(&mut self.line)?;
+        self.line_count += 1;
+        self.byte_count += bytes as u64;
+        Ok(bytes > 0)
+    }
+
+    /// Skips a line without decoding it. Returns `false` at the end of the input.
+    fn skip_line(&mut self) -> error::Result<bool> {
+        let bytes = self.reader.skip_until(b'\n')?;
+        self.line_count += 1;
+        self.byte_count += bytes as u64;
+        Ok(bytes > 0)
+    }
+
+    /// Calls `frame` with the offset, atom count and comment of every frame, or no comment if `comments` is false.
+    fn run(mut self, comments: bool, mut frame: impl FnMut(u64, usize, Option<&str>)) -> error::Result<()> {
+        loop {
+            let offset = self.byte_count;
+            if !self.next_line()? {
+                return Ok(());
+            }
+            if self.line.trim().is_empty() {
+                continue;
+            }
+            let count: usize = self.line.trim().parse().map_err(|_| FileParseError::InvalidAtomCount(self.line_count))?;
+
+            let comment = match comments {
+                true => self.next_line()?.then(|| self.line.trim_end_matches(['\n', '\r'])),
+                false => self.skip_line()?.then_some(""),
+            };
+            if comment.is_none() && count > 0 {
+                return Err(FileParseError::EmptyLine(self.line_count));
+            }
+            frame(offset, count, comment.filter(|_| comments));
+            for _ in 0..count {
+                if !self.skip_line()? {
+                    return Err(FileParseError::EmptyLine(self.line_count));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::temp_path;
+
+    const TEXT: &str = "2\nenergy=-1.5 step=0\nH 0 0 0\nH 0.74 0 0\n\n2\r\nstep=1\r\nH 0 0 0\r\nH 0.75 0 0\r\n";
+
+    #[test]
+    fn headers_and_properties() {
+        let headers = scan_headers_reader(Cursor::new(TEXT)).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!((headers[0].offset, headers[0].count), (0, 2));
+        assert_eq!(headers[0].energy(), Some(-1.5));
+        assert_eq!(headers[1].offset, TEXT.find("2\r\n").unwrap() as u64);
+        assert_eq!(headers[1].comment, "step=1");
+        assert_eq!(headers[1].property("STEP").as_deref(), Some("1"));
+        assert_eq!(headers[1].energy(), None);
+    }
+
+    #[test]
+    fn count_frames_and_errors() {
+        let path = temp_path("scan.xyz");
+        std::fs::write(&path, TEXT).unwrap();
+        assert_eq!(count_frames(&path).unwrap(), (2, 4));
+        assert_eq!(scan_headers(&path).unwrap(), scan_headers_reader(Cursor::new(TEXT)).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let truncated = scan_headers_reader(Cursor::new("3\ncomment\nH 0 0 0\n"));
+        assert!(matches!(truncated, Err(FileParseError::EmptyLine(_))));
+        let invalid = scan_headers_reader(Cursor::new("1\nok\nH 0 0 0\nH 0 0 0\n"));
+        assert!(matches!(invalid, Err(FileParseError::InvalidAtomCount(4))));
+    }
+}
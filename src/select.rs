@@ -0,0 +1,347 @@
+//! Selects atoms with expressions such as `element C and z > 1.5`, `group LIG` or `not (index 0 to 9)`.
+//!
+//! The following keywords match atoms:
+//!
+//! - `all` and `none`.
+//! - `element` or `symbol`, followed by one or more atomic symbols or labels (case-insensitive).
+//! - `name`, followed by one or more atom names.
+//! - `group`, `residue` or `resname`, followed by one or more group names.
+//! - `index`, followed by one or more zero-based atom indices or inclusive ranges `a to b`.
+//! - `id`, like `index` but matching the index stored in the file.
+//! - `tag`, followed by a tag layer and one or more values, see [`tags`](crate::tags).
+//! - `x`, `y` or `z`, followed by a comparison `<`, `<=`, `>`, `>=`, `==` or `!=` and a number.
+//!
+//! These combine with `not`, `and`, `or` and parentheses, where `not` binds strongest and `or` weakest.
+//!
+//! Selections, and other index sets such as [`Data::atoms_within_sphere`], are ascending lists of atom indices,
+//! which can be combined with [`union`], [`intersection`] and [`difference`].
+
+use super::data::{AtomData, Data};
+use super::error::SelectionError;
+
+/// A parsed selection expression.
+#[derive(Debug, Clone)]
+pub struct Selection(Node);
+
+#[derive(Debug, Clone)]
+enum Node {
+    All,
+    None,
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Element(Vec<String>),
+    Name(Vec<String>),
+    Group(Vec<String>),
+    Index(Vec<(usize, usize)>),
+    Id(Vec<(usize, usize)>),
+    Tag(String, Vec<String>),
+    Compare(usize, Comparison, f64),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Selection {
+    /// Parses a selection expression.
+    pub fn parse(expression: &str) -> Result<Selection, SelectionError> {
+        let tokens = tokenize(expression);
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+        let node = parser.or()?;
+        match parser.peek() {
+            None => Ok(Selection(node)),
+            Some(token) => Err(SelectionError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// Whether the atom at `index` of a frame is selected.
+    pub fn matches(&self, index: usize, atom: &AtomData) -> bool {
+        self.0.matches(index, atom)
+    }
+
+    /// The indices of the selected atoms of a frame, in ascending order.
+    pub fn apply(&self, data: &Data) -> Vec<usize> {
+        data.atoms
+            .iter()
+            .enumerate()
+            .filter(|(i, atom)| self.matches(*i, atom))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl Data {
+    /// The indices of the atoms selected by an expression, in ascending order.
+    /// See the [`select`](crate::select) module for the syntax.
+    pub fn select(&self, expression: &str) -> Result<Vec<usize>, SelectionError> {
+        Ok(Selection::parse(expression)?.apply(self))
+    }
+}
+
+impl Node {
+    fn matches(&self, index: usize, atom: &AtomData) -> bool {
+        let in_ranges = |ranges: &[(usize, usize)], i: usize| ranges.iter().any(|&(a, b)| a <= i && i <= b);
+        let in_names = |names: &[String], name: &Option<String>| name.as_ref().is_some_and(|n| names.contains(n));
+
+        match self {
+            Node::All => true,
+            Node::None => false,
+            Node::Not(node) => !node.matches(index, atom),
+            Node::And(a, b) => a.matches(index, atom) && b.matches(index, atom),
+            Node::Or(a, b) => a.matches(index, atom) || b.matches(index, atom),
+            Node::Element(symbols) => {
+                let element = atom.element();
+                symbols.iter().any(|s| {
+                    s.eq_ignore_ascii_case(&atom.symbol) || element.is_some_and(|e| e.symbol().eq_ignore_ascii_case(s))
+                })
+            }
+            Node::Name(names) => in_names(names, &atom.name),
+            Node::Group(names) => in_names(names, &atom.group),
+            Node::Index(ranges) => in_ranges(ranges, index),
+            Node::Id(ranges) => atom.index.is_some_and(|i| in_ranges(ranges, i)),
+            Node::Tag(layer, values) => atom.tag(layer).is_some_and(|tag| values.iter().any(|v| v == tag)),
+            Node::Compare(axis, comparison, value) => {
+                let v = atom.position[*axis];
+                match comparison {
+                    Comparison::Less => v < *value,
+                    Comparison::LessEqual => v <= *value,
+                    Comparison::Greater => v > *value,
+                    Comparison::GreaterEqual => v >= *value,
+                    Comparison::Equal => v == *value,
+                    Comparison::NotEqual => v != *value,
+                }
+            }
+        }
+    }
+}
+
+/// Splits an expression into words, parentheses and comparison operators.
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if "<>=!".contains(c) {
+            let mut op = String::from(c);
+            chars.next();
+            if let Some(c) = chars.next_if_eq(&'=') {
+                op.push(c);
+            }
+            tokens.push(op);
+        } else {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"()<>=!".contains(*c)) {
+                word.push(c);
+            }
+            tokens.push(word);
+        }
+    }
+
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+}
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<&'a str, SelectionError> {
+        let token = self.peek().ok_or(SelectionError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<Node, SelectionError> {
+        let mut node = self.and()?;
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, SelectionError> {
+        let mut node = self.not()?;
+        while self.peek() == Some("and") {
+            self.pos += 1;
+            node = Node::And(Box::new(node), Box::new(self.not()?));
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> Result<Node, SelectionError> {
+        if self.peek() == Some("not") {
+            self.pos += 1;
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, SelectionError> {
+        let token = self.next()?;
+        Ok(match token {
+            "(" => {
+                let node = self.or()?;
+                match self.next()? {
+                    ")" => node,
+                    token => return Err(SelectionError::UnexpectedToken(token.to_string())),
+                }
+            }
+            "all" => Node::All,
+            "none" => Node::None,
+            "element" | "symbol" => Node::Element(self.words()?),
+            "name" => Node::Name(self.words()?),
+            "group" | "residue" | "resname" => Node::Group(self.words()?),
+            "index" => Node::Index(self.ranges()?),
+            "id" => Node::Id(self.ranges()?),
+            "tag" => match self.next()? {
+                layer @ ("and" | "or" | "(" | ")") => return Err(SelectionError::UnexpectedToken(layer.to_string())),
+                layer => Node::Tag(layer.to_string(), self.words()?),
+            },
+            "x" | "y" | "z" => {
+                let axis = match token {
+                    "x" => 0,
+                    "y" => 1,
+                    _ => 2,
+                };
+                let comparison = match self.next()? {
+                    "<" => Comparison::Less,
+                    "<=" => Comparison::LessEqual,
+                    ">" => Comparison::Greater,
+                    ">=" => Comparison::GreaterEqual,
+                    "==" | "=" => Comparison::Equal,
+                    "!=" => Comparison::NotEqual,
+                    token => return Err(SelectionError::UnexpectedToken(token.to_string())),
+                };
+                Node::Compare(axis, comparison, number(self.next()?)?)
+            }
+            token => return Err(SelectionError::UnexpectedToken(token.to_string())),
+        })
+    }
+
+    /// One or more values, up to the next operator or parenthesis.
+    fn words(&mut self) -> Result<Vec<String>, SelectionError> {
+        let mut words = vec![];
+        while let Some(token) = self.peek().filter(|t| !matches!(*t, "and" | "or" | "(" | ")")) {
+            words.push(token.to_string());
+            self.pos += 1;
+        }
+        if words.is_empty() {
+            let token = self.peek().map(|t| SelectionError::UnexpectedToken(t.to_string()));
+            return Err(token.unwrap_or(SelectionError::UnexpectedEnd));
+        }
+        Ok(words)
+    }
+
+    /// One or more indices or inclusive ranges `a to b`.
+    fn ranges(&mut self) -> Result<Vec<(usize, usize)>, SelectionError> {
+        let words = self.words()?;
+        let mut ranges = vec![];
+        let mut words = words.iter().peekable();
+
+        while let Some(word) = words.next() {
+            let start = index(word)?;
+            if words.next_if(|w| *w == "to").is_some() {
+                let end = index(words.next().ok_or(SelectionError::UnexpectedEnd)?)?;
+                ranges.push((start, end));
+            } else {
+                ranges.push((start, start));
+            }
+        }
+
+        Ok(ranges)
+    }
+}
+
+/// The indices in either of two ascending index sets.
+pub fn union(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut union: Vec<usize> = a.iter().chain(b).copied().collect();
+    union.sort_unstable();
+    union.dedup();
+    union
+}
+
+/// The indices in both of two ascending index sets.
+pub fn intersection(a: &[usize], b: &[usize]) -> Vec<usize> {
+    a.iter().copied().filter(|i| b.binary_search(i).is_ok()).collect()
+}
+
+/// The indices in the first but not the second of two ascending index sets.
+pub fn difference(a: &[usize], b: &[usize]) -> Vec<usize> {
+    a.iter().copied().filter(|i| b.binary_search(i).is_err()).collect()
+}
+
+fn number(token: &str) -> Result<f64, SelectionError> {
+    token.parse().map_err(|_| SelectionError::InvalidNumber(token.to_string()))
+}
+
+fn index(token: &str) -> Result<usize, SelectionError> {
+    token.parse().map_err(|_| SelectionError::InvalidNumber(token.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::frame;
+
+    /// Water with a methane ligand, labelled with names and groups.
+    fn labelled() -> Data {
+        let atoms = [("O", [0.0; 3]), ("H", [0.96, 0.0, 0.0]), ("H", [-0.24, 0.93, 0.0]), ("C", [3.0, 0.0, 2.0])];
+        let mut data = frame("", &atoms);
+        for (i, atom) in data.atoms.iter_mut().enumerate() {
+            atom.group = Some(if i < 3 { "SOL" } else { "LIG" }.to_string());
+            atom.name = Some(format!("{}{}", atom.symbol, i));
+            atom.index = Some(10 + i);
+        }
+        data
+    }
+
+    #[test]
+    fn keywords() {
+        let data = labelled();
+        assert_eq!(data.select("all").unwrap(), [0, 1, 2, 3]);
+        assert!(data.select("none").unwrap().is_empty());
+        assert_eq!(data.select("element h").unwrap(), [1, 2]);
+        assert_eq!(data.select("name O0 C3").unwrap(), [0, 3]);
+        assert_eq!(data.select("group LIG").unwrap(), [3]);
+        assert_eq!(data.select("index 1 to 2").unwrap(), [1, 2]);
+        assert_eq!(data.select("id 10 13").unwrap(), [0, 3]);
+        assert_eq!(data.select("z > 1.5").unwrap(), [3]);
+        assert_eq!(data.select("x != 0").unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn operators_and_precedence() {
+        let data = labelled();
+        assert_eq!(data.select("element H and x > 0 or group LIG").unwrap(), [1, 3]);
+        assert_eq!(data.select("element H and (x > 0 or group LIG)").unwrap(), [1]);
+        assert_eq!(data.select("not element H and not group LIG").unwrap(), [0]);
+        assert!(Selection::parse("element H and").is_err());
+        assert!(Selection::parse("(all").is_err());
+        assert!(Selection::parse("all all").is_err());
+    }
+
+    #[test]
+    fn index_set_operations() {
+        assert_eq!(union(&[0, 2, 4], &[1, 2, 5]), [0, 1, 2, 4, 5]);
+        assert_eq!(intersection(&[0, 2, 4], &[1, 2, 4, 5]), [2, 4]);
+        assert_eq!(difference(&[0, 2, 4], &[2, 5]), [0, 4]);
+        assert!(intersection(&[], &[1]).is_empty());
+    }
+}
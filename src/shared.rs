@@ -0,0 +1,290 @@
+//! A read-only trajectory that threads share without copying its frames, e.g. to run several analyses
+//! of one large trajectory concurrently, and copy-on-write edits of its frames, which only store the atoms
+//! they change.
+//!
+//! ```no_run
+//! use rust_xyz_chem::shared::SharedTrajectory;
+//!
+//! let trajectory = SharedTrajectory::from(rust_xyz_chem::read("traj.xyz")?);
+//! let handles: Vec<_> = (0..4)
+//!     .map(|thread| {
+//!         let trajectory = trajectory.clone();
+//!         let frames = move || trajectory.iter().skip(thread).step_by(4).map(|data| data.atoms.len()).sum::<usize>();
+//!         std::thread::spawn(frames)
+//!     })
+//!     .collect();
+//! let atoms: usize = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+//! # Ok::<(), rust_xyz_chem::error::FileParseError>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::ops::Index;
+use std::sync::Arc;
+
+use super::data::{AtomData, Data, File};
+
+/// A handle to the frames of a trajectory. Cloning the handle only counts a reference, so every thread can hold
+/// its own handle to the same frames, which are freed once the last handle is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct SharedTrajectory {
+    frames: Arc<Vec<Data>>,
+}
+impl SharedTrajectory {
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether there are no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The frame at `index`, or `None` if there is no such frame.
+    pub fn get(&self, index: usize) -> Option<&Data> {
+        self.frames.get(index)
+    }
+
+    /// The frames, in order.
+    pub fn frames(&self) -> &[Data] {
+        &self.frames
+    }
+
+    /// Iterates over the frames in order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Data> {
+        self.frames.iter()
+    }
+
+    /// The number of handles to the frames, including this one.
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.frames)
+    }
+
+    /// Takes the frames back as a file without copying them if this is the last handle,
+    /// or returns the handle otherwise.
+    pub fn try_into_file(self) -> Result<File, SharedTrajectory> {
+        Arc::try_unwrap(self.frames)
+            .map(|data| File { data })
+            .map_err(|frames| SharedTrajectory { frames })
+    }
+
+    /// A copy of the frames as a file.
+    pub fn to_file(&self) -> File {
+        File {
+            data: self.frames.to_vec(),
+        }
+    }
+
+    /// A copy-on-write view of the frame at `index`, or `None` if there is no such frame.
+    pub fn frame(&self, index: usize) -> Option<CowFrame> {
+        (index < self.len()).then(|| CowFrame {
+            trajectory: self.clone(),
+            index,
+            comment: None,
+            atoms: BTreeMap::new(),
+        })
+    }
+
+    /// Applies `f` to a copy-on-write view of every frame with its index, e.g. to change a few atoms
+    /// of every frame of a long trajectory, which only stores the changed atoms.
+    pub fn map_frames<F: FnMut(usize, &mut CowFrame)>(&self, mut f: F) -> Vec<CowFrame> {
+        (0..self.len())
+            .map(|index| {
+                let mut frame = self.frame(index).expect("index is in range");
+                f(index, &mut frame);
+                frame
+            })
+            .collect()
+    }
+}
+impl From<File> for SharedTrajectory {
+    fn from(file: File) -> SharedTrajectory {
+        SharedTrajectory {
+            frames: Arc::new(file.data),
+        }
+    }
+}
+impl Index<usize> for SharedTrajectory {
+    type Output = Data;
+
+    fn index(&self, index: usize) -> &Data {
+        &self.frames[index]
+    }
+}
+impl<'a> IntoIterator for &'a SharedTrajectory {
+    type Item = &'a Data;
+    type IntoIter = std::slice::Iter<'a, Data>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.frames.iter()
+    }
+}
+
+impl File {
+    /// Moves the frames into a [`SharedTrajectory`] without copying them, and applies `f` to a copy-on-write view
+    /// of every frame with its index, like [`SharedTrajectory::map_frames`]. The views only store the atoms
+    /// they change, and share the others with the trajectory.
+    pub fn map_frames<F: FnMut(usize, &mut CowFrame)>(self, f: F) -> Vec<CowFrame> {
+        SharedTrajectory::from(self).map_frames(f)
+    }
+}
+
+/// A frame of a [`SharedTrajectory`] with copy-on-write edits: atoms are copied from the shared frame
+/// the first time they are changed, and the other atoms are read from the shared frame.
+/// The number of atoms and the cell are those of the shared frame.
+#[derive(Debug, Clone)]
+pub struct CowFrame {
+    trajectory: SharedTrajectory,
+    index: usize,
+    comment: Option<String>,
+    atoms: BTreeMap<usize, AtomData>,
+}
+impl CowFrame {
+    /// The shared frame, without the edits.
+    pub fn original(&self) -> &Data {
+        &self.trajectory[self.index]
+    }
+
+    /// The index of the frame in the trajectory.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The number of atoms.
+    pub fn len(&self) -> usize {
+        self.original().atoms.len()
+    }
+
+    /// Whether the frame has no atoms.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The comment, edited or shared.
+    pub fn comment(&self) -> &str {
+        self.comment.as_deref().unwrap_or(&self.original().comment)
+    }
+
+    /// Replaces the comment.
+    pub fn set_comment<S: Into<String>>(&mut self, comment: S) {
+        self.comment = Some(comment.into());
+    }
+
+    /// The atom at `index`, edited or shared, or `None` if there is no such atom.
+    pub fn atom(&self, index: usize) -> Option<&AtomData> {
+        self.atoms.get(&index).or_else(|| self.original().atoms.get(index))
+    }
+
+    /// The atom at `index` for editing, copied from the shared frame on first access,
+    /// or `None` if there is no such atom.
+    pub fn atom_mut(&mut self, index: usize) -> Option<&mut AtomData> {
+        let atom = self.trajectory.frames.get(self.index)?.atoms.get(index)?;
+        Some(self.atoms.entry(index).or_insert_with(|| atom.clone()))
+    }
+
+    /// Iterates over the atoms in order, edited or shared.
+    pub fn atoms(&self) -> impl Iterator<Item = &AtomData> + '_ {
+        let original = &self.original().atoms;
+        original.iter().enumerate().map(|(i, atom)| self.atoms.get(&i).unwrap_or(atom))
+    }
+
+    /// The indices of the atoms that were accessed for editing, in ascending order.
+    pub fn edited_atoms(&self) -> Vec<usize> {
+        self.atoms.keys().copied().collect()
+    }
+
+    /// Drops the edits, returning to the shared frame.
+    pub fn revert(&mut self) {
+        self.comment = None;
+        self.atoms.clear();
+    }
+
+    /// A copy of the frame with the edits applied, e.g. for writing.
+    pub fn to_data(&self) -> Data {
+        let original = self.original();
+        Data {
+            count: original.count,
+            comment: self.comment().to_string(),
+            atoms: self.atoms().cloned().collect(),
+            cell: original.cell,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::trajectory;
+
+    #[test]
+    fn handles_share_the_frames() {
+        let trajectory = SharedTrajectory::from(trajectory(4));
+        assert_eq!(trajectory.len(), 4);
+        assert!(!trajectory.is_empty());
+        assert_eq!(trajectory[2].comment, "step=2");
+        assert_eq!(trajectory.get(3).unwrap().comment, "step=3");
+        assert!(trajectory.get(4).is_none());
+        assert_eq!((&trajectory).into_iter().count(), 4);
+
+        let handle = trajectory.clone();
+        assert_eq!(trajectory.handle_count(), 2);
+        let atoms = std::thread::spawn(move || handle.iter().map(|data| data.atoms.len()).sum::<usize>());
+        assert_eq!(atoms.join().unwrap(), 12);
+        assert_eq!(trajectory.handle_count(), 1);
+
+        let copy = trajectory.to_file();
+        let other = trajectory.clone();
+        let trajectory = trajectory.try_into_file().unwrap_err();
+        drop(other);
+        let file = trajectory.try_into_file().unwrap();
+        assert_eq!(file.data.len(), copy.data.len());
+        assert!(SharedTrajectory::default().is_empty());
+    }
+
+    #[test]
+    fn copy_on_write_frames() {
+        let trajectory = SharedTrajectory::from(trajectory(2));
+        assert!(trajectory.frame(2).is_none());
+        let mut frame = trajectory.frame(1).unwrap();
+        assert_eq!((frame.index(), frame.len(), frame.comment()), (1, 3, "step=1"));
+        assert!(!frame.is_empty());
+
+        frame.atom_mut(2).unwrap().position[1] = 0.5;
+        frame.set_comment("edited");
+        assert!(frame.atom_mut(3).is_none());
+        assert_eq!(frame.edited_atoms(), [2]);
+        assert_eq!(frame.atom(2).unwrap().position[1], 0.5);
+        assert_eq!(frame.original().atoms[2].position[1], 0.93);
+        assert_eq!(trajectory[1].atoms[2].position[1], 0.93);
+
+        let data = frame.to_data();
+        assert_eq!(data.comment, "edited");
+        assert_eq!(data.atoms.len(), 3);
+        assert_eq!(data.atoms[2].position[1], 0.5);
+        assert_eq!(data.atoms[0].position, trajectory[1].atoms[0].position);
+
+        frame.revert();
+        assert!(frame.edited_atoms().is_empty());
+        assert_eq!(frame.comment(), "step=1");
+    }
+
+    #[test]
+    fn map_frames_copies_only_edited_atoms() {
+        let edit = |i: usize, frame: &mut CowFrame| {
+            if i != 1 {
+                frame.atom_mut(1).unwrap().position[2] = 0.5;
+            }
+        };
+        let frames = trajectory(3).map_frames(edit);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].edited_atoms(), [1]);
+        assert!(frames[1].edited_atoms().is_empty());
+
+        let edited = frames[2].to_data();
+        assert_eq!(edited.atoms[1].position[2], 0.5);
+        assert_eq!(edited.atoms[0].position, frames[2].original().atoms[0].position);
+        assert_eq!(edited.comment, "step=2");
+        assert_eq!(frames[2].original().atoms[1].position[2], 0.0);
+        assert_eq!(SharedTrajectory::from(trajectory(3)).map_frames(edit)[2].edited_atoms(), [1]);
+    }
+}
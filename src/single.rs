@@ -0,0 +1,124 @@
+//! Single-precision frames, for analyses of very large trajectories where `f64` precision is unnecessary.
+//!
+//! A [`FrameF32`] stores the atomic symbols once per frame as a table, and the positions as `f32`,
+//! taking well under half the memory of a [`Data`] frame.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::cell::Cell;
+use super::data::{AtomData, Data};
+use super::error;
+
+/// A frame with single-precision positions and without per-atom metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameF32 {
+    /// A comment, title, or filename.
+    pub comment: String,
+    /// The periodic cell of the structure, if any.
+    pub cell: Option<Cell>,
+    /// The distinct atomic symbols of the frame.
+    pub symbols: Vec<String>,
+    /// The index into `symbols` of each atom.
+    pub species: Vec<usize>,
+    /// The position of each atom in Ångström.
+    pub positions: Vec<[f32; 3]>,
+}
+impl FrameF32 {
+    /// The atomic symbol of atom `i`.
+    pub fn symbol(&self, i: usize) -> &str {
+        &self.symbols[self.species[i]]
+    }
+}
+
+impl From<&Data> for FrameF32 {
+    /// Converts a frame to single precision, dropping per-atom metadata.
+    fn from(data: &Data) -> FrameF32 {
+        let mut symbols: Vec<String> = vec![];
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+        let species = data
+            .atoms
+            .iter()
+            .map(|atom| {
+                *indices.entry(&atom.symbol).or_insert_with(|| {
+                    symbols.push(atom.symbol.clone());
+                    symbols.len() - 1
+                })
+            })
+            .collect();
+
+        FrameF32 {
+            comment: data.comment.clone(),
+            cell: data.cell,
+            symbols,
+            species,
+            positions: data.atoms.iter().map(|atom| atom.position.0.map(|v| v as f32)).collect(),
+        }
+    }
+}
+
+impl From<&FrameF32> for Data {
+    fn from(frame: &FrameF32) -> Data {
+        Data {
+            count: frame.positions.len(),
+            comment: frame.comment.clone(),
+            atoms: frame
+                .positions
+                .iter()
+                .enumerate()
+                .map(|(i, position)| AtomData::new(frame.symbol(i), position.map(f64::from)))
+                .collect(),
+            cell: frame.cell,
+        }
+    }
+}
+
+/// Reads a `.xyz` file into single-precision frames, converting one frame at a time.
+pub fn read<P: AsRef<Path>>(path: P) -> error::Result<Vec<FrameF32>> {
+    super::frames(path)?.map(|data| data.map(|data| FrameF32::from(&data))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{temp_path, trajectory, water};
+
+    #[test]
+    fn convert_to_single_precision_and_back() {
+        let frame = FrameF32::from(&water());
+        assert_eq!(frame.symbols, ["O", "H"]);
+        assert_eq!(frame.species, [0, 1, 1]);
+        assert_eq!(frame.symbol(2), "H");
+        assert_eq!(frame.positions[1], [0.96, 0.0, 0.0]);
+
+        let data = Data::from(&frame);
+        assert_eq!(data.count, 3);
+        assert_eq!(data.comment, "water");
+        assert!((data.atoms[2].position[0] + 0.24).abs() < 1e-6);
+    }
+
+    #[test]
+    fn read_single_precision_frames() {
+        let path = temp_path("single.xyz");
+        let mut text = String::new();
+        for data in trajectory(2).data {
+            text.push_str(&format!("{}\n", data));
+        }
+        std::fs::write(&path, text).unwrap();
+        let frames = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].positions[0], [0.1, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn more_than_u16_symbols() {
+        let mut data = water();
+        data.atoms = (0..70_000).map(|i| AtomData::new(format!("X{}", i), [i as f64, 0.0, 0.0])).collect();
+        data.count = data.atoms.len();
+        let frame = FrameF32::from(&data);
+        assert_eq!(frame.symbols.len(), 70_000);
+        assert_eq!(frame.symbol(69_999), "X69999");
+        assert_eq!(Data::from(&frame).atoms[66_000].symbol, "X66000");
+    }
+}
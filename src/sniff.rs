@@ -0,0 +1,295 @@
+//! Detection of the dialect of a `.xyz` file from a sample of its lines, to choose the [`ParseOptions`]
+//! for files of unknown origin, and to report files that mix several dialects.
+
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use super::element::Element;
+use super::error;
+use super::extended::Layout;
+use super::labels::LabelMap;
+use super::{Dialect, ParseOptions};
+
+/// The number of lines read from the start of a file by [`sniff`].
+pub const SAMPLE_LINES: usize = 10_000;
+
+/// The layout of the frames of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// An element symbol and three coordinates per atom line, possibly followed by other columns.
+    Plain,
+    /// Extended XYZ, with the columns described by `Properties=...` in the comment.
+    Extended,
+    /// The layout of the QM9 dataset, see [`Dialect::Qm9`].
+    Qm9,
+}
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Plain => write!(f, "plain XYZ"),
+            Format::Extended => write!(f, "extended XYZ"),
+            Format::Qm9 => write!(f, "QM9"),
+        }
+    }
+}
+
+/// What [`sniff`] found in the sampled lines of a file.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The number of frames found in the sample.
+    pub frames: usize,
+    /// The number of frames of each format, the most common first.
+    pub formats: Vec<(Format, usize)>,
+    /// Whether the frames differ in their format or in the number of columns of their atom lines.
+    pub mixed: bool,
+    /// Whether the atoms have velocities, as an extended XYZ column or as three more numbers in plain XYZ.
+    pub velocities: bool,
+    /// Whether atoms are given by their atomic number instead of their symbol, e.g. `8` for oxygen.
+    pub numeric_symbols: bool,
+    /// Whether the columns of the atom lines are aligned at the same character positions in every frame.
+    pub fixed_width: bool,
+    /// Whether frames are separated by blank lines.
+    pub blank_separators: bool,
+    /// The number of lines that are neither blank nor part of a frame, such as log output.
+    pub unparsed_lines: usize,
+    /// How consistent the sample is, from 0 to 1: the fraction of frames with the most common layout,
+    /// times the fraction of non-blank lines that are part of a frame. Zero if no frame was found.
+    pub confidence: f64,
+    /// The recommended options for reading the file.
+    pub options: ParseOptions,
+}
+impl Report {
+    /// The most common format, or `None` if no frame was found.
+    pub fn format(&self) -> Option<Format> {
+        self.formats.first().map(|&(format, _)| format)
+    }
+}
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(format) = self.format() else {
+            return write!(f, "unknown");
+        };
+        write!(f, "{}", format)?;
+        let features = [
+            (self.mixed, "mixed"),
+            (self.velocities, "velocities"),
+            (self.numeric_symbols, "numeric symbols"),
+            (self.fixed_width, "fixed-width"),
+        ];
+        for (_, name) in features.iter().filter(|(present, _)| *present) {
+            write!(f, ", {}", name)?;
+        }
+        write!(f, " (confidence {:.2})", self.confidence)
+    }
+}
+
+/// Samples the first [`SAMPLE_LINES`] lines of a file and reports its dialect.
+pub fn sniff<P: AsRef<Path>>(path: P) -> error::Result<Report> {
+    sniff_reader(BufReader::new(std::fs::File::open(path)?))
+}
+
+/// Samples the first [`SAMPLE_LINES`] lines read from `reader` and reports their dialect.
+pub fn sniff_reader<R: BufRead>(reader: R) -> error::Result<Report> {
+    let lines = reader.lines().take(SAMPLE_LINES).collect::<std::io::Result<Vec<String>>>()?;
+    Ok(analyze(&lines))
+}
+
+/// A frame found in the sample.
+struct Frame {
+    format: Format,
+    /// The number of columns of the atom lines, if they all have the same number.
+    columns: Option<usize>,
+    /// The character positions where the columns start, if they are the same on every atom line.
+    offsets: Option<Vec<usize>>,
+    velocities: bool,
+}
+
+fn analyze(lines: &[String]) -> Report {
+    let mut frames: Vec<Frame> = vec![];
+    let mut symbols: Vec<&str> = vec![];
+    let (mut unparsed_lines, mut separated) = (0, false);
+    let mut previous_end = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        if line.is_empty() {
+            i += 1;
+            continue;
+        }
+        let count = line.parse::<usize>().ok();
+        let Some((count, (frame, length))) = count.and_then(|n| Some((n, parse_frame(&lines[i..], n)?))) else {
+            unparsed_lines += 1;
+            i += 1;
+            continue;
+        };
+        if previous_end.is_some_and(|end| end < i) {
+            separated = true;
+        }
+        symbols.extend(lines[i + 2..i + 2 + count].iter().filter_map(|line| line.split_whitespace().next()));
+        frames.push(frame);
+        i += length;
+        previous_end = Some(i);
+    }
+
+    let mut formats: Vec<(Format, usize)> = vec![];
+    for frame in &frames {
+        match formats.iter_mut().find(|(format, _)| *format == frame.format) {
+            Some((_, n)) => *n += 1,
+            None => formats.push((frame.format, 1)),
+        }
+    }
+    formats.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+
+    let mut layouts: Vec<((Format, Option<usize>), usize)> = vec![];
+    for frame in &frames {
+        let layout = (frame.format, frame.columns);
+        match layouts.iter_mut().find(|(l, _)| *l == layout) {
+            Some((_, n)) => *n += 1,
+            None => layouts.push((layout, 1)),
+        }
+    }
+    let most_common = layouts.iter().map(|&(_, n)| n).max().unwrap_or(0);
+    let non_blank = lines.iter().filter(|line| !line.trim().is_empty()).count();
+    let confidence = match frames.len() {
+        0 => 0.0,
+        n => most_common as f64 / n as f64 * (non_blank - unparsed_lines) as f64 / non_blank as f64,
+    };
+
+    let first_offsets = frames.first().and_then(|frame| frame.offsets.as_ref());
+    let fixed_width = first_offsets.is_some() && frames.iter().all(|frame| frame.offsets.as_ref() == first_offsets);
+    let numeric_symbols = symbols.iter().any(|symbol| atomic_number(symbol).is_some());
+    let common = LabelMap::common();
+    let needs_labels = symbols.iter().any(|symbol| Element::from_symbol(symbol).is_none());
+    let all_known = symbols.iter().all(|symbol| common.resolve(symbol).or_else(|| atomic_number(symbol)).is_some());
+
+    let mut options = ParseOptions::default();
+    if needs_labels && all_known {
+        let mut labels = common;
+        for symbol in &symbols {
+            if let Some(element) = atomic_number(symbol) {
+                labels.insert(*symbol, element);
+            }
+        }
+        options.labels = Some(labels);
+        options.fix_symbols = true;
+    }
+    options.dialect = match formats.first() {
+        Some((Format::Qm9, _)) if formats.len() == 1 => Dialect::Qm9,
+        _ if unparsed_lines > 0 || formats.len() > 1 => Dialect::Permissive,
+        _ => Dialect::Default,
+    };
+
+    Report {
+        frames: frames.len(),
+        mixed: layouts.len() > 1,
+        velocities: frames.iter().any(|frame| frame.velocities),
+        numeric_symbols,
+        fixed_width,
+        blank_separators: separated,
+        unparsed_lines,
+        confidence,
+        formats,
+        options,
+    }
+}
+
+/// Recognizes a frame of `count` atoms starting at the first line, returning it and its number of lines.
+/// Returns `None` if the frame is cut off or an atom line has no symbol and three coordinates.
+fn parse_frame(lines: &[String], count: usize) -> Option<(Frame, usize)> {
+    let comment = lines.get(1)?;
+    let atoms = lines.get(2..2 + count)?;
+    let tokens: Vec<Vec<&str>> = atoms.iter().map(|line| line.split_whitespace().collect()).collect();
+    let is_number = |token: &str| token.replace("*^", "e").parse::<f64>().is_ok();
+    if !tokens.iter().all(|t| t.len() >= 4 && t[1..4].iter().all(|&v| is_number(v))) {
+        return None;
+    }
+
+    let columns = tokens.first().map(Vec::len).filter(|&n| tokens.iter().all(|t| t.len() == n));
+    let offsets: Vec<Vec<usize>> = atoms.iter().map(|line| column_offsets(line)).collect();
+    let offsets = offsets.first().cloned().filter(|first| count > 1 && offsets.iter().all(|o| o == first));
+
+    let qm9_comment = comment.split_whitespace().count() == 17;
+    let (format, velocities, length) = if let Some(layout) = Layout::from_comment(comment) {
+        (Format::Extended, layout.has_velocities(), 2 + count)
+    } else if qm9_comment && columns == Some(5) && lines.len() >= 5 + count {
+        (Format::Qm9, false, 5 + count)
+    } else {
+        let velocities = columns.is_some_and(|n| n >= 7);
+        let velocities = velocities && tokens.iter().all(|t| t[4..7].iter().all(|&v| is_number(v)));
+        (Format::Plain, velocities, 2 + count)
+    };
+    Some((
+        Frame {
+            format,
+            columns,
+            offsets,
+            velocities,
+        },
+        length,
+    ))
+}
+
+/// The character positions where the whitespace-separated columns of a line start.
+fn column_offsets(line: &str) -> Vec<usize> {
+    let mut offsets = vec![];
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        if !c.is_whitespace() && previous.is_whitespace() {
+            offsets.push(i);
+        }
+        previous = c;
+    }
+    offsets
+}
+
+/// The element with the atomic number given as a symbol, e.g. oxygen for `8`.
+fn atomic_number(symbol: &str) -> Option<Element> {
+    symbol.parse().ok().and_then(Element::from_atomic_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn report(text: &str) -> Report {
+        sniff_reader(Cursor::new(text)).unwrap()
+    }
+
+    #[test]
+    fn plain_and_extended_files() {
+        let first = "2\nfirst\nO 0.00 0.00 0.00\nH 0.96 0.00 0.00\n";
+        let plain = report(&format!("{}\n2\nsecond\nO 0.10 0.00 0.00\nH 1.06 0.00 0.00\n", first));
+        assert_eq!((plain.frames, plain.format()), (2, Some(Format::Plain)));
+        assert!(plain.blank_separators && plain.fixed_width && !plain.mixed && !plain.velocities);
+        assert_eq!(plain.confidence, 1.0);
+        assert_eq!(plain.options.dialect, Dialect::Default);
+        assert_eq!(plain.to_string(), "plain XYZ, fixed-width (confidence 1.00)");
+
+        let velocities = "1\nProperties=species:S:1:pos:R:3:velo:R:3\nAr 0 0 0 1 2 3\n";
+        let extended = report(&format!("{}1\nProperties=species:S:1:pos:R:3\nAr 1 0 0\n", velocities));
+        assert_eq!(extended.format(), Some(Format::Extended));
+        assert!(extended.velocities && extended.mixed && !extended.blank_separators);
+        assert_eq!(extended.confidence, 0.5);
+        assert_eq!(extended.options.dialect, Dialect::Default);
+    }
+
+    #[test]
+    fn numeric_symbols_and_log_output() {
+        let numeric = report("2\nwater\n8 0.0 0.0 0.0\n1 0.96 0.0 0.0\n");
+        assert!(numeric.numeric_symbols);
+        assert!(numeric.options.fix_symbols);
+        let labels = numeric.options.labels.as_ref().unwrap();
+        assert_eq!(labels.resolve("8").map(|e| e.symbol()), Some("O"));
+
+        let logged = report("step 1 done\n1\nlone atom\nHe 0 0 0\nstep 2 done\n1\nlone atom\nHe 1 0 0\n");
+        assert_eq!((logged.frames, logged.unparsed_lines), (2, 2));
+        assert!((logged.confidence - 6.0 / 8.0).abs() < 1e-12);
+        assert_eq!(logged.options.dialect, Dialect::Permissive);
+
+        let unknown = report("not an xyz file\n");
+        assert_eq!((unknown.frames, unknown.confidence), (0, 0.0));
+        assert_eq!(unknown.to_string(), "unknown");
+    }
+}
@@ -0,0 +1,162 @@
+//! Mirror images and stereocenters perceived from the bond graph, for automated stereochemical sanity checks.
+
+use super::data::Data;
+use super::geometry::Axis;
+use super::position::Position;
+
+/// The symmetry operation used by [`Data::enantiomer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mirror {
+    /// Inversion through the centroid of the atoms.
+    #[default]
+    Inversion,
+    /// Reflection through the plane containing the centroid, perpendicular to an axis.
+    Plane(Axis),
+}
+
+/// An atom with four bonded neighbours that are all different, as far as the bond graph can tell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stereocenter {
+    /// The index of the central atom.
+    pub atom: usize,
+    /// The neighbours, ordered from the highest to the lowest rank, see [`Data::stereocenters`].
+    pub neighbours: [usize; 4],
+    /// The signed volume spanned by the bonds to the first three neighbours, in Å³.
+    /// Its sign flips between mirror images.
+    pub volume: f64,
+}
+
+impl Data {
+    /// The mirror image of the frame under `mirror`. The cell, if any, is kept unchanged.
+    pub fn enantiomer(&self, mirror: Mirror) -> Data {
+        let centroid = self.atoms.iter().map(|atom| atom.position).sum::<Position>() / self.atoms.len().max(1) as f64;
+        let mut data = self.clone();
+        for atom in &mut data.atoms {
+            let relative = atom.position - centroid;
+            atom.position = match mirror {
+                Mirror::Inversion => centroid - relative,
+                Mirror::Plane(axis) => {
+                    let mut position = atom.position;
+                    position[axis.index()] = centroid[axis.index()] - relative[axis.index()];
+                    position
+                }
+            };
+        }
+        data
+    }
+
+    /// The stereocenters of the frame, in ascending order of their atom.
+    /// Atoms are ranked by their atomic number, refined by the ranks of their neighbours until the ranking
+    /// stops changing, and an atom is a stereocenter if it has four neighbours of different rank.
+    /// These ranks are not CIP priorities, but they are the same for every conformation with the same bonds,
+    /// so the signs of the volumes can be compared between frames.
+    pub fn stereocenters(&self) -> Vec<Stereocenter> {
+        let mut neighbours = vec![vec![]; self.atoms.len()];
+        for (i, j) in self.bonds() {
+            neighbours[i].push(j);
+            neighbours[j].push(i);
+        }
+        let ranks = self.ranks(&neighbours);
+
+        let mut centers = vec![];
+        for (atom, bonded) in neighbours.iter().enumerate() {
+            let Ok(mut bonded) = <[usize; 4]>::try_from(bonded.as_slice()) else {
+                continue;
+            };
+            bonded.sort_by(|&a, &b| ranks[b].cmp(&ranks[a]));
+            if bonded.windows(2).any(|pair| ranks[pair[0]] == ranks[pair[1]]) {
+                continue;
+            }
+            let center = self.atoms[atom].position;
+            let [a, b, c, _] = bonded.map(|n| self.atoms[n].position - center);
+            centers.push(Stereocenter {
+                atom,
+                neighbours: bonded,
+                volume: a.dot(b.cross(c)),
+            });
+        }
+        centers
+    }
+
+    /// The stereocenters whose handedness differs from the same atom in `reference`, a frame with the same atoms
+    /// in the same order, e.g. the starting structure of an optimization.
+    /// Stereocenters missing from either frame are skipped.
+    pub fn inverted_stereocenters(&self, reference: &Data) -> Vec<usize> {
+        let before = reference.stereocenters();
+        self.stereocenters()
+            .into_iter()
+            .filter(|center| {
+                before.iter().any(|b| {
+                    b.atom == center.atom && b.neighbours == center.neighbours && b.volume * center.volume < 0.0
+                })
+            })
+            .map(|center| center.atom)
+            .collect()
+    }
+
+    /// Ranks the atoms by atomic number, refined by the sorted ranks of their neighbours.
+    fn ranks(&self, neighbours: &[Vec<usize>]) -> Vec<usize> {
+        let mut ranks: Vec<usize> =
+            self.atoms.iter().map(|atom| atom.element().map_or(0, |e| e.atomic_number() as usize)).collect();
+        let mut classes = 0;
+        loop {
+            let keys: Vec<(usize, Vec<usize>)> = neighbours
+                .iter()
+                .enumerate()
+                .map(|(i, bonded)| {
+                    let mut around: Vec<usize> = bonded.iter().map(|&n| ranks[n]).collect();
+                    around.sort_unstable_by(|a, b| b.cmp(a));
+                    (ranks[i], around)
+                })
+                .collect();
+            let mut distinct = keys.clone();
+            distinct.sort();
+            distinct.dedup();
+            ranks = keys.iter().map(|key| distinct.binary_search(key).unwrap()).collect();
+
+            if distinct.len() == classes {
+                return ranks;
+            }
+            classes = distinct.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::frame;
+
+    /// Bromochlorofluoromethane, with its bonds along the diagonals of a cube.
+    fn bromochlorofluoromethane() -> Data {
+        let bond = |length: f64, [x, y, z]: [f64; 3]| [x, y, z].map(|v| v * length / 3f64.sqrt());
+        frame(
+            "",
+            &[
+                ("C", [0.0; 3]),
+                ("H", bond(1.09, [1.0, 1.0, 1.0])),
+                ("F", bond(1.35, [1.0, -1.0, -1.0])),
+                ("Cl", bond(1.77, [-1.0, 1.0, -1.0])),
+                ("Br", bond(1.94, [-1.0, -1.0, 1.0])),
+            ],
+        )
+    }
+
+    #[test]
+    fn stereocenters_and_mirror_images() {
+        let molecule = bromochlorofluoromethane();
+        let centers = molecule.stereocenters();
+        assert_eq!(centers.len(), 1);
+        assert_eq!((centers[0].atom, centers[0].neighbours), (0, [4, 3, 2, 1]));
+        assert!(molecule.inverted_stereocenters(&molecule).is_empty());
+
+        for mirror in [Mirror::Inversion, Mirror::Plane(Axis::Y)] {
+            let image = molecule.enantiomer(mirror);
+            let volume = image.stereocenters()[0].volume;
+            assert!((volume + centers[0].volume).abs() < 1e-12);
+            assert_eq!(image.inverted_stereocenters(&molecule), [0]);
+        }
+        assert_eq!(molecule.enantiomer(Mirror::Plane(Axis::X)).atoms[1].position[1], molecule.atoms[1].position[1]);
+        assert!(crate::testing::water().stereocenters().is_empty());
+    }
+}
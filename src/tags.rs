@@ -0,0 +1,89 @@
+//! Per-atom annotations by layer, such as ONIOM layers or QM/MM regions, stored in [`AtomData::tags`].
+//!
+//! Tags are read from the string columns of extended XYZ files that have no other meaning, e.g.
+//! `Properties=species:S:1:pos:R:3:layer:S:1`, and written back as such columns by
+//! [`write_extxyz`](crate::ml::write_extxyz). Atoms without a tag in a written layer get [`UNTAGGED`],
+//! which is not read back as a tag. Tags can be selected with `tag LAYER VALUE...`, see [`select`](crate::select).
+
+use super::data::{AtomData, Data};
+
+/// The column value written for atoms without a tag in a layer.
+pub const UNTAGGED: &str = "_";
+
+impl AtomData {
+    /// The tag of the atom in `layer`, if any.
+    pub fn tag(&self, layer: &str) -> Option<&str> {
+        self.tags.get(layer).map(String::as_str)
+    }
+}
+
+impl Data {
+    /// Tags the atoms at `indices` with `value` in `layer`, replacing their previous tag in that layer.
+    /// Indices out of range are ignored.
+    pub fn set_tag(&mut self, indices: &[usize], layer: &str, value: &str) {
+        for &i in indices {
+            if let Some(atom) = self.atoms.get_mut(i) {
+                atom.tags.insert(layer.to_string(), value.to_string());
+            }
+        }
+    }
+
+    /// Removes the tags of all atoms in `layer`.
+    pub fn clear_tags(&mut self, layer: &str) {
+        for atom in &mut self.atoms {
+            atom.tags.remove(layer);
+        }
+    }
+
+    /// The indices of the atoms tagged with `value` in `layer`, in ascending order.
+    pub fn tagged(&self, layer: &str, value: &str) -> Vec<usize> {
+        (0..self.atoms.len()).filter(|&i| self.atoms[i].tag(layer) == Some(value)).collect()
+    }
+
+    /// The layers any atom is tagged in, in alphabetical order.
+    pub fn tag_layers(&self) -> Vec<String> {
+        let mut layers: Vec<String> = self.atoms.iter().flat_map(|atom| atom.tags.keys().cloned()).collect();
+        layers.sort();
+        layers.dedup();
+        layers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::data::File;
+    use crate::testing::water;
+
+    #[test]
+    fn tag_layers_and_selection() {
+        let mut data = water();
+        data.set_tag(&[0, 1, 7], "layer", "H");
+        data.set_tag(&[2], "layer", "L");
+        data.set_tag(&[2], "region", "qm");
+        assert_eq!(data.tagged("layer", "H"), [0, 1]);
+        assert_eq!(data.atoms[2].tag("layer"), Some("L"));
+        assert_eq!(data.tag_layers(), ["layer", "region"]);
+        assert_eq!(data.select("tag layer H or tag region qm").unwrap(), [0, 1, 2]);
+        assert!(data.select("tag and").is_err());
+        data.clear_tags("layer");
+        assert_eq!(data.tag_layers(), ["region"]);
+    }
+
+    #[test]
+    fn tags_round_trip_through_extxyz() {
+        let mut data = water();
+        data.set_tag(&[0], "layer", "H");
+        let mut buf = vec![];
+        crate::ml::write_extxyz(&mut buf, &File { data: vec![data] }).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("Properties=species:S:1:pos:R:3:layer:S:1 "));
+        assert!(text.contains(&format!("H 0.96 0 0 {}\n", UNTAGGED)));
+
+        let read = crate::Frames::new(Cursor::new(text)).next().unwrap().unwrap();
+        assert_eq!(read.tagged("layer", "H"), [0]);
+        assert_eq!(read.atoms[1].tag("layer"), None);
+    }
+}
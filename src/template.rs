@@ -0,0 +1,142 @@
+//! Renders frames into arbitrary text formats using templates.
+//!
+//! A template is plain text with placeholders in braces, which are substituted by data from the frame:
+//!
+//! - `{natoms}`: the number of atoms.
+//! - `{comment}`: the comment line.
+//! - `{coords}`: one line per atom with the atomic symbol and the position.
+//! - `{positions}`: one line per atom with the position only.
+//! - `{symbols}`: the atomic symbols, separated by spaces.
+//!
+//! Numbers in `{coords}` and `{positions}` can be formatted with a printf-like format,
+//! e.g. `{coords:%.6f}`, `{coords:%14.8f}` or `{positions:%.4e}`.
+//! Literal braces are written as `{{` and `}}`.
+
+use super::data::Data;
+use super::error::TemplateError;
+
+/// Renders a frame using a template.
+pub fn render(data: &Data, template: &str) -> Result<String, TemplateError> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    let mut offset = 0;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        output.push_str(&rest[..i]);
+        let brace = rest.as_bytes()[i];
+
+        if rest[i + 1..].starts_with(brace as char) {
+            output.push(brace as char);
+            rest = &rest[i + 2..];
+            offset += i + 2;
+            continue;
+        }
+        if brace == b'}' {
+            return Err(TemplateError::UnmatchedBrace(offset + i));
+        }
+
+        let end = rest[i..].find('}').ok_or(TemplateError::UnclosedPlaceholder(offset + i))?;
+        substitute(data, &rest[i + 1..i + end], &mut output)?;
+        rest = &rest[i + end + 1..];
+        offset += i + end + 1;
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+fn substitute(data: &Data, placeholder: &str, output: &mut String) -> Result<(), TemplateError> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name, NumberFormat::parse(format)?),
+        None => (placeholder, NumberFormat::default()),
+    };
+
+    match name {
+        "natoms" => output.push_str(&data.atoms.len().to_string()),
+        "comment" => output.push_str(&data.comment),
+        "symbols" => {
+            let symbols: Vec<&str> = data.atoms.iter().map(|atom| atom.symbol.as_str()).collect();
+            output.push_str(&symbols.join(" "));
+        }
+        "coords" | "positions" => {
+            for (i, atom) in data.atoms.iter().enumerate() {
+                if i > 0 {
+                    output.push('\n');
+                }
+                if name == "coords" {
+                    output.push_str(&atom.symbol);
+                    output.push(' ');
+                }
+                let values: Vec<String> = atom.position.0.iter().map(|&v| format.format(v)).collect();
+                output.push_str(&values.join(" "));
+            }
+        }
+        _ => return Err(TemplateError::UnknownPlaceholder(placeholder.to_string())),
+    }
+
+    Ok(())
+}
+
+/// A printf-like number format `%[width][.precision](f|e)`.
+#[derive(Debug, Default)]
+struct NumberFormat {
+    width: usize,
+    precision: Option<usize>,
+    exponent: bool,
+}
+impl NumberFormat {
+    fn parse(format: &str) -> Result<NumberFormat, TemplateError> {
+        let invalid = || TemplateError::InvalidFormat(format.to_string());
+
+        let spec = format.strip_prefix('%').ok_or_else(invalid)?;
+        let (spec, exponent) = match spec.chars().last() {
+            Some('f') => (&spec[..spec.len() - 1], false),
+            Some('e') => (&spec[..spec.len() - 1], true),
+            _ => return Err(invalid()),
+        };
+        let (width, precision) = match spec.split_once('.') {
+            Some((width, precision)) => (width, Some(precision.parse().map_err(|_| invalid())?)),
+            None => (spec, None),
+        };
+        let width = if width.is_empty() { 0 } else { width.parse().map_err(|_| invalid())? };
+
+        Ok(NumberFormat {
+            width,
+            precision,
+            exponent,
+        })
+    }
+
+    fn format(&self, value: f64) -> String {
+        let width = self.width;
+        match (self.precision, self.exponent) {
+            (Some(p), false) => format!("{:>width$.p$}", value),
+            (Some(p), true) => format!("{:>width$.p$e}", value),
+            (None, false) => format!("{:>width$}", value),
+            (None, true) => format!("{:>width$e}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::water;
+
+    #[test]
+    fn placeholders_and_formats() {
+        let rendered = render(&water(), "{natoms} {{{comment}}}\n{symbols}\n{coords:%.2f}\n{positions:%.1e}").unwrap();
+        let expected = "3 {water}\nO H H\nO 0.00 0.00 0.00\nH 0.96 0.00 0.00\nH -0.24 0.93 0.00\n\
+                        0.0e0 0.0e0 0.0e0\n9.6e-1 0.0e0 0.0e0\n-2.4e-1 9.3e-1 0.0e0";
+        assert_eq!(rendered, expected);
+        assert_eq!(render(&water(), "{positions:%6.2f}").unwrap().lines().nth(2), Some(" -0.24   0.93   0.00"));
+    }
+
+    #[test]
+    fn template_errors() {
+        assert!(matches!(render(&water(), "{mass}"), Err(TemplateError::UnknownPlaceholder(_))));
+        assert!(matches!(render(&water(), "ab {coords"), Err(TemplateError::UnclosedPlaceholder(3))));
+        assert!(matches!(render(&water(), "a } b"), Err(TemplateError::UnmatchedBrace(2))));
+        assert!(matches!(render(&water(), "{coords:%d}"), Err(TemplateError::InvalidFormat(_))));
+    }
+}
@@ -0,0 +1,39 @@
+//! Fixtures shared by the unit tests.
+
+use std::path::PathBuf;
+
+use super::data::{AtomData, Data, File};
+
+/// A frame with the `(symbol, position)` atoms and no cell.
+pub(crate) fn frame(comment: &str, atoms: &[(&str, [f64; 3])]) -> Data {
+    Data {
+        count: atoms.len(),
+        comment: comment.to_string(),
+        atoms: atoms.iter().map(|&(symbol, position)| AtomData::new(symbol, position)).collect(),
+        cell: None,
+    }
+}
+
+/// A water molecule, with the oxygen at the origin.
+pub(crate) fn water() -> Data {
+    frame("water", &[("O", [0.0, 0.0, 0.0]), ("H", [0.96, 0.0, 0.0]), ("H", [-0.24, 0.93, 0.0])])
+}
+
+
+/// A trajectory of `frames` water molecules, moving 0.1 Å along x per frame, with `step=i` comments.
+pub(crate) fn trajectory(frames: usize) -> File {
+    File {
+        data: (0..frames)
+            .map(|i| {
+                let x = 0.1 * i as f64;
+                let atoms = [("O", [x, 0.0, 0.0]), ("H", [x + 0.96, 0.0, 0.0]), ("H", [x - 0.24, 0.93, 0.0])];
+                frame(&format!("step={}", i), &atoms)
+            })
+            .collect(),
+    }
+}
+
+/// A path named `name` in the temporary directory, unique to the test process.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rust-xyz-chem-{}-{}", std::process::id(), name))
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::data::Data;
+use super::writer::{write_frame_at, WriteOptions};
+
+/// Options for a [`TrajectoryWriter`].
+#[derive(Debug, Clone, Default)]
+pub struct TrajectoryOptions {
+    /// How the frames are formatted.
+    pub write: WriteOptions,
+    /// Appends to an existing file instead of replacing it.
+    pub append: bool,
+    /// Flushes the written frames to the file after this many frames.
+    pub flush_frames: Option<usize>,
+    /// Flushes the written frames to the file when a frame is written this long after the last flush.
+    pub flush_interval: Option<Duration>,
+    /// Also asks the operating system to store flushed frames on disk, so they survive a crash of the machine.
+    pub fsync: bool,
+    /// Starts a new file after this many frames.
+    pub rotate_frames: Option<usize>,
+    /// Starts a new file once the current one holds at least this many bytes.
+    pub rotate_bytes: Option<u64>,
+}
+
+/// Writes the frames of a long simulation as they are produced, flushing them regularly so a crash loses
+/// few frames, and optionally rotating to a new file after a number of frames or bytes.
+///
+/// Without rotation, the frames are written to the given path. With rotation, they are written to numbered
+/// files next to it, e.g. `traj_0000.xyz`, `traj_0001.xyz` and so on for `traj.xyz`.
+/// Frames are formatted like [`write_file`](crate::write_file). The remaining frames are flushed when the writer
+/// is dropped, ignoring errors; call [`TrajectoryWriter::flush`] to handle them.
+#[derive(Debug)]
+pub struct TrajectoryWriter {
+    path: PathBuf,
+    options: TrajectoryOptions,
+    writer: BufWriter<fs::File>,
+    part: usize,
+    frames: usize,
+    part_frames: usize,
+    part_bytes: u64,
+    unflushed: usize,
+    last_flush: Instant,
+}
+impl TrajectoryWriter {
+    /// Creates a writer for the trajectory at `path`.
+    pub fn create<P: AsRef<Path>>(path: P, options: TrajectoryOptions) -> io::Result<TrajectoryWriter> {
+        let path = path.as_ref().to_path_buf();
+        let rotating = options.rotate_frames.is_some() || options.rotate_bytes.is_some();
+        let first = if rotating { part_path(&path, 0) } else { path.clone() };
+        let (writer, bytes) = open(&first, options.append)?;
+        Ok(TrajectoryWriter {
+            path,
+            options,
+            writer,
+            part: 0,
+            frames: 0,
+            part_frames: 0,
+            part_bytes: bytes,
+            unflushed: 0,
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// Writes a frame, flushing or rotating the file as configured.
+    pub fn write_frame(&mut self, data: &Data) -> io::Result<()> {
+        let full = self.options.rotate_frames.is_some_and(|n| self.part_frames >= n)
+            || self.options.rotate_bytes.is_some_and(|n| self.part_bytes >= n);
+        if full && self.part_bytes > 0 {
+            self.flush()?;
+            self.part += 1;
+            (self.writer, self.part_bytes) = open(&part_path(&self.path, self.part), self.options.append)?;
+            self.part_frames = 0;
+        }
+
+        let mut buf = vec![];
+        write_frame_at(&mut buf, data, self.frames, &self.options.write)?;
+        self.writer.write_all(&buf)?;
+        self.frames += 1;
+        self.part_frames += 1;
+        self.part_bytes += buf.len() as u64;
+        self.unflushed += 1;
+
+        if self.options.flush_frames.is_some_and(|n| self.unflushed >= n)
+            || self.options.flush_interval.is_some_and(|t| self.last_flush.elapsed() >= t)
+        {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the written frames to the file, and to disk if [`TrajectoryOptions::fsync`] is set.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        if self.options.fsync {
+            self.writer.get_ref().sync_data()?;
+        }
+        self.unflushed = 0;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// The path of the file currently written to.
+    pub fn current_path(&self) -> PathBuf {
+        if self.options.rotate_frames.is_some() || self.options.rotate_bytes.is_some() {
+            part_path(&self.path, self.part)
+        } else {
+            self.path.clone()
+        }
+    }
+
+    /// The number of frames written so far, over all files.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+impl Drop for TrajectoryWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Opens a file for writing, returning the writer and the size of the file.
+fn open(path: &Path, append: bool) -> io::Result<(BufWriter<fs::File>, u64)> {
+    let file = fs::OpenOptions::new().create(true).write(true).append(append).truncate(!append).open(path)?;
+    let bytes = file.metadata()?.len();
+    Ok((BufWriter::new(file), bytes))
+}
+
+/// The path of part `n` of a rotated trajectory, e.g. `traj_0001.xyz` for `traj.xyz`.
+fn part_path(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{:04}.{}", stem, n, extension.to_string_lossy()),
+        None => format!("{}_{:04}", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{temp_path, trajectory};
+
+    #[test]
+    fn rotation_by_frames() {
+        let dir = temp_path("trajectory-rotation");
+        fs::create_dir_all(&dir).unwrap();
+        let options = TrajectoryOptions {
+            rotate_frames: Some(2),
+            flush_frames: Some(1),
+            ..TrajectoryOptions::default()
+        };
+        let mut writer = TrajectoryWriter::create(dir.join("traj.xyz"), options).unwrap();
+        for data in &trajectory(5).data {
+            writer.write_frame(data).unwrap();
+        }
+        assert_eq!(writer.frames(), 5);
+        assert_eq!(writer.current_path(), dir.join("traj_0002.xyz"));
+
+        let comments = |n| fs::read_to_string(dir.join(format!("traj_{:04}.xyz", n))).unwrap().matches("step=").count();
+        assert_eq!((0..3).map(comments).collect::<Vec<_>>(), [2, 2, 1]);
+        assert_eq!(part_path(Path::new("dir/traj"), 12), Path::new("dir/traj_0012"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn append_and_flush_on_drop() {
+        let path = temp_path("trajectory-append.xyz");
+        let append = TrajectoryOptions {
+            append: true,
+            ..TrajectoryOptions::default()
+        };
+        for options in [TrajectoryOptions::default(), append] {
+            let mut writer = TrajectoryWriter::create(&path, options).unwrap();
+            writer.write_frame(&trajectory(1).data[0]).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap().matches("step=0").count(), 2);
+        fs::remove_file(path).unwrap();
+    }
+}
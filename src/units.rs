@@ -0,0 +1,245 @@
+//! Units of energies and forces, and conversions between them.
+//!
+//! Values are converted explicitly between units, e.g. a Hartree energy to eV:
+//!
+//! ```
+//! use rust_xyz_chem::units::EnergyUnit;
+//!
+//! let ev = EnergyUnit::Hartree.convert(-76.4, EnergyUnit::ElectronVolt);
+//! assert!((ev - -2078.95).abs() < 0.01);
+//! ```
+//!
+//! Comments state the units of their values with the `energy_unit` and `force_unit` properties,
+//! e.g. `energy=-76.4 energy_unit=Ha force_unit=Ha/Bohr`. Values without a stated unit are in eV and eV/Å,
+//! as in extended XYZ files written by ASE.
+
+use super::data::Data;
+use super::position::Position;
+
+/// Energy of one Hartree in eV (CODATA 2018).
+pub const HARTREE: f64 = 27.211386245988;
+/// Energy of one kcal/mol in eV.
+pub const KCAL_PER_MOL: f64 = 0.043364104241800934;
+/// Energy of one kJ/mol in eV.
+pub const KJ_PER_MOL: f64 = 0.010364269656262174;
+/// Length of one Bohr radius in Ångström (CODATA 2018).
+pub const BOHR: f64 = 0.529177210903;
+
+/// A unit of energy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EnergyUnit {
+    Hartree,
+    #[default]
+    ElectronVolt,
+    KcalPerMol,
+    KjPerMol,
+}
+impl EnergyUnit {
+    /// Looks up a unit by its symbol, ignoring case: `Ha` (or `Hartree`, `Eh`, `au`), `eV`, `kcal/mol` or `kJ/mol`.
+    pub fn from_symbol(symbol: &str) -> Option<EnergyUnit> {
+        match symbol.to_ascii_lowercase().as_str() {
+            "ha" | "hartree" | "eh" | "au" | "a.u." => Some(EnergyUnit::Hartree),
+            "ev" => Some(EnergyUnit::ElectronVolt),
+            "kcal/mol" | "kcal_mol" | "kcal" => Some(EnergyUnit::KcalPerMol),
+            "kj/mol" | "kj_mol" | "kj" => Some(EnergyUnit::KjPerMol),
+            _ => None,
+        }
+    }
+
+    /// The symbol of the unit, e.g. `kcal/mol`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            EnergyUnit::Hartree => "Ha",
+            EnergyUnit::ElectronVolt => "eV",
+            EnergyUnit::KcalPerMol => "kcal/mol",
+            EnergyUnit::KjPerMol => "kJ/mol",
+        }
+    }
+
+    /// The energy of one unit in eV.
+    pub fn in_ev(&self) -> f64 {
+        match self {
+            EnergyUnit::Hartree => HARTREE,
+            EnergyUnit::ElectronVolt => 1.0,
+            EnergyUnit::KcalPerMol => KCAL_PER_MOL,
+            EnergyUnit::KjPerMol => KJ_PER_MOL,
+        }
+    }
+
+    /// Converts an energy in this unit to the unit `to`.
+    pub fn convert(&self, value: f64, to: EnergyUnit) -> f64 {
+        if *self == to {
+            value
+        } else {
+            value * self.in_ev() / to.in_ev()
+        }
+    }
+}
+impl std::fmt::Display for EnergyUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// A unit of length, as used in force units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LengthUnit {
+    #[default]
+    Angstrom,
+    Bohr,
+}
+impl LengthUnit {
+    /// Looks up a unit by its symbol, ignoring case: `Å` (or `A`, `Ang`, `Angstrom`) or `Bohr` (or `a0`, `au`).
+    pub fn from_symbol(symbol: &str) -> Option<LengthUnit> {
+        match symbol.to_lowercase().as_str() {
+            "å" | "a" | "ang" | "angstrom" | "ångström" => Some(LengthUnit::Angstrom),
+            "bohr" | "a0" | "au" | "a.u." => Some(LengthUnit::Bohr),
+            _ => None,
+        }
+    }
+
+    /// The symbol of the unit, `Ang` or `Bohr`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LengthUnit::Angstrom => "Ang",
+            LengthUnit::Bohr => "Bohr",
+        }
+    }
+
+    /// The length of one unit in Ångström.
+    pub fn in_angstrom(&self) -> f64 {
+        match self {
+            LengthUnit::Angstrom => 1.0,
+            LengthUnit::Bohr => BOHR,
+        }
+    }
+
+    /// Converts a length in this unit to the unit `to`.
+    pub fn convert(&self, value: f64, to: LengthUnit) -> f64 {
+        if *self == to {
+            value
+        } else {
+            value * self.in_angstrom() / to.in_angstrom()
+        }
+    }
+}
+impl std::fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// A unit of force, an energy unit per length unit, e.g. Ha/Bohr. The default is eV/Å.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ForceUnit {
+    pub energy: EnergyUnit,
+    pub length: LengthUnit,
+}
+impl ForceUnit {
+    /// Creates a force unit from an energy unit and a length unit.
+    pub fn new(energy: EnergyUnit, length: LengthUnit) -> ForceUnit {
+        ForceUnit { energy, length }
+    }
+
+    /// Looks up a unit by its symbol, an energy symbol and a length symbol separated by `/`, e.g. `Ha/Bohr`,
+    /// `eV/Å` or `kcal/mol/Ang`. `kcal/(mol*Å)` and `kJ/(mol*Å)` are accepted as well.
+    pub fn from_symbol(symbol: &str) -> Option<ForceUnit> {
+        if let Some((energy, length)) = symbol.strip_suffix(')').and_then(|s| s.split_once("/(mol")) {
+            let length = length.trim_start_matches(['*', '·', ' ']);
+            let energy = EnergyUnit::from_symbol(&format!("{}/mol", energy))?;
+            return Some(ForceUnit::new(energy, LengthUnit::from_symbol(length)?));
+        }
+        let (energy, length) = symbol.rsplit_once('/')?;
+        Some(ForceUnit::new(EnergyUnit::from_symbol(energy)?, LengthUnit::from_symbol(length)?))
+    }
+
+    /// The force of one unit in eV/Å.
+    pub fn in_ev_per_angstrom(&self) -> f64 {
+        self.energy.in_ev() / self.length.in_angstrom()
+    }
+
+    /// Converts a force in this unit to the unit `to`.
+    pub fn convert(&self, value: f64, to: ForceUnit) -> f64 {
+        if *self == to {
+            value
+        } else {
+            value * self.in_ev_per_angstrom() / to.in_ev_per_angstrom()
+        }
+    }
+}
+impl std::fmt::Display for ForceUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.energy, self.length)
+    }
+}
+
+impl Data {
+    /// The unit of the energy of the frame, from the `energy_unit` property of the comment, or eV without.
+    /// Returns `None` for an unknown unit.
+    pub fn energy_unit(&self) -> Option<EnergyUnit> {
+        self.property("energy_unit").map_or(Some(EnergyUnit::default()), |unit| EnergyUnit::from_symbol(&unit))
+    }
+
+    /// The unit of the forces on the atoms, from the `force_unit` property of the comment, or eV/Å without.
+    /// Returns `None` for an unknown unit.
+    pub fn force_unit(&self) -> Option<ForceUnit> {
+        self.property("force_unit").map_or(Some(ForceUnit::default()), |unit| ForceUnit::from_symbol(&unit))
+    }
+
+    /// The energy of the frame (see [`Data::energy`]) converted to `unit`.
+    /// Returns `None` without an energy or if its unit is unknown.
+    pub fn energy_in(&self, unit: EnergyUnit) -> Option<f64> {
+        Some(self.energy_unit()?.convert(self.energy()?, unit))
+    }
+
+    /// The forces on the atoms converted to `unit`, or `None` for atoms without a force or if the unit is unknown.
+    pub fn forces_in(&self, unit: ForceUnit) -> Vec<Option<Position>> {
+        let from = self.force_unit();
+        self.atoms
+            .iter()
+            .map(|atom| Some(atom.force? * from?.convert(1.0, unit)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::water;
+
+    #[test]
+    fn unit_symbols_and_conversions() {
+        assert_eq!(EnergyUnit::from_symbol("EH"), Some(EnergyUnit::Hartree));
+        assert_eq!(EnergyUnit::from_symbol("kJ/mol"), Some(EnergyUnit::KjPerMol));
+        assert_eq!(EnergyUnit::from_symbol("Ry"), None);
+        assert!((EnergyUnit::Hartree.convert(1.0, EnergyUnit::KcalPerMol) - 627.5095).abs() < 1e-3);
+        assert!((EnergyUnit::KcalPerMol.convert(1.0, EnergyUnit::KjPerMol) - 4.184).abs() < 1e-12);
+        assert_eq!(LengthUnit::Bohr.convert(1.0, LengthUnit::Angstrom), BOHR);
+
+        let ha_per_bohr = ForceUnit::new(EnergyUnit::Hartree, LengthUnit::Bohr);
+        assert_eq!(ForceUnit::from_symbol("Ha/Bohr"), Some(ha_per_bohr));
+        assert_eq!(ForceUnit::from_symbol("eV/Å"), Some(ForceUnit::default()));
+        let kcal = ForceUnit::new(EnergyUnit::KcalPerMol, LengthUnit::Angstrom);
+        assert_eq!(ForceUnit::from_symbol("kcal/mol/Ang"), Some(kcal));
+        assert_eq!(ForceUnit::from_symbol("kcal/(mol*Å)"), Some(kcal));
+        assert_eq!(ForceUnit::from_symbol("Ha"), None);
+        assert_eq!(kcal.to_string(), "kcal/mol/Ang");
+        assert!((ha_per_bohr.convert(1.0, ForceUnit::default()) - 51.422067).abs() < 1e-6);
+    }
+
+    #[test]
+    fn energies_and_forces_in_stated_units() {
+        let mut data = water();
+        data.comment = "energy=-2 energy_unit=Ha force_unit=Ha/Bohr".to_string();
+        data.atoms[0].force = Some(Position::new(1.0, 0.0, 0.0));
+        assert_eq!(data.energy_in(EnergyUnit::Hartree), Some(-2.0));
+        assert_eq!(data.energy_in(EnergyUnit::ElectronVolt), Some(-2.0 * HARTREE));
+        let forces = data.forces_in(ForceUnit::new(EnergyUnit::Hartree, LengthUnit::Angstrom));
+        assert!((forces[0].unwrap()[0] - 1.0 / BOHR).abs() < 1e-12);
+        assert_eq!(forces[1], None);
+
+        data.comment = "energy=-2 energy_unit=Ry".to_string();
+        assert_eq!(data.energy_unit(), None);
+        assert_eq!(data.energy_in(EnergyUnit::Hartree), None);
+    }
+}
@@ -0,0 +1,107 @@
+//! Voronoi cells as convex polyhedra, cut out of a large cube by the bisector planes of the neighbours.
+
+use super::position::Position;
+
+/// A convex polyhedron around the origin, as polygons counterclockwise seen from outside.
+pub(crate) struct Polyhedron {
+    faces: Vec<Vec<Position>>,
+}
+impl Polyhedron {
+    /// A cube centered at the origin with edges of length `2 * half`.
+    pub(crate) fn cube(half: f64) -> Polyhedron {
+        let corner = |x: f64, y: f64, z: f64| Position::new(x * half, y * half, z * half);
+        let faces = [
+            [(1., -1., -1.), (1., 1., -1.), (1., 1., 1.), (1., -1., 1.)],
+            [(-1., -1., -1.), (-1., -1., 1.), (-1., 1., 1.), (-1., 1., -1.)],
+            [(-1., 1., -1.), (-1., 1., 1.), (1., 1., 1.), (1., 1., -1.)],
+            [(-1., -1., -1.), (1., -1., -1.), (1., -1., 1.), (-1., -1., 1.)],
+            [(-1., -1., 1.), (1., -1., 1.), (1., 1., 1.), (-1., 1., 1.)],
+            [(-1., -1., -1.), (-1., 1., -1.), (1., 1., -1.), (1., -1., -1.)],
+        ];
+        Polyhedron {
+            faces: faces.iter().map(|face| face.iter().map(|&(x, y, z)| corner(x, y, z)).collect()).collect(),
+        }
+    }
+
+    /// The largest distance of a vertex from the origin.
+    pub(crate) fn radius(&self) -> f64 {
+        self.faces.iter().flatten().map(Position::norm).fold(0.0, f64::max)
+    }
+
+    /// Cuts away the part closer to `neighbour` than to the origin.
+    pub(crate) fn cut(&mut self, neighbour: Position) {
+        let offset = neighbour.dot(neighbour) / 2.0;
+        let eps = 1e-12 * offset.max(1e-12);
+        let side = |p: Position| p.dot(neighbour) - offset;
+        if self.faces.iter().flatten().all(|&p| side(p) <= eps) {
+            return;
+        }
+
+        let mut cap = vec![];
+        for face in &mut self.faces {
+            let mut clipped = Vec::with_capacity(face.len() + 1);
+            for (i, &p) in face.iter().enumerate() {
+                let q = face[(i + 1) % face.len()];
+                let (sp, sq) = (side(p), side(q));
+                if sp <= eps {
+                    clipped.push(p);
+                }
+                if (sp <= eps) != (sq <= eps) {
+                    let crossing = p + (q - p) * (sp / (sp - sq));
+                    clipped.push(crossing);
+                    cap.push(crossing);
+                }
+            }
+            *face = clipped;
+        }
+        self.faces.retain(|face| face.len() >= 3);
+
+        // The crossings, each found on two faces, bound the new face in the bisector plane.
+        let mut cap_vertices: Vec<Position> = vec![];
+        for p in cap {
+            if !cap_vertices.iter().any(|q| q.distance(p) <= 1e-9 * offset.sqrt()) {
+                cap_vertices.push(p);
+            }
+        }
+        if cap_vertices.len() < 3 {
+            return;
+        }
+        let center = cap_vertices.iter().copied().sum::<Position>() / cap_vertices.len() as f64;
+        let normal = neighbour.normalized().unwrap_or_default();
+        let u = (cap_vertices[0] - center).normalized().unwrap_or_default();
+        let v = normal.cross(u);
+        let angle = |p: &Position| (p.dot(v) - center.dot(v)).atan2(p.dot(u) - center.dot(u));
+        cap_vertices.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+        self.faces.push(cap_vertices);
+    }
+
+    /// The enclosed volume.
+    pub(crate) fn volume(&self) -> f64 {
+        self.faces
+            .iter()
+            .map(|face| (1..face.len() - 1).map(|i| face[0].dot(face[i].cross(face[i + 1]))).sum::<f64>())
+            .sum::<f64>()
+            / 6.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cut_polyhedra() {
+        let mut polyhedron = Polyhedron::cube(1.0);
+        assert!((polyhedron.volume() - 8.0).abs() < 1e-12);
+        assert!((polyhedron.radius() - 3f64.sqrt()).abs() < 1e-12);
+
+        // A neighbour too far away leaves the cube unchanged.
+        polyhedron.cut(Position::new(4.0, 0.0, 0.0));
+        assert!((polyhedron.volume() - 8.0).abs() < 1e-12);
+        polyhedron.cut(Position::new(0.0, 0.0, 1.0));
+        assert!((polyhedron.volume() - 6.0).abs() < 1e-12);
+        // Cutting off a corner with edges of 1 Å removes a tetrahedron of 1/6 Å³.
+        polyhedron.cut(Position::new(-1.0, -1.0, 1.0));
+        assert!((polyhedron.volume() - (6.0 - 1.0 / 6.0)).abs() < 1e-9, "{}", polyhedron.volume());
+    }
+}
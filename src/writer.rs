@@ -0,0 +1,589 @@
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::data::{AtomData, Data, File};
+use super::provenance;
+use super::tags::UNTAGGED;
+
+/// Options for writing `.xyz` files.
+///
+/// Numbers are formatted by Rust's own float formatting rather than the C library,
+/// so the written bytes are identical across platforms and compilers.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// The number of decimals of the coordinates.
+    /// If `None`, the shortest representation that reads back to the same value is used,
+    /// in scientific notation for magnitudes below 1e-5 or from 1e16.
+    pub precision: Option<usize>,
+    /// How the comment lines are written.
+    pub comment: CommentPolicy,
+    /// Appends the creating tool, a timestamp and a checksum of the atoms to every comment,
+    /// see [`provenance`](crate::provenance).
+    pub provenance: bool,
+    /// The largest number of characters of a comment, which is truncated if longer. Unlimited if `None`.
+    /// Comments are cut between whitespace-separated entries, and the `Lattice`, `Properties` and `pbc` entries
+    /// are kept even beyond the limit. The provenance entries are appended after truncating.
+    pub max_comment_length: Option<usize>,
+    /// How characters outside ASCII in comments are written.
+    pub non_ascii: NonAsciiPolicy,
+}
+
+/// A function generating the comment of a frame from the frame and its index.
+pub type CommentFn = Arc<dyn Fn(&Data, usize) -> String + Send + Sync>;
+
+/// How the comment line of each frame is written.
+#[derive(Clone, Default)]
+pub enum CommentPolicy {
+    /// Writes the stored comment as is.
+    #[default]
+    Keep,
+    /// Generates the comment from `key=value` fields, e.g. `frame=0 formula=H2O energy=-76.4`.
+    /// The `Lattice="..."` entry is written first if the frame has a cell.
+    Fields(Vec<CommentField>),
+    /// Generates the comment with a function of the frame and its index.
+    Custom(CommentFn),
+}
+impl std::fmt::Debug for CommentPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommentPolicy::Keep => write!(f, "Keep"),
+            CommentPolicy::Fields(fields) => f.debug_tuple("Fields").field(fields).finish(),
+            CommentPolicy::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// How characters outside ASCII in comments are written, e.g. for programs that only read ASCII.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonAsciiPolicy {
+    /// Writes them as is, encoded as UTF-8.
+    #[default]
+    Keep,
+    /// Writes them as escapes like `\u{e5}`.
+    Escape,
+    /// Replaces each of them by `?`.
+    Replace,
+    /// Leaves them out.
+    Strip,
+}
+
+/// A field of a generated comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentField {
+    /// The index of the frame, as `frame=N`.
+    Index,
+    /// The time of writing in UTC, as `time=2024-01-31T12:00:00Z`.
+    Timestamp,
+    /// The chemical formula in Hill notation, as `formula=H2O`.
+    Formula,
+    /// The energy from the stored comment, as `energy=E`. Left out if the comment has none.
+    Energy,
+}
+
+/// Writes the frames of a file to `writer`.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(frames = file.data.len()), err))]
+pub fn write_file<W: Write + ?Sized>(writer: &mut W, file: &File, options: &WriteOptions) -> io::Result<()> {
+    for (index, data) in file.data.iter().enumerate() {
+        write_frame_at(writer, data, index, options)?;
+    }
+    Ok(())
+}
+
+impl File {
+    /// The number of bytes [`write_file`] would write with `options`, without storing the output,
+    /// e.g. to check the free disk space before writing.
+    pub fn serialized_size(&self, options: &WriteOptions) -> u64 {
+        let mut counter = Counter(0);
+        // Writing to the counter never fails.
+        write_file(&mut counter, self, options).unwrap();
+        counter.0
+    }
+}
+
+/// A writer that only counts the bytes written to it.
+struct Counter(u64);
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a single frame to `writer`, as frame 0 for generated comments.
+pub fn write_frame<W: Write + ?Sized>(writer: &mut W, data: &Data, options: &WriteOptions) -> io::Result<()> {
+    write_frame_at(writer, data, 0, options)
+}
+
+/// Writes a single frame with the given frame index to `writer`.
+///
+/// Per-atom data that every atom has, i.e. names, groups, indices, velocities, forces, charges and tags,
+/// is written in extended XYZ columns after the position. The columns are declared by a `Properties=` entry,
+/// which replaces the one of the comment, so that a comment kept from an extended XYZ file matches the atom lines.
+pub fn write_frame_at<W: Write + ?Sized>(
+    writer: &mut W,
+    data: &Data,
+    index: usize,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    let columns = Columns::of(data);
+    let mut atoms = String::new();
+    // The coordinates as they read back, for the checksum.
+    let mut written = Vec::with_capacity(if options.provenance { data.atoms.len() } else { 0 });
+    for atom in &data.atoms {
+        atoms.push_str(&atom.symbol);
+        let mut position = [0.0; 3];
+        for (i, v) in atom.position.0.into_iter().enumerate() {
+            atoms.push('\t');
+            let start = atoms.len();
+            format_number(&mut atoms, v, options.precision);
+            if options.provenance {
+                position[i] = atoms[start..].parse().unwrap_or(v);
+            }
+        }
+        columns.write(&mut atoms, atom, options.precision);
+        atoms.push('\n');
+        if options.provenance {
+            written.push((atom.symbol.as_str(), position));
+        }
+    }
+
+    let comment = match &options.comment {
+        CommentPolicy::Keep => data.comment.clone(),
+        CommentPolicy::Fields(fields) => generate_comment(data, index, fields),
+        CommentPolicy::Custom(f) => f(data, index),
+    };
+    let comment = match columns.properties() {
+        Some(properties) => with_properties(&comment, &properties),
+        None if comment.contains("Properties=") => with_properties(&comment, POSITION_PROPERTIES),
+        None => comment,
+    };
+    let mut comment = sanitize_comment(&comment, options);
+    if options.provenance {
+        let entries = provenance::entries(written.into_iter(), &timestamp(SystemTime::now()));
+        comment = format!("{} {}", provenance::strip(&comment), entries).trim_start().to_string();
+    }
+
+    let buf = format!("{}\n{}\n{}", data.atoms.len(), comment, atoms);
+    writer.write_all(buf.as_bytes())
+}
+
+/// The `Properties=` value of atom lines with only the symbol and the position.
+const POSITION_PROPERTIES: &str = "species:S:1:pos:R:3";
+
+/// The per-atom data written in extended XYZ columns after the position, each if every atom has it.
+struct Columns {
+    name: bool,
+    group: bool,
+    index: bool,
+    velocity: bool,
+    force: bool,
+    charge: bool,
+    layers: Vec<String>,
+}
+impl Columns {
+    fn of(data: &Data) -> Columns {
+        let all = |has: fn(&AtomData) -> bool| !data.atoms.is_empty() && data.atoms.iter().all(has);
+        Columns {
+            name: all(|atom| atom.name.is_some()),
+            group: all(|atom| atom.group.is_some()),
+            index: all(|atom| atom.index.is_some()),
+            velocity: all(|atom| atom.velocity.is_some()),
+            force: all(|atom| atom.force.is_some()),
+            charge: all(|atom| atom.charge.is_some()),
+            layers: data.tag_layers(),
+        }
+    }
+
+    /// The `Properties=` value declaring the atom lines, with the names the reader reads the columns from,
+    /// or `None` if there are no columns after the position.
+    fn properties(&self) -> Option<String> {
+        let mut properties = String::from(POSITION_PROPERTIES);
+        let columns = [
+            (self.name, "name:S:1"),
+            (self.group, "group:S:1"),
+            (self.index, "index:I:1"),
+            (self.velocity, "velo:R:3"),
+            (self.force, "forces:R:3"),
+            (self.charge, "charges:R:1"),
+        ];
+        for (_, column) in columns.iter().filter(|(written, _)| *written) {
+            properties.push(':');
+            properties.push_str(column);
+        }
+        for layer in &self.layers {
+            properties.push_str(&format!(":{}:S:1", layer));
+        }
+        (properties.len() > POSITION_PROPERTIES.len()).then_some(properties)
+    }
+
+    /// Appends the columns of `atom` to its atom line.
+    fn write(&self, line: &mut String, atom: &AtomData, precision: Option<usize>) {
+        for (written, value) in [(self.name, &atom.name), (self.group, &atom.group)] {
+            if let Some(value) = value.as_ref().filter(|_| written) {
+                line.push('\t');
+                line.push_str(value);
+            }
+        }
+        if let Some(index) = atom.index.filter(|_| self.index) {
+            line.push_str(&format!("\t{}", index));
+        }
+        for (written, vector) in [(self.velocity, atom.velocity), (self.force, atom.force)] {
+            for v in vector.filter(|_| written).into_iter().flat_map(|v| v.0) {
+                line.push('\t');
+                format_number(line, v, precision);
+            }
+        }
+        if let Some(charge) = atom.charge.filter(|_| self.charge) {
+            line.push('\t');
+            format_number(line, charge, precision);
+        }
+        for layer in &self.layers {
+            line.push('\t');
+            line.push_str(atom.tag(layer).unwrap_or(UNTAGGED));
+        }
+    }
+}
+
+/// The comment with its `Properties=` entry replaced by one declaring `properties`, or with one prepended.
+fn with_properties(comment: &str, properties: &str) -> String {
+    let entry = format!("Properties={}", properties);
+    match comment.find("Properties=") {
+        Some(start) => {
+            let end = comment[start..].find(char::is_whitespace).map_or(comment.len(), |n| start + n);
+            format!("{}{}{}", &comment[..start], entry, &comment[end..])
+        }
+        None if comment.trim().is_empty() => entry,
+        None => format!("{} {}", entry, comment),
+    }
+}
+
+/// The comment on a single line, with line breaks replaced by spaces since they would corrupt the file,
+/// and with the non-ASCII policy and the length limit of `options` applied.
+fn sanitize_comment(comment: &str, options: &WriteOptions) -> String {
+    let mut sanitized = String::with_capacity(comment.len());
+    for c in comment.chars() {
+        match c {
+            '\n' | '\r' => sanitized.push(' '),
+            c if c.is_ascii() => sanitized.push(c),
+            c => match options.non_ascii {
+                NonAsciiPolicy::Keep => sanitized.push(c),
+                NonAsciiPolicy::Escape => sanitized.push_str(&c.escape_unicode().to_string()),
+                NonAsciiPolicy::Replace => sanitized.push('?'),
+                NonAsciiPolicy::Strip => {}
+            },
+        }
+    }
+    match options.max_comment_length {
+        Some(max) if sanitized.chars().count() > max => truncate_comment(&sanitized, max),
+        _ => sanitized,
+    }
+}
+
+/// The entries of a comment, separated by whitespace outside double quotes, up to `max` characters in total,
+/// joined by single spaces. The `Lattice`, `Properties` and `pbc` entries, without which the atoms do not read back
+/// the same, are kept even beyond the limit.
+fn truncate_comment(comment: &str, max: usize) -> String {
+    let mut entries = vec![];
+    let (mut start, mut quoted) = (None, false);
+    for (i, c) in comment.char_indices() {
+        match c {
+            c if c.is_whitespace() && !quoted => {
+                if let Some(start) = start.take() {
+                    entries.push(&comment[start..i]);
+                }
+                continue;
+            }
+            '"' => quoted = !quoted,
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    entries.extend(start.map(|start| &comment[start..]));
+
+    let mut truncated = String::new();
+    let mut full = false;
+    for entry in entries {
+        let required = ["Lattice=", "Properties=", "pbc="].iter().any(|key| entry.starts_with(key));
+        full |= truncated.chars().count() + usize::from(!truncated.is_empty()) + entry.chars().count() > max;
+        if required || !full {
+            if !truncated.is_empty() {
+                truncated.push(' ');
+            }
+            truncated.push_str(entry);
+        }
+    }
+    truncated
+}
+
+/// Formats a number, writing negative zero (also after rounding, e.g. `-0.000`) without the sign.
+pub(crate) fn format_number(buf: &mut String, value: f64, precision: Option<usize>) {
+    let formatted = match precision {
+        Some(p) => format!("{:.p$}", value),
+        None if value != 0.0 && (value.abs() < 1e-5 || value.abs() >= 1e16) => format!("{:e}", value),
+        None => format!("{}", value),
+    };
+    match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.bytes().all(|b| b == b'0' || b == b'.') => buf.push_str(unsigned),
+        _ => buf.push_str(&formatted),
+    }
+}
+
+/// An extended XYZ comment for a frame with the per-atom columns `properties`, e.g. `species:S:1:pos:R:3`,
+/// keeping the `key=value` properties of its comment.
+pub(crate) fn extended_comment(data: &Data, properties: &str) -> String {
+    let mut comment = String::new();
+    if let Some(cell) = &data.cell {
+        comment.push_str(&cell.to_comment());
+        comment.push(' ');
+    }
+    comment.push_str(&format!("Properties={}", properties));
+    for (key, value) in data.properties() {
+        if ["lattice", "properties", "pbc"].contains(&key.to_ascii_lowercase().as_str()) {
+            continue;
+        }
+        if value.contains(char::is_whitespace) || value.is_empty() {
+            comment.push_str(&format!(" {}=\"{}\"", key, value));
+        } else {
+            comment.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    comment.push_str(if data.cell.is_some() { " pbc=\"T T T\"" } else { " pbc=\"F F F\"" });
+    comment
+}
+
+fn generate_comment(data: &Data, index: usize, fields: &[CommentField]) -> String {
+    let mut entries = vec![];
+    if let Some(cell) = &data.cell {
+        entries.push(cell.to_comment());
+    }
+    for field in fields {
+        match field {
+            CommentField::Index => entries.push(format!("frame={}", index)),
+            CommentField::Timestamp => entries.push(format!("time={}", timestamp(SystemTime::now()))),
+            CommentField::Formula => entries.push(format!("formula={}", data.formula())),
+            CommentField::Energy => {
+                if let Some(energy) = data.energy() {
+                    entries.push(format!("energy={}", energy));
+                }
+            }
+        }
+    }
+    entries.join(" ")
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with whole seconds.
+pub(crate) fn timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+
+    // Converts days since 1970-01-01 to a civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::cell::Cell;
+    use crate::Frames;
+    use crate::testing::trajectory;
+    use crate::testing::water;
+
+    fn written(data: &Data, options: &WriteOptions) -> String {
+        let mut buf = vec![];
+        write_frame(&mut buf, data, options).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn shortest_and_fixed_precision() {
+        let mut data = water();
+        data.atoms[0].position.0 = [-0.0, 1e-7, 2.5e16];
+        data.atoms[1].position.0 = [-0.0001, 1.0 / 3.0, 12.0];
+        let shortest = written(&data, &WriteOptions::default());
+        assert_eq!(shortest.lines().nth(2), Some("O\t0\t1e-7\t2.5e16"));
+        assert_eq!(shortest.lines().nth(3), Some("H\t-0.0001\t0.3333333333333333\t12"));
+
+        let options = WriteOptions {
+            precision: Some(3),
+            ..WriteOptions::default()
+        };
+        let fixed = written(&data, &options);
+        assert_eq!(fixed.lines().nth(3), Some("H\t0.000\t0.333\t12.000"));
+    }
+
+    #[test]
+    fn round_trip_single_frame() {
+        let data = water();
+        let text = written(&data, &WriteOptions::default());
+        let read: Vec<Data> = Frames::new(Cursor::new(text)).collect::<Result<_, _>>().unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].comment, data.comment);
+        for (a, b) in data.atoms.iter().zip(&read[0].atoms) {
+            assert_eq!(a.symbol, b.symbol);
+            assert_eq!(a.position, b.position);
+        }
+    }
+
+    #[test]
+    fn comment_policies() {
+        let mut data = water();
+        data.comment = "energy=-76.4 step=3".to_string();
+        let options = |comment| WriteOptions {
+            comment,
+            ..WriteOptions::default()
+        };
+        let comment = |data: &Data, options: &WriteOptions| {
+            let mut buf = vec![];
+            write_frame_at(&mut buf, data, 7, options).unwrap();
+            String::from_utf8(buf).unwrap().lines().nth(1).unwrap().to_string()
+        };
+        assert_eq!(comment(&data, &options(CommentPolicy::Keep)), "energy=-76.4 step=3");
+        let fields = vec![CommentField::Index, CommentField::Formula, CommentField::Energy];
+        let fields = options(CommentPolicy::Fields(fields));
+        assert_eq!(comment(&data, &fields), "frame=7 formula=H2O energy=-76.4");
+        data.comment.clear();
+        data.cell = Some(Cell::new([3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 3.0]));
+        assert_eq!(comment(&data, &fields), r#"Lattice="3 0 0 0 3 0 0 0 3" frame=7 formula=H2O"#);
+        let custom = CommentPolicy::Custom(Arc::new(|data, i| format!("{} atoms\nframe {}", data.atoms.len(), i)));
+        let custom = options(custom);
+        assert_eq!(comment(&data, &custom), "3 atoms frame 7");
+    }
+
+    #[test]
+    fn utc_timestamps() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + std::time::Duration::from_secs(951_782_400 + 3661);
+        assert_eq!(timestamp(leap_day), "2000-02-29T01:01:01Z");
+    }
+
+    #[test]
+    fn serialized_size_matches_output() {
+        let file = trajectory(4);
+        let options = WriteOptions {
+            precision: Some(6),
+            ..WriteOptions::default()
+        };
+        let mut buf = vec![];
+        write_file(&mut buf, &file, &options).unwrap();
+        assert_eq!(file.serialized_size(&options), buf.len() as u64);
+        assert_eq!(File { data: vec![] }.serialized_size(&options), 0);
+    }
+
+    #[test]
+    fn extended_comments() {
+        let mut data = water();
+        data.comment = "Lattice=\"1 0 0 0 1 0 0 0 1\" energy=-1.5 note=\"two words\" empty=\"\"".to_string();
+        let comment = extended_comment(&data, "species:S:1:pos:R:3");
+        assert_eq!(comment, "Properties=species:S:1:pos:R:3 energy=-1.5 note=\"two words\" empty=\"\" pbc=\"F F F\"");
+        data.cell = Some(Cell::new([2.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 2.0]));
+        let comment = extended_comment(&data, "species:S:1:pos:R:3");
+        assert!(comment.starts_with("Lattice=\"2 0 0 0 2 0 0 0 2\" Properties="), "{}", comment);
+        assert!(comment.ends_with(" pbc=\"T T T\""));
+    }
+
+    #[test]
+    fn sanitized_comments() {
+        let mut data = water();
+        data.comment = "Ångström\nline".to_string();
+        let comment = |options: WriteOptions| written(&data, &options).lines().nth(1).unwrap().to_string();
+        assert_eq!(comment(WriteOptions::default()), "Ångström line");
+        let policy = |non_ascii| WriteOptions {
+            non_ascii,
+            ..WriteOptions::default()
+        };
+        assert_eq!(comment(policy(NonAsciiPolicy::Escape)), "\\u{c5}ngstr\\u{f6}m line");
+        assert_eq!(comment(policy(NonAsciiPolicy::Replace)), "?ngstr?m line");
+        assert_eq!(comment(policy(NonAsciiPolicy::Strip)), "ngstrm line");
+        let short = |max| WriteOptions {
+            max_comment_length: Some(max),
+            ..WriteOptions::default()
+        };
+        assert_eq!(comment(short(10)), "Ångström");
+        assert_eq!(comment(short(13)), "Ångström line");
+        assert_eq!(data.to_string().lines().nth(1), Some("Ångström line"));
+
+        data.comment = "Lattice=\"9 0 0 0 9 0 0 0 9\" title=\"a long title\" step=1 pbc=\"T T T\"".to_string();
+        data.cell = Cell::from_comment(&data.comment);
+        let output = written(&data, &short(30));
+        assert_eq!(output.lines().nth(1), Some("Lattice=\"9 0 0 0 9 0 0 0 9\" pbc=\"T T T\""));
+        let read = Frames::new(Cursor::new(output)).next().unwrap().unwrap();
+        assert_eq!(read.cell, data.cell);
+    }
+
+    #[test]
+    fn round_trip_trajectory() {
+        let file = trajectory(3);
+        let mut buf = vec![];
+        write_file(&mut buf, &file, &WriteOptions::default()).unwrap();
+        let read: Vec<Data> = Frames::new(Cursor::new(buf)).collect::<Result<_, _>>().unwrap();
+        assert_eq!(read.len(), 3);
+        for (original, read) in file.data.iter().zip(&read) {
+            assert_eq!(read.comment, original.comment);
+            assert_eq!(read.atoms.len(), original.atoms.len());
+            assert_eq!(read.atoms[0].position, original.atoms[0].position);
+        }
+    }
+
+    #[test]
+    fn extended_columns_round_trip() {
+        let text = "2\nLattice=\"10 0 0 0 10 0 0 0 10\" Properties=species:S:1:pos:R:3:name:S:1:resname:S:1:id:I:1:\
+                    velo:R:3:forces:R:3:charge:R:1:layer:S:1 step=3 pbc=\"T T T\"\n\
+                    O 0 0 0 OW SOL 1 0.1 0 0 0 0 -0.5 -0.8 H\n\
+                    H 0.96 0 0 HW1 SOL 2 0 0.2 0 0 0.5 0 0.4 _\n";
+        let data = Frames::new(Cursor::new(text)).next().unwrap().unwrap();
+        let output = written(&data, &WriteOptions::default());
+        let properties = "Properties=species:S:1:pos:R:3:name:S:1:group:S:1:index:I:1:velo:R:3:forces:R:3:charges:R:1:\
+                          layer:S:1";
+        assert!(output.lines().nth(1).unwrap().contains(&format!(" {} step=3 ", properties)), "{}", output);
+        assert_eq!(output.lines().nth(3), Some("H\t0.96\t0\t0\tHW1\tSOL\t2\t0\t0.2\t0\t0\t0.5\t0\t0.4\t_"));
+
+        let read = Frames::new(Cursor::new(output)).next().unwrap().unwrap();
+        assert_eq!(read.cell.unwrap().vectors, data.cell.unwrap().vectors);
+        assert_eq!(read.property("step").as_deref(), Some("3"));
+        for (a, b) in data.atoms.iter().zip(&read.atoms) {
+            assert_eq!((&a.symbol, a.position, a.index), (&b.symbol, b.position, b.index));
+            assert_eq!((&a.name, &a.group), (&b.name, &b.group));
+            assert_eq!((a.velocity, a.force, a.charge, &a.tags), (b.velocity, b.force, b.charge, &b.tags));
+        }
+    }
+
+    #[test]
+    fn unwritten_columns_are_not_declared() {
+        let text = "1\nProperties=species:S:1:pos:R:3:mass:R:1 step=1\nAr 1 2 3 39.9\n";
+        let data = Frames::new(Cursor::new(text)).next().unwrap().unwrap();
+        let output = written(&data, &WriteOptions::default());
+        assert_eq!(output, "1\nProperties=species:S:1:pos:R:3 step=1\nAr\t1\t2\t3\n");
+
+        let mut data = water();
+        data.atoms.iter_mut().for_each(|atom| atom.charge = Some(0.0));
+        let charges = written(&data, &WriteOptions::default());
+        assert!(charges.starts_with("3\nProperties=species:S:1:pos:R:3:charges:R:1 water\n"), "{}", charges);
+        data.atoms[0].charge = None;
+        assert!(written(&data, &WriteOptions::default()).starts_with("3\nwater\nO\t0\t0\t0\n"));
+    }
+}